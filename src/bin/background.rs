@@ -1,5 +1,8 @@
-use bong::modules::background::BackgroundTaskManager;
-use bong::modules::ipc::{IpcServer, IpcCommand, BACKGROUND_IPC_PORT};
+use bong::modules::{api, engine, watch};
+use bong::modules::aria2::Aria2Daemon;
+use bong::modules::background::{BackgroundTaskManager, CommandHandler};
+use bong::modules::ipc::{IpcServer, BACKGROUND_IPC_PORT};
+use bong::modules::settings::{EngineKind, SettingsStore};
 use std::sync::Arc;
 use parking_lot::Mutex;
 
@@ -7,39 +10,46 @@ use parking_lot::Mutex;
 async fn main() -> anyhow::Result<()> {
     println!("Background Task starting...");
     
+    // Load settings shared between the IPC handler and the task loop
+    let settings = Arc::new(SettingsStore::open());
+    
+    // Launch the managed aria2 daemon, the built-in engine doesn't need it
+    let aria2 = Arc::new(Aria2Daemon::new());
+    if settings.get().aria2.auto_start
+        && settings.get().engine.kind == EngineKind::Aria2
+        && let Err(e) = aria2.start(&settings.get())
+    {
+        println!("Failed to start aria2c: {}", e);
+    }
+    
     // Create background task manager
-    let manager = Arc::new(Mutex::new(BackgroundTaskManager::new()));
+    let manager = Arc::new(Mutex::new(BackgroundTaskManager::new(settings.clone(), aria2.clone())));
     manager.lock().start();
     
     println!("Background Task started on port {}", BACKGROUND_IPC_PORT);
     
+    // Commands are answered the same way over IPC and the HTTP API
+    let running = Arc::new(Mutex::new(true));
+    let handler = Arc::new(CommandHandler::new(settings.clone(), aria2.clone(), manager.clone(), running.clone()));
+    api::spawn(handler.clone());
+    
+    // Enqueue files dropped into the watch folders
+    watch::spawn(settings.clone());
+    
     // Setup IPC server
     let server = IpcServer::new(BACKGROUND_IPC_PORT)?;
-    let running = Arc::new(Mutex::new(true));
-    let running_clone = running.clone();
-    let manager_clone = manager.clone();
     
     // Listen for commands
     let result = server.listen(move |command| {
         println!("Background Task received command: {:?}", command);
-        
-        match command {
-            IpcCommand::StopBackground | IpcCommand::QuitAll => {
-                println!("Stopping background task...");
-                *running_clone.lock() = false;
-                Ok(None)
-            }
-            IpcCommand::Ping => {
-                let is_running = manager_clone.lock().is_running();
-                Ok(Some(IpcCommand::BackgroundStatus(is_running)))
-            }
-            _ => Ok(None),
-        }
+        Ok(handler.handle(command))
     });
     
     // Cleanup
     if !*running.lock() {
         manager.lock().stop().await;
+        aria2.stop(&settings.get());
+        engine::shutdown();
         println!("Background Task stopped");
     }
     
@@ -0,0 +1,73 @@
+use bong::modules::native_host::{handle_message, install, read_message, uninstall, write_message};
+
+const USAGE: &str = "Usage:
+  bong-native-host --install [--chromium-id <extension id>]...
+  bong-native-host --uninstall
+
+Browsers start the host themselves with their own arguments.";
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("--install") => run_install(&args[1..]),
+        Some("--uninstall") => {
+            let removed = uninstall()?;
+            if removed.is_empty() {
+                println!("No browser manifests were installed");
+            }
+            for path in removed {
+                println!("Removed {}", path.display());
+            }
+            Ok(())
+        }
+        Some("--help" | "-h") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        // Started by a browser, which passes the manifest path or the extension origin
+        _ => serve(),
+    }
+}
+
+fn run_install(args: &[String]) -> anyhow::Result<()> {
+    let mut chromium_ids = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--chromium-id" => match args.next() {
+                Some(id) => chromium_ids.push(id.clone()),
+                None => anyhow::bail!("--chromium-id needs an extension ID\n\n{}", USAGE),
+            },
+            other => anyhow::bail!("Unknown argument {}\n\n{}", other, USAGE),
+        }
+    }
+
+    let host_path = std::env::current_exe()?.canonicalize()?;
+    let written = install(&host_path, &chromium_ids)?;
+    if written.is_empty() {
+        println!("No supported browser profile found");
+    }
+    for path in written {
+        println!("Installed {}", path.display());
+    }
+    if chromium_ids.is_empty() {
+        println!("Chromium based browsers were skipped, pass the extension ID with --chromium-id");
+    }
+    Ok(())
+}
+
+/// Answer messages until the browser closes the pipe. Stdout carries the protocol,
+/// so diagnostics go to stderr where the browser logs them.
+fn serve() -> anyhow::Result<()> {
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout().lock();
+
+    while let Some(message) = read_message(&mut stdin)? {
+        let response = handle_message(&message);
+        if response["ok"] == false {
+            eprintln!("bong-native-host: {}", response["error"]);
+        }
+        write_message(&mut stdout, &response)?;
+    }
+    Ok(())
+}
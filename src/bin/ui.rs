@@ -1,6 +1,6 @@
 use gpui::*;
 use gpui_component::*;
-use bong::modules::ui::BongApp;
+use bong::modules::ui::{BongApp, IpcInbox};
 use bong::modules::ipc::{IpcServer, IpcCommand, UI_IPC_PORT};
 use std::sync::Arc;
 use parking_lot::Mutex;
@@ -12,6 +12,8 @@ async fn main() -> anyhow::Result<()> {
     // Setup IPC server in a separate thread
     let should_close = Arc::new(Mutex::new(false));
     let should_close_clone = should_close.clone();
+    let inbox: IpcInbox = Arc::new(Mutex::new(Vec::new()));
+    let inbox_clone = inbox.clone();
     
     std::thread::spawn(move || {
         if let Ok(server) = IpcServer::new(UI_IPC_PORT) {
@@ -29,6 +31,10 @@ async fn main() -> anyhow::Result<()> {
                     IpcCommand::Ping => {
                         Ok(Some(IpcCommand::UIStatus(true)))
                     }
+                    IpcCommand::Settings(_) => {
+                        inbox_clone.lock().push(command);
+                        Ok(None)
+                    }
                     _ => Ok(None),
                 }
             });
@@ -74,7 +80,7 @@ async fn main() -> anyhow::Result<()> {
                         true
                     });
                     
-                    let view = cx.new(|cx| BongApp::new(cx));
+                    let view = cx.new(|cx| BongApp::new(inbox, window, cx));
                     cx.new(|cx| Root::new(view, window, cx))
                 }, 
             )?;
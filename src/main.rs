@@ -1,11 +1,28 @@
 mod modules;
 
-use modules::tray::TrayManager;
-use modules::ipc::{IpcClient, IpcCommand, BACKGROUND_IPC_PORT, UI_IPC_PORT};
+use modules::batch::{batch_requests, dedupe, read_source, SharedOptions};
+use modules::engine::{AddRequest, TransferSnapshot};
+use modules::settings::Settings;
+use modules::tray::{TrayAction, TrayManager};
+use modules::ipc::{IpcClient, IpcCommand, IpcServer, BACKGROUND_IPC_PORT, TRAY_IPC_PORT, UI_IPC_PORT};
 use std::sync::Arc;
 use parking_lot::Mutex;
 use tray_icon::menu::MenuEvent;
 use std::process::{Command, Child};
+use std::io::Read;
+
+const IMPORT_USAGE: &str = "Usage:
+  bong import [options] <file | text | pattern | ->...
+
+Adds every URL found in the files or text, expanding patterns such as
+https://example.com/part[001-120].rar and {iso,sig}. - reads standard input.
+
+Options:
+  --dir <folder>         Save the downloads into this folder
+  --category <name>      Put the downloads into this category
+  --header <header>      Send an extra HTTP header, can be repeated
+  --option <key=value>   Pass an aria2 option such as split=8, can be repeated
+  --dry-run              Only list the URLs";
 
 /// Process manager for background and UI processes
 struct ProcessManager {
@@ -127,36 +144,107 @@ impl ProcessManager {
 }
 
 fn main() -> anyhow::Result<()> {
+    // `bong import` adds a batch of downloads and exits instead of starting the tray
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("import") {
+        return run_import(&args[1..]);
+    }
+
     println!("Bong Tray starting...");
     
     // Create process manager
     let process_manager = Arc::new(ProcessManager::new());
     
-    // Auto-start background and UI
-    process_manager.start_background()?;
-    process_manager.start_ui()?;
+    // Start the background task and the UI as configured
+    let settings = Settings::load().unwrap_or_default();
+    if settings.general.start_background_on_start {
+        process_manager.start_background()?;
+    }
+    if settings.general.open_ui_on_start {
+        process_manager.start_ui()?;
+    }
     
     // Setup tray icon
-    let mut tray_manager = TrayManager::new()?;
-    let menu_items = tray_manager.setup()?;
+    let mut tray_manager = TrayManager::new(&settings)?;
+    tray_manager.setup()?;
     println!("Tray icon initialized");
-    
-    // Clone menu IDs for event handling
-    let open_id = menu_items.open_id.clone();
-    let quit_id = menu_items.quit_id.clone();
-    
+
+    // Receive transfer snapshots and settings pushed by the background task
+    let latest_snapshot: Arc<Mutex<Option<TransferSnapshot>>> = Arc::new(Mutex::new(None));
+    let latest_snapshot_clone = latest_snapshot.clone();
+    let latest_settings: Arc<Mutex<Option<Settings>>> = Arc::new(Mutex::new(None));
+    let latest_settings_clone = latest_settings.clone();
+    std::thread::spawn(move || {
+        if let Ok(server) = IpcServer::new(TRAY_IPC_PORT) {
+            println!("Tray IPC server listening on port {}", TRAY_IPC_PORT);
+
+            let _ = server.listen(move |command| match command {
+                IpcCommand::Transfers(snapshot) => {
+                    *latest_snapshot_clone.lock() = Some(snapshot);
+                    Ok(Some(IpcCommand::Ok))
+                }
+                IpcCommand::Settings(settings) => {
+                    *latest_settings_clone.lock() = Some(settings);
+                    Ok(Some(IpcCommand::Ok))
+                }
+                IpcCommand::Ping => Ok(Some(IpcCommand::Ok)),
+                _ => Ok(None),
+            });
+        }
+    });
+
+    // Fill the menu right away instead of waiting for the first push
+    if let Ok(Some(IpcCommand::Transfers(snapshot))) =
+        IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::GetTransfers)
+    {
+        *latest_snapshot.lock() = Some(snapshot);
+    }
+
     // Main event loop
     let process_manager_clone = process_manager.clone();
+    let mut ticks: u32 = 0;
     loop {
+        if let Some(settings) = latest_settings.lock().take()
+            && let Err(e) = tray_manager.set_settings(&settings)
+        {
+            println!("Failed to apply settings to the tray: {}", e);
+        }
+
+        // Follow desktop theme switches every few seconds
+        ticks = ticks.wrapping_add(1);
+        if ticks.is_multiple_of(50)
+            && let Err(e) = tray_manager.refresh_theme()
+        {
+            println!("Failed to update tray icon theme: {}", e);
+        }
+
+        if let Some(snapshot) = latest_snapshot.lock().take()
+            && let Err(e) = tray_manager.update(snapshot)
+        {
+            println!("Failed to update tray menu: {}", e);
+        }
+
         if let Ok(event) = MenuEvent::receiver().try_recv() {
-            if event.id == open_id {
-                println!("Open clicked - Starting/Showing UI");
-                let _ = process_manager_clone.start_ui();
-            } else if event.id == quit_id {
-                println!("Exit clicked - Shutting down everything");
-                let _ = process_manager_clone.stop_all();
-                std::thread::sleep(std::time::Duration::from_millis(500));
-                break;
+            match TrayAction::from_menu_id(&event.id) {
+                Some(TrayAction::Open) => {
+                    println!("Open clicked - Starting/Showing UI");
+                    let _ = process_manager_clone.start_ui();
+                    if let Err(e) = tray_manager.acknowledge_errors() {
+                        println!("Failed to update tray icon: {}", e);
+                    }
+                }
+                Some(TrayAction::Quit) => {
+                    println!("Exit clicked - Shutting down everything");
+                    let _ = process_manager_clone.stop_all();
+                    std::thread::sleep(std::time::Duration::from_millis(500));
+                    break;
+                }
+                Some(action) => {
+                    if let Err(e) = run_action(action) {
+                        println!("Tray action failed: {}", e);
+                    }
+                }
+                None => {}
             }
         }
         std::thread::sleep(std::time::Duration::from_millis(100));
@@ -164,4 +252,102 @@ fn main() -> anyhow::Result<()> {
     
     println!("Bong Tray exiting");
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Forward a download control picked in the tray menu to the background task
+fn run_action(action: TrayAction) -> anyhow::Result<()> {
+    let command = match action {
+        TrayAction::PauseAll => IpcCommand::PauseAll,
+        TrayAction::ResumeAll => IpcCommand::ResumeAll,
+        TrayAction::SpeedLimit(limit) => IpcCommand::SetSpeedLimit(limit),
+        TrayAction::SpeedProfile(name) => IpcCommand::SetSpeedProfile(name),
+        TrayAction::AddFromClipboard => {
+            let text = arboard::Clipboard::new()?.get_text()?;
+            let url = text.trim();
+            let supported = ["http://", "https://", "ftp://", "sftp://", "magnet:"];
+            if !supported.iter().any(|scheme| url.starts_with(scheme)) {
+                anyhow::bail!("Clipboard does not contain a download URL");
+            }
+            IpcCommand::AddDownload(AddRequest::new(url))
+        }
+        TrayAction::Open | TrayAction::Quit => return Ok(()),
+    };
+
+    match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(command)? {
+        Some(IpcCommand::Error(e)) => anyhow::bail!(e),
+        Some(IpcCommand::DownloadAdded(gid)) => println!("Download added: {}", gid),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Add the URLs of files, text or patterns to the queue in one batch
+fn run_import(args: &[String]) -> anyhow::Result<()> {
+    let mut shared = SharedOptions::default();
+    let mut sources = Vec::new();
+    let mut dry_run = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value\n\n{}", name, IMPORT_USAGE))
+        };
+        match arg.as_str() {
+            "--dir" => shared.dir = Some(value("--dir")?),
+            "--category" => shared.category = Some(value("--category")?),
+            "--header" => shared.headers.push(value("--header")?),
+            "--option" => {
+                let option = value("--option")?;
+                let (key, value) = option
+                    .split_once('=')
+                    .ok_or_else(|| anyhow::anyhow!("Expected --option key=value, got {}", option))?;
+                shared.options.push((key.trim().to_string(), value.trim().to_string()));
+            }
+            "--dry-run" => dry_run = true,
+            "--help" | "-h" => {
+                println!("{}", IMPORT_USAGE);
+                return Ok(());
+            }
+            other if other.starts_with("--") => anyhow::bail!("Unknown option {}\n\n{}", other, IMPORT_USAGE),
+            source => sources.push(source.to_string()),
+        }
+    }
+    if sources.is_empty() {
+        anyhow::bail!("Nothing to import\n\n{}", IMPORT_USAGE);
+    }
+
+    let mut requests = Vec::new();
+    for source in sources {
+        let text = if source == "-" {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            text
+        } else {
+            read_source(&source)?
+        };
+        requests.extend(batch_requests(&text)?);
+    }
+    let requests = dedupe(requests);
+
+    for request in &requests {
+        println!("{}", request.uris.join("\t"));
+    }
+    println!("{} downloads", requests.len());
+    if dry_run {
+        return Ok(());
+    }
+
+    let response = IpcClient::new(BACKGROUND_IPC_PORT)
+        .send_with_response(IpcCommand::AddBatch(requests, shared))
+        .map_err(|_| anyhow::anyhow!("Background task is not running"))?;
+    match response {
+        Some(IpcCommand::BatchQueued(count)) => {
+            println!("Adding {} downloads, failures are listed in the background task's log", count);
+            Ok(())
+        }
+        Some(IpcCommand::Error(e)) => anyhow::bail!(e),
+        Some(_) => anyhow::bail!("Unexpected answer from the background task"),
+        None => anyhow::bail!("The background task didn't answer in time"),
+    }
+}
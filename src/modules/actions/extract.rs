@@ -0,0 +1,75 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Suffixes of archives unpacked in-process, longest first
+const TAR_GZ: [&str; 2] = [".tar.gz", ".tgz"];
+
+/// Unpack an archive into a folder named after it, next to the archive.
+/// zip and tar(.gz) are handled here, anything else is handed to 7z.
+pub fn extract(archive: &Path) -> anyhow::Result<PathBuf> {
+    let name = archive
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Invalid archive path {}", archive.display()))?;
+    let lower = name.to_lowercase();
+    let dir = archive.parent().unwrap_or(Path::new("."));
+    let dest = dir.join(stem(name));
+    if dest.exists() {
+        anyhow::bail!("{} already exists", dest.display());
+    }
+
+    let result = if lower.ends_with(".zip") {
+        extract_zip(archive, &dest)
+    } else if TAR_GZ.iter().any(|suffix| lower.ends_with(suffix)) {
+        let decoder = flate2::read::GzDecoder::new(File::open(archive)?);
+        tar::Archive::new(decoder).unpack(&dest).map_err(Into::into)
+    } else if lower.ends_with(".tar") {
+        tar::Archive::new(File::open(archive)?).unpack(&dest).map_err(Into::into)
+    } else {
+        extract_7z(archive, &dest)
+    };
+
+    if let Err(e) = result {
+        // Don't leave a half extracted folder behind
+        let _ = std::fs::remove_dir_all(&dest);
+        anyhow::bail!("Failed to extract {}: {}", name, e);
+    }
+    Ok(dest)
+}
+
+fn extract_zip(archive: &Path, dest: &Path) -> anyhow::Result<()> {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+    // Entries escaping the destination are rejected by the zip crate
+    zip.extract(dest)?;
+    Ok(())
+}
+
+fn extract_7z(archive: &Path, dest: &Path) -> anyhow::Result<()> {
+    let mut output_arg = std::ffi::OsString::from("-o");
+    output_arg.push(dest);
+    let output = Command::new("7z")
+        .arg("x")
+        .arg("-y")
+        .arg(output_arg)
+        .arg(archive)
+        .output()
+        .map_err(|e| anyhow::anyhow!("7z is needed for this archive type: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("7z failed: {}", stderr.trim());
+    }
+    Ok(())
+}
+
+/// Archive name without its archive extensions, e.g. "linux.tar.gz" becomes "linux"
+fn stem(name: &str) -> String {
+    let lower = name.to_lowercase();
+    let suffixes = [".tar.gz", ".tar.xz", ".tar.bz2", ".tar.zst", ".tgz", ".zip", ".tar", ".7z", ".rar", ".gz", ".xz", ".bz2"];
+    suffixes
+        .iter()
+        .find(|suffix| lower.ends_with(*suffix) && lower.len() > suffix.len())
+        .map(|suffix| name[..name.len() - suffix.len()].to_string())
+        .unwrap_or_else(|| format!("{}.extracted", name))
+}
@@ -0,0 +1,366 @@
+mod extract;
+
+use crate::modules::engine::{Download, DownloadStatus};
+use crate::modules::checksum::{self, ChecksumSource, Verification};
+use crate::modules::settings::{is_local_url, PostAction, Settings};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long a command action may run before it's killed
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Result of one post-download action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionOutcome {
+    /// Description of the action, e.g. "Move to /data"
+    pub action: String,
+    pub success: bool,
+    pub message: String,
+}
+
+/// A finished download with its files as seen from this machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletedDownload {
+    pub gid: String,
+    pub name: String,
+    pub status: DownloadStatus,
+    pub error: Option<String>,
+    pub dir: PathBuf,
+    pub files: Vec<PathBuf>,
+    pub url: Option<String>,
+    pub size: u64,
+    pub downloaded: u64,
+    pub category: Option<String>,
+    /// Unix timestamp of when the background task first saw the download
+    pub started_at: Option<i64>,
+}
+
+impl CompletedDownload {
+    /// Translate the paths of a download polled from the active endpoint
+    pub fn new(settings: &Settings, download: &Download) -> Self {
+        let endpoint = settings.active_endpoint();
+        let dir = endpoint.to_local(&download.dir);
+        let url = download.uris.first().cloned();
+        let category = settings
+            .categories
+            .for_download(&dir, &download.name, url.as_deref(), &settings.downloads.download_dir)
+            .map(|category| category.name.clone());
+
+        Self {
+            gid: download.gid.clone(),
+            name: download.name.clone(),
+            status: download.status,
+            error: download.error_message.clone(),
+            files: download.files.iter().map(|file| endpoint.to_local(file)).collect(),
+            dir,
+            url,
+            size: download.total_length,
+            downloaded: download.completed_length,
+            category,
+            started_at: None,
+        }
+    }
+
+    /// The first file, the one `{file}` stands for
+    pub fn main_file(&self) -> Option<&Path> {
+        self.files.first().map(PathBuf::as_path)
+    }
+
+    /// Replace the placeholders of a command argument
+    pub fn expand(&self, template: &str) -> String {
+        template
+            .replace("{file}", &self.main_file().map(|file| file.display().to_string()).unwrap_or_default())
+            .replace("{dir}", &self.dir.display().to_string())
+            .replace("{name}", &self.name)
+            .replace("{url}", self.url.as_deref().unwrap_or_default())
+            .replace("{gid}", &self.gid)
+            .replace("{size}", &self.size.to_string())
+            .replace("{category}", self.category.as_deref().unwrap_or_default())
+            .replace("{status}", &format!("{:?}", self.status).to_lowercase())
+    }
+}
+
+/// Check the main file against the checksum given when the download was added
+pub fn verify_expected(download: &CompletedDownload, source: &ChecksumSource) -> (Verification, ActionOutcome) {
+    let result = download
+        .main_file()
+        .ok_or_else(|| anyhow::anyhow!("Download has no files"))
+        .and_then(|file| {
+            let name = file.file_name().and_then(|name| name.to_str()).unwrap_or(&download.name);
+            let expected = source.resolve(name)?;
+            Ok((expected.algorithm, expected.verify(file)?))
+        });
+
+    let (verification, success, message) = match result {
+        Ok((algorithm, true)) => (Verification::Verified, true, format!("{} matches", algorithm.as_str())),
+        Ok((algorithm, false)) => (Verification::Corrupt, false, format!("{} doesn't match, the file is corrupt", algorithm.as_str())),
+        Err(e) => (Verification::Failed(e.to_string()), false, e.to_string()),
+    };
+    println!("Verify checksum for {}: {}", download.name, message);
+
+    let outcome = ActionOutcome {
+        action: PostAction::VerifyChecksum.describe(),
+        success,
+        message,
+    };
+    (verification, outcome)
+}
+
+/// Run the actions in order. A move changes where the following actions find the files.
+pub fn run_actions(actions: &[PostAction], download: &mut CompletedDownload, stop_on_failure: bool) -> Vec<ActionOutcome> {
+    let mut outcomes = Vec::new();
+    for action in actions {
+        let result = run_action(action, download);
+        let outcome = ActionOutcome {
+            action: action.describe(),
+            success: result.is_ok(),
+            message: result.unwrap_or_else(|e| e.to_string()),
+        };
+        println!(
+            "{} for {}: {}",
+            outcome.action,
+            download.name,
+            if outcome.success { "ok" } else { outcome.message.as_str() }
+        );
+
+        let failed = !outcome.success;
+        outcomes.push(outcome);
+        if failed && stop_on_failure {
+            break;
+        }
+    }
+    outcomes
+}
+
+/// Run a single action, returning a short message on success
+fn run_action(action: &PostAction, download: &mut CompletedDownload) -> anyhow::Result<String> {
+    match action {
+        PostAction::VerifyChecksum => verify(download),
+        PostAction::Extract { delete_archive } => {
+            let archive = download
+                .main_file()
+                .ok_or_else(|| anyhow::anyhow!("Download has no files"))?
+                .to_path_buf();
+            let dest = extract::extract(&archive)?;
+            if *delete_archive {
+                std::fs::remove_file(&archive)?;
+            }
+            Ok(format!("Extracted to {}", dest.display()))
+        }
+        PostAction::Move { dir } => move_files(download, dir),
+        PostAction::Command { program, args } => run_command(download, program, args),
+        PostAction::Webhook { url } => send_webhook(download, url),
+    }
+}
+
+fn verify(download: &CompletedDownload) -> anyhow::Result<String> {
+    let file = download.main_file().ok_or_else(|| anyhow::anyhow!("Download has no files"))?;
+    // Most downloads don't publish a checksum, that isn't a failure
+    let Some((source, expected)) = checksum::find_sidecar(file) else {
+        return Ok("Skipped, no checksum published next to the file".to_string());
+    };
+
+    if expected.verify(file)? {
+        Ok(format!("{} matches {}", expected.algorithm.as_str(), source.display()))
+    } else {
+        anyhow::bail!("{} doesn't match {}", expected.algorithm.as_str(), source.display())
+    }
+}
+
+/// Move the download's top level entries, so a torrent keeps its folder
+fn move_files(download: &mut CompletedDownload, dest: &Path) -> anyhow::Result<String> {
+    std::fs::create_dir_all(dest).map_err(|e| anyhow::anyhow!("Failed to create {}: {}", dest.display(), e))?;
+
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for file in &download.files {
+        let top = match file.strip_prefix(&download.dir) {
+            Ok(relative) => match relative.components().next() {
+                Some(first) => download.dir.join(first),
+                None => continue,
+            },
+            Err(_) => file.clone(),
+        };
+        if moved.iter().any(|(from, _)| *from == top) {
+            continue;
+        }
+
+        let target = dest.join(top.file_name().ok_or_else(|| anyhow::anyhow!("Invalid path {}", top.display()))?);
+        move_path(&top, &target)?;
+        moved.push((top, target));
+    }
+
+    // Point the remaining actions at the new location
+    for file in download.files.iter_mut() {
+        if let Some((from, to)) = moved.iter().find(|(from, _)| file.starts_with(from))
+            && let Ok(rest) = file.strip_prefix(from)
+        {
+            *file = if rest.as_os_str().is_empty() { to.clone() } else { to.join(rest) };
+        }
+    }
+    download.dir = dest.to_path_buf();
+
+    Ok(format!("Moved to {}", dest.display()))
+}
+
+/// Rename, falling back to copy and delete when the target is on another drive
+fn move_path(from: &Path, to: &Path) -> anyhow::Result<()> {
+    if to.exists() {
+        anyhow::bail!("{} already exists", to.display());
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_recursive(from, to).map_err(|e| anyhow::anyhow!("Failed to move {}: {}", from.display(), e))?;
+    if from.is_dir() {
+        std::fs::remove_dir_all(from)?;
+    } else {
+        std::fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        std::fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// Run a program directly, without a shell, so file names can't inject commands.
+/// It's killed when it runs longer than COMMAND_TIMEOUT.
+fn run_command(download: &CompletedDownload, program: &str, args: &[String]) -> anyhow::Result<String> {
+    run_with_timeout(download, program, args, COMMAND_TIMEOUT)
+}
+
+fn run_with_timeout(download: &CompletedDownload, program: &str, args: &[String], timeout: Duration) -> anyhow::Result<String> {
+    let args: Vec<String> = args.iter().map(|arg| download.expand(arg)).collect();
+    let mut child = Command::new(download.expand(program))
+        .args(&args)
+        .current_dir(&download.dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", program, e))?;
+
+    // Read stderr on the side so a chatty program can't fill the pipe and stall
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let errors = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stderr.read_to_end(&mut output);
+        output
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("{} didn't finish within {} seconds and was stopped", program, timeout.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+
+    if status.success() {
+        Ok(format!("{} exited successfully", program))
+    } else {
+        let stderr = errors.join().unwrap_or_default();
+        let stderr = String::from_utf8_lossy(&stderr);
+        match stderr.lines().rev().map(str::trim).find(|line| !line.is_empty()) {
+            Some(last_line) => anyhow::bail!("{} failed with {}: {}", program, status, last_line),
+            None => anyhow::bail!("{} failed with {}", program, status),
+        }
+    }
+}
+
+fn send_webhook(download: &CompletedDownload, url: &str) -> anyhow::Result<String> {
+    // Settings edited by hand skip validation
+    if !is_local_url(url) {
+        anyhow::bail!("Webhooks can only notify localhost, not {}", url);
+    }
+    let body = json!({
+        "event": "download.complete",
+        "gid": download.gid,
+        "name": download.name,
+        "status": format!("{:?}", download.status).to_lowercase(),
+        "dir": download.dir,
+        "files": download.files,
+        "url": download.url,
+        "size": download.size,
+        "category": download.category,
+    });
+
+    // A redirect could send the download's details to another machine
+    let response = ureq::AgentBuilder::new()
+        .redirects(0)
+        .build()
+        .post(url)
+        .timeout(Duration::from_secs(10))
+        .send_json(body)
+        .map_err(|e| anyhow::anyhow!("Webhook failed: {}", e))?;
+    Ok(format!("{} answered {}", url, response.status()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download() -> CompletedDownload {
+        CompletedDownload {
+            gid: "gid".to_string(),
+            name: "file.bin".to_string(),
+            status: DownloadStatus::Complete,
+            error: None,
+            dir: std::env::temp_dir(),
+            files: Vec::new(),
+            url: None,
+            size: 0,
+            downloaded: 0,
+            category: None,
+            started_at: None,
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_commands_that_run_too_long() {
+        let started = Instant::now();
+        let error = run_with_timeout(&download(), "sleep", &["5".to_string()], Duration::from_millis(300)).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(3));
+        assert!(error.to_string().contains("didn't finish"), "{}", error);
+
+        let done = run_with_timeout(&download(), "true", &[], Duration::from_secs(5)).unwrap();
+        assert_eq!(done, "true exited successfully");
+    }
+
+    #[test]
+    fn webhooks_stay_on_this_machine() {
+        assert!(is_local_url("http://localhost:8080/hook"));
+        assert!(is_local_url("https://LOCALHOST/hook"));
+        assert!(is_local_url("http://127.0.0.1/hook"));
+        assert!(is_local_url("http://127.1.2.3:9000"));
+        assert!(is_local_url("http://[::1]:8080/hook"));
+        assert!(!is_local_url("http://example.com/hook"));
+        assert!(!is_local_url("http://127.0.0.1@example.com/hook"));
+        assert!(!is_local_url("http://localhost.example.com/hook"));
+        assert!(!is_local_url("http://10.0.0.1/hook"));
+        assert!(!is_local_url("ftp://localhost/hook"));
+
+        let error = send_webhook(&download(), "http://example.com/hook").unwrap_err();
+        assert!(error.to_string().contains("only notify localhost"), "{}", error);
+    }
+}
@@ -0,0 +1,440 @@
+mod web;
+
+use crate::modules::engine::{engine_for, video_engine, AddRequest, DownloadEngine};
+use crate::modules::background::{broadcast, engine_key, CommandHandler};
+use crate::modules::ipc::IpcCommand;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Largest request body accepted, an add request is a few hundred bytes
+const MAX_BODY_LEN: usize = 1024 * 1024;
+
+/// Largest request line plus headers
+const MAX_HEAD_LEN: u64 = 16 * 1024;
+
+/// Seconds between comments that keep an idle event stream open through proxies
+const KEEP_ALIVE_SECS: u32 = 15;
+
+/// Serve the HTTP API on localhost while it's enabled in the settings.
+/// Turning it on or off or changing the port takes effect within a second.
+pub fn spawn(handler: Arc<CommandHandler>) {
+    std::thread::spawn(move || {
+        // Port tried last, so a port in use is reported once rather than every second
+        let mut attempted: Option<u16> = None;
+        let mut listener: Option<TcpListener> = None;
+
+        loop {
+            let api = handler.settings().get().api;
+            if api.enabled && api.token.is_empty() {
+                generate_token(&handler);
+                continue;
+            }
+            let wanted = api.enabled.then_some(api.port);
+            if wanted != attempted {
+                attempted = wanted;
+                listener = wanted.and_then(|port| match bind(port) {
+                    Ok(listener) => {
+                        println!("HTTP API listening on 127.0.0.1:{}", port);
+                        Some(listener)
+                    }
+                    Err(e) => {
+                        println!("Failed to start the HTTP API on port {}: {}", port, e);
+                        None
+                    }
+                });
+                if wanted.is_none() {
+                    println!("HTTP API stopped");
+                }
+            }
+
+            let Some(ref listener) = listener else {
+                std::thread::sleep(Duration::from_secs(1));
+                continue;
+            };
+            for _ in 0..10 {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let handler = handler.clone();
+                        std::thread::spawn(move || {
+                            if let Err(e) = serve_connection(stream, &handler) {
+                                println!("HTTP API connection failed: {}", e);
+                            }
+                        });
+                    }
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(Duration::from_millis(100));
+                    }
+                    Err(e) => {
+                        println!("HTTP API accept failed: {}", e);
+                        std::thread::sleep(Duration::from_millis(100));
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Give an API turned on without a token one, e.g. after editing the settings file on a headless machine
+fn generate_token(handler: &CommandHandler) {
+    let mut settings = handler.settings().get();
+    settings.api.token = crate::modules::settings::generate_token();
+    match handler.settings().update(settings.clone()) {
+        Ok(()) => {
+            println!("Generated an HTTP API token, it's in the settings file");
+            broadcast(IpcCommand::Settings(settings));
+        }
+        Err(e) => {
+            println!("Failed to save the HTTP API token: {}", e);
+            std::thread::sleep(Duration::from_secs(1));
+        }
+    }
+}
+
+fn bind(port: u16) -> anyhow::Result<TcpListener> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+/// A parsed HTTP request
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Token from `Authorization: Bearer <token>`, or from `?token=` for clients
+    /// such as EventSource that can't set headers
+    fn token(&self) -> Option<&str> {
+        self.header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .or_else(|| self.query_param("token"))
+    }
+}
+
+fn read_request(stream: &TcpStream) -> anyhow::Result<Request> {
+    let mut head = BufReader::new(stream.take(MAX_HEAD_LEN));
+
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        anyhow::bail!("Malformed request line");
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+        headers: Vec::new(),
+        body: Vec::new(),
+    };
+
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? == 0 {
+            anyhow::bail!("Request headers are incomplete or too large");
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            request.headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let len: usize = request.header("content-length").and_then(|len| len.parse().ok()).unwrap_or(0);
+    if len > MAX_BODY_LEN {
+        anyhow::bail!("Request body of {} bytes is too large", len);
+    }
+    // Part of the body may already sit in the header buffer
+    request.body = vec![0u8; len];
+    let buffered = head.buffer().len().min(len);
+    request.body[..buffered].copy_from_slice(&head.buffer()[..buffered]);
+    head.consume(buffered);
+    let mut stream = stream;
+    stream.read_exact(&mut request.body[buffered..])?;
+    Ok(request)
+}
+
+fn serve_connection(mut stream: TcpStream, handler: &CommandHandler) -> anyhow::Result<()> {
+    // The listener is non-blocking, its connections shouldn't be
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+
+    let request = match read_request(&stream) {
+        Ok(request) => request,
+        Err(e) => return write_json(&mut stream, 400, &json!({ "error": e.to_string() })),
+    };
+    println!("HTTP API {} {}", request.method, request.path);
+
+    let api = handler.settings().get().api;
+    match access(&request, &api.token) {
+        Access::Asset => {
+            return match web::asset(&request.path).filter(|_| api.web_ui) {
+                Some((content_type, data)) => write_response(&mut stream, 200, content_type, &data),
+                None => write_json(&mut stream, 404, &json!({ "error": "Not found" })),
+            };
+        }
+        Access::Denied => return write_json(&mut stream, 401, &json!({ "error": "Missing or invalid token" })),
+        Access::Api => {}
+    }
+
+    if request.method == "GET" && request.path == "/api/events" {
+        return stream_events(stream, handler, &api.token);
+    }
+    let (status, body) = route(&request, handler);
+    write_json(&mut stream, status, &body)
+}
+
+/// What a request is allowed to reach
+#[derive(Debug, PartialEq)]
+enum Access {
+    /// A file of the dashboard, public since the dashboard asks for the token itself
+    Asset,
+    /// The API, the request carries the token
+    Api,
+    Denied,
+}
+
+fn access(request: &Request, token: &str) -> Access {
+    if request.method == "GET" && !request.path.starts_with("/api/") {
+        Access::Asset
+    } else if request.token().is_some_and(|given| token_matches(given, token)) {
+        Access::Api
+    } else {
+        Access::Denied
+    }
+}
+
+/// Compare without returning early, so the time taken doesn't reveal the token
+fn token_matches(given: &str, expected: &str) -> bool {
+    !expected.is_empty()
+        && given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn route(request: &Request, handler: &CommandHandler) -> (u16, Value) {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["api", "downloads"]) => dispatch(handler, IpcCommand::GetTransfers).map(|response| match response {
+            IpcCommand::Transfers(snapshot) => (200, json!(snapshot.downloads)),
+            _ => unexpected(),
+        }),
+        ("POST", ["api", "downloads"]) => match serde_json::from_slice::<AddRequest>(&request.body) {
+            Ok(add) if add.uris.is_empty() => Err((400, json!({ "error": "A download needs at least one URI" }))),
+            Ok(add) => dispatch(handler, IpcCommand::AddDownload(add)).map(|response| match response {
+                IpcCommand::DownloadAdded(gid) => (201, json!({ "gid": gid })),
+                _ => unexpected(),
+            }),
+            Err(e) => Err((400, json!({ "error": format!("Invalid download: {}", e) }))),
+        },
+        ("POST", ["api", "downloads", gid, "pause"]) => dispatch_ok(handler, IpcCommand::PauseDownload(gid.to_string())),
+        ("POST", ["api", "downloads", gid, "resume"]) => dispatch_ok(handler, IpcCommand::ResumeDownload(gid.to_string())),
+        ("DELETE", ["api", "downloads", gid]) => dispatch_ok(handler, IpcCommand::RemoveDownload(gid.to_string())),
+        ("POST", ["api", "pause"]) => dispatch_ok(handler, IpcCommand::PauseAll),
+        ("POST", ["api", "resume"]) => dispatch_ok(handler, IpcCommand::ResumeAll),
+        ("GET", ["api", "stats"]) => dispatch(handler, IpcCommand::GetTransfers).map(|response| match response {
+            IpcCommand::Transfers(snapshot) => (200, json!({ "stat": snapshot.stat, "download_limit": snapshot.download_limit })),
+            _ => unexpected(),
+        }),
+        ("GET", ["api", "history"]) => dispatch(handler, IpcCommand::GetHistory).map(|response| match response {
+            IpcCommand::History(entries) => (200, json!(entries)),
+            _ => unexpected(),
+        }),
+        _ => Err((404, json!({ "error": format!("No route for {} {}", request.method, request.path) }))),
+    };
+    result.unwrap_or_else(|error| error)
+}
+
+/// Run a command through the same handler as IPC, turning an error response into a 400
+fn dispatch(handler: &CommandHandler, command: IpcCommand) -> Result<IpcCommand, (u16, Value)> {
+    match handler.handle(command) {
+        Some(IpcCommand::Error(e)) => Err((400, json!({ "error": e }))),
+        Some(response) => Ok(response),
+        None => Err(unexpected()),
+    }
+}
+
+fn dispatch_ok(handler: &CommandHandler, command: IpcCommand) -> Result<(u16, Value), (u16, Value)> {
+    dispatch(handler, command).map(|_| (200, json!({ "ok": true })))
+}
+
+fn unexpected() -> (u16, Value) {
+    (500, json!({ "error": "Unexpected answer from the background task" }))
+}
+
+/// Push the transfer snapshot as Server-Sent Events whenever it changes, and
+/// each engine event as it happens.
+/// Ends when the client goes away or the API is turned off or gets a new token.
+fn stream_events(mut stream: TcpStream, handler: &CommandHandler, token: &str) -> anyhow::Result<()> {
+    stream.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+    )?;
+
+    let mut last = String::new();
+    let mut idle = 0;
+    let settings = handler.settings().get();
+    let mut key = engine_key(&settings);
+    let mut events = engine_for(&settings).events();
+    let mut videos = video_engine(&settings).events();
+    loop {
+        let settings = handler.settings().get();
+        if !settings.api.enabled || settings.api.token != token {
+            return Ok(());
+        }
+
+        // Follow the engine and aria2 endpoint selected in the settings
+        let current = engine_key(&settings);
+        if key != current {
+            key = current;
+            events = engine_for(&settings).events();
+        }
+        while let Ok(event) = events.try_recv().or_else(|_| videos.try_recv()) {
+            write!(stream, "event: download\ndata: {}\n\n", serde_json::to_string(&event)?)?;
+            idle = 0;
+        }
+
+        if let Some(IpcCommand::Transfers(snapshot)) = handler.handle(IpcCommand::GetTransfers) {
+            let data = serde_json::to_string(&snapshot)?;
+            if data != last {
+                write!(stream, "event: transfers\ndata: {}\n\n", data)?;
+                last = data;
+                idle = 0;
+            }
+        }
+        if idle >= KEEP_ALIVE_SECS {
+            stream.write_all(b": keep-alive\n\n")?;
+            idle = 0;
+        }
+        stream.flush()?;
+
+        idle += 1;
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn write_json(stream: &mut TcpStream, status: u16, body: &Value) -> anyhow::Result<()> {
+    write_response(stream, status, "application/json", &serde_json::to_vec(body)?)
+}
+
+fn write_response(stream: &mut TcpStream, status: u16, content_type: &str, body: &[u8]) -> anyhow::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::aria2::Aria2Daemon;
+    use crate::modules::background::BackgroundTaskManager;
+    use crate::modules::settings::SettingsStore;
+    use parking_lot::Mutex;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    fn request(method: &str, target: &str, headers: &[(&str, &str)]) -> Request {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            headers: headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            body: Vec::new(),
+        }
+    }
+
+    fn handler() -> CommandHandler {
+        let path = std::env::temp_dir().join(format!("bong-api-{}", std::process::id())).join("settings.json");
+        let settings = Arc::new(SettingsStore::open_at(path));
+        let aria2 = Arc::new(Aria2Daemon::new());
+        let manager = Arc::new(Mutex::new(BackgroundTaskManager::new(settings.clone(), aria2.clone())));
+        CommandHandler::new(settings, aria2, manager, Arc::new(Mutex::new(true)))
+    }
+
+    #[test]
+    fn api_requires_the_token() {
+        let bearer = format!("Bearer {}", TOKEN);
+        for (method, path) in [("GET", "/api/downloads"), ("POST", "/api/pause"), ("GET", "/api/events")] {
+            assert_eq!(access(&request(method, path, &[]), TOKEN), Access::Denied);
+            assert_eq!(access(&request(method, path, &[("Authorization", "Bearer wrong")]), TOKEN), Access::Denied);
+            assert_eq!(access(&request(method, &format!("{}?token=0123456789abcdeF", path), &[]), TOKEN), Access::Denied);
+            assert_eq!(access(&request(method, path, &[("authorization", &bearer)]), TOKEN), Access::Api);
+            assert_eq!(access(&request(method, &format!("{}?token={}", path, TOKEN), &[]), TOKEN), Access::Api);
+        }
+        // Non-GET requests outside the API aren't assets
+        assert_eq!(access(&request("POST", "/", &[]), TOKEN), Access::Denied);
+    }
+
+    #[test]
+    fn an_empty_token_matches_nothing() {
+        assert!(!token_matches("", ""));
+        assert_eq!(access(&request("GET", "/api/downloads", &[("Authorization", "Bearer ")]), ""), Access::Denied);
+        assert!(!token_matches(TOKEN, &TOKEN[1..]));
+        assert!(token_matches(TOKEN, TOKEN));
+    }
+
+    #[test]
+    fn dashboard_files_are_public() {
+        assert_eq!(access(&request("GET", "/", &[]), TOKEN), Access::Asset);
+        assert_eq!(access(&request("GET", "/index.html", &[]), TOKEN), Access::Asset);
+        assert!(web::asset("/").is_some());
+    }
+
+    #[test]
+    fn routes_reject_bad_requests_before_the_engine() {
+        let handler = handler();
+        let (status, _) = route(&request("GET", "/api/unknown", &[]), &handler);
+        assert_eq!(status, 404);
+        let (status, _) = route(&request("PUT", "/api/downloads", &[]), &handler);
+        assert_eq!(status, 404);
+
+        let mut add = request("POST", "/api/downloads", &[]);
+        add.body = b"not json".to_vec();
+        assert_eq!(route(&add, &handler).0, 400);
+        add.body = br#"{"uris": []}"#.to_vec();
+        let (status, body) = route(&add, &handler);
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "A download needs at least one URI");
+    }
+}
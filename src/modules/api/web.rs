@@ -0,0 +1,28 @@
+use rust_embed::RustEmbed;
+use std::borrow::Cow;
+
+/// The web dashboard, compiled into the binary so headless installs need nothing else
+#[derive(RustEmbed)]
+#[folder = "src/web/"]
+struct WebAssets;
+
+/// Content and type of a dashboard file, `/` being the page itself
+pub fn asset(path: &str) -> Option<(&'static str, Cow<'static, [u8]>)> {
+    let name = match path.trim_start_matches('/') {
+        "" => "index.html",
+        name => name,
+    };
+    let file = WebAssets::get(name)?;
+    Some((content_type(name), file.data))
+}
+
+fn content_type(name: &str) -> &'static str {
+    match name.rsplit('.').next() {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    }
+}
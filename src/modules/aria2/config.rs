@@ -0,0 +1,73 @@
+use crate::modules::settings::{config_dir, Settings};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Path of the aria2.conf generated from the settings
+pub fn conf_path() -> PathBuf {
+    config_dir().join("aria2.conf")
+}
+
+/// Path of the session file aria2 uses to remember unfinished downloads
+pub fn session_path() -> PathBuf {
+    config_dir().join("aria2.session")
+}
+
+/// Options that can be changed on a running daemon via `aria2.changeGlobalOption`
+pub fn live_options(settings: &Settings) -> Vec<(&'static str, String)> {
+    let aria2 = &settings.aria2;
+
+    vec![
+        ("dir", settings.downloads.download_dir.display().to_string()),
+        ("max-concurrent-downloads", settings.downloads.max_concurrent.to_string()),
+        ("split", aria2.split.to_string()),
+        ("min-split-size", aria2.min_split_size.clone()),
+        ("max-connection-per-server", aria2.max_connection_per_server.to_string()),
+        ("file-allocation", aria2.file_allocation.as_str().to_string()),
+        ("seed-ratio", aria2.seed_ratio.to_string()),
+    ]
+}
+
+/// Render the contents of aria2.conf
+pub fn render_conf(settings: &Settings) -> String {
+    let mut conf = String::new();
+    let network = &settings.network;
+    let session = session_path();
+
+    let _ = writeln!(conf, "# Generated by bong, changes are overwritten when settings are saved");
+    let _ = writeln!(conf, "continue=true");
+    let _ = writeln!(conf, "pause={}", !settings.downloads.auto_start);
+
+    let _ = writeln!(conf, "\n# RPC");
+    let _ = writeln!(conf, "enable-rpc=true");
+    let _ = writeln!(conf, "rpc-listen-port={}", network.rpc_port);
+    let _ = writeln!(conf, "rpc-listen-all=false");
+    if !settings.aria2.rpc_secret.is_empty() {
+        let _ = writeln!(conf, "rpc-secret={}", settings.aria2.rpc_secret);
+    }
+
+    let _ = writeln!(conf, "\n# Session");
+    let _ = writeln!(conf, "input-file={}", session.display());
+    let _ = writeln!(conf, "save-session={}", session.display());
+    let _ = writeln!(conf, "save-session-interval=60");
+
+    let _ = writeln!(conf, "\n# Network");
+    let _ = writeln!(conf, "timeout={}", network.timeout_secs);
+    let _ = writeln!(conf, "user-agent={}", network.user_agent);
+    if !network.proxy.is_empty() {
+        let _ = writeln!(conf, "all-proxy={}", network.proxy);
+    }
+
+    let _ = writeln!(conf, "\n# Transfers");
+    for (key, value) in live_options(settings) {
+        let _ = writeln!(conf, "{}={}", key, value);
+    }
+    let _ = writeln!(conf, "disk-cache={}", settings.aria2.disk_cache);
+
+    let profile = settings.bandwidth.active();
+    let _ = writeln!(conf, "\n# Speed profile {}", profile.name);
+    for (key, value) in profile.global_options() {
+        let _ = writeln!(conf, "{}={}", key, value);
+    }
+
+    conf
+}
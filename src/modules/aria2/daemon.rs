@@ -0,0 +1,128 @@
+use super::{conf_path, live_options, render_conf, session_path, Aria2Client};
+use crate::modules::settings::{write_atomic, Settings};
+use parking_lot::Mutex;
+use std::process::{Child, Command, Stdio};
+
+/// The aria2c process managed by the background task
+pub struct Aria2Daemon {
+    child: Mutex<Option<Child>>,
+}
+
+impl Aria2Daemon {
+    pub fn new() -> Self {
+        Self {
+            child: Mutex::new(None),
+        }
+    }
+
+    /// Write aria2.conf and launch aria2c with it
+    pub fn start(&self, settings: &Settings) -> anyhow::Result<()> {
+        let mut child_lock = self.child.lock();
+
+        // Check if already running
+        if let Some(ref mut child) = *child_lock
+            && child.try_wait()?.is_none()
+        {
+            println!("aria2c already running");
+            return Ok(());
+        }
+
+        write_conf(settings)?;
+        if !session_path().exists() {
+            std::fs::write(session_path(), "")?;
+        }
+        std::fs::create_dir_all(&settings.downloads.download_dir)?;
+
+        println!("Starting aria2c: {:?}", settings.aria2.binary);
+        let child = Command::new(&settings.aria2.binary)
+            .arg(format!("--conf-path={}", conf_path().display()))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start {:?}: {}", settings.aria2.binary, e))?;
+        *child_lock = Some(child);
+
+        Ok(())
+    }
+
+    /// Shut aria2c down, killing it if it doesn't exit on request
+    pub fn stop(&self, settings: &Settings) {
+        let mut child_lock = self.child.lock();
+
+        if let Some(mut child) = child_lock.take() {
+            println!("Stopping aria2c...");
+            let _ = local_client(settings).shutdown();
+
+            for _ in 0..20 {
+                if let Ok(Some(_)) = child.try_wait() {
+                    return;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        match *self.child.lock() {
+            Some(ref mut child) => matches!(child.try_wait(), Ok(None)),
+            None => false,
+        }
+    }
+
+    /// Regenerate aria2.conf and bring the running daemon in line with it.
+    /// Options aria2 can't change at runtime restart the daemon.
+    pub fn apply_settings(&self, old: &Settings, new: &Settings) -> anyhow::Result<()> {
+        if self.apply_live(old, new)? {
+            self.restart(old, new)?;
+        }
+        Ok(())
+    }
+
+    /// Regenerate aria2.conf and change the options the running daemon takes at runtime,
+    /// returning whether the rest needs a `restart`
+    pub fn apply_live(&self, old: &Settings, new: &Settings) -> anyhow::Result<bool> {
+        write_conf(new)?;
+
+        if !self.is_running() {
+            return Ok(false);
+        }
+        if needs_restart(old, new) {
+            return Ok(true);
+        }
+        local_client(new).change_global_option(&live_options(new))?;
+        Ok(false)
+    }
+
+    /// Stop the daemon started with the old settings and start it with the new ones
+    pub fn restart(&self, old: &Settings, new: &Settings) -> anyhow::Result<()> {
+        self.stop(old);
+        self.start(new)
+    }
+}
+
+impl Default for Aria2Daemon {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// RPC client for the managed daemon, whichever endpoint is active
+fn local_client(settings: &Settings) -> Aria2Client {
+    Aria2Client::local(settings.network.rpc_port, &settings.aria2.rpc_secret)
+}
+
+/// Whether the difference between two settings requires restarting aria2c
+fn needs_restart(old: &Settings, new: &Settings) -> bool {
+    old.aria2.binary != new.aria2.binary
+        || old.aria2.rpc_secret != new.aria2.rpc_secret
+        || old.aria2.disk_cache != new.aria2.disk_cache
+        || old.network != new.network
+        || old.downloads.auto_start != new.downloads.auto_start
+}
+
+fn write_conf(settings: &Settings) -> anyhow::Result<()> {
+    write_atomic(&conf_path(), render_conf(settings).as_bytes())
+}
@@ -0,0 +1,389 @@
+mod config;
+mod daemon;
+
+pub use config::{conf_path, live_options, render_conf, session_path};
+pub use daemon::Aria2Daemon;
+
+use crate::modules::engine::{AddRequest, Download, DownloadStatus, EngineStatus, GlobalStat};
+use crate::modules::settings::{Aria2Endpoint, Settings, SpeedProfile};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Client for the aria2 JSON-RPC interface
+#[derive(Clone)]
+pub struct Aria2Client {
+    url: String,
+    secret: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl Aria2Client {
+    /// Create a client for an RPC endpoint such as `http://127.0.0.1:6800/jsonrpc`
+    pub fn new(url: impl Into<String>, secret: Option<String>) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(5))
+            .build();
+
+        Self {
+            url: url.into(),
+            secret: secret.filter(|s| !s.is_empty()),
+            agent,
+        }
+    }
+
+    /// Client for the aria2 daemon managed on this machine
+    pub fn local(port: u16, secret: &str) -> Self {
+        Self::new(format!("http://127.0.0.1:{}/jsonrpc", port), Some(secret.to_string()))
+    }
+
+    /// Client for a configured endpoint
+    pub fn for_endpoint(endpoint: &Aria2Endpoint) -> Self {
+        Self::new(endpoint.url(), Some(endpoint.secret.clone()))
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Call an RPC method, prepending the secret token when one is set
+    pub fn call(&self, method: &str, params: Vec<Value>) -> anyhow::Result<Value> {
+        let mut all_params = Vec::with_capacity(params.len() + 1);
+        if let Some(ref secret) = self.secret {
+            all_params.push(json!(format!("token:{}", secret)));
+        }
+        all_params.extend(params);
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "bong",
+            "method": method,
+            "params": all_params,
+        });
+
+        // aria2 answers RPC errors with a 4xx status and an error object
+        let response: Value = match self.agent.post(&self.url).send_json(request) {
+            Ok(response) => response.into_json()?,
+            Err(ureq::Error::Status(_, response)) => response.into_json()?,
+            Err(e) => anyhow::bail!("Failed to reach aria2 at {}: {}", self.url, e),
+        };
+
+        if let Some(error) = response.get("error") {
+            let message = error["message"].as_str().unwrap_or("unknown error");
+            anyhow::bail!("aria2 {} failed: {}", method, message);
+        }
+
+        Ok(response["result"].clone())
+    }
+
+    /// Change global options of the running daemon
+    pub fn change_global_option(&self, options: &[(&str, String)]) -> anyhow::Result<()> {
+        let options: serde_json::Map<String, Value> = options
+            .iter()
+            .map(|(key, value)| (key.to_string(), json!(value)))
+            .collect();
+        self.call("aria2.changeGlobalOption", vec![Value::Object(options)])?;
+        Ok(())
+    }
+
+    /// Change options of a single download
+    pub fn change_option(&self, gid: &str, options: &[(&str, String)]) -> anyhow::Result<()> {
+        let options: serde_json::Map<String, Value> = options
+            .iter()
+            .map(|(key, value)| (key.to_string(), json!(value)))
+            .collect();
+        self.call("aria2.changeOption", vec![json!(gid), Value::Object(options)])?;
+        Ok(())
+    }
+
+    /// Ask the daemon to exit
+    pub fn shutdown(&self) -> anyhow::Result<()> {
+        self.call("aria2.shutdown", vec![])?;
+        Ok(())
+    }
+}
+
+/// Client for the endpoint currently selected in the settings
+pub fn client_for(settings: &Settings) -> Aria2Client {
+    Aria2Client::for_endpoint(&settings.active_endpoint())
+}
+
+impl Aria2Client {
+    /// Global download/upload speed and queue sizes
+    pub fn get_global_stat(&self) -> anyhow::Result<GlobalStat> {
+        let result = self.call("aria2.getGlobalStat", vec![])?;
+
+        Ok(GlobalStat {
+            download_speed: number(&result["downloadSpeed"]) as u64,
+            upload_speed: number(&result["uploadSpeed"]) as u64,
+            num_active: number(&result["numActive"]) as u32,
+            num_waiting: number(&result["numWaiting"]) as u32,
+            num_stopped: number(&result["numStopped"]) as u32,
+        })
+    }
+
+    /// Version string of the daemon
+    pub fn get_version(&self) -> anyhow::Result<String> {
+        let result = self.call("aria2.getVersion", vec![])?;
+        Ok(result["version"].as_str().unwrap_or("unknown").to_string())
+    }
+
+    /// Poll the daemon, reporting failures as an error only when it is expected to run
+    pub fn status(&self, expected_running: bool) -> EngineStatus {
+        let mut status = EngineStatus {
+            engine: "aria2".to_string(),
+            endpoint: self.url.clone(),
+            ..Default::default()
+        };
+
+        match self.get_global_stat().and_then(|stat| Ok((stat, self.get_version()?))) {
+            Ok((stat, version)) => {
+                status.running = true;
+                status.stat = stat;
+                status.version = Some(version);
+            }
+            Err(e) if expected_running => status.error = Some(e.to_string()),
+            Err(_) => {}
+        }
+
+        status
+    }
+}
+
+/// aria2 encodes numbers as strings
+fn number(value: &Value) -> f64 {
+    match value {
+        Value::String(s) => s.parse().unwrap_or(0.0),
+        Value::Number(n) => n.as_f64().unwrap_or(0.0),
+        _ => 0.0,
+    }
+}
+
+fn parse_status(value: &str) -> DownloadStatus {
+    match value {
+        "active" => DownloadStatus::Active,
+        "paused" => DownloadStatus::Paused,
+        "error" => DownloadStatus::Error,
+        "complete" => DownloadStatus::Complete,
+        "removed" => DownloadStatus::Removed,
+        _ => DownloadStatus::Waiting,
+    }
+}
+
+/// A download from the status structure returned by tellActive/tellWaiting/tellStopped
+fn parse_download(value: &Value) -> Download {
+    let files: Vec<String> = value["files"]
+        .as_array()
+        .map(|files| {
+            files
+                .iter()
+                .filter_map(|file| file["path"].as_str())
+                .filter(|path| !path.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let uris: Vec<String> = value["files"][0]["uris"]
+        .as_array()
+        .map(|uris| {
+            let mut uris: Vec<String> = uris
+                .iter()
+                .filter_map(|uri| uri["uri"].as_str())
+                .map(str::to_string)
+                .collect();
+            uris.dedup();
+            uris
+        })
+        .unwrap_or_default();
+    let gid = value["gid"].as_str().unwrap_or_default().to_string();
+    // Magnet links first download the metadata into a placeholder named after the info hash
+    let metadata = files.first().is_some_and(|path| path.starts_with(METADATA_PREFIX));
+
+    // Prefer the torrent name, then the file name, then the last URI segment
+    let name = value["bittorrent"]["info"]["name"]
+        .as_str()
+        .map(str::to_string)
+        .or_else(|| files.first().and_then(|path| path.strip_prefix(METADATA_PREFIX)).map(str::to_string))
+        .or_else(|| files.first().and_then(|path| file_name(path)))
+        .or_else(|| uris.first().and_then(|uri| file_name(uri.split(['?', '#']).next().unwrap_or(uri))))
+        .unwrap_or_else(|| gid.clone());
+
+    Download {
+        status: parse_status(value["status"].as_str().unwrap_or_default()),
+        name,
+        dir: value["dir"].as_str().unwrap_or_default().to_string(),
+        total_length: number(&value["totalLength"]) as u64,
+        completed_length: number(&value["completedLength"]) as u64,
+        download_speed: number(&value["downloadSpeed"]) as u64,
+        upload_speed: number(&value["uploadSpeed"]) as u64,
+        error_message: value["errorMessage"]
+            .as_str()
+            .filter(|message| !message.is_empty())
+            .map(str::to_string),
+        metadata,
+        followed_by: value["followedBy"][0].as_str().map(str::to_string),
+        files,
+        uris,
+        gid,
+    }
+}
+
+/// aria2 options of a new download
+fn add_options(request: &AddRequest) -> Value {
+    let mut options = serde_json::Map::new();
+    if let Some(ref dir) = request.dir {
+        options.insert("dir".to_string(), json!(dir));
+    }
+    if let Some(ref out) = request.out {
+        options.insert("out".to_string(), json!(out));
+    }
+    if !request.headers.is_empty() {
+        options.insert("header".to_string(), json!(request.headers));
+    }
+    for (key, value) in &request.options {
+        options.insert(key.clone(), json!(value));
+    }
+    Value::Object(options)
+}
+
+/// Fields requested from tellActive/tellWaiting/tellStopped
+const DOWNLOAD_KEYS: [&str; 11] = [
+    "gid",
+    "status",
+    "totalLength",
+    "completedLength",
+    "downloadSpeed",
+    "uploadSpeed",
+    "dir",
+    "files",
+    "bittorrent",
+    "errorMessage",
+    "followedBy",
+];
+
+/// Path aria2 gives the metadata download of a magnet link, followed by the info hash
+const METADATA_PREFIX: &str = "[METADATA]";
+
+impl Aria2Client {
+    /// Running downloads
+    pub fn tell_active(&self) -> anyhow::Result<Vec<Download>> {
+        let result = self.call("aria2.tellActive", vec![json!(DOWNLOAD_KEYS)])?;
+        Ok(downloads(&result))
+    }
+
+    /// Queued and paused downloads
+    pub fn tell_waiting(&self, offset: i64, count: u32) -> anyhow::Result<Vec<Download>> {
+        let result = self.call("aria2.tellWaiting", vec![json!(offset), json!(count), json!(DOWNLOAD_KEYS)])?;
+        Ok(downloads(&result))
+    }
+
+    /// Completed, failed and removed downloads still known to aria2
+    pub fn tell_stopped(&self, offset: i64, count: u32) -> anyhow::Result<Vec<Download>> {
+        let result = self.call("aria2.tellStopped", vec![json!(offset), json!(count), json!(DOWNLOAD_KEYS)])?;
+        Ok(downloads(&result))
+    }
+
+    /// Active, waiting and stopped downloads in that order
+    pub fn tell_all(&self) -> anyhow::Result<Vec<Download>> {
+        let mut all = self.tell_active()?;
+        all.extend(self.tell_waiting(0, 1000)?);
+        all.extend(self.tell_stopped(0, 1000)?);
+        Ok(all)
+    }
+
+    /// Add a download by URI, returning its GID
+    pub fn add_uri(&self, request: &AddRequest) -> anyhow::Result<String> {
+        let result = self.call("aria2.addUri", vec![json!(request.uris), add_options(request)])?;
+        Ok(result.as_str().unwrap_or_default().to_string())
+    }
+
+    /// Add a base64 encoded .torrent file, the request's URIs become web seeds
+    pub fn add_torrent(&self, torrent: &str, request: &AddRequest) -> anyhow::Result<String> {
+        let result = self.call("aria2.addTorrent", vec![json!(torrent), json!(request.uris), add_options(request)])?;
+        Ok(result.as_str().unwrap_or_default().to_string())
+    }
+
+    /// Add a base64 encoded Metalink document, returning the GID of its first file
+    pub fn add_metalink(&self, metalink: &str, request: &AddRequest) -> anyhow::Result<String> {
+        let result = self.call("aria2.addMetalink", vec![json!(metalink), add_options(request)])?;
+        result[0]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("The Metalink file lists no downloads"))
+    }
+
+    pub fn pause_all(&self) -> anyhow::Result<()> {
+        self.call("aria2.pauseAll", vec![])?;
+        Ok(())
+    }
+
+    pub fn unpause_all(&self) -> anyhow::Result<()> {
+        self.call("aria2.unpauseAll", vec![])?;
+        Ok(())
+    }
+
+    pub fn pause(&self, gid: &str) -> anyhow::Result<()> {
+        self.call("aria2.pause", vec![json!(gid)])?;
+        Ok(())
+    }
+
+    pub fn unpause(&self, gid: &str) -> anyhow::Result<()> {
+        self.call("aria2.unpause", vec![json!(gid)])?;
+        Ok(())
+    }
+
+    /// Remove an unfinished download, or forget a finished one
+    pub fn remove(&self, gid: &str) -> anyhow::Result<()> {
+        if self.call("aria2.remove", vec![json!(gid)]).is_err() {
+            self.call("aria2.removeDownloadResult", vec![json!(gid)])?;
+        }
+        Ok(())
+    }
+
+    /// Global download limit in bytes per second, 0 for unlimited
+    pub fn download_limit(&self) -> anyhow::Result<u64> {
+        let result = self.call("aria2.getGlobalOption", vec![])?;
+        let limit = result["max-overall-download-limit"].as_str().unwrap_or("0");
+        Ok(crate::modules::settings::parse_size(limit).unwrap_or(0))
+    }
+
+    /// Set the global download limit in bytes per second, 0 for unlimited
+    pub fn set_download_limit(&self, limit: u64) -> anyhow::Result<()> {
+        self.change_global_option(&[("max-overall-download-limit", limit.to_string())])
+    }
+
+    /// Apply the limits of a speed profile globally and to every unfinished download
+    pub fn apply_speed_profile(&self, profile: &SpeedProfile) -> anyhow::Result<()> {
+        self.change_global_option(&profile.global_options())?;
+
+        let mut unfinished = self.tell_active()?;
+        unfinished.extend(self.tell_waiting(0, 1000)?);
+        // A download that finished in the meantime shouldn't keep the others at the old speed
+        let options = profile.download_options();
+        let failed: Vec<String> = unfinished
+            .iter()
+            .filter_map(|download| {
+                self.change_option(&download.gid, &options)
+                    .err()
+                    .map(|e| format!("{}: {}", download.gid, e))
+            })
+            .collect();
+        if !failed.is_empty() {
+            anyhow::bail!("Failed to change {} download(s): {}", failed.len(), failed.join(", "));
+        }
+        Ok(())
+    }
+}
+
+fn downloads(result: &Value) -> Vec<Download> {
+    result
+        .as_array()
+        .map(|items| items.iter().map(parse_download).collect())
+        .unwrap_or_default()
+}
+
+fn file_name(path: &str) -> Option<String> {
+    path.rsplit(['/', '\\'])
+        .next()
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+use parking_lot::Mutex;
+use crate::modules::aria2::Aria2Daemon;
+use crate::modules::engine::{engine_for, engine_for_gid, video_engine, DownloadEngine};
+use crate::modules::feeds::FeedStore;
+use crate::modules::history::HistoryStore;
+use crate::modules::ipc::IpcCommand;
+use crate::modules::settings::SettingsStore;
+use super::{add_download_by_name, apply_bandwidth, broadcast, queue_batch, set_speed_profile, BackgroundTaskManager};
+
+/// Answers commands for the background task, whether they arrive over IPC or the HTTP API
+pub struct CommandHandler {
+    settings: Arc<SettingsStore>,
+    aria2: Arc<Aria2Daemon>,
+    manager: Arc<Mutex<BackgroundTaskManager>>,
+    /// Cleared when the background task is asked to stop
+    running: Arc<Mutex<bool>>,
+}
+
+impl CommandHandler {
+    pub fn new(
+        settings: Arc<SettingsStore>,
+        aria2: Arc<Aria2Daemon>,
+        manager: Arc<Mutex<BackgroundTaskManager>>,
+        running: Arc<Mutex<bool>>,
+    ) -> Self {
+        Self { settings, aria2, manager, running }
+    }
+
+    pub fn settings(&self) -> &Arc<SettingsStore> {
+        &self.settings
+    }
+
+    /// Handle a command, returning the response if it has one
+    pub fn handle(&self, command: IpcCommand) -> Option<IpcCommand> {
+        match command {
+            IpcCommand::StopBackground | IpcCommand::QuitAll => {
+                println!("Stopping background task...");
+                *self.running.lock() = false;
+                None
+            }
+            IpcCommand::Ping => {
+                let is_running = self.manager.lock().is_running();
+                Some(IpcCommand::BackgroundStatus(is_running))
+            }
+            IpcCommand::GetEngineStatus => {
+                let status = self.manager.lock().engine_status();
+                Some(IpcCommand::EngineStatus(status))
+            }
+            IpcCommand::GetTransfers => {
+                let transfers = self.manager.lock().transfers();
+                Some(IpcCommand::Transfers(transfers))
+            }
+            IpcCommand::PauseAll => {
+                let settings = self.settings.get();
+                let result = engine_for(&settings).pause_all().and_then(|_| video_engine(&settings).pause_all());
+                Some(respond(result.map(|_| IpcCommand::Ok)))
+            }
+            IpcCommand::ResumeAll => {
+                let settings = self.settings.get();
+                let result = engine_for(&settings).resume_all().and_then(|_| video_engine(&settings).resume_all());
+                Some(respond(result.map(|_| IpcCommand::Ok)))
+            }
+            IpcCommand::PauseDownload(gid) => {
+                let result = engine_for_gid(&self.settings.get(), &gid).pause(&gid);
+                Some(respond(result.map(|_| IpcCommand::Ok)))
+            }
+            IpcCommand::ResumeDownload(gid) => {
+                let result = engine_for_gid(&self.settings.get(), &gid).resume(&gid);
+                Some(respond(result.map(|_| IpcCommand::Ok)))
+            }
+            IpcCommand::RemoveDownload(gid) => {
+                let result = engine_for_gid(&self.settings.get(), &gid).remove(&gid);
+                Some(respond(result.map(|_| IpcCommand::Ok)))
+            }
+            IpcCommand::SetSpeedLimit(limit) => {
+                let settings = self.settings.get();
+                let result = engine_for(&settings)
+                    .set_download_limit(limit)
+                    .and_then(|_| video_engine(&settings).set_download_limit(limit));
+                Some(respond(result.map(|_| IpcCommand::Ok)))
+            }
+            IpcCommand::SetSpeedProfile(name) => {
+                let result = set_speed_profile(&self.settings, &name);
+                Some(respond(result.map(IpcCommand::Settings)))
+            }
+            IpcCommand::AddDownload(request) => {
+                let result = add_download_by_name(&self.settings.get(), request);
+                Some(respond(result.map(IpcCommand::DownloadAdded)))
+            }
+            IpcCommand::AddBatch(requests, shared) => {
+                let result = queue_batch(&self.settings.get(), requests, shared);
+                Some(respond(result.map(IpcCommand::BatchQueued)))
+            }
+            IpcCommand::GetHistory => {
+                Some(IpcCommand::History(HistoryStore::open().load()))
+            }
+            IpcCommand::GetFeeds => {
+                Some(IpcCommand::Feeds(FeedStore::open().load()))
+            }
+            IpcCommand::RefreshFeeds => {
+                self.manager.lock().refresh_feeds();
+                Some(IpcCommand::Ok)
+            }
+            IpcCommand::GetSettings => {
+                Some(IpcCommand::Settings(self.settings.get()))
+            }
+            IpcCommand::UpdateSettings(new_settings) => {
+                let old_settings = self.settings.get();
+                match self.settings.update(new_settings.clone()) {
+                    Ok(()) => {
+                        broadcast(IpcCommand::Settings(new_settings.clone()));
+                        match self.aria2.apply_live(&old_settings, &new_settings) {
+                            Ok(false) => {
+                                apply_bandwidth(&old_settings, &new_settings);
+                                Some(IpcCommand::Settings(new_settings))
+                            }
+                            // Restarting can take longer than the client waits for the answer
+                            Ok(true) => {
+                                let aria2 = self.aria2.clone();
+                                let settings = new_settings.clone();
+                                std::thread::spawn(move || {
+                                    match aria2.restart(&old_settings, &settings) {
+                                        Ok(()) => println!("Restarted aria2 with the new settings"),
+                                        Err(e) => println!("Failed to restart aria2: {}", e),
+                                    }
+                                    apply_bandwidth(&old_settings, &settings);
+                                });
+                                Some(IpcCommand::Settings(new_settings))
+                            }
+                            Err(e) => {
+                                apply_bandwidth(&old_settings, &new_settings);
+                                Some(IpcCommand::Error(format!("Settings saved, but aria2 couldn't apply them: {}", e)))
+                            }
+                        }
+                    }
+                    Err(e) => Some(IpcCommand::Error(e.to_string())),
+                }
+            }
+            IpcCommand::StartAria2 => {
+                match self.aria2.start(&self.settings.get()) {
+                    Ok(()) => Some(IpcCommand::Aria2Running(true)),
+                    Err(e) => Some(IpcCommand::Error(e.to_string())),
+                }
+            }
+            IpcCommand::StopAria2 => {
+                self.aria2.stop(&self.settings.get());
+                Some(IpcCommand::Aria2Running(false))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Turn a failed request into an error response
+fn respond(result: anyhow::Result<IpcCommand>) -> IpcCommand {
+    result.unwrap_or_else(|e| IpcCommand::Error(e.to_string()))
+}
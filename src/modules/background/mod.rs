@@ -1,19 +1,53 @@
+mod handler;
+mod scheduler;
+
+pub use handler::CommandHandler;
+pub use scheduler::Scheduler;
+
+use std::collections::HashMap;
 use std::sync::Arc;
 use parking_lot::RwLock;
 use tokio::task::JoinHandle;
+use crate::modules::actions::{run_actions, verify_expected, ActionOutcome, CompletedDownload};
+use crate::modules::checksum::{ChecksumSource, ChecksumStore, Verification};
+use crate::modules::aria2::Aria2Daemon;
+use crate::modules::batch::{BatchResult, SharedOptions};
+use crate::modules::feeds::FeedPoller;
+use crate::modules::engine::{engine_for, is_video_request, video_engine, AddRequest, Download, DownloadEngine, DownloadStatus, EngineStatus, TransferSnapshot};
+use crate::modules::history::{DownloadTracker, FinishedDownload, HistoryEntry, HistoryStore};
+use crate::modules::ipc::{IpcClient, IpcCommand, TRAY_IPC_PORT, UI_IPC_PORT};
+use crate::modules::settings::{EngineKind, PostAction, Settings, SettingsStore};
+use std::path::Path;
 
 /// Background task manager that runs tasks independently of the UI
 pub struct BackgroundTaskManager {
     is_running: Arc<RwLock<bool>>,
     task_handle: Option<JoinHandle<()>>,
+    settings: Arc<SettingsStore>,
+    aria2: Arc<Aria2Daemon>,
+    engine_status: Arc<RwLock<EngineStatus>>,
+    transfers: Arc<RwLock<TransferSnapshot>>,
+    /// Results of post-download actions by GID
+    action_results: Arc<RwLock<HashMap<String, Vec<ActionOutcome>>>>,
+    /// Checksum results of downloads added with an expected checksum, by GID
+    verifications: Arc<RwLock<HashMap<String, Verification>>>,
+    /// Set to check every feed on the next round of the task loop
+    refresh_feeds: Arc<RwLock<bool>>,
 }
 
 impl BackgroundTaskManager {
     /// Create a new background task manager
-    pub fn new() -> Self {
+    pub fn new(settings: Arc<SettingsStore>, aria2: Arc<Aria2Daemon>) -> Self {
         Self {
             is_running: Arc::new(RwLock::new(false)),
             task_handle: None,
+            settings,
+            aria2,
+            engine_status: Arc::new(RwLock::new(EngineStatus::default())),
+            transfers: Arc::new(RwLock::new(TransferSnapshot::default())),
+            action_results: Arc::new(RwLock::new(HashMap::new())),
+            verifications: Arc::new(RwLock::new(HashMap::new())),
+            refresh_feeds: Arc::new(RwLock::new(false)),
         }
     }
 
@@ -23,19 +57,119 @@ impl BackgroundTaskManager {
         *is_running.write() = true;
 
         let is_running_clone = is_running.clone();
+        let settings = self.settings.clone();
+        let aria2 = self.aria2.clone();
+        let engine_status = self.engine_status.clone();
+        let transfers = self.transfers.clone();
+        let action_results = self.action_results.clone();
+        let verifications = self.verifications.clone();
+        let refresh_feeds = self.refresh_feeds.clone();
         let handle = tokio::spawn(async move {
             println!("Background task started");
+            let mut scheduler = Scheduler::new();
+            let mut tracker = DownloadTracker::new();
+            let mut video_tracker = DownloadTracker::new();
+            let mut polled_endpoint = String::new();
+            let feed_poller = Arc::new(parking_lot::Mutex::new(FeedPoller::open()));
+            let mut feed_check: Option<JoinHandle<()>> = None;
             
             while *is_running_clone.read() {
-                // Perform background work here
-                // Example: periodic checks, data syncing, monitoring, etc.
+                // Pick up edits made to the settings file by hand
+                let old_settings = settings.get();
+                if let Some(new_settings) = settings.reload_if_changed() {
+                    println!("Settings file changed, reloaded");
+                    if let Err(e) = aria2.apply_settings(&old_settings, &new_settings) {
+                        println!("Failed to apply aria2 settings: {}", e);
+                    }
+                    apply_bandwidth(&old_settings, &new_settings);
+                    broadcast(IpcCommand::Settings(new_settings));
+                }
                 
-                // Simulate some background work
-                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                // Start, pause or throttle the queue when a schedule window begins or ends
+                let current = settings.get();
+                if let Some((action, reason)) = scheduler.pending(&current, chrono::Local::now().naive_local()) {
+                    let engine = engine_for(&current);
+                    let video = video_engine(&current);
+                    let profile = current.bandwidth.active();
+                    let paused = scheduler.paused().to_vec();
+                    let apply = move || {
+                        let mut stopped = scheduler::apply_action(engine.as_ref(), action, &profile, &paused)?;
+                        stopped.extend(scheduler::apply_action(video.as_ref(), action, &profile, &paused)?);
+                        anyhow::Ok(stopped)
+                    };
+                    match tokio::task::spawn_blocking(apply).await {
+                        Ok(Ok(stopped)) => {
+                            println!("{}", reason);
+                            scheduler.mark_applied(&current, action, stopped);
+                        }
+                        Ok(Err(e)) => println!("Failed to apply schedule: {}", e),
+                        Err(e) => println!("Failed to apply schedule: {}", e),
+                    }
+                }
+                
+                // Check the feeds that are due, one round at a time since fetching can be slow
+                let feeds_idle = feed_check.as_ref().is_none_or(|check| check.is_finished());
+                if feeds_idle {
+                    let force = std::mem::take(&mut *refresh_feeds.write());
+                    if force || !feed_poller.lock().due(&current, false).is_empty() {
+                        let poller = feed_poller.clone();
+                        let feed_settings = current.clone();
+                        feed_check = Some(tokio::task::spawn_blocking(move || poller.lock().check_due(&feed_settings, force)));
+                    }
+                }
                 
-                if *is_running_clone.read() {
-                    println!("Background task running... (heartbeat)");
+                // Poll the active engine without blocking the runtime.
+                // Remote endpoints are always expected to be up.
+                let engine = engine_for(&current);
+                let video = video_engine(&current);
+                let expected_running = !current.active_endpoint().managed || aria2.is_running();
+                let polled = tokio::task::spawn_blocking(move || (poll(engine.as_ref(), expected_running), video.list())).await;
+                if let Ok(((status, listed), videos)) = polled {
+                    *engine_status.write() = status;
+                    
+                    // Another endpoint has its own finished downloads, don't record those again
+                    let endpoint = engine_key(&current);
+                    if polled_endpoint != endpoint {
+                        polled_endpoint = endpoint;
+                        tracker = DownloadTracker::new();
+                    }
+                    // Downloads of an unreachable daemon or a failed call are unknown, not removed
+                    let mut finished = listed.as_ref().map(|snapshot| tracker.update(&snapshot.downloads)).unwrap_or_default();
+                    let complete = listed.is_some() && videos.is_ok();
+                    let mut snapshot = listed.unwrap_or_default();
+                    
+                    // yt-dlp jobs are listed next to the engine's downloads
+                    if let Ok(ref videos) = videos {
+                        finished.extend(video_tracker.update(videos));
+                    }
+                    add_videos(&mut snapshot, videos.unwrap_or_default());
+                    for finished in finished {
+                        if finished.download.is_replaced() {
+                            hand_over(&finished.download);
+                            continue;
+                        }
+                        if finished.download.status == DownloadStatus::Complete {
+                            spawn_actions(&current, finished, action_results.clone(), verifications.clone());
+                        } else {
+                            record_history(&current, finished);
+                        }
+                    }
+                    if complete {
+                        let known = |gid: &String| snapshot.downloads.iter().any(|download| download.gid == *gid);
+                        action_results.write().retain(|gid, _| known(gid));
+                        verifications.write().retain(|gid, _| known(gid));
+                    }
+                    snapshot.actions = action_results.read().clone();
+                    snapshot.verifications = verifications.read().clone();
+                    
+                    // Push changes to the tray so its menu follows the queue
+                    if *transfers.read() != snapshot {
+                        *transfers.write() = snapshot.clone();
+                        notify_tray(IpcCommand::Transfers(snapshot));
+                    }
                 }
+                
+                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
             }
             
             println!("Background task stopped");
@@ -53,6 +187,21 @@ impl BackgroundTaskManager {
         }
     }
 
+    /// Latest engine status polled by the task loop
+    pub fn engine_status(&self) -> EngineStatus {
+        self.engine_status.read().clone()
+    }
+
+    /// Latest downloads polled by the task loop
+    pub fn transfers(&self) -> TransferSnapshot {
+        self.transfers.read().clone()
+    }
+
+    /// Check every feed on the next round instead of waiting for the interval
+    pub fn refresh_feeds(&self) {
+        *self.refresh_feeds.write() = true;
+    }
+
     /// Check if background tasks are running
     #[allow(dead_code)]
     pub fn is_running(&self) -> bool {
@@ -62,6 +211,277 @@ impl BackgroundTaskManager {
 
 impl Default for BackgroundTaskManager {
     fn default() -> Self {
-        Self::new()
+        Self::new(Arc::new(SettingsStore::open()), Arc::new(Aria2Daemon::new()))
+    }
+}
+
+/// Verify a completed download and run its post-download actions off the task loop,
+/// then record it in the history
+fn spawn_actions(
+    settings: &Settings,
+    finished: FinishedDownload,
+    results: Arc<RwLock<HashMap<String, Vec<ActionOutcome>>>>,
+    verifications: Arc<RwLock<HashMap<String, Verification>>>,
+) {
+    let mut completed = CompletedDownload::new(settings, &finished.download);
+    completed.started_at = finished.started_at;
+    let mut actions = settings
+        .actions
+        .for_category(completed.category.as_deref(), &settings.downloads.download_dir);
+    let stop_on_failure = settings.actions.stop_on_failure;
+
+    tokio::task::spawn_blocking(move || {
+        let mut outcomes = Vec::new();
+        let mut verification = None;
+
+        // A checksum given when adding replaces the sidecar lookup of the verify action
+        if let Some(source) = ChecksumStore::open().take(&completed.gid) {
+            actions.retain(|action| *action != PostAction::VerifyChecksum);
+            verifications.write().insert(completed.gid.clone(), Verification::Verifying);
+            let (result, outcome) = verify_expected(&completed, &source);
+            verifications.write().insert(completed.gid.clone(), result.clone());
+            if !outcome.success && stop_on_failure {
+                actions.clear();
+            }
+            outcomes.push(outcome);
+            verification = Some(result);
+        }
+
+        outcomes.extend(run_actions(&actions, &mut completed, stop_on_failure));
+        if !outcomes.is_empty() {
+            results.write().insert(completed.gid.clone(), outcomes.clone());
+        }
+        let mut entry = HistoryEntry::new(&completed, outcomes);
+        entry.verification = verification;
+        if let Err(e) = HistoryStore::open().append(&entry) {
+            println!("Failed to record {} in the history: {}", completed.name, e);
+        }
+    });
+}
+
+/// Move the checksum of a magnet link's metadata download to the download that
+/// replaced it. The metadata itself isn't recorded in the history.
+fn hand_over(metadata: &Download) {
+    let Some(ref gid) = metadata.followed_by else {
+        return;
+    };
+    let store = ChecksumStore::open();
+    if let Some(source) = store.take(&metadata.gid)
+        && let Err(e) = store.insert(gid, source)
+    {
+        println!("Failed to remember the checksum of {}: {}", gid, e);
+    }
+}
+
+/// Record a failed or removed download in the history
+fn record_history(settings: &Settings, finished: FinishedDownload) {
+    let mut download = CompletedDownload::new(settings, &finished.download);
+    download.started_at = finished.started_at;
+    if let Err(e) = HistoryStore::open().append(&HistoryEntry::new(&download, Vec::new())) {
+        println!("Failed to record {} in the history: {}", download.name, e);
+    }
+}
+
+/// Add a download on the active endpoint, or to yt-dlp for video pages. The category's
+/// folder and options are applied, and the directory is translated for remote endpoints.
+/// URLs the category rules can't place by name are sent a HEAD request for their
+/// content type, which can take seconds.
+pub fn add_download(settings: &Settings, request: AddRequest) -> anyhow::Result<String> {
+    add_request(settings, request, true)
+}
+
+/// `add_download` for the IPC server, which has to answer before the client gives up.
+/// Categories are assigned by URL and file name only.
+pub fn add_download_by_name(settings: &Settings, request: AddRequest) -> anyhow::Result<String> {
+    add_request(settings, request, false)
+}
+
+/// Start adding the downloads of a batch import on a thread of their own, returning
+/// how many there are. The outcome is broadcast as `BatchAdded` once all were tried.
+pub fn queue_batch(settings: &Settings, requests: Vec<AddRequest>, shared: SharedOptions) -> anyhow::Result<usize> {
+    if let Some(ref name) = shared.category
+        && settings.categories.find(name).is_none()
+    {
+        anyhow::bail!("Category {} doesn't exist", name);
+    }
+
+    let count = requests.len();
+    let settings = settings.clone();
+    std::thread::spawn(move || {
+        let result = add_batch(&settings, requests, &shared);
+        broadcast(IpcCommand::BatchAdded(result));
+    });
+    Ok(count)
+}
+
+/// Add the downloads of a batch import one after another with their shared options.
+/// They're assigned to categories by URL only, asking every server for the content
+/// type would take too long for long lists.
+pub fn add_batch(settings: &Settings, requests: Vec<AddRequest>, shared: &SharedOptions) -> BatchResult {
+    let mut result = BatchResult::default();
+    for mut request in requests {
+        shared.apply(&mut request);
+        let uri = request.uris.first().cloned().unwrap_or_default();
+        match add_request(settings, request, false) {
+            Ok(gid) => result.added.push(gid),
+            Err(e) => {
+                println!("Failed to add {}: {}", uri, e);
+                result.failed.push((uri, e.to_string()));
+            }
+        }
     }
+    println!("Batch import: {} added, {} failed", result.added.len(), result.failed.len());
+    result
+}
+
+/// `add_download`, `probe` allows a HEAD request for the content type of URLs the
+/// category rules can't place by name
+fn add_request(settings: &Settings, mut request: AddRequest, probe: bool) -> anyhow::Result<String> {
+    let categories = &settings.categories;
+    let category = match request.category {
+        Some(ref name) => Some(
+            categories
+                .find(name)
+                .ok_or_else(|| anyhow::anyhow!("Category {} doesn't exist", name))?,
+        ),
+        None if categories.auto_assign => request.uris.first().and_then(|url| {
+            categories
+                .classify(url, request.out.as_deref(), None)
+                .or_else(|| categories.classify(url, request.out.as_deref(), probe.then(|| probe_mime(url)).flatten().as_deref()))
+        }),
+        None => None,
+    };
+    // Parsed before adding so a typo doesn't leave an unverifiable download behind
+    let checksum = request.checksum.as_deref().map(ChecksumSource::parse).transpose()?;
+
+    if let Some(category) = category {
+        if request.dir.is_none() {
+            request.dir = Some(settings.category_dir(category).display().to_string());
+        }
+        for (key, value) in category.options() {
+            if !request.options.iter().any(|(existing, _)| existing == key) {
+                request.options.push((key.to_string(), value));
+            }
+        }
+        request.category = Some(category.name.clone());
+    }
+
+    // yt-dlp runs on this machine, only remote aria2 endpoints need their own paths
+    let is_video = is_video_request(settings, &request);
+    let endpoint = settings.active_endpoint();
+    if !is_video
+        && !endpoint.managed
+        && let Some(ref dir) = request.dir
+        && let Some(remote) = endpoint.to_remote(Path::new(dir))
+    {
+        request.dir = Some(remote);
+    }
+
+    let engine: Arc<dyn DownloadEngine> = if is_video { video_engine(settings) } else { engine_for(settings) };
+    let gid = engine.add(&request)?;
+    if let Some(checksum) = checksum
+        && let Err(e) = ChecksumStore::open().insert(&gid, checksum)
+    {
+        println!("Failed to remember the checksum of {}: {}", gid, e);
+    }
+    Ok(gid)
+}
+
+/// Content type of a URL from a HEAD request, None if it can't be determined quickly
+fn probe_mime(url: &str) -> Option<String> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return None;
+    }
+
+    let response = ureq::head(url)
+        .timeout(std::time::Duration::from_secs(5))
+        .call()
+        .ok()?;
+    response.header("Content-Type").map(str::to_string)
+}
+
+/// Switch to another speed profile, returning the saved settings
+pub fn set_speed_profile(store: &SettingsStore, name: &str) -> anyhow::Result<Settings> {
+    let old_settings = store.get();
+    let mut new_settings = old_settings.clone();
+    new_settings.bandwidth.active_profile = name.to_string();
+    store.update(new_settings.clone())?;
+
+    apply_bandwidth(&old_settings, &new_settings);
+    broadcast(IpcCommand::Settings(new_settings.clone()));
+    Ok(new_settings)
+}
+
+/// Apply the active speed profile when it or the active engine changed
+pub fn apply_bandwidth(old: &Settings, new: &Settings) {
+    let profile = new.bandwidth.active();
+    if old.bandwidth.active() == profile && engine_key(old) == engine_key(new) {
+        return;
+    }
+
+    let engine = engine_for(new);
+    let video = video_engine(new);
+    let mut result = engine
+        .apply_speed_profile(&profile)
+        .and_then(|_| video.apply_speed_profile(&profile));
+    // A schedule window throttling the queue keeps its limit until it ends
+    if let Some(limit) = scheduler::scheduled_limit(new, chrono::Local::now().naive_local()) {
+        let limited = engine.set_download_limit(limit).and_then(|_| video.set_download_limit(limit));
+        result = result.and(limited);
+    }
+    match result {
+        Ok(()) => println!("Applied speed profile {}", profile.name),
+        Err(e) => println!("Failed to apply speed profile {}: {}", profile.name, e),
+    }
+}
+
+/// Which engine or aria2 endpoint the settings point at
+pub fn engine_key(settings: &Settings) -> String {
+    match settings.engine.kind {
+        EngineKind::Aria2 => settings.aria2.active_endpoint.clone(),
+        EngineKind::Native => EngineKind::Native.label().to_string(),
+    }
+}
+
+/// Poll status and queue of an engine, no snapshot when the queue couldn't be listed
+fn poll(engine: &dyn DownloadEngine, expected_running: bool) -> (EngineStatus, Option<TransferSnapshot>) {
+    let status = engine.status(expected_running);
+    if !status.running {
+        return (status, None);
+    }
+
+    // A failed call lists no downloads, that doesn't mean they were removed
+    let snapshot = engine.list().ok().map(|downloads| TransferSnapshot {
+        stat: status.stat.clone(),
+        downloads,
+        download_limit: engine.download_limit().unwrap_or(0),
+        ..Default::default()
+    });
+    (status, snapshot)
+}
+
+/// Append yt-dlp jobs to a snapshot of the engine's downloads
+fn add_videos(snapshot: &mut TransferSnapshot, videos: Vec<Download>) {
+    for video in &videos {
+        match video.status {
+            DownloadStatus::Active => {
+                snapshot.stat.num_active += 1;
+                snapshot.stat.download_speed += video.download_speed;
+            }
+            DownloadStatus::Waiting | DownloadStatus::Paused => snapshot.stat.num_waiting += 1,
+            _ => snapshot.stat.num_stopped += 1,
+        }
+    }
+    snapshot.downloads.extend(videos);
+}
+
+/// Notify the UI and tray processes, ignoring the ones that aren't running
+pub fn broadcast(command: IpcCommand) {
+    let _ = IpcClient::new(UI_IPC_PORT).send(command.clone());
+    notify_tray(command);
+}
+
+/// Notify the tray process, ignoring the error if it isn't running
+pub fn notify_tray(command: IpcCommand) {
+    let _ = IpcClient::new(TRAY_IPC_PORT).send(command);
 }
@@ -0,0 +1,216 @@
+use crate::modules::engine::{DownloadEngine, DownloadStatus};
+use crate::modules::settings::{Day, ScheduleAction, Settings, SpeedProfile};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+/// Tracks which schedule action was last applied, so the queue is only
+/// touched when a window starts or ends. Changes made by hand inside a
+/// window are left alone until the next transition.
+#[derive(Default)]
+pub struct Scheduler {
+    /// Engine or endpoint and the action applied last
+    applied: Option<(String, ScheduleAction)>,
+    /// Downloads a pause window stopped, the only ones resumed when it ends
+    paused: Vec<String>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The action to apply now and a description of why, or None when the
+    /// schedule is disabled or the action is already applied. Turning the schedule
+    /// off runs the queue once, resuming what a window paused and dropping its limit.
+    pub fn pending(&self, settings: &Settings, now: NaiveDateTime) -> Option<(ScheduleAction, String)> {
+        if !settings.schedule.enabled {
+            return self
+                .applied
+                .is_some()
+                .then(|| (ScheduleAction::Run, "Schedule turned off: run with the speed profile".to_string()));
+        }
+
+        let day = Day::from_index(now.weekday().num_days_from_monday());
+        let minute = now.hour() * 60 + now.minute();
+        let (action, window) = settings.schedule.action_at(day, minute);
+
+        let key = (super::engine_key(settings), action);
+        if self.applied.as_ref() == Some(&key) {
+            return None;
+        }
+
+        let reason = match window {
+            Some(window) => format!("Schedule window {}: {}", window.name, action.describe()),
+            None => format!("Outside schedule windows: {}", action.describe()),
+        };
+        Some((action, reason))
+    }
+
+    /// Downloads paused by the schedule that are still waiting to be resumed
+    pub fn paused(&self) -> &[String] {
+        &self.paused
+    }
+
+    /// Remember that `action` was applied on the active engine, pausing `paused`
+    pub fn mark_applied(&mut self, settings: &Settings, action: ScheduleAction, paused: Vec<String>) {
+        if !settings.schedule.enabled {
+            self.applied = None;
+            self.paused.clear();
+            return;
+        }
+        self.applied = Some((super::engine_key(settings), action));
+        match action {
+            ScheduleAction::Pause => self.paused.extend(paused),
+            ScheduleAction::Run | ScheduleAction::Limit(_) => self.paused.clear(),
+        }
+    }
+}
+
+/// Limit of the schedule window active at `now`, which wins over the speed profile
+pub fn scheduled_limit(settings: &Settings, now: NaiveDateTime) -> Option<u64> {
+    if !settings.schedule.enabled {
+        return None;
+    }
+    let day = Day::from_index(now.weekday().num_days_from_monday());
+    match settings.schedule.action_at(day, now.hour() * 60 + now.minute()).0 {
+        ScheduleAction::Limit(limit) => Some(limit),
+        _ => None,
+    }
+}
+
+/// Start, pause or throttle the queue, returning the downloads it paused. Running
+/// restores the limit of the speed profile and resumes only the downloads `paused`
+/// by an earlier window, the ones paused by hand stay paused.
+pub fn apply_action(
+    engine: &dyn DownloadEngine,
+    action: ScheduleAction,
+    profile: &SpeedProfile,
+    paused: &[String],
+) -> anyhow::Result<Vec<String>> {
+    match action {
+        ScheduleAction::Run => {
+            engine.set_download_limit(profile.download_limit)?;
+            resume(engine, paused)?;
+        }
+        ScheduleAction::Pause => {
+            let running = engine
+                .list()?
+                .into_iter()
+                .filter(|download| matches!(download.status, DownloadStatus::Active | DownloadStatus::Waiting))
+                .map(|download| download.gid)
+                .collect();
+            engine.pause_all()?;
+            return Ok(running);
+        }
+        ScheduleAction::Limit(limit) => {
+            engine.set_download_limit(limit)?;
+            resume(engine, paused)?;
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Resume the downloads of this engine among `paused` that are still paused
+fn resume(engine: &dyn DownloadEngine, paused: &[String]) -> anyhow::Result<()> {
+    for download in engine.list()? {
+        if download.status == DownloadStatus::Paused && paused.contains(&download.gid) {
+            engine.resume(&download.gid)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::settings::ScheduleWindow;
+    use chrono::NaiveDate;
+
+    /// 2024-01-01 is a Monday
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn settings() -> Settings {
+        let mut settings = Settings::default();
+        settings.schedule.enabled = true;
+        settings.schedule.windows = vec![ScheduleWindow {
+            name: "Night".to_string(),
+            start: "22:00".to_string(),
+            end: "06:00".to_string(),
+            action: ScheduleAction::Pause,
+            ..Default::default()
+        }];
+        settings
+    }
+
+    #[test]
+    fn applies_each_action_once() {
+        let settings = settings();
+        let mut scheduler = Scheduler::new();
+
+        let (action, reason) = scheduler.pending(&settings, at(1, 12, 0)).unwrap();
+        assert_eq!(action, ScheduleAction::Run);
+        assert!(reason.starts_with("Outside schedule windows"));
+        scheduler.mark_applied(&settings, action, Vec::new());
+        assert_eq!(scheduler.pending(&settings, at(1, 13, 0)), None);
+
+        let (action, reason) = scheduler.pending(&settings, at(1, 22, 0)).unwrap();
+        assert_eq!(action, ScheduleAction::Pause);
+        assert_eq!(reason, "Schedule window Night: pause");
+    }
+
+    #[test]
+    fn remembers_paused_downloads_until_the_window_ends() {
+        let settings = settings();
+        let mut scheduler = Scheduler::new();
+        scheduler.mark_applied(&settings, ScheduleAction::Pause, vec!["a".to_string()]);
+        scheduler.mark_applied(&settings, ScheduleAction::Pause, vec!["b".to_string()]);
+        assert_eq!(scheduler.paused(), ["a", "b"]);
+
+        // Not applied until the engine accepted it, so a failed attempt is retried
+        assert_eq!(scheduler.pending(&settings, at(2, 7, 0)).map(|(action, _)| action), Some(ScheduleAction::Run));
+        assert_eq!(scheduler.paused(), ["a", "b"]);
+        scheduler.mark_applied(&settings, ScheduleAction::Run, Vec::new());
+        assert!(scheduler.paused().is_empty());
+    }
+
+    #[test]
+    fn turning_the_schedule_off_runs_once() {
+        let mut settings = settings();
+        let mut scheduler = Scheduler::new();
+        scheduler.mark_applied(&settings, ScheduleAction::Pause, vec!["a".to_string()]);
+
+        settings.schedule.enabled = false;
+        let (action, _) = scheduler.pending(&settings, at(1, 23, 0)).unwrap();
+        assert_eq!(action, ScheduleAction::Run);
+        assert_eq!(scheduler.paused(), ["a"]);
+
+        scheduler.mark_applied(&settings, action, Vec::new());
+        assert!(scheduler.paused().is_empty());
+        assert_eq!(scheduler.pending(&settings, at(1, 23, 1)), None);
+
+        // Never enabled, nothing to undo
+        assert_eq!(Scheduler::new().pending(&settings, at(1, 23, 0)), None);
+    }
+
+    #[test]
+    fn switching_engines_applies_the_action_again() {
+        let mut settings = settings();
+        let mut scheduler = Scheduler::new();
+        scheduler.mark_applied(&settings, ScheduleAction::Run, Vec::new());
+        assert_eq!(scheduler.pending(&settings, at(1, 12, 0)), None);
+
+        settings.aria2.active_endpoint = "remote".to_string();
+        assert!(scheduler.pending(&settings, at(1, 12, 0)).is_some());
+    }
+
+    #[test]
+    fn limits_only_inside_limit_windows() {
+        let mut settings = settings();
+        settings.schedule.windows[0].action = ScheduleAction::Limit(1024);
+        assert_eq!(scheduled_limit(&settings, at(1, 23, 0)), Some(1024));
+        assert_eq!(scheduled_limit(&settings, at(1, 12, 0)), None);
+        settings.schedule.enabled = false;
+        assert_eq!(scheduled_limit(&settings, at(1, 23, 0)), None);
+    }
+}
@@ -0,0 +1,379 @@
+use crate::modules::engine::AddRequest;
+use crate::modules::url_list::parse_url_list;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Most downloads a single import may add
+pub const MAX_BATCH: usize = 10_000;
+
+/// URLs inside free text, up to the next whitespace or quote
+static URL_PATTERN: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r#"(?i)\b(?:(?:https?|ftps?|sftp)://|magnet:\?)[^\s<>"'`]+"#).unwrap()
+});
+
+/// Options given once for every download of a batch. Whatever an entry sets itself,
+/// such as `dir` in a URL list, wins.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SharedOptions {
+    pub dir: Option<String>,
+    pub category: Option<String>,
+    pub headers: Vec<String>,
+    /// aria2 style options such as split
+    pub options: Vec<(String, String)>,
+}
+
+impl SharedOptions {
+    pub fn apply(&self, request: &mut AddRequest) {
+        if request.dir.is_none() {
+            request.dir = self.dir.clone();
+        }
+        if request.category.is_none() {
+            request.category = self.category.clone();
+        }
+        request.headers.extend(self.headers.iter().cloned());
+        for (key, value) in &self.options {
+            if !request.options.iter().any(|(existing, _)| existing == key) {
+                request.options.push((key.clone(), value.clone()));
+            }
+        }
+    }
+}
+
+/// Outcome of adding a batch, in the order of the entries
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// GIDs of the added downloads
+    pub added: Vec<String>,
+    /// URL and error of the entries that couldn't be added
+    pub failed: Vec<(String, String)>,
+}
+
+/// Text to import from a batch source: the contents of a file when the source is the
+/// path of one, the source itself otherwise
+pub fn read_source(source: &str) -> anyhow::Result<String> {
+    let trimmed = source.trim();
+    let path = Path::new(trimmed);
+    if !trimmed.is_empty() && !trimmed.contains('\n') && !trimmed.contains("://") && path.is_file() {
+        return std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", trimmed, e));
+    }
+    Ok(source.to_string())
+}
+
+/// Downloads described by pasted text or a file. aria2 input files keep their mirrors
+/// and options, any other text is searched for URLs. Patterns such as `part[001-120].rar`
+/// are expanded, and repeated URLs are only added once.
+pub fn batch_requests(text: &str) -> anyhow::Result<Vec<AddRequest>> {
+    let requests = match parse_url_list(text) {
+        Ok(requests) => requests,
+        Err(_) => extract_urls(text).into_iter().map(AddRequest::new).collect(),
+    };
+
+    let mut expanded = Vec::new();
+    for request in requests {
+        expanded.extend(expand_request(request)?);
+        if expanded.len() > MAX_BATCH {
+            anyhow::bail!("The import expands to more than {} downloads", MAX_BATCH);
+        }
+    }
+    let requests = dedupe(expanded);
+    if requests.is_empty() {
+        anyhow::bail!("No URLs found");
+    }
+    Ok(requests)
+}
+
+/// Drop the requests whose first URL came earlier, keeping the order
+pub fn dedupe(requests: Vec<AddRequest>) -> Vec<AddRequest> {
+    let mut seen = HashSet::new();
+    requests
+        .into_iter()
+        .filter(|request| request.uris.first().is_none_or(|uri| seen.insert(uri.clone())))
+        .collect()
+}
+
+/// URLs found anywhere in the text, in order, without the punctuation of the
+/// sentence around them
+pub fn extract_urls(text: &str) -> Vec<String> {
+    URL_PATTERN
+        .find_iter(text)
+        .map(|found| trim_url(found.as_str()).to_string())
+        .filter(|url| !url.ends_with("://") && !url.ends_with(":?"))
+        .collect()
+}
+
+fn trim_url(mut url: &str) -> &str {
+    while let Some(last) = url.chars().last() {
+        let unbalanced = |open: char| url.matches(last).count() > url.matches(open).count();
+        let strip = match last {
+            '.' | ',' | ';' | ':' | '!' | '?' => true,
+            ')' => unbalanced('('),
+            ']' => unbalanced('['),
+            '}' => unbalanced('{'),
+            _ => false,
+        };
+        if !strip {
+            break;
+        }
+        url = &url[..url.len() - last.len_utf8()];
+    }
+    url
+}
+
+/// Every URL a pattern stands for. `[001-120]` counts with the zero padding of the
+/// start, `[a-z]` runs through letters, `[0-100:5]` counts in steps of 5 and
+/// `{iso,sig}` picks each alternative. Several patterns give every combination.
+/// Brackets that aren't a range, like an IPv6 host, are kept as they are.
+pub fn expand_pattern(pattern: &str) -> anyhow::Result<Vec<String>> {
+    let mut urls = vec![String::new()];
+    let mut rest = pattern;
+
+    while !rest.is_empty() {
+        let group = rest
+            .find(['[', '{'])
+            .and_then(|start| {
+                let close = if rest[start..].starts_with('[') { ']' } else { '}' };
+                rest[start..].find(close).map(|length| (start, start + length))
+            });
+        let Some((start, end)) = group else {
+            urls.iter_mut().for_each(|url| url.push_str(rest));
+            break;
+        };
+
+        let inner = &rest[start + 1..end];
+        let values = if rest[start..].starts_with('[') {
+            range(inner)?
+        } else {
+            Some(inner.split(',').map(str::to_string).collect::<Vec<_>>()).filter(|values| values.len() > 1)
+        };
+        let Some(values) = values else {
+            // Not a pattern, keep the opening bracket and look further
+            urls.iter_mut().for_each(|url| url.push_str(&rest[..=start]));
+            rest = &rest[start + 1..];
+            continue;
+        };
+
+        if urls.len().saturating_mul(values.len()) > MAX_BATCH {
+            anyhow::bail!("{} expands to more than {} URLs", pattern, MAX_BATCH);
+        }
+        let prefix = &rest[..start];
+        urls = urls
+            .iter()
+            .flat_map(|url| values.iter().map(move |value| format!("{}{}{}", url, prefix, value)))
+            .collect();
+        rest = &rest[end + 1..];
+    }
+    Ok(urls)
+}
+
+/// Values of a numeric or letter range, None if the text isn't one
+fn range(inner: &str) -> anyhow::Result<Option<Vec<String>>> {
+    let (span, step) = match inner.split_once(':') {
+        Some((span, step)) => match step.parse::<u64>() {
+            Ok(step) => (span, step),
+            Err(_) => return Ok(None),
+        },
+        None => (inner, 1),
+    };
+    let Some((start, end)) = span.split_once('-') else {
+        return Ok(None);
+    };
+    if step == 0 {
+        anyhow::bail!("The step of [{}] must be at least 1", inner);
+    }
+
+    let is_number = |text: &str| !text.is_empty() && text.len() <= 18 && text.chars().all(|c| c.is_ascii_digit());
+    let letter = |text: &str| {
+        let mut chars = text.chars();
+        chars.next().filter(|c| c.is_ascii_alphabetic() && chars.next().is_none())
+    };
+    let values: Vec<String> = if is_number(start) && is_number(end) {
+        let (first, last): (u64, u64) = (start.parse()?, end.parse()?);
+        if first > last {
+            anyhow::bail!("[{}] counts down, write the smaller number first", inner);
+        }
+        if (last - first) / step >= MAX_BATCH as u64 {
+            anyhow::bail!("[{}] has more than {} values", inner, MAX_BATCH);
+        }
+        let width = if start.len() > 1 && start.starts_with('0') { start.len() } else { 0 };
+        (first..=last).step_by(step as usize).map(|n| format!("{:0width$}", n, width = width)).collect()
+    } else if let (Some(first), Some(last)) = (letter(start), letter(end))
+        && first.is_ascii_lowercase() == last.is_ascii_lowercase()
+    {
+        if first > last {
+            anyhow::bail!("[{}] counts down, write the earlier letter first", inner);
+        }
+        (first..=last).step_by(step as usize).map(String::from).collect()
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(values))
+}
+
+/// A request for each URL its patterns stand for. Mirrors are expanded side by side,
+/// so `a/part[1-3]` and `b/part[1-3]` stay mirrors of the same part.
+fn expand_request(request: AddRequest) -> anyhow::Result<Vec<AddRequest>> {
+    let expanded = request
+        .uris
+        .iter()
+        .map(|uri| expand_pattern(uri))
+        .collect::<anyhow::Result<Vec<Vec<String>>>>()?;
+    let count = expanded.iter().map(Vec::len).max().unwrap_or(0);
+    if expanded.iter().any(|uris| uris.len() != 1 && uris.len() != count) {
+        anyhow::bail!("The mirrors of {} expand to different numbers of URLs", request.uris[0]);
+    }
+    if count > 1 && request.out.is_some() {
+        anyhow::bail!("{} expands to several downloads, which can't share one out= file name", request.uris[0]);
+    }
+
+    Ok((0..count)
+        .map(|index| AddRequest {
+            uris: expanded.iter().map(|uris| uris[index.min(uris.len() - 1)].clone()).collect(),
+            ..request.clone()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uris(requests: &[AddRequest]) -> Vec<Vec<&str>> {
+        requests.iter().map(|request| request.uris.iter().map(String::as_str).collect()).collect()
+    }
+
+    #[test]
+    fn expands_padded_ranges() {
+        let urls = expand_pattern("https://example.com/part[001-120].rar").unwrap();
+        assert_eq!(urls.len(), 120);
+        assert_eq!(urls[0], "https://example.com/part001.rar");
+        assert_eq!(urls[9], "https://example.com/part010.rar");
+        assert_eq!(urls[119], "https://example.com/part120.rar");
+
+        assert_eq!(expand_pattern("a[8-10]").unwrap(), ["a8", "a9", "a10"]);
+        assert_eq!(expand_pattern("a[0-2]").unwrap(), ["a0", "a1", "a2"]);
+    }
+
+    #[test]
+    fn expands_steps_letters_and_alternatives() {
+        assert_eq!(expand_pattern("f[0-10:5]").unwrap(), ["f0", "f5", "f10"]);
+        assert_eq!(expand_pattern("f[0-9:4]").unwrap(), ["f0", "f4", "f8"]);
+        assert_eq!(expand_pattern("[a-c].txt").unwrap(), ["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(expand_pattern("[X-Z]").unwrap(), ["X", "Y", "Z"]);
+        assert_eq!(expand_pattern("f.{iso,sig}").unwrap(), ["f.iso", "f.sig"]);
+        assert_eq!(
+            expand_pattern("d{1,2}/f[1-2]").unwrap(),
+            ["d1/f1", "d1/f2", "d2/f1", "d2/f2"]
+        );
+    }
+
+    #[test]
+    fn keeps_brackets_that_are_not_patterns() {
+        for url in [
+            "http://[::1]:8080/file",
+            "http://[2001:db8::1]/file",
+            "https://example.com/{single}/[x]",
+            "https://example.com/[a-Z]",
+            "https://example.com/[1-b]",
+            "https://example.com/unclosed[1-2",
+            "https://example.com/{}",
+        ] {
+            assert_eq!(expand_pattern(url).unwrap(), [url]);
+        }
+        assert_eq!(
+            expand_pattern("http://[::1]/f[1-2]").unwrap(),
+            ["http://[::1]/f1", "http://[::1]/f2"]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_and_oversized_ranges() {
+        assert!(expand_pattern("f[10-1]").is_err());
+        assert!(expand_pattern("f[c-a]").is_err());
+        assert!(expand_pattern("f[1-5:0]").is_err());
+        assert!(expand_pattern("f[0-10000]").is_err());
+        assert_eq!(expand_pattern("f[0-9999]").unwrap().len(), MAX_BATCH);
+        // Each range is small, together they are too many
+        assert!(expand_pattern("f[0-200]/[0-200]").is_err());
+        assert!(expand_pattern("f[0-999999999999999999]").is_err());
+    }
+
+    #[test]
+    fn finds_urls_in_text() {
+        let text = "Get https://example.com/a.iso, or (see https://example.com/wiki/Foo_(bar)). \
+                    Also \"ftp://host/f.txt\" and magnet:?xt=urn:btih:abc! Not http:// though.";
+        assert_eq!(
+            extract_urls(text),
+            [
+                "https://example.com/a.iso",
+                "https://example.com/wiki/Foo_(bar)",
+                "ftp://host/f.txt",
+                "magnet:?xt=urn:btih:abc",
+            ]
+        );
+    }
+
+    #[test]
+    fn batch_requests_expands_and_dedupes_text() {
+        let requests = batch_requests("see https://a/f[1-3] and https://a/f2 and https://b/x").unwrap();
+        assert_eq!(uris(&requests), [vec!["https://a/f1"], vec!["https://a/f2"], vec!["https://a/f3"], vec!["https://b/x"]]);
+        assert!(batch_requests("nothing to see").is_err());
+    }
+
+    #[test]
+    fn batch_requests_keeps_url_list_mirrors_and_options() {
+        let list = "https://a/part[1-2]\thttps://b/part[1-2]\n  dir=/tmp/parts\n  split=4\nhttps://c/one.iso\n  out=one.iso\n";
+        let requests = batch_requests(list).unwrap();
+        assert_eq!(
+            uris(&requests),
+            [vec!["https://a/part1", "https://b/part1"], vec!["https://a/part2", "https://b/part2"], vec!["https://c/one.iso"]]
+        );
+        assert_eq!(requests[1].dir.as_deref(), Some("/tmp/parts"));
+        assert_eq!(requests[1].options, [("split".to_string(), "4".to_string())]);
+        assert_eq!(requests[2].out.as_deref(), Some("one.iso"));
+
+        assert!(batch_requests("https://a/part[1-2]\thttps://b/part[1-3]\n").is_err());
+        assert!(batch_requests("https://a/part[1-2]\n  out=part.rar\n").is_err());
+    }
+
+    #[test]
+    fn batch_requests_limits_the_total() {
+        let text = "https://a/[0-5000] https://b/[0-5000]";
+        assert!(batch_requests(text).unwrap_err().to_string().contains("more than"));
+    }
+
+    #[test]
+    fn shared_options_fill_in_without_overriding() {
+        let shared = SharedOptions {
+            dir: Some("/shared".to_string()),
+            category: Some("Video".to_string()),
+            headers: vec!["X-A: 1".to_string()],
+            options: vec![("split".to_string(), "8".to_string()), ("max-tries".to_string(), "3".to_string())],
+        };
+        let mut request = AddRequest {
+            dir: Some("/own".to_string()),
+            options: vec![("split".to_string(), "2".to_string())],
+            ..AddRequest::new("https://a/f")
+        };
+        shared.apply(&mut request);
+        assert_eq!(request.dir.as_deref(), Some("/own"));
+        assert_eq!(request.category.as_deref(), Some("Video"));
+        assert_eq!(request.headers, ["X-A: 1"]);
+        assert_eq!(
+            request.options,
+            [("split".to_string(), "2".to_string()), ("max-tries".to_string(), "3".to_string())]
+        );
+    }
+
+    #[test]
+    fn reads_files_and_passes_text_through() {
+        let path = std::env::temp_dir().join(format!("bong-batch-{}.txt", std::process::id()));
+        std::fs::write(&path, "https://a/f\n").unwrap();
+        assert_eq!(read_source(path.to_str().unwrap()).unwrap(), "https://a/f\n");
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read_source("https://a/f").unwrap(), "https://a/f");
+        assert_eq!(read_source("/no/such/file").unwrap(), "/no/such/file");
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+/// Nesting allowed in a document, deeper ones are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 64;
+
+/// A bencoded value. Dictionary keys are kept sorted, which is how bencode requires them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Dict(BTreeMap<Vec<u8>, Value>),
+}
+
+impl Value {
+    /// Entry of a dictionary
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dict(dict) => dict.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Byte string as text, None if it isn't UTF-8
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes().and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&BTreeMap<Vec<u8>, Value>> {
+        match self {
+            Value::Dict(dict) => Some(dict),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Value::Bytes(text.as_bytes().to_vec())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+/// Decode a complete bencoded document
+pub fn decode(data: &[u8]) -> anyhow::Result<Value> {
+    let (value, end) = parse(data, 0, 0)?;
+    if end != data.len() {
+        anyhow::bail!("Unexpected data after the end at byte {}", end);
+    }
+    Ok(value)
+}
+
+/// Encode a value, dictionaries with their keys in sorted order
+pub fn encode(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write(value, &mut out);
+    out
+}
+
+/// The bytes a top-level dictionary entry was encoded as, e.g. the `info` dictionary
+/// of a torrent whose hash must be taken over exactly what the file contains
+pub fn raw_entry<'a>(data: &'a [u8], key: &str) -> anyhow::Result<Option<&'a [u8]>> {
+    if data.first() != Some(&b'd') {
+        anyhow::bail!("Expected a dictionary at byte 0");
+    }
+
+    let mut pos = 1;
+    while data.get(pos) != Some(&b'e') {
+        let (entry_key, value_start) = parse(data, pos, 1)?;
+        let (_, value_end) = parse(data, value_start, 1)?;
+        if entry_key.as_bytes() == Some(key.as_bytes()) {
+            return Ok(Some(&data[value_start..value_end]));
+        }
+        pos = value_end;
+    }
+    Ok(None)
+}
+
+/// Parse the value starting at `pos`, returning it with the position after it
+fn parse(data: &[u8], pos: usize, depth: usize) -> anyhow::Result<(Value, usize)> {
+    if depth > MAX_DEPTH {
+        anyhow::bail!("Nested too deeply at byte {}", pos);
+    }
+
+    match data.get(pos) {
+        Some(b'i') => {
+            let end = find(data, pos + 1, b'e')?;
+            let text = std::str::from_utf8(&data[pos + 1..end]).unwrap_or_default();
+            // Leading zeros, negative zero and a plus sign are invalid
+            let digits = text.strip_prefix('-').unwrap_or(text);
+            let canonical = text == "0"
+                || (!digits.is_empty() && !digits.starts_with('0') && digits.bytes().all(|b| b.is_ascii_digit()));
+            let value = text
+                .parse::<i64>()
+                .ok()
+                .filter(|_| canonical)
+                .ok_or_else(|| anyhow::anyhow!("Invalid integer at byte {}", pos))?;
+            Ok((Value::Int(value), end + 1))
+        }
+        Some(b'l') => {
+            let mut list = Vec::new();
+            let mut pos = pos + 1;
+            while data.get(pos) != Some(&b'e') {
+                let (value, next) = parse(data, pos, depth + 1)?;
+                list.push(value);
+                pos = next;
+            }
+            Ok((Value::List(list), pos + 1))
+        }
+        Some(b'd') => {
+            let mut dict = BTreeMap::new();
+            let mut pos = pos + 1;
+            while data.get(pos) != Some(&b'e') {
+                let (key, next) = parse(data, pos, depth + 1)?;
+                let Value::Bytes(key) = key else {
+                    anyhow::bail!("Dictionary key at byte {} isn't a string", pos);
+                };
+                let (value, next) = parse(data, next, depth + 1)?;
+                dict.insert(key, value);
+                pos = next;
+            }
+            Ok((Value::Dict(dict), pos + 1))
+        }
+        Some(b'0'..=b'9') => {
+            let colon = find(data, pos, b':')?;
+            let length: usize = std::str::from_utf8(&data[pos..colon])
+                .ok()
+                .filter(|text| text.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|text| text.parse().ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid string length at byte {}", pos))?;
+            let start = colon + 1;
+            let end = start
+                .checked_add(length)
+                .filter(|end| *end <= data.len())
+                .ok_or_else(|| anyhow::anyhow!("String at byte {} runs past the end", pos))?;
+            Ok((Value::Bytes(data[start..end].to_vec()), end))
+        }
+        Some(byte) => anyhow::bail!("Unexpected {:?} at byte {}", *byte as char, pos),
+        None => anyhow::bail!("Unexpected end of data"),
+    }
+}
+
+fn find(data: &[u8], from: usize, byte: u8) -> anyhow::Result<usize> {
+    data[from.min(data.len())..]
+        .iter()
+        .position(|candidate| *candidate == byte)
+        .map(|offset| from + offset)
+        .ok_or_else(|| anyhow::anyhow!("Unexpected end of data"))
+}
+
+fn write(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Int(value) => out.extend_from_slice(format!("i{}e", value).as_bytes()),
+        Value::Bytes(bytes) => {
+            out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+            out.extend_from_slice(bytes);
+        }
+        Value::List(list) => {
+            out.push(b'l');
+            for value in list {
+                write(value, out);
+            }
+            out.push(b'e');
+        }
+        Value::Dict(dict) => {
+            out.push(b'd');
+            for (key, value) in dict {
+                write(&Value::Bytes(key.clone()), out);
+                write(value, out);
+            }
+            out.push(b'e');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_every_type() {
+        let value = decode(b"d4:listli1ei-20ee3:numi0e4:text5:helloe").unwrap();
+        assert_eq!(value.get("num").and_then(Value::as_int), Some(0));
+        assert_eq!(value.get("text").and_then(Value::as_str), Some("hello"));
+        let list = value.get("list").and_then(Value::as_list).unwrap();
+        assert_eq!(list, [Value::Int(1), Value::Int(-20)]);
+    }
+
+    #[test]
+    fn encode_round_trips() {
+        let data = b"d1:ai1e1:bl3:xyzi-5ee1:cdee";
+        assert_eq!(encode(&decode(data).unwrap()), data);
+    }
+
+    #[test]
+    fn encode_sorts_keys() {
+        let mut dict = BTreeMap::new();
+        dict.insert(b"zz".to_vec(), Value::from(1));
+        dict.insert(b"a".to_vec(), Value::from("x"));
+        assert_eq!(encode(&Value::Dict(dict)), b"d1:a1:x2:zzi1ee");
+    }
+
+    #[test]
+    fn rejects_non_canonical_integers() {
+        for data in ["i-0e", "i01e", "i-01e", "ie", "i-e", "i+5e", "i 5e", "i1.5e"] {
+            assert!(decode(data.as_bytes()).is_err(), "{} was accepted", data);
+        }
+        assert_eq!(decode(b"i-7e").unwrap(), Value::Int(-7));
+    }
+
+    #[test]
+    fn rejects_integer_overflow() {
+        assert!(decode(b"i9223372036854775808e").is_err());
+        assert_eq!(decode(b"i-9223372036854775808e").unwrap(), Value::Int(i64::MIN));
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        for data in ["5:abc", "3:", "4", "i12", "l1:a", "d1:a", "d1:ai1e", "", "18446744073709551615:a"] {
+            assert!(decode(data.as_bytes()).is_err(), "{:?} was accepted", data);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_string_lengths() {
+        assert!(decode(b"+1:a").is_err());
+        assert!(decode(b"-1:a").is_err());
+    }
+
+    #[test]
+    fn rejects_trailing_data_and_non_string_keys() {
+        assert!(decode(b"i1ei2e").is_err());
+        assert!(decode(b"di1e1:ae").is_err());
+        assert!(decode(b"x").is_err());
+    }
+
+    #[test]
+    fn rejects_deep_nesting_without_overflowing() {
+        let mut data = vec![b'l'; 100_000];
+        data.extend(vec![b'e'; 100_000]);
+        assert!(decode(&data).is_err());
+
+        let mut data = b"d1:a".repeat(100_000);
+        data.extend(vec![b'e'; 100_000]);
+        assert!(decode(&data).is_err());
+
+        let mut data = vec![b'l'; MAX_DEPTH];
+        data.extend(vec![b'e'; MAX_DEPTH]);
+        assert!(decode(&data).is_ok());
+    }
+
+    #[test]
+    fn raw_entry_returns_the_exact_bytes() {
+        let data = b"d8:announce3:url4:infod4:name1:x6:lengthi3eee";
+        assert_eq!(raw_entry(data, "info").unwrap(), Some(&b"d4:name1:x6:lengthi3ee"[..]));
+        assert_eq!(raw_entry(data, "missing").unwrap(), None);
+        assert!(raw_entry(b"l1:ae", "info").is_err());
+        assert!(raw_entry(b"d4:info", "info").is_err());
+    }
+}
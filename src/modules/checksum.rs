@@ -0,0 +1,370 @@
+use crate::modules::settings::{config_dir, write_atomic};
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// File name of the expected checksums inside the config directory
+pub const CHECKSUMS_FILE: &str = "checksums.json";
+
+/// Hash functions used by published checksums
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 4] = [HashAlgorithm::Sha256, HashAlgorithm::Sha512, HashAlgorithm::Sha1, HashAlgorithm::Md5];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Length of a digest in hex characters
+    pub fn hex_len(self) -> usize {
+        match self {
+            HashAlgorithm::Md5 => 32,
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Sha256 => 64,
+            HashAlgorithm::Sha512 => 128,
+        }
+    }
+
+    /// Parse names such as "sha256", "SHA-256" or "md5"
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase().replace('-', "");
+        Self::ALL.into_iter().find(|algorithm| algorithm.as_str() == name)
+    }
+
+    /// Guess the algorithm of a hex digest from its length
+    pub fn from_hex_len(len: usize) -> Option<Self> {
+        Self::ALL.into_iter().find(|algorithm| algorithm.hex_len() == len)
+    }
+
+    /// Names of the checksum files published for whole folders, e.g. `SHA256SUMS`
+    fn sums_files(self) -> [String; 2] {
+        let upper = self.as_str().to_uppercase();
+        [format!("{}SUMS", upper), format!("{}sums.txt", self.as_str())]
+    }
+}
+
+/// An expected digest of a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checksum {
+    pub algorithm: HashAlgorithm,
+    /// Lowercase hex digest
+    pub hex: String,
+}
+
+impl Checksum {
+    /// Checksum from a hex digest, the algorithm is picked by its length
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.trim().to_lowercase();
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(Self {
+            algorithm: HashAlgorithm::from_hex_len(hex.len())?,
+            hex,
+        })
+    }
+
+    /// Parse `<algorithm>:<hex>`, aria2's `<algorithm>=<hex>` or a bare hex digest
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let text = text.trim();
+        let checksum = match text.split_once([':', '=']) {
+            Some((name, hex)) => {
+                let algorithm = HashAlgorithm::parse(name)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown hash algorithm {}, use md5, sha1, sha256 or sha512", name))?;
+                let checksum = Self::from_hex(hex).filter(|checksum| checksum.algorithm == algorithm);
+                checksum.ok_or_else(|| anyhow::anyhow!("{} is not a valid {} digest", hex.trim(), algorithm.as_str()))?
+            }
+            None => Self::from_hex(text)
+                .ok_or_else(|| anyhow::anyhow!("Expected a hex md5, sha1, sha256 or sha512 digest"))?,
+        };
+        Ok(checksum)
+    }
+
+    /// Hash the file and compare it with the expected digest
+    pub fn verify(&self, path: &Path) -> anyhow::Result<bool> {
+        Ok(hash_file(path, self.algorithm)? == self.hex)
+    }
+}
+
+/// Where the expected digest of a download comes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChecksumSource {
+    Digest(Checksum),
+    /// URL of a checksum list such as `SHA256SUMS` or of a `file.iso.sha256`
+    File(String),
+}
+
+impl ChecksumSource {
+    /// A checksum file URL or a digest as accepted by `Checksum::parse`
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let text = text.trim();
+        if text.starts_with("http://") || text.starts_with("https://") {
+            Ok(ChecksumSource::File(text.to_string()))
+        } else {
+            Checksum::parse(text).map(ChecksumSource::Digest)
+        }
+    }
+
+    /// The expected digest of `file_name`, downloading the checksum file if needed
+    pub fn resolve(&self, file_name: &str) -> anyhow::Result<Checksum> {
+        match self {
+            ChecksumSource::Digest(checksum) => Ok(checksum.clone()),
+            ChecksumSource::File(url) => {
+                let text = ureq::get(url)
+                    .timeout(Duration::from_secs(30))
+                    .call()
+                    .map_err(|e| anyhow::anyhow!("Failed to fetch the checksum file: {}", e))?
+                    .into_string()?;
+                find_in_sums(&text, file_name).ok_or_else(|| anyhow::anyhow!("{} has no checksum for {}", url, file_name))
+            }
+        }
+    }
+}
+
+/// Outcome of checking a completed download against its expected checksum
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Verification {
+    Verifying,
+    Verified,
+    Corrupt,
+    /// The file couldn't be hashed or the checksum file couldn't be read
+    Failed(String),
+}
+
+/// Serializes read-modify-write cycles of the checksum file inside this process
+static STORE_LOCK: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
+
+/// Checksums given when downloads were added, kept until the download is verified
+pub struct ChecksumStore {
+    path: PathBuf,
+}
+
+impl ChecksumStore {
+    pub fn open() -> Self {
+        Self::open_at(config_dir().join(CHECKSUMS_FILE))
+    }
+
+    pub fn open_at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn load(&self) -> HashMap<String, ChecksumSource> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, entries: &HashMap<String, ChecksumSource>) -> anyhow::Result<()> {
+        write_atomic(&self.path, &serde_json::to_vec_pretty(entries)?)
+    }
+
+    /// Remember the expected checksum of a download by its GID
+    pub fn insert(&self, gid: &str, source: ChecksumSource) -> anyhow::Result<()> {
+        let _lock = STORE_LOCK.lock();
+        let mut entries = self.load();
+        entries.insert(gid.to_string(), source);
+        self.save(&entries)
+    }
+
+    /// Take the expected checksum of a download out of the store
+    pub fn take(&self, gid: &str) -> Option<ChecksumSource> {
+        let _lock = STORE_LOCK.lock();
+        let mut entries = self.load();
+        let source = entries.remove(gid)?;
+        if let Err(e) = self.save(&entries) {
+            println!("Failed to update {:?}: {}", self.path, e);
+        }
+        Some(source)
+    }
+}
+
+/// Hex digest of a file, read in chunks so large ISOs don't end up in memory
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> anyhow::Result<String> {
+    let file = std::fs::File::open(path).map_err(|e| anyhow::anyhow!("Failed to open {:?}: {}", path, e))?;
+    match algorithm {
+        HashAlgorithm::Md5 => hash_reader::<md5::Md5>(file),
+        HashAlgorithm::Sha1 => hash_reader::<sha1::Sha1>(file),
+        HashAlgorithm::Sha256 => hash_reader::<sha2::Sha256>(file),
+        HashAlgorithm::Sha512 => hash_reader::<sha2::Sha512>(file),
+    }
+}
+
+fn hash_reader<D: Digest>(mut reader: impl Read) -> anyhow::Result<String> {
+    let mut hasher = D::new();
+    let mut buffer = vec![0u8; 256 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Digest of `file_name` in a checksum list such as `SHA256SUMS`.
+/// Accepts the GNU format (`<hex>  name`, `<hex> *name`), the BSD format
+/// (`SHA256 (name) = <hex>`) and files holding a single bare digest.
+pub fn find_in_sums(text: &str, file_name: &str) -> Option<Checksum> {
+    let lines: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).collect();
+
+    for line in &lines {
+        // BSD style
+        if let Some((head, hex)) = line.rsplit_once(" = ")
+            && let Some((_, name)) = head.split_once(" (")
+            && name.strip_suffix(')') == Some(file_name)
+        {
+            return Checksum::from_hex(hex);
+        }
+
+        // GNU style
+        if let Some((hex, name)) = line.split_once(char::is_whitespace) {
+            let name = name.trim_start().trim_start_matches('*');
+            let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+            if name == file_name {
+                return Checksum::from_hex(hex);
+            }
+        }
+    }
+
+    // A `file.iso.sha256` often holds only the digest
+    match lines.as_slice() {
+        [line] => Checksum::from_hex(line.split_whitespace().next()?),
+        _ => None,
+    }
+}
+
+/// Look for a checksum published next to a downloaded file, either a sidecar
+/// such as `file.iso.sha256` or a folder list such as `SHA256SUMS`
+pub fn find_sidecar(path: &Path) -> Option<(PathBuf, Checksum)> {
+    let file_name = path.file_name()?.to_str()?;
+    let dir = path.parent()?;
+
+    let mut candidates = Vec::new();
+    for algorithm in HashAlgorithm::ALL {
+        candidates.push(dir.join(format!("{}.{}", file_name, algorithm.as_str())));
+        candidates.push(dir.join(format!("{}.{}sum", file_name, algorithm.as_str())));
+    }
+    for algorithm in HashAlgorithm::ALL {
+        candidates.extend(algorithm.sums_files().iter().map(|name| dir.join(name)));
+    }
+
+    candidates.into_iter().find_map(|candidate| {
+        let text = std::fs::read_to_string(&candidate).ok()?;
+        let checksum = find_in_sums(&text, file_name)?;
+        Some((candidate, checksum))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Digests of "hello\n" in each algorithm
+    const DIGESTS: [(HashAlgorithm, &str); 4] = [
+        (HashAlgorithm::Md5, "b1946ac92492d2347c6235b4d2611184"),
+        (HashAlgorithm::Sha1, "f572d396fae9206628714fb2ce00f72e94f2258f"),
+        (HashAlgorithm::Sha256, "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"),
+        (
+            HashAlgorithm::Sha512,
+            "e7c22b994c59d9cf2b48e549b1e24666636045930d3da7c1acb299d1c3b7f931f94aae41edda2c2b207a36e10f8bcb8d45223e54878f5b316e7ce3b6bc019629",
+        ),
+    ];
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bong-checksum-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.iso");
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_each_algorithm_bare_and_named() {
+        for (algorithm, hex) in DIGESTS {
+            let expected = ChecksumSource::Digest(Checksum { algorithm, hex: hex.to_string() });
+            assert_eq!(ChecksumSource::parse(hex).unwrap(), expected);
+            assert_eq!(ChecksumSource::parse(&format!("  {}  ", hex.to_uppercase())).unwrap(), expected);
+            assert_eq!(ChecksumSource::parse(&format!("{}={}", algorithm.as_str(), hex)).unwrap(), expected);
+            assert_eq!(ChecksumSource::parse(&format!("{}:{}", algorithm.as_str().to_uppercase(), hex)).unwrap(), expected);
+        }
+        assert_eq!(
+            ChecksumSource::parse("SHA-256=5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03").unwrap(),
+            ChecksumSource::Digest(Checksum { algorithm: HashAlgorithm::Sha256, hex: DIGESTS[2].1.to_string() })
+        );
+        assert_eq!(
+            ChecksumSource::parse("https://example.com/SHA256SUMS").unwrap(),
+            ChecksumSource::File("https://example.com/SHA256SUMS".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_digests() {
+        // The name has to match the length of the digest
+        assert!(ChecksumSource::parse(&format!("sha256={}", DIGESTS[0].1)).is_err());
+        assert!(ChecksumSource::parse(&format!("crc32={}", DIGESTS[0].1)).is_err());
+        assert!(ChecksumSource::parse("md5=").is_err());
+        assert!(ChecksumSource::parse("").is_err());
+        assert!(ChecksumSource::parse("abc123").is_err());
+        assert!(ChecksumSource::parse(&DIGESTS[0].1.replace('b', "g")).is_err());
+    }
+
+    #[test]
+    fn verifies_each_algorithm() {
+        let path = temp_file("verify", "hello\n");
+        for (algorithm, hex) in DIGESTS {
+            assert_eq!(hash_file(&path, algorithm).unwrap(), hex);
+            assert!(Checksum::parse(hex).unwrap().verify(&path).unwrap());
+        }
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn reports_a_mismatch() {
+        let path = temp_file("mismatch", "hello!\n");
+        for (_, hex) in DIGESTS {
+            assert!(!Checksum::parse(hex).unwrap().verify(&path).unwrap());
+        }
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn fails_when_the_file_is_missing() {
+        let path = temp_file("missing", "hello\n");
+        std::fs::remove_file(&path).unwrap();
+        let error = Checksum::parse(DIGESTS[2].1).unwrap().verify(&path).unwrap_err();
+        assert!(error.to_string().contains("Failed to open"), "{}", error);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn finds_digests_in_checksum_lists() {
+        let sha256 = DIGESTS[2].1;
+        let gnu = format!("# comment\n{}  other.iso\n{}  file.iso\n", DIGESTS[3].1, sha256);
+        assert_eq!(find_in_sums(&gnu, "file.iso").unwrap().hex, sha256);
+        let binary = format!("{} *dir/file.iso\n", sha256);
+        assert_eq!(find_in_sums(&binary, "file.iso").unwrap().hex, sha256);
+        let bsd = format!("SHA256 (other.iso) = {}\nSHA256 (file.iso) = {}\n", DIGESTS[3].1, sha256);
+        assert_eq!(find_in_sums(&bsd, "file.iso").unwrap().hex, sha256);
+        let bare = format!("{}\n", DIGESTS[0].1);
+        assert_eq!(find_in_sums(&bare, "file.iso").unwrap().algorithm, HashAlgorithm::Md5);
+        assert_eq!(find_in_sums(&gnu, "missing.iso"), None);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::modules::actions::ActionOutcome;
+use crate::modules::checksum::Verification;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Global transfer statistics of an engine
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GlobalStat {
+    /// Bytes per second
+    pub download_speed: u64,
+    /// Bytes per second
+    pub upload_speed: u64,
+    pub num_active: u32,
+    pub num_waiting: u32,
+    pub num_stopped: u32,
+}
+
+/// State of the active engine as seen by the background task
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EngineStatus {
+    /// Name of the engine, e.g. "aria2" or "Built-in"
+    #[serde(default)]
+    pub engine: String,
+    /// The engine answered the last poll
+    pub running: bool,
+    /// Why the last poll failed while the engine was expected to run
+    pub error: Option<String>,
+    pub version: Option<String>,
+    /// RPC endpoint that was polled, or where the engine runs
+    pub endpoint: String,
+    pub stat: GlobalStat,
+}
+
+/// State of a download
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum DownloadStatus {
+    Active,
+    #[default]
+    Waiting,
+    Paused,
+    Error,
+    Complete,
+    Removed,
+}
+
+/// A single download in an engine's queue
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Download {
+    pub gid: String,
+    pub status: DownloadStatus,
+    /// Torrent name or file name
+    pub name: String,
+    /// Destination directory on the engine's host
+    pub dir: String,
+    /// Paths of the downloaded files on the engine's host
+    pub files: Vec<String>,
+    /// URIs the download was added with
+    pub uris: Vec<String>,
+    pub total_length: u64,
+    pub completed_length: u64,
+    /// Bytes per second
+    pub download_speed: u64,
+    /// Bytes per second
+    pub upload_speed: u64,
+    pub error_message: Option<String>,
+    /// Only fetching the metadata of a magnet link, aria2 replaces it with the real
+    /// download once the metadata arrives
+    #[serde(default)]
+    pub metadata: bool,
+    /// GID of the download that replaced this one
+    #[serde(default)]
+    pub followed_by: Option<String>,
+}
+
+impl Download {
+    /// Completed fraction between 0 and 1
+    pub fn progress(&self) -> f32 {
+        if self.total_length == 0 {
+            0.0
+        } else {
+            self.completed_length as f32 / self.total_length as f32
+        }
+    }
+
+    /// A metadata download whose real download took over
+    pub fn is_replaced(&self) -> bool {
+        self.metadata && self.status == DownloadStatus::Complete && self.followed_by.is_some()
+    }
+
+    /// Seconds until completion at the current speed
+    pub fn eta_secs(&self) -> Option<u64> {
+        if self.download_speed == 0 || self.total_length == 0 {
+            return None;
+        }
+        Some(self.total_length.saturating_sub(self.completed_length) / self.download_speed)
+    }
+}
+
+/// A new download to hand to an engine
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AddRequest {
+    /// Mirrors of the same file, or a single magnet link
+    pub uris: Vec<String>,
+    /// Destination directory, the default download folder when empty
+    pub dir: Option<String>,
+    /// Output file name, detected by the engine when empty
+    pub out: Option<String>,
+    /// Category name, picked by the assignment rules when empty
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Additional aria2 style options such as split
+    #[serde(default)]
+    pub options: Vec<(String, String)>,
+    /// Expected digest such as "sha256:<hex>" or the URL of a checksum file,
+    /// verified by the background task once the download completes
+    #[serde(default)]
+    pub checksum: Option<String>,
+    /// Extra HTTP headers such as "Cookie: id=1", for downloads captured from a browser
+    #[serde(default)]
+    pub headers: Vec<String>,
+    /// yt-dlp format selector, makes the URL a video page for yt-dlp to download
+    #[serde(default)]
+    pub format: Option<String>,
+    /// A .torrent or Metalink file to download from, URIs are then extra web seeds
+    #[serde(default)]
+    pub file: Option<DownloadFile>,
+}
+
+impl AddRequest {
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uris: vec![uri.into()],
+            ..Default::default()
+        }
+    }
+}
+
+/// A file describing a download, sent base64 encoded like aria2 expects it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum DownloadFile {
+    Torrent(String),
+    Metalink(String),
+}
+
+impl DownloadFile {
+    pub fn torrent(data: &[u8]) -> Self {
+        Self::Torrent(STANDARD.encode(data))
+    }
+
+    pub fn metalink(data: &[u8]) -> Self {
+        Self::Metalink(STANDARD.encode(data))
+    }
+
+    /// Decoded contents of the file
+    pub fn data(&self) -> anyhow::Result<Vec<u8>> {
+        let (DownloadFile::Torrent(data) | DownloadFile::Metalink(data)) = self;
+        Ok(STANDARD.decode(data)?)
+    }
+}
+
+/// Everything the tray needs to show, pushed by the background task after each poll
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransferSnapshot {
+    pub stat: GlobalStat,
+    pub downloads: Vec<Download>,
+    /// Global download limit in bytes per second, 0 for unlimited
+    pub download_limit: u64,
+    /// Results of post-download actions by GID
+    #[serde(default)]
+    pub actions: HashMap<String, Vec<ActionOutcome>>,
+    /// Checksum results of downloads added with an expected checksum, by GID
+    #[serde(default)]
+    pub verifications: HashMap<String, Verification>,
+}
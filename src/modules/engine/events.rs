@@ -0,0 +1,113 @@
+use super::{Download, DownloadEngine, DownloadStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Changes to an engine's downloads, ends when the engine goes away.
+/// Usable outside a runtime with `blocking_recv` or `try_recv`.
+pub type EventStream = UnboundedReceiver<EngineEvent>;
+
+/// Something that happened to a download
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EngineEvent {
+    /// A download was queued
+    Added { download: Download },
+    /// A download started, paused, finished, failed or was removed
+    StatusChanged { previous: DownloadStatus, download: Download },
+    /// A finished download was forgotten by the engine
+    Forgotten { gid: String },
+}
+
+/// Turns successive download listings into events
+#[derive(Default)]
+pub struct EventTracker {
+    /// Status of every listed download, None until the first listing
+    known: Option<HashMap<String, DownloadStatus>>,
+}
+
+impl EventTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events between the previous listing and this one. The first listing only
+    /// records what exists, it doesn't report everything as added.
+    pub fn update(&mut self, downloads: &[Download]) -> Vec<EngineEvent> {
+        let current: HashMap<String, DownloadStatus> = downloads
+            .iter()
+            .map(|download| (download.gid.clone(), download.status))
+            .collect();
+        let Some(known) = self.known.replace(current.clone()) else {
+            return Vec::new();
+        };
+
+        let mut events: Vec<EngineEvent> = downloads
+            .iter()
+            .filter_map(|download| match known.get(&download.gid) {
+                None => Some(EngineEvent::Added { download: download.clone() }),
+                Some(&previous) if previous != download.status => Some(EngineEvent::StatusChanged {
+                    previous,
+                    download: download.clone(),
+                }),
+                Some(_) => None,
+            })
+            .collect();
+        events.extend(
+            known
+                .into_keys()
+                .filter(|gid| !current.contains_key(gid))
+                .map(|gid| EngineEvent::Forgotten { gid }),
+        );
+        events
+    }
+}
+
+/// Receivers of the events of one engine
+#[derive(Default)]
+pub struct Subscribers {
+    senders: parking_lot::Mutex<Vec<UnboundedSender<EngineEvent>>>,
+}
+
+impl Subscribers {
+    pub fn subscribe(&self) -> EventStream {
+        let (sender, receiver) = unbounded_channel();
+        self.senders.lock().push(sender);
+        receiver
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.senders.lock().is_empty()
+    }
+
+    /// Send to every subscriber, dropping the ones that stopped listening
+    pub fn publish(&self, events: Vec<EngineEvent>) {
+        let mut senders = self.senders.lock();
+        senders.retain(|sender| !sender.is_closed());
+        for event in events {
+            for sender in senders.iter() {
+                let _ = sender.send(event.clone());
+            }
+        }
+    }
+}
+
+/// Events of an engine that can only be listed, found by polling it every `interval`
+/// on a thread that ends once the stream is dropped
+pub fn poll_events(engine: impl DownloadEngine + 'static, interval: Duration) -> EventStream {
+    let (sender, receiver) = unbounded_channel();
+    std::thread::spawn(move || {
+        let mut tracker = EventTracker::new();
+        while !sender.is_closed() {
+            // An unreachable engine lists nothing, that doesn't mean everything was removed
+            if let Ok(downloads) = engine.list() {
+                for event in tracker.update(&downloads) {
+                    let _ = sender.send(event);
+                }
+            }
+            std::thread::sleep(interval);
+        }
+    });
+    receiver
+}
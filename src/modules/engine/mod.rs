@@ -0,0 +1,169 @@
+mod download;
+mod events;
+mod native;
+mod ytdlp;
+
+pub use download::{AddRequest, Download, DownloadFile, DownloadStatus, EngineStatus, GlobalStat, TransferSnapshot};
+pub use events::{poll_events, EngineEvent, EventStream, EventTracker};
+pub use native::NativeEngine;
+pub use ytdlp::{is_video_host, is_video_download, probe as probe_video, VideoFormat, VideoInfo, YtDlpEngine};
+
+use crate::modules::aria2::{client_for, Aria2Client};
+use crate::modules::settings::{EngineKind, Settings, SpeedProfile};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// Something that runs downloads: aria2 over RPC or the built-in HTTP downloader
+pub trait DownloadEngine: Send + Sync {
+    /// Queue a download, returning its GID
+    fn add(&self, request: &AddRequest) -> anyhow::Result<String>;
+    fn pause(&self, gid: &str) -> anyhow::Result<()>;
+    fn resume(&self, gid: &str) -> anyhow::Result<()>;
+    /// Remove an unfinished download, or forget a finished one
+    fn remove(&self, gid: &str) -> anyhow::Result<()>;
+    fn pause_all(&self) -> anyhow::Result<()>;
+    fn resume_all(&self) -> anyhow::Result<()>;
+    /// Active, waiting and stopped downloads in that order
+    fn list(&self) -> anyhow::Result<Vec<Download>>;
+    fn stats(&self) -> anyhow::Result<GlobalStat>;
+    /// Global download limit in bytes per second, 0 for unlimited
+    fn download_limit(&self) -> anyhow::Result<u64>;
+    fn set_download_limit(&self, limit: u64) -> anyhow::Result<()>;
+    /// Apply the limits of a speed profile globally and to every unfinished download
+    fn apply_speed_profile(&self, profile: &SpeedProfile) -> anyhow::Result<()>;
+    /// Whether the engine can take commands, reporting failures as an error only
+    /// when it is expected to run
+    fn status(&self, expected_running: bool) -> EngineStatus;
+    /// Downloads being added, changing status and being forgotten from now on
+    fn events(&self) -> EventStream;
+}
+
+/// The engine selected in the settings
+pub fn engine_for(settings: &Settings) -> Arc<dyn DownloadEngine> {
+    match settings.engine.kind {
+        EngineKind::Aria2 => Arc::new(client_for(settings)),
+        EngineKind::Native => native_engine(settings),
+    }
+}
+
+/// The engine that runs a download: yt-dlp for its own jobs, otherwise the selected one
+pub fn engine_for_gid(settings: &Settings, gid: &str) -> Arc<dyn DownloadEngine> {
+    if is_video_download(gid) {
+        video_engine(settings)
+    } else {
+        engine_for(settings)
+    }
+}
+
+/// Whether a new download is a video page for yt-dlp rather than a file for the selected engine
+pub fn is_video_request(settings: &Settings, request: &AddRequest) -> bool {
+    let is_video = request.format.is_some() || request.uris.first().is_some_and(|url| is_video_host(&settings.video.hosts, url));
+    settings.video.enabled && is_video
+}
+
+static VIDEO_ENGINE: OnceLock<Arc<YtDlpEngine>> = OnceLock::new();
+
+/// The yt-dlp engine of this process, running next to the selected engine
+pub fn video_engine(settings: &Settings) -> Arc<YtDlpEngine> {
+    let engine = VIDEO_ENGINE.get_or_init(|| {
+        let engine = YtDlpEngine::open(settings);
+        if let Err(e) = engine.apply_speed_profile(&settings.bandwidth.active()) {
+            println!("Failed to apply the speed profile: {}", e);
+        }
+        engine
+    });
+    engine.configure(settings);
+    engine.clone()
+}
+
+/// Stop the processes of this process' engines before it exits
+pub fn shutdown() {
+    if let Some(engine) = VIDEO_ENGINE.get() {
+        engine.shutdown();
+    }
+}
+
+/// The built-in engine of this process, following the latest settings
+fn native_engine(settings: &Settings) -> Arc<NativeEngine> {
+    static ENGINE: OnceLock<Arc<NativeEngine>> = OnceLock::new();
+
+    let engine = ENGINE.get_or_init(|| {
+        let engine = NativeEngine::open(settings);
+        if let Err(e) = engine.apply_speed_profile(&settings.bandwidth.active()) {
+            println!("Failed to apply the speed profile: {}", e);
+        }
+        engine
+    });
+    engine.configure(settings);
+    engine.clone()
+}
+
+impl DownloadEngine for Aria2Client {
+    fn add(&self, request: &AddRequest) -> anyhow::Result<String> {
+        match request.file {
+            None => self.add_uri(request),
+            Some(DownloadFile::Torrent(ref torrent)) => self.add_torrent(torrent, request),
+            Some(DownloadFile::Metalink(ref metalink)) => self.add_metalink(metalink, request),
+        }
+    }
+
+    fn pause(&self, gid: &str) -> anyhow::Result<()> {
+        Aria2Client::pause(self, gid)
+    }
+
+    fn resume(&self, gid: &str) -> anyhow::Result<()> {
+        self.unpause(gid)
+    }
+
+    fn remove(&self, gid: &str) -> anyhow::Result<()> {
+        Aria2Client::remove(self, gid)
+    }
+
+    fn pause_all(&self) -> anyhow::Result<()> {
+        Aria2Client::pause_all(self)
+    }
+
+    fn resume_all(&self) -> anyhow::Result<()> {
+        self.unpause_all()
+    }
+
+    fn list(&self) -> anyhow::Result<Vec<Download>> {
+        self.tell_all()
+    }
+
+    fn stats(&self) -> anyhow::Result<GlobalStat> {
+        self.get_global_stat()
+    }
+
+    fn download_limit(&self) -> anyhow::Result<u64> {
+        Aria2Client::download_limit(self)
+    }
+
+    fn set_download_limit(&self, limit: u64) -> anyhow::Result<()> {
+        Aria2Client::set_download_limit(self, limit)
+    }
+
+    fn apply_speed_profile(&self, profile: &SpeedProfile) -> anyhow::Result<()> {
+        Aria2Client::apply_speed_profile(self, profile)
+    }
+
+    fn status(&self, expected_running: bool) -> EngineStatus {
+        Aria2Client::status(self, expected_running)
+    }
+
+    /// aria2 only pushes notifications over WebSocket, so the queue is polled instead
+    fn events(&self) -> EventStream {
+        poll_events(self.clone(), Duration::from_secs(1))
+    }
+}
+
+/// A random 16 digit hex GID like aria2's
+fn new_gid() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("{:016x}", hasher.finish())
+}
@@ -0,0 +1,590 @@
+mod transfer;
+
+use super::events::Subscribers;
+use super::{new_gid, AddRequest, Download, DownloadEngine, DownloadStatus, EngineStatus, EventStream, EventTracker, GlobalStat};
+use crate::modules::settings::{config_dir, parse_size, write_atomic, EngineKind, Settings, SpeedProfile};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+use transfer::{discard, sanitize_name, Outcome, RateLimiter, Transfer};
+
+/// Finished downloads kept in the list, like aria2's max-download-result
+const MAX_STOPPED: usize = 1000;
+/// How often waiting downloads are started and the queue is saved
+const TICK: Duration = Duration::from_millis(250);
+
+/// Options of the built-in engine taken from the settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    download_dir: PathBuf,
+    max_concurrent: usize,
+    /// Queue new downloads paused
+    start_paused: bool,
+    connections: u32,
+    min_segment_size: u64,
+    max_retries: u32,
+    user_agent: String,
+    proxy: String,
+    timeout: Duration,
+}
+
+impl Config {
+    fn from_settings(settings: &Settings) -> Self {
+        Self {
+            download_dir: settings.downloads.download_dir.clone(),
+            max_concurrent: settings.downloads.max_concurrent as usize,
+            start_paused: !settings.downloads.auto_start,
+            connections: settings.engine.connections,
+            min_segment_size: parse_size(&settings.engine.min_segment_size).unwrap_or(4 << 20),
+            max_retries: settings.engine.max_retries,
+            user_agent: settings.network.user_agent.clone(),
+            proxy: settings.network.proxy.clone(),
+            timeout: Duration::from_secs(settings.network.timeout_secs as u64),
+        }
+    }
+}
+
+/// What the transfer of a task should do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Control {
+    Run,
+    Pause,
+    Remove,
+}
+
+/// A download known to the engine
+pub struct Task {
+    gid: String,
+    request: AddRequest,
+    /// Bytes written so far, counted by the transfer threads
+    completed: AtomicU64,
+    control: AtomicU8,
+    /// A transfer thread owns the task's files
+    running: AtomicBool,
+    /// Per-download speed limit
+    limiter: RateLimiter,
+    state: Mutex<TaskState>,
+}
+
+#[derive(Default)]
+struct TaskState {
+    status: DownloadStatus,
+    /// File name once known, from the request, the server or the URL
+    name: Option<String>,
+    /// Name of the .part file without its extension, reserved when the transfer starts
+    part: Option<String>,
+    dir: PathBuf,
+    /// Where the finished file ended up
+    file: Option<PathBuf>,
+    total: u64,
+    /// Bytes per second
+    speed: u64,
+    /// Completed bytes at the last speed sample
+    sampled: u64,
+    error: Option<String>,
+}
+
+impl Task {
+    fn control(&self) -> Control {
+        match self.control.load(Ordering::Relaxed) {
+            0 => Control::Run,
+            1 => Control::Pause,
+            _ => Control::Remove,
+        }
+    }
+
+    fn set_control(&self, control: Control) {
+        self.control.store(control as u8, Ordering::Relaxed);
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    fn to_download(&self) -> Download {
+        let state = self.state.lock();
+        let files = match (&state.file, &state.name) {
+            (Some(file), _) => vec![file.display().to_string()],
+            (None, Some(name)) => vec![state.dir.join(name).display().to_string()],
+            (None, None) => Vec::new(),
+        };
+
+        Download {
+            gid: self.gid.clone(),
+            status: state.status,
+            name: state.name.clone().unwrap_or_else(|| self.gid.clone()),
+            dir: state.dir.display().to_string(),
+            files,
+            uris: self.request.uris.clone(),
+            total_length: state.total,
+            completed_length: self.completed.load(Ordering::Relaxed),
+            download_speed: if state.status == DownloadStatus::Active { state.speed } else { 0 },
+            upload_speed: 0,
+            error_message: state.error.clone(),
+            metadata: false,
+            followed_by: None,
+        }
+    }
+}
+
+/// An unfinished download as written to the queue file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedTask {
+    gid: String,
+    request: AddRequest,
+    status: DownloadStatus,
+    name: Option<String>,
+    #[serde(default)]
+    part: Option<String>,
+    dir: PathBuf,
+    completed: u64,
+    total: u64,
+    error: Option<String>,
+}
+
+/// Downloads HTTP(S) URLs itself, splitting files over several connections when the
+/// server supports range requests. Unfinished downloads are written to `<name>.part`,
+/// numbered when several share a name, with their progress in `<name>.part.json`, so
+/// they resume after a restart.
+pub struct NativeEngine {
+    config: RwLock<Config>,
+    tasks: Mutex<Vec<Arc<Task>>>,
+    /// Limit shared by all downloads
+    limiter: Arc<RateLimiter>,
+    /// Limit each new download starts with
+    per_download_limit: AtomicU64,
+    subscribers: Subscribers,
+    queue_path: PathBuf,
+}
+
+impl NativeEngine {
+    /// Start the engine, restoring the queue saved by a previous run
+    pub fn open(settings: &Settings) -> Arc<Self> {
+        Self::open_at(settings, config_dir().join("native-queue.json"))
+    }
+
+    pub fn open_at(settings: &Settings, queue_path: PathBuf) -> Arc<Self> {
+        let engine = Arc::new(Self {
+            config: RwLock::new(Config::from_settings(settings)),
+            tasks: Mutex::new(load_queue(&queue_path)),
+            limiter: Arc::new(RateLimiter::default()),
+            per_download_limit: AtomicU64::new(0),
+            subscribers: Subscribers::default(),
+            queue_path,
+        });
+
+        let weak = Arc::downgrade(&engine);
+        thread::spawn(move || supervise(weak));
+        engine
+    }
+
+    /// Follow changed settings, running transfers keep the options they started with
+    pub fn configure(&self, settings: &Settings) {
+        let config = Config::from_settings(settings);
+        if *self.config.read() != config {
+            *self.config.write() = config;
+        }
+    }
+
+    fn find(&self, gid: &str) -> anyhow::Result<Arc<Task>> {
+        self.tasks
+            .lock()
+            .iter()
+            .find(|task| task.gid == gid)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Download {} doesn't exist", gid))
+    }
+
+    /// Start waiting downloads while there is room
+    fn start_waiting(&self) {
+        let config = self.config.read().clone();
+        let tasks = self.tasks.lock();
+        let mut running = tasks.iter().filter(|task| task.is_running()).count();
+
+        for task in tasks.iter() {
+            if running >= config.max_concurrent {
+                break;
+            }
+            if task.is_running() {
+                continue;
+            }
+            {
+                let mut state = task.state.lock();
+                if state.status != DownloadStatus::Waiting {
+                    continue;
+                }
+                state.status = DownloadStatus::Active;
+                state.error = None;
+            }
+
+            task.set_control(Control::Run);
+            task.running.store(true, Ordering::Relaxed);
+            running += 1;
+            spawn_transfer(task.clone(), config.clone(), self.limiter.clone());
+        }
+    }
+
+    /// Update the speed of every download from the bytes written since the last sample
+    fn sample_speeds(&self, elapsed: Duration) {
+        for task in self.tasks.lock().iter() {
+            let completed = task.completed.load(Ordering::Relaxed);
+            let mut state = task.state.lock();
+            state.speed = (completed.saturating_sub(state.sampled) as f64 / elapsed.as_secs_f64()) as u64;
+            state.sampled = completed;
+        }
+    }
+
+    /// Forget the oldest finished downloads beyond MAX_STOPPED
+    fn trim_stopped(&self) {
+        let mut tasks = self.tasks.lock();
+        let mut excess = tasks.iter().filter(|task| is_stopped(task)).count().saturating_sub(MAX_STOPPED);
+        tasks.retain(|task| {
+            if excess > 0 && is_stopped(task) {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+
+    fn saved_queue(&self) -> Vec<SavedTask> {
+        self.tasks
+            .lock()
+            .iter()
+            .filter_map(|task| {
+                let state = task.state.lock();
+                let status = match state.status {
+                    DownloadStatus::Complete | DownloadStatus::Removed => return None,
+                    DownloadStatus::Active => DownloadStatus::Waiting,
+                    status => status,
+                };
+                Some(SavedTask {
+                    gid: task.gid.clone(),
+                    request: task.request.clone(),
+                    status,
+                    name: state.name.clone(),
+                    part: state.part.clone(),
+                    dir: state.dir.clone(),
+                    completed: task.completed.load(Ordering::Relaxed),
+                    total: state.total,
+                    error: state.error.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Start downloads, sample speeds, publish events and save the queue until the engine is dropped
+fn supervise(engine: Weak<NativeEngine>) {
+    let mut saved: Option<Vec<SavedTask>> = None;
+    let mut sampled_at = Instant::now();
+    let mut tracker = EventTracker::new();
+
+    loop {
+        thread::sleep(TICK);
+        let Some(engine) = engine.upgrade() else {
+            return;
+        };
+
+        engine.start_waiting();
+        if sampled_at.elapsed() >= Duration::from_secs(1) {
+            engine.sample_speeds(sampled_at.elapsed());
+            engine.trim_stopped();
+            sampled_at = Instant::now();
+        }
+
+        // Tracked even without subscribers so a new one only hears about what happens next
+        if let Ok(downloads) = engine.list() {
+            let events = tracker.update(&downloads);
+            if !events.is_empty() && !engine.subscribers.is_empty() {
+                engine.subscribers.publish(events);
+            }
+        }
+
+        // Progress changes constantly, only the queue itself is worth writing
+        let queue = engine.saved_queue();
+        let changed = saved.as_ref().is_none_or(|saved| {
+            saved.len() != queue.len()
+                || saved.iter().zip(&queue).any(|(old, new)| {
+                    old.gid != new.gid
+                        || old.status != new.status
+                        || old.name != new.name
+                        || old.part != new.part
+                        || old.error != new.error
+                })
+        });
+        if changed {
+            if let Err(e) = save_queue(&engine.queue_path, &queue) {
+                println!("Failed to save the download queue: {}", e);
+            }
+            saved = Some(queue);
+        }
+    }
+}
+
+/// Run a task's transfer on its own thread and record how it ended
+fn spawn_transfer(task: Arc<Task>, config: Config, limiter: Arc<RateLimiter>) {
+    thread::spawn(move || {
+        let result = Transfer::new(&task, &config, &limiter).and_then(|transfer| transfer.run());
+
+        let mut state = task.state.lock();
+        state.speed = 0;
+        match (result, task.control()) {
+            (Ok(Outcome::Complete(path)), _) => {
+                println!("Downloaded {}", path.display());
+                state.name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+                state.file = Some(path);
+                state.total = state.total.max(task.completed.load(Ordering::Relaxed));
+                state.status = DownloadStatus::Complete;
+            }
+            (_, Control::Remove) => {
+                if let Some(ref part) = state.part {
+                    discard(&state.dir, part);
+                }
+                state.status = DownloadStatus::Removed;
+            }
+            (_, Control::Pause) => state.status = DownloadStatus::Paused,
+            // Resumed before the transfer noticed it was paused, go again
+            (Ok(Outcome::Stopped), Control::Run) => state.status = DownloadStatus::Waiting,
+            (Err(e), Control::Run) => {
+                println!("Download {} failed: {}", task.gid, e);
+                state.status = DownloadStatus::Error;
+                state.error = Some(e.to_string());
+            }
+        }
+        drop(state);
+        task.running.store(false, Ordering::Relaxed);
+    });
+}
+
+fn is_stopped(task: &Task) -> bool {
+    !task.is_running()
+        && matches!(
+            task.state.lock().status,
+            DownloadStatus::Complete | DownloadStatus::Error | DownloadStatus::Removed
+        )
+}
+
+fn load_queue(path: &Path) -> Vec<Arc<Task>> {
+    let Ok(data) = std::fs::read(path) else {
+        return Vec::new();
+    };
+    let saved: Vec<SavedTask> = match serde_json::from_slice(&data) {
+        Ok(saved) => saved,
+        Err(e) => {
+            println!("Ignoring invalid download queue {}: {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+
+    saved
+        .into_iter()
+        .map(|saved| {
+            Arc::new(Task {
+                gid: saved.gid,
+                request: saved.request,
+                completed: AtomicU64::new(saved.completed),
+                control: AtomicU8::new(Control::Run as u8),
+                running: AtomicBool::new(false),
+                limiter: RateLimiter::default(),
+                state: Mutex::new(TaskState {
+                    status: saved.status,
+                    // Queues saved before .part names were reserved used the file name
+                    part: saved
+                        .part
+                        .or_else(|| saved.name.clone().filter(|name| saved.dir.join(format!("{}.part", name)).exists())),
+                    name: saved.name,
+                    dir: saved.dir,
+                    total: saved.total,
+                    sampled: saved.completed,
+                    error: saved.error,
+                    ..Default::default()
+                }),
+            })
+        })
+        .collect()
+}
+
+fn save_queue(path: &Path, queue: &[SavedTask]) -> anyhow::Result<()> {
+    write_atomic(path, &serde_json::to_vec_pretty(queue)?)
+}
+
+impl DownloadEngine for NativeEngine {
+    fn add(&self, request: &AddRequest) -> anyhow::Result<String> {
+        if request.file.is_some() {
+            anyhow::bail!("The built-in engine can't download .torrent or Metalink files, aria2 can");
+        }
+        let url = request.uris.first().ok_or_else(|| anyhow::anyhow!("A download needs a URL"))?;
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            anyhow::bail!("The built-in engine only downloads http and https URLs, not {}", url);
+        }
+
+        let config = self.config.read().clone();
+        let paused = config.start_paused || request.options.iter().any(|(key, value)| key == "pause" && value == "true");
+        let limit = request
+            .options
+            .iter()
+            .find(|(key, _)| key == "max-download-limit")
+            .and_then(|(_, value)| parse_size(value))
+            .unwrap_or_else(|| self.per_download_limit.load(Ordering::Relaxed));
+
+        let mut tasks = self.tasks.lock();
+        let gid = loop {
+            let gid = new_gid();
+            if !tasks.iter().any(|task| task.gid == gid) {
+                break gid;
+            }
+        };
+        tasks.push(Arc::new(Task {
+            gid: gid.clone(),
+            request: request.clone(),
+            completed: AtomicU64::new(0),
+            control: AtomicU8::new(Control::Run as u8),
+            running: AtomicBool::new(false),
+            limiter: RateLimiter::new(limit),
+            state: Mutex::new(TaskState {
+                status: if paused { DownloadStatus::Paused } else { DownloadStatus::Waiting },
+                name: request.out.as_deref().and_then(sanitize_name),
+                dir: request.dir.as_ref().map(PathBuf::from).unwrap_or(config.download_dir),
+                ..Default::default()
+            }),
+        }));
+        Ok(gid)
+    }
+
+    fn pause(&self, gid: &str) -> anyhow::Result<()> {
+        let task = self.find(gid)?;
+        let mut state = task.state.lock();
+        match state.status {
+            DownloadStatus::Active | DownloadStatus::Waiting => {
+                task.set_control(Control::Pause);
+                state.status = DownloadStatus::Paused;
+                Ok(())
+            }
+            DownloadStatus::Paused => Ok(()),
+            _ => anyhow::bail!("Download {} has already stopped", gid),
+        }
+    }
+
+    fn resume(&self, gid: &str) -> anyhow::Result<()> {
+        let task = self.find(gid)?;
+        let mut state = task.state.lock();
+        match state.status {
+            // Failed downloads are retried from where they stopped
+            DownloadStatus::Paused | DownloadStatus::Error => {
+                task.set_control(Control::Run);
+                state.status = if task.is_running() { DownloadStatus::Active } else { DownloadStatus::Waiting };
+                state.error = None;
+                Ok(())
+            }
+            DownloadStatus::Active | DownloadStatus::Waiting => Ok(()),
+            _ => anyhow::bail!("Download {} has already stopped", gid),
+        }
+    }
+
+    fn remove(&self, gid: &str) -> anyhow::Result<()> {
+        let task = self.find(gid)?;
+        let mut state = task.state.lock();
+        match state.status {
+            DownloadStatus::Active | DownloadStatus::Waiting | DownloadStatus::Paused => {
+                task.set_control(Control::Remove);
+                if !task.is_running()
+                    && let Some(ref part) = state.part
+                {
+                    discard(&state.dir, part);
+                }
+                state.status = DownloadStatus::Removed;
+            }
+            _ => {
+                drop(state);
+                self.tasks.lock().retain(|task| task.gid != gid || task.is_running());
+            }
+        }
+        Ok(())
+    }
+
+    fn pause_all(&self) -> anyhow::Result<()> {
+        let gids: Vec<String> = self.tasks.lock().iter().map(|task| task.gid.clone()).collect();
+        for gid in gids {
+            let _ = self.pause(&gid);
+        }
+        Ok(())
+    }
+
+    fn resume_all(&self) -> anyhow::Result<()> {
+        let paused: Vec<String> = self
+            .tasks
+            .lock()
+            .iter()
+            .filter(|task| task.state.lock().status == DownloadStatus::Paused)
+            .map(|task| task.gid.clone())
+            .collect();
+        for gid in paused {
+            self.resume(&gid)?;
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> anyhow::Result<Vec<Download>> {
+        let mut downloads: Vec<Download> = self.tasks.lock().iter().map(|task| task.to_download()).collect();
+        downloads.sort_by_key(|download| match download.status {
+            DownloadStatus::Active => 0,
+            DownloadStatus::Waiting | DownloadStatus::Paused => 1,
+            _ => 2,
+        });
+        Ok(downloads)
+    }
+
+    fn stats(&self) -> anyhow::Result<GlobalStat> {
+        let mut stat = GlobalStat::default();
+        for download in self.list()? {
+            match download.status {
+                DownloadStatus::Active => {
+                    stat.num_active += 1;
+                    stat.download_speed += download.download_speed;
+                }
+                DownloadStatus::Waiting | DownloadStatus::Paused => stat.num_waiting += 1,
+                _ => stat.num_stopped += 1,
+            }
+        }
+        Ok(stat)
+    }
+
+    fn download_limit(&self) -> anyhow::Result<u64> {
+        Ok(self.limiter.limit())
+    }
+
+    fn set_download_limit(&self, limit: u64) -> anyhow::Result<()> {
+        self.limiter.set_limit(limit);
+        Ok(())
+    }
+
+    fn apply_speed_profile(&self, profile: &SpeedProfile) -> anyhow::Result<()> {
+        self.limiter.set_limit(profile.download_limit);
+        self.per_download_limit.store(profile.per_download_limit, Ordering::Relaxed);
+        for task in self.tasks.lock().iter() {
+            task.limiter.set_limit(profile.per_download_limit);
+        }
+        Ok(())
+    }
+
+    fn status(&self, _expected_running: bool) -> EngineStatus {
+        EngineStatus {
+            engine: EngineKind::Native.label().to_string(),
+            running: true,
+            error: None,
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            endpoint: "built-in".to_string(),
+            stat: self.stats().unwrap_or_default(),
+        }
+    }
+
+    fn events(&self) -> EventStream {
+        self.subscribers.subscribe()
+    }
+}
@@ -0,0 +1,944 @@
+use super::{Config, Control, Task};
+use crate::modules::engine::AddRequest;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Bytes read between checks for pause and remove
+const CHUNK_SIZE: usize = 16 * 1024;
+/// Longest wait between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// How often the state file is rewritten while segments download
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+/// Redirects followed before giving up
+const MAX_REDIRECTS: u32 = 10;
+
+/// How a transfer ended without failing
+pub enum Outcome {
+    /// Everything was written to this file
+    Complete(PathBuf),
+    /// Paused or removed, see the task's control
+    Stopped,
+}
+
+/// A failed attempt, and whether trying again could help
+struct Failure {
+    error: anyhow::Error,
+    retry: bool,
+}
+
+impl Failure {
+    fn retry(error: anyhow::Error) -> Self {
+        Self { error, retry: true }
+    }
+
+    fn fatal(error: anyhow::Error) -> Self {
+        Self { error, retry: false }
+    }
+}
+
+/// Resume information kept next to the .part file
+#[derive(Serialize, Deserialize)]
+struct PartState {
+    url: String,
+    total: u64,
+    segments: Vec<SavedSegment>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSegment {
+    start: u64,
+    end: u64,
+    done: u64,
+}
+
+/// A byte range fetched over its own connection, `end` inclusive
+struct Segment {
+    start: u64,
+    end: u64,
+    done: AtomicU64,
+}
+
+impl Segment {
+    fn new(start: u64, end: u64, done: u64) -> Self {
+        Self {
+            start,
+            end,
+            done: AtomicU64::new(done),
+        }
+    }
+
+    fn position(&self) -> u64 {
+        self.start + self.done.load(Ordering::Relaxed)
+    }
+
+    fn finished(&self) -> bool {
+        self.position() > self.end
+    }
+}
+
+/// Caps throughput to a number of bytes per second, 0 for unlimited
+#[derive(Default)]
+pub struct RateLimiter {
+    limit: AtomicU64,
+    /// Start of the current one second window and the bytes let through in it
+    window: Mutex<Option<(Instant, u64)>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit: AtomicU64::new(limit),
+            window: Mutex::new(None),
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    pub fn set_limit(&self, limit: u64) {
+        self.limit.store(limit, Ordering::Relaxed);
+    }
+
+    /// Account for `bytes`, sleeping until they fit into the limit
+    fn consume(&self, bytes: u64) {
+        let limit = self.limit();
+        if limit == 0 {
+            return;
+        }
+
+        let wait = {
+            let mut window = self.window.lock();
+            let (started, sent) = match *window {
+                Some((started, sent)) if started.elapsed() < Duration::from_secs(1) => (started, sent + bytes),
+                _ => (Instant::now(), bytes),
+            };
+            *window = Some((started, sent));
+            Duration::from_secs_f64(sent as f64 / limit as f64).saturating_sub(started.elapsed())
+        };
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Everything a transfer needs besides its task
+pub struct Transfer<'a> {
+    task: &'a Task,
+    config: &'a Config,
+    /// Limit shared by all downloads
+    limiter: &'a RateLimiter,
+    agent: ureq::Agent,
+    /// Set when one segment failed for good, so the others stop too
+    abort: AtomicBool,
+}
+
+impl<'a> Transfer<'a> {
+    pub fn new(task: &'a Task, config: &'a Config, limiter: &'a RateLimiter) -> anyhow::Result<Self> {
+        let mut builder = ureq::AgentBuilder::new()
+            .timeout_connect(config.timeout)
+            .timeout_read(config.timeout)
+            .redirects(MAX_REDIRECTS)
+            .user_agent(&config.user_agent);
+        if !config.proxy.is_empty() {
+            let proxy = ureq::Proxy::new(&config.proxy)
+                .map_err(|e| anyhow::anyhow!("Unsupported proxy {}: {}", config.proxy, e))?;
+            builder = builder.proxy(proxy);
+        }
+
+        Ok(Self {
+            task,
+            config,
+            limiter,
+            agent: builder.build(),
+            abort: AtomicBool::new(false),
+        })
+    }
+
+    /// Download the task's URL into its directory, resuming a previous attempt
+    pub fn run(&self) -> anyhow::Result<Outcome> {
+        let url = self
+            .task
+            .request
+            .uris
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("The download has no URL"))?;
+        let dir = self.task.state.lock().dir.clone();
+        std::fs::create_dir_all(&dir).map_err(|e| anyhow::anyhow!("Failed to create {}: {}", dir.display(), e))?;
+
+        // Ask for the first byte only: a 206 tells the size and that ranges work,
+        // servers without range support send the whole file which is used as is
+        let Some(response) = self.retrying(|| self.fetch(url, Some((0, 0))))? else {
+            return Ok(Outcome::Stopped);
+        };
+        let ranged = response.status() == 206;
+        let total = if ranged {
+            response.header("Content-Range").and_then(range_total)
+        } else {
+            response.header("Content-Length").and_then(|length| length.parse().ok())
+        };
+
+        let name = {
+            let mut state = self.task.state.lock();
+            let name = state
+                .name
+                .clone()
+                .or_else(|| response.header("Content-Disposition").and_then(disposition_name))
+                .or_else(|| url_name(response.get_url()))
+                .unwrap_or_else(|| "download".to_string());
+            state.name = Some(name.clone());
+            state.total = total.unwrap_or(0);
+            name
+        };
+        // Another download of the same name in this folder must not share the .part file
+        let reserved = self.task.state.lock().part.clone();
+        let part_name = match reserved {
+            Some(part_name) => part_name,
+            None => {
+                let part_name = reserve_part(&dir, &name)?;
+                self.task.state.lock().part = Some(part_name.clone());
+                part_name
+            }
+        };
+        let part = dir.join(format!("{}.part", part_name));
+        let state_path = dir.join(format!("{}.part.json", part_name));
+
+        let finished = match total {
+            Some(total) if ranged && total > 0 => {
+                drop(response);
+                self.segmented(url, total, &part, &state_path)?
+            }
+            // A 206 without a known size says nothing about the rest of the file
+            _ if ranged => {
+                drop(response);
+                self.single(url, None, &part)?
+            }
+            _ => self.single(url, Some(response), &part)?,
+        };
+
+        if !finished {
+            return Ok(Outcome::Stopped);
+        }
+
+        let target = claim_target(&dir, &name)?;
+        if let Err(e) = std::fs::rename(&part, &target) {
+            let _ = std::fs::remove_file(&target);
+            anyhow::bail!("Failed to move the download to {}: {}", target.display(), e);
+        }
+        let _ = std::fs::remove_file(&state_path);
+        Ok(Outcome::Complete(target))
+    }
+
+    /// Download over several connections, returning whether every segment finished
+    fn segmented(&self, url: &str, total: u64, part: &Path, state_path: &Path) -> anyhow::Result<bool> {
+        let saved = std::fs::read(state_path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<PartState>(&data).ok())
+            .filter(|saved| saved.url == url && saved.total == total && part.exists());
+        let segments: Vec<Segment> = match saved {
+            Some(saved) => {
+                println!("Resuming {}", part.display());
+                saved
+                    .segments
+                    .iter()
+                    .map(|segment| Segment::new(segment.start, segment.end, segment.done))
+                    .collect()
+            }
+            None => {
+                let file = File::create(part).map_err(|e| anyhow::anyhow!("Failed to create {}: {}", part.display(), e))?;
+                file.set_len(total)?;
+                split(total, self.connections(), self.config.min_segment_size)
+            }
+        };
+        let done: u64 = segments.iter().map(|segment| segment.done.load(Ordering::Relaxed)).sum();
+        self.task.completed.store(done, Ordering::Relaxed);
+        save_state(state_path, url, total, &segments);
+
+        let results: Vec<anyhow::Result<()>> = thread::scope(|scope| {
+            let handles: Vec<_> = segments
+                .iter()
+                .filter(|segment| !segment.finished())
+                .map(|segment| {
+                    scope.spawn(|| {
+                        let result = self.download_segment(url, part, segment);
+                        if result.is_err() {
+                            self.abort.store(true, Ordering::Relaxed);
+                        }
+                        result
+                    })
+                })
+                .collect();
+
+            // Persist progress so a crash or restart only loses a few seconds
+            let mut saved_at = Instant::now();
+            while !handles.iter().all(|handle| handle.is_finished()) {
+                thread::sleep(Duration::from_millis(100));
+                if saved_at.elapsed() >= SAVE_INTERVAL {
+                    save_state(state_path, url, total, &segments);
+                    saved_at = Instant::now();
+                }
+            }
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|_| Err(anyhow::anyhow!("A segment thread panicked"))))
+                .collect()
+        });
+
+        save_state(state_path, url, total, &segments);
+        if let Some(error) = results.into_iter().find_map(Result::err) {
+            return Err(error);
+        }
+        Ok(segments.iter().all(Segment::finished))
+    }
+
+    /// Fetch one segment, retrying until it is complete, stopped or out of attempts
+    fn download_segment(&self, url: &str, part: &Path, segment: &Segment) -> anyhow::Result<()> {
+        let mut attempt = 0;
+        while !segment.finished() && !self.stopped() {
+            let before = segment.position();
+            let Err(failure) = self.fetch_segment(url, part, segment) else {
+                continue;
+            };
+            // Only consecutive failures count, a flaky connection that makes progress is fine
+            if segment.position() > before {
+                attempt = 0;
+            }
+            if !failure.retry || attempt >= self.config.max_retries {
+                return Err(failure.error);
+            }
+            attempt += 1;
+            println!("Retrying {} (attempt {}): {}", url, attempt, failure.error);
+            self.backoff(attempt);
+        }
+        Ok(())
+    }
+
+    fn fetch_segment(&self, url: &str, part: &Path, segment: &Segment) -> Result<(), Failure> {
+        let position = segment.position();
+        let response = self.fetch(url, Some((position, segment.end)))?;
+        if response.status() != 206 {
+            return Err(Failure::fatal(anyhow::anyhow!("The server stopped answering range requests")));
+        }
+
+        let mut file = OpenOptions::new().write(true).open(part).map_err(|e| disk_error(part, e))?;
+        file.seek(SeekFrom::Start(position)).map_err(|e| disk_error(part, e))?;
+        let mut reader = response.into_reader().take(segment.end + 1 - position);
+        self.copy(&mut reader, &mut file, part, |read| {
+            segment.done.fetch_add(read, Ordering::Relaxed);
+        })?;
+
+        if !segment.finished() && !self.stopped() {
+            return Err(Failure::retry(anyhow::anyhow!("The connection closed early")));
+        }
+        Ok(())
+    }
+
+    /// Download over a single connection from the start, for servers without range
+    /// support. Returns whether the whole file was written.
+    fn single(&self, url: &str, mut response: Option<ureq::Response>, part: &Path) -> anyhow::Result<bool> {
+        let mut attempt = 0;
+        loop {
+            if self.stopped() {
+                return Ok(false);
+            }
+            let result = match response.take() {
+                Some(response) => self.stream(response, part),
+                None => self.fetch(url, None).and_then(|response| self.stream(response, part)),
+            };
+            let failure = match result {
+                Ok(()) if self.stopped() => return Ok(false),
+                Ok(()) => return Ok(true),
+                Err(failure) => failure,
+            };
+            if !failure.retry || attempt >= self.config.max_retries {
+                return Err(failure.error);
+            }
+            attempt += 1;
+            println!("Retrying {} (attempt {}): {}", url, attempt, failure.error);
+            self.backoff(attempt);
+        }
+    }
+
+    fn stream(&self, response: ureq::Response, part: &Path) -> Result<(), Failure> {
+        let length: Option<u64> = response.header("Content-Length").and_then(|length| length.parse().ok());
+        let mut file = File::create(part).map_err(|e| disk_error(part, e))?;
+        self.task.completed.store(0, Ordering::Relaxed);
+
+        let mut written = 0;
+        self.copy(&mut response.into_reader(), &mut file, part, |read| written += read)?;
+        if let Some(length) = length
+            && written < length
+            && !self.stopped()
+        {
+            return Err(Failure::retry(anyhow::anyhow!("The connection closed early")));
+        }
+        self.task.state.lock().total = written.max(length.unwrap_or(0));
+        Ok(())
+    }
+
+    /// Copy until the reader ends or the task is stopped, counting progress
+    fn copy(&self, reader: &mut impl Read, file: &mut File, path: &Path, mut progress: impl FnMut(u64)) -> Result<(), Failure> {
+        let mut buffer = vec![0; CHUNK_SIZE];
+        while !self.stopped() {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Failure::retry(anyhow::anyhow!("Connection failed: {}", e))),
+            };
+            file.write_all(&buffer[..read]).map_err(|e| disk_error(path, e))?;
+
+            let read = read as u64;
+            progress(read);
+            self.task.completed.fetch_add(read, Ordering::Relaxed);
+            self.limiter.consume(read);
+            self.task.limiter.consume(read);
+        }
+        Ok(())
+    }
+
+    /// GET the URL, optionally a byte range, with the request's headers
+    fn fetch(&self, url: &str, range: Option<(u64, u64)>) -> Result<ureq::Response, Failure> {
+        let mut request = self.agent.get(url);
+        for (name, value) in headers(&self.task.request) {
+            request = request.set(&name, &value);
+        }
+        if let Some((start, end)) = range {
+            request = request.set("Range", &format!("bytes={}-{}", start, end));
+        }
+
+        match request.call() {
+            Ok(response) => Ok(response),
+            // Client errors won't go away by asking again, except for timeouts and throttling
+            Err(ureq::Error::Status(code, response)) => Err(Failure {
+                error: anyhow::anyhow!("The server answered {} {}", code, response.status_text()),
+                retry: code == 408 || code == 429 || code >= 500,
+            }),
+            Err(e) => Err(Failure::retry(anyhow::anyhow!("Request failed: {}", e))),
+        }
+    }
+
+    /// Run an attempt until it succeeds, retrying with backoff. None when the task was
+    /// stopped before it succeeded.
+    fn retrying<T>(&self, mut attempt_fn: impl FnMut() -> Result<T, Failure>) -> anyhow::Result<Option<T>> {
+        let mut attempt = 0;
+        while !self.stopped() {
+            let failure = match attempt_fn() {
+                Ok(value) => return Ok(Some(value)),
+                Err(failure) => failure,
+            };
+            if !failure.retry || attempt >= self.config.max_retries {
+                return Err(failure.error);
+            }
+            attempt += 1;
+            println!("Retrying {} (attempt {}): {}", self.task.gid, attempt, failure.error);
+            self.backoff(attempt);
+        }
+        Ok(None)
+    }
+
+    /// Wait 2, 4, 8... seconds before the next attempt, waking up early when stopped
+    fn backoff(&self, attempt: u32) {
+        let delay = Duration::from_secs(1 << attempt.min(5)).min(MAX_BACKOFF);
+        let until = Instant::now() + delay;
+        while Instant::now() < until && !self.stopped() {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        self.task.control() != Control::Run || self.abort.load(Ordering::Relaxed)
+    }
+
+    /// Connections for this download, the request's split option winning over the settings
+    fn connections(&self) -> u32 {
+        self.task
+            .request
+            .options
+            .iter()
+            .find(|(key, _)| key == "split")
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(self.config.connections)
+            .clamp(1, 16)
+    }
+}
+
+/// Delete the .part and state files of an unfinished download, `part_name` being the
+/// name `reserve_part` picked
+pub fn discard(dir: &Path, part_name: &str) {
+    let _ = std::fs::remove_file(dir.join(format!("{}.part", part_name)));
+    let _ = std::fs::remove_file(dir.join(format!("{}.part.json", part_name)));
+}
+
+/// Claim `<name>.part` by creating it, or "name (1).ext.part" and so on when another
+/// download already owns that one. Returns the name without the .part extension.
+fn reserve_part(dir: &Path, name: &str) -> anyhow::Result<String> {
+    for index in 0.. {
+        let part_name = numbered(name, index);
+        let part = dir.join(format!("{}.part", part_name));
+        match OpenOptions::new().write(true).create_new(true).open(&part) {
+            Ok(_) => return Ok(part_name),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => anyhow::bail!("Failed to create {}: {}", part.display(), e),
+        }
+    }
+    unreachable!()
+}
+
+fn save_state(path: &Path, url: &str, total: u64, segments: &[Segment]) {
+    let state = PartState {
+        url: url.to_string(),
+        total,
+        segments: segments
+            .iter()
+            .map(|segment| SavedSegment {
+                start: segment.start,
+                end: segment.end,
+                done: segment.done.load(Ordering::Relaxed),
+            })
+            .collect(),
+    };
+    let result = serde_json::to_vec(&state)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| crate::modules::settings::write_atomic(path, &data));
+    if let Err(e) = result {
+        println!("Failed to save {}: {}", path.display(), e);
+    }
+}
+
+/// Split `total` bytes into at most `connections` segments of at least `min_size`
+fn split(total: u64, connections: u32, min_size: u64) -> Vec<Segment> {
+    let count = (total / min_size.max(1)).clamp(1, connections as u64);
+    let size = total.div_ceil(count);
+    (0..count)
+        .map(|i| i * size)
+        .filter(|start| *start < total)
+        .map(|start| Segment::new(start, (start + size).min(total) - 1, 0))
+        .collect()
+}
+
+/// Headers given with the request plus the referrer and user agent options
+fn headers(request: &AddRequest) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = request
+        .headers
+        .iter()
+        .filter_map(|header| header.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    for (key, value) in &request.options {
+        match key.as_str() {
+            "referer" => headers.push(("Referer".to_string(), value.clone())),
+            "user-agent" => headers.push(("User-Agent".to_string(), value.clone())),
+            _ => {}
+        }
+    }
+    headers
+}
+
+fn disk_error(path: &Path, e: std::io::Error) -> Failure {
+    Failure::fatal(anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Size of the whole file from a Content-Range such as "bytes 0-0/1234"
+fn range_total(value: &str) -> Option<u64> {
+    value.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+/// File name suggested by a Content-Disposition header, preferring the RFC 5987
+/// `filename*` form over the plain one
+pub fn disposition_name(value: &str) -> Option<String> {
+    let params: Vec<(String, &str)> = value
+        .split(';')
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim()))
+        .collect();
+
+    let extended = params
+        .iter()
+        .find(|(key, _)| key == "filename*")
+        .and_then(|(_, value)| value.trim_matches('"').split_once("''"))
+        .map(|(_, encoded)| percent_decode(encoded));
+    let plain = || {
+        params
+            .iter()
+            .find(|(key, _)| key == "filename")
+            .map(|(_, value)| value.trim_matches('"').to_string())
+    };
+    extended.or_else(plain).and_then(|name| sanitize_name(&name))
+}
+
+/// Last path segment of a URL, None when the path is empty
+pub fn url_name(url: &str) -> Option<String> {
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    let (_, path) = url.split_once("://").map_or(("", url), |(_, rest)| rest.split_once('/').unwrap_or((rest, "")));
+    let name = path.rsplit('/').next()?;
+    sanitize_name(&percent_decode(name))
+}
+
+/// A file name that stays inside the download folder and is valid everywhere
+pub fn sanitize_name(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| if matches!(c, ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .collect();
+    let name = name.trim();
+    if name.is_empty() || name.chars().all(|c| c == '.') {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) if bytes[i] == b'%' => {
+                decoded.push(byte);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// `name` in `dir`, or "name (1).ext" and so on when that already exists
+/// Create an empty file under the first free "name (index).ext", so downloads of the
+/// same name finishing together can't both pick it. The finished file replaces it.
+fn claim_target(dir: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    for index in 0.. {
+        let target = dir.join(numbered(name, index));
+        match OpenOptions::new().write(true).create_new(true).open(&target) {
+            Ok(_) => return Ok(target),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => anyhow::bail!("Failed to create {}: {}", target.display(), e),
+        }
+    }
+    unreachable!()
+}
+
+/// "name (index).ext", the name itself for index 0
+fn numbered(name: &str, index: u32) -> String {
+    if index == 0 {
+        return name.to_string();
+    }
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{} ({}).{}", stem, index, ext),
+        _ => format!("{} ({})", name, index),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::TaskState;
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicU8, AtomicUsize};
+    use std::sync::Arc;
+
+    /// What the test server was asked for
+    struct Request {
+        path: String,
+        /// First and last byte of a Range header
+        range: Option<(u64, u64)>,
+    }
+
+    /// Serve every connection with `handler`, which returns the raw response
+    fn serve(handler: impl Fn(&Request) -> Vec<u8> + Send + Sync + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handler = Arc::new(handler);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let handler = handler.clone();
+                thread::spawn(move || {
+                    let mut stream = stream;
+                    let mut head = Vec::new();
+                    let mut byte = [0; 1];
+                    while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).is_ok_and(|read| read == 1) {
+                        head.push(byte[0]);
+                    }
+                    let head = String::from_utf8_lossy(&head).into_owned();
+                    let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    let range = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Range: bytes="))
+                        .and_then(|range| range.split_once('-'))
+                        .and_then(|(start, end)| Some((start.parse().ok()?, end.parse().ok()?)));
+                    let _ = stream.write_all(&handler(&Request { path, range }));
+                });
+            }
+        });
+        base
+    }
+
+    fn response(status: &str, headers: &[String], body: &[u8]) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+        for header in headers {
+            response.push_str(header);
+            response.push_str("\r\n");
+        }
+        response.push_str("\r\n");
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    /// The requested part of `body` as a 206, or all of it as a 200
+    fn ranged(request: &Request, body: &[u8]) -> Vec<u8> {
+        match request.range {
+            Some((start, end)) => {
+                let end = end.min(body.len() as u64 - 1);
+                let range = format!("Content-Range: bytes {}-{}/{}", start, end, body.len());
+                response("206 Partial Content", &[range], &body[start as usize..=end as usize])
+            }
+            None => response("200 OK", &[], body),
+        }
+    }
+
+    fn body(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bong-transfer-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn config() -> Config {
+        Config {
+            download_dir: PathBuf::new(),
+            max_concurrent: 4,
+            start_paused: false,
+            connections: 4,
+            min_segment_size: 16 * 1024,
+            max_retries: 3,
+            user_agent: "bong-test".to_string(),
+            proxy: String::new(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn task(url: &str, dir: &Path) -> Task {
+        Task {
+            gid: "test".to_string(),
+            request: AddRequest::new(url),
+            completed: AtomicU64::new(0),
+            control: AtomicU8::new(Control::Run as u8),
+            running: AtomicBool::new(true),
+            limiter: RateLimiter::default(),
+            state: Mutex::new(TaskState {
+                dir: dir.to_path_buf(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn run(task: &Task) -> PathBuf {
+        let limiter = RateLimiter::default();
+        match Transfer::new(task, &config(), &limiter).unwrap().run().unwrap() {
+            Outcome::Complete(path) => path,
+            Outcome::Stopped => panic!("The transfer stopped"),
+        }
+    }
+
+    #[test]
+    fn downloads_segments_in_parallel() {
+        let data = body(100 * 1024);
+        let served = data.clone();
+        let ranges = Arc::new(AtomicUsize::new(0));
+        let counted = ranges.clone();
+        let base = serve(move |request| {
+            counted.fetch_add(1, Ordering::Relaxed);
+            ranged(request, &served)
+        });
+        let dir = temp_dir("segments");
+
+        let path = run(&task(&format!("{}/files/data.bin", base), &dir));
+        assert_eq!(path, dir.join("data.bin"));
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        // The probe plus one request per segment
+        assert_eq!(ranges.load(Ordering::Relaxed), 5);
+        assert!(!dir.join("data.bin.part").exists());
+        assert!(!dir.join("data.bin.part.json").exists());
+    }
+
+    #[test]
+    fn downloads_without_range_support() {
+        let data = body(40 * 1024);
+        let served = data.clone();
+        let base = serve(move |_| response("200 OK", &[], &served));
+        let dir = temp_dir("no-ranges");
+
+        let path = run(&task(&format!("{}/plain.bin", base), &dir));
+        assert_eq!(std::fs::read(path).unwrap(), data);
+    }
+
+    #[test]
+    fn resumes_from_the_part_file() {
+        let data = body(64 * 1024);
+        let served = data.clone();
+        let starts = Arc::new(Mutex::new(Vec::new()));
+        let seen = starts.clone();
+        let base = serve(move |request| {
+            if let Some((start, _)) = request.range {
+                seen.lock().push(start);
+            }
+            ranged(request, &served)
+        });
+        let dir = temp_dir("resume");
+        let url = format!("{}/resume.bin", base);
+
+        // Half of each of two segments was written by an earlier run
+        let half = 16 * 1024;
+        let mut part = data.clone();
+        part[half as usize..32 * 1024].fill(0);
+        part[48 * 1024..].fill(0);
+        std::fs::write(dir.join("resume.bin.part"), &part).unwrap();
+        let segments = [Segment::new(0, 32 * 1024 - 1, half), Segment::new(32 * 1024, 64 * 1024 - 1, half)];
+        save_state(&dir.join("resume.bin.part.json"), &url, data.len() as u64, &segments);
+
+        let task = task(&url, &dir);
+        task.state.lock().part = Some("resume.bin".to_string());
+        let path = run(&task);
+        assert_eq!(std::fs::read(path).unwrap(), data);
+        let mut starts = starts.lock().clone();
+        starts.sort();
+        assert_eq!(starts, vec![0, half, 32 * 1024 + half]);
+    }
+
+    #[test]
+    fn names_the_file_after_content_disposition() {
+        let base = serve(|request| {
+            let disposition = "Content-Disposition: attachment; filename=\"fallback.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf".to_string();
+            let mut response = ranged(request, b"pdf");
+            let at = response.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 2;
+            response.splice(at..at, format!("{}\r\n", disposition).into_bytes());
+            response
+        });
+        let dir = temp_dir("disposition");
+
+        let path = run(&task(&format!("{}/download?id=1", base), &dir));
+        assert_eq!(path, dir.join("résumé.pdf"));
+    }
+
+    #[test]
+    fn follows_redirects() {
+        let base = serve(|request| match request.path.as_str() {
+            "/old" => response("302 Found", &["Location: /new/file.txt".to_string()], b""),
+            _ => ranged(request, b"moved"),
+        });
+        let dir = temp_dir("redirect");
+
+        let path = run(&task(&format!("{}/old", base), &dir));
+        assert_eq!(path, dir.join("file.txt"));
+        assert_eq!(std::fs::read(path).unwrap(), b"moved");
+    }
+
+    #[test]
+    fn retries_after_service_unavailable() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counted = attempts.clone();
+        let base = serve(move |request| match counted.fetch_add(1, Ordering::Relaxed) {
+            0 => response("503 Service Unavailable", &[], b""),
+            _ => ranged(request, b"finally"),
+        });
+        let dir = temp_dir("retry");
+
+        let path = run(&task(&format!("{}/busy.txt", base), &dir));
+        assert_eq!(std::fs::read(path).unwrap(), b"finally");
+        assert!(attempts.load(Ordering::Relaxed) >= 2);
+    }
+
+    #[test]
+    fn same_names_get_their_own_part_files() {
+        let data = body(64 * 1024);
+        let served = data.clone();
+        let base = serve(move |request| ranged(request, &served));
+        let dir = temp_dir("same-name");
+        let url = format!("{}/same.bin", base);
+
+        let (first, second) = (task(&url, &dir), task(&url, &dir));
+        let paths = thread::scope(|scope| {
+            let first = scope.spawn(|| run(&first));
+            let second = scope.spawn(|| run(&second));
+            [first.join().unwrap(), second.join().unwrap()]
+        });
+        let mut names: Vec<_> = paths.iter().map(|path| path.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        names.sort();
+        assert_eq!(names, vec!["same (1).bin", "same.bin"]);
+        for path in paths {
+            assert_eq!(std::fs::read(path).unwrap(), data);
+        }
+        let mut parts = [first.state.lock().part.clone(), second.state.lock().part.clone()];
+        parts.sort();
+        assert_eq!(parts, [Some("same (1).bin".to_string()), Some("same.bin".to_string())]);
+    }
+
+    #[test]
+    fn claims_each_target_once() {
+        let dir = temp_dir("claim");
+        let claimed: Vec<PathBuf> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..8).map(|_| scope.spawn(|| claim_target(&dir, "same.bin").unwrap())).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        let mut names: Vec<_> = claimed.iter().map(|path| path.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 8);
+        assert!(names.contains(&"same.bin".to_string()));
+        assert!(names.contains(&"same (7).bin".to_string()));
+    }
+
+    #[test]
+    fn splits_into_segments() {
+        let ranges = |segments: Vec<Segment>| segments.iter().map(|segment| (segment.start, segment.end)).collect::<Vec<_>>();
+        assert_eq!(ranges(split(100, 4, 10)), vec![(0, 24), (25, 49), (50, 74), (75, 99)]);
+        // Segments don't get smaller than the minimum
+        assert_eq!(ranges(split(100, 8, 40)), vec![(0, 49), (50, 99)]);
+        assert_eq!(ranges(split(10, 4, 100)), vec![(0, 9)]);
+        assert_eq!(ranges(split(10, 4, 0)), vec![(0, 2), (3, 5), (6, 8), (9, 9)]);
+    }
+
+    #[test]
+    fn reads_the_total_of_a_content_range() {
+        assert_eq!(range_total("bytes 0-0/1234"), Some(1234));
+        assert_eq!(range_total("bytes 0-0/*"), None);
+        assert_eq!(range_total("bytes 0-0"), None);
+    }
+
+    #[test]
+    fn names_files() {
+        assert_eq!(disposition_name("attachment; filename=\"a b.zip\""), Some("a b.zip".to_string()));
+        assert_eq!(disposition_name("attachment; filename*=UTF-8''%E2%82%AC.txt; filename=x.txt"), Some("€.txt".to_string()));
+        assert_eq!(disposition_name("attachment; filename=\"../../etc/passwd\""), Some("passwd".to_string()));
+        assert_eq!(disposition_name("inline"), None);
+
+        assert_eq!(url_name("https://example.com/dir/file%20name.iso?x=1#top"), Some("file name.iso".to_string()));
+        assert_eq!(url_name("https://example.com/"), None);
+        assert_eq!(url_name("https://example.com"), None);
+
+        assert_eq!(sanitize_name("a:b*c?.txt"), Some("a_b_c_.txt".to_string()));
+        assert_eq!(sanitize_name("C:\\Windows\\evil.exe"), Some("evil.exe".to_string()));
+        assert_eq!(sanitize_name(" ..  "), None);
+        assert_eq!(sanitize_name("bad\u{7}name"), Some("badname".to_string()));
+        assert_eq!(numbered("archive.tar.gz", 2), "archive.tar (2).gz");
+        assert_eq!(numbered(".hidden", 1), ".hidden (1)");
+    }
+}
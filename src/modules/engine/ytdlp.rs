@@ -0,0 +1,848 @@
+use super::events::Subscribers;
+use super::native::Control;
+use super::{new_gid, AddRequest, Download, DownloadEngine, DownloadStatus, EngineStatus, EventStream, EventTracker, GlobalStat};
+use crate::modules::settings::{config_dir, write_atomic, Settings, SpeedProfile};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+/// GIDs of yt-dlp jobs start with this, aria2 and the built-in engine only use hex digits
+const GID_PREFIX: &str = "yt";
+/// Finished jobs kept in the list
+const MAX_STOPPED: usize = 1000;
+/// How often waiting jobs are started and the queue is saved
+const TICK: Duration = Duration::from_millis(250);
+/// Starts the lines bong asks yt-dlp to print, anything else is yt-dlp's own output
+const MARKER: &str = "bong:";
+
+/// Whether a URL is on one of `hosts` or their subdomains, the video sites from the
+/// settings. Other sites yt-dlp supports can be added there, and picking a format in
+/// the video view sends any page to yt-dlp, since requests with a format always go there.
+pub fn is_video_host(hosts: &[String], url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) else {
+        return false;
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default().to_ascii_lowercase();
+
+    hosts.iter().any(|known| {
+        let known = known.to_ascii_lowercase();
+        host == known || host.strip_suffix(&known).is_some_and(|sub| sub.ends_with('.'))
+    })
+}
+
+/// Whether a download belongs to the yt-dlp engine
+pub fn is_video_download(gid: &str) -> bool {
+    gid.starts_with(GID_PREFIX)
+}
+
+/// A video page and the formats it can be downloaded in
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VideoInfo {
+    pub title: String,
+    /// Length in seconds
+    pub duration: Option<u64>,
+    /// Worst to best, as yt-dlp sorts them
+    pub formats: Vec<VideoFormat>,
+}
+
+/// One way yt-dlp can download a video
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VideoFormat {
+    pub id: String,
+    pub ext: String,
+    /// e.g. "1920x1080" or "audio only"
+    pub resolution: String,
+    /// e.g. "1080p60" or "medium"
+    pub note: String,
+    /// Exact or estimated size in bytes
+    pub filesize: Option<u64>,
+    pub has_video: bool,
+    pub has_audio: bool,
+}
+
+impl VideoFormat {
+    /// Format selector for yt-dlp, video-only formats get the best audio merged in
+    pub fn selector(&self) -> String {
+        if self.has_video && !self.has_audio {
+            format!("{}+bestaudio/{}", self.id, self.id)
+        } else {
+            self.id.clone()
+        }
+    }
+}
+
+/// Ask yt-dlp which formats a video page offers
+pub fn probe(settings: &Settings, url: &str) -> anyhow::Result<VideoInfo> {
+    let binary = &settings.video.binary;
+    let mut command = Command::new(binary);
+    command.args(["--dump-single-json", "--no-playlist", "--no-warnings"]);
+    if !settings.network.proxy.is_empty() {
+        command.args(["--proxy", &settings.network.proxy]);
+    }
+    let output = command
+        .arg("--")
+        .arg(url)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| launch_error(binary, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(error_line(&stderr).unwrap_or_else(|| format!("yt-dlp exited with {}", output.status)));
+    }
+
+    parse_info(&serde_json::from_slice(&output.stdout)?)
+}
+
+fn parse_info(value: &serde_json::Value) -> anyhow::Result<VideoInfo> {
+    let text = |value: &serde_json::Value, key: &str| value[key].as_str().unwrap_or_default().to_string();
+
+    let formats: Vec<VideoFormat> = value["formats"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        // Storyboards are thumbnails, not something to download
+        .filter(|format| format["ext"] != "mhtml")
+        .map(|format| VideoFormat {
+            id: text(format, "format_id"),
+            ext: text(format, "ext"),
+            resolution: text(format, "resolution"),
+            note: text(format, "format_note"),
+            filesize: format["filesize"].as_u64().or_else(|| format["filesize_approx"].as_f64().map(|size| size as u64)),
+            has_video: format["vcodec"].as_str().is_some_and(|codec| codec != "none"),
+            has_audio: format["acodec"].as_str().is_some_and(|codec| codec != "none"),
+        })
+        .filter(|format| !format.id.is_empty())
+        .collect();
+    if formats.is_empty() {
+        anyhow::bail!("yt-dlp found no formats to download");
+    }
+
+    Ok(VideoInfo {
+        title: text(value, "title"),
+        duration: value["duration"].as_f64().map(|duration| duration as u64),
+        formats,
+    })
+}
+
+/// Options of the yt-dlp engine taken from the settings
+#[derive(Debug, Clone, PartialEq)]
+struct Config {
+    binary: PathBuf,
+    download_dir: PathBuf,
+    max_concurrent: usize,
+    /// Queue new jobs paused
+    start_paused: bool,
+    format: String,
+    proxy: String,
+}
+
+impl Config {
+    fn from_settings(settings: &Settings) -> Self {
+        Self {
+            binary: settings.video.binary.clone(),
+            download_dir: settings.downloads.download_dir.clone(),
+            max_concurrent: settings.downloads.max_concurrent as usize,
+            start_paused: !settings.downloads.auto_start,
+            format: settings.video.format.clone(),
+            proxy: settings.network.proxy.clone(),
+        }
+    }
+}
+
+/// A video page being downloaded by a yt-dlp process
+struct Job {
+    gid: String,
+    request: AddRequest,
+    control: AtomicU8,
+    /// A thread is watching the job's process
+    running: AtomicBool,
+    /// The process was told to stop, its exit isn't a failure even if the job was
+    /// resumed before it ended
+    interrupted: AtomicBool,
+    /// The yt-dlp process while the job runs
+    child: Mutex<Option<Child>>,
+    state: Mutex<JobState>,
+}
+
+#[derive(Default)]
+struct JobState {
+    status: DownloadStatus,
+    /// Video title once yt-dlp printed it
+    title: Option<String>,
+    dir: PathBuf,
+    /// Finished files after yt-dlp moved them into place
+    files: Vec<PathBuf>,
+    /// Temporary files written so far, deleted when the job is removed
+    partial: Vec<PathBuf>,
+    /// Bytes of the parts finished in this run, a format may be a video and an audio part
+    done: u64,
+    /// Progress of the current part
+    downloaded: u64,
+    total: u64,
+    /// Bytes per second
+    speed: u64,
+    error: Option<String>,
+}
+
+impl Job {
+    fn control(&self) -> Control {
+        match self.control.load(Ordering::Relaxed) {
+            0 => Control::Run,
+            1 => Control::Pause,
+            _ => Control::Remove,
+        }
+    }
+
+    /// Tell the job what to do, killing its process unless it should keep running
+    fn set_control(&self, control: Control) {
+        self.control.store(control as u8, Ordering::Relaxed);
+        if control == Control::Run {
+            return;
+        }
+        self.interrupted.store(true, Ordering::Relaxed);
+        if let Some(ref mut child) = *self.child.lock() {
+            let _ = child.kill();
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    fn to_download(&self) -> Download {
+        let state = self.state.lock();
+        let name = match (&state.files.last(), &state.title) {
+            (Some(file), _) => file.file_name().map(|name| name.to_string_lossy().into_owned()),
+            (None, Some(title)) => Some(title.clone()),
+            (None, None) => None,
+        };
+        let completed = state.done + state.downloaded;
+
+        Download {
+            gid: self.gid.clone(),
+            status: state.status,
+            name: name.unwrap_or_else(|| self.request.uris.first().cloned().unwrap_or_else(|| self.gid.clone())),
+            dir: state.dir.display().to_string(),
+            files: state.files.iter().map(|file| file.display().to_string()).collect(),
+            uris: self.request.uris.clone(),
+            total_length: (state.done + state.total).max(completed),
+            completed_length: completed,
+            download_speed: if state.status == DownloadStatus::Active { state.speed } else { 0 },
+            upload_speed: 0,
+            error_message: state.error.clone(),
+            metadata: false,
+            followed_by: None,
+        }
+    }
+
+    /// Take in a line bong asked yt-dlp to print
+    fn record(&self, line: &str) {
+        let Some(line) = line.strip_prefix(MARKER) else {
+            return;
+        };
+        let (kind, value) = line.split_once(' ').unwrap_or((line, ""));
+        let mut state = self.state.lock();
+        match kind {
+            "title" => state.title = Some(value.to_string()),
+            "file" => state.files.push(PathBuf::from(value)),
+            "progress" => {
+                // status, downloaded, total, estimated total, speed, temporary file
+                let fields: Vec<&str> = value.splitn(6, ' ').collect();
+                let [status, downloaded, total, estimate, speed, partial] = fields[..] else {
+                    return;
+                };
+                let number = |value: &str| value.parse::<f64>().ok().map(|value| value as u64);
+                state.downloaded = number(downloaded).unwrap_or(state.downloaded);
+                state.total = number(total).or_else(|| number(estimate)).unwrap_or(state.total);
+                state.speed = number(speed).unwrap_or(0);
+                if partial != "NA" && !state.partial.iter().any(|path| path.as_os_str() == partial) {
+                    state.partial.push(PathBuf::from(partial));
+                }
+                if status == "finished" {
+                    state.done += state.downloaded.max(state.total);
+                    state.downloaded = 0;
+                    state.total = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// An unfinished job as written to the queue file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedJob {
+    gid: String,
+    request: AddRequest,
+    status: DownloadStatus,
+    title: Option<String>,
+    dir: PathBuf,
+    partial: Vec<PathBuf>,
+    error: Option<String>,
+}
+
+/// Downloads from video pages by running one yt-dlp process per job. Progress comes
+/// from yt-dlp's `--progress-template` output, pausing kills the process and resuming
+/// runs it again, yt-dlp continues from its `.part` files.
+pub struct YtDlpEngine {
+    config: RwLock<Config>,
+    jobs: Mutex<Vec<Arc<Job>>>,
+    /// Rate every job is started with, processes can't share a limit
+    limit: AtomicU64,
+    subscribers: Subscribers,
+    queue_path: PathBuf,
+    /// Set while the background task exits, so killed jobs aren't saved as failed
+    closing: AtomicBool,
+}
+
+impl YtDlpEngine {
+    /// Start the engine, restoring the queue saved by a previous run
+    pub fn open(settings: &Settings) -> Arc<Self> {
+        Self::open_at(settings, config_dir().join("ytdlp-queue.json"))
+    }
+
+    pub fn open_at(settings: &Settings, queue_path: PathBuf) -> Arc<Self> {
+        let engine = Arc::new(Self {
+            config: RwLock::new(Config::from_settings(settings)),
+            jobs: Mutex::new(load_queue(&queue_path)),
+            limit: AtomicU64::new(0),
+            subscribers: Subscribers::default(),
+            queue_path,
+            closing: AtomicBool::new(false),
+        });
+
+        let weak = Arc::downgrade(&engine);
+        thread::spawn(move || supervise(weak));
+        engine
+    }
+
+    /// Follow changed settings, running jobs keep the options they started with
+    pub fn configure(&self, settings: &Settings) {
+        let config = Config::from_settings(settings);
+        if *self.config.read() != config {
+            *self.config.write() = config;
+        }
+    }
+
+    /// Kill every yt-dlp process, the queue file still lists them to resume on the next start
+    pub fn shutdown(&self) {
+        self.closing.store(true, Ordering::Relaxed);
+        for job in self.jobs.lock().iter() {
+            if let Some(ref mut child) = *job.child.lock() {
+                let _ = child.kill();
+            }
+        }
+    }
+
+    fn find(&self, gid: &str) -> anyhow::Result<Arc<Job>> {
+        self.jobs
+            .lock()
+            .iter()
+            .find(|job| job.gid == gid)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Download {} doesn't exist", gid))
+    }
+
+    /// Start waiting jobs while there is room
+    fn start_waiting(&self) {
+        let config = self.config.read().clone();
+        let limit = self.limit.load(Ordering::Relaxed);
+        let jobs = self.jobs.lock();
+        let mut running = jobs.iter().filter(|job| job.is_running()).count();
+
+        for job in jobs.iter() {
+            if running >= config.max_concurrent {
+                break;
+            }
+            if job.is_running() {
+                continue;
+            }
+            {
+                let mut state = job.state.lock();
+                if state.status != DownloadStatus::Waiting {
+                    continue;
+                }
+                state.status = DownloadStatus::Active;
+                state.error = None;
+            }
+
+            job.set_control(Control::Run);
+            job.interrupted.store(false, Ordering::Relaxed);
+            job.running.store(true, Ordering::Relaxed);
+            running += 1;
+            spawn_job(job.clone(), config.clone(), limit);
+        }
+    }
+
+    /// Forget the oldest finished jobs beyond MAX_STOPPED
+    fn trim_stopped(&self) {
+        let mut jobs = self.jobs.lock();
+        let mut excess = jobs.iter().filter(|job| is_stopped(job)).count().saturating_sub(MAX_STOPPED);
+        jobs.retain(|job| {
+            if excess > 0 && is_stopped(job) {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+
+    fn saved_queue(&self) -> Vec<SavedJob> {
+        self.jobs
+            .lock()
+            .iter()
+            .filter_map(|job| {
+                let state = job.state.lock();
+                let status = match state.status {
+                    DownloadStatus::Complete | DownloadStatus::Removed => return None,
+                    DownloadStatus::Active => DownloadStatus::Waiting,
+                    status => status,
+                };
+                Some(SavedJob {
+                    gid: job.gid.clone(),
+                    request: job.request.clone(),
+                    status,
+                    title: state.title.clone(),
+                    dir: state.dir.clone(),
+                    partial: state.partial.clone(),
+                    error: state.error.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Start jobs, publish events and save the queue until the engine is dropped
+fn supervise(engine: Weak<YtDlpEngine>) {
+    let mut saved: Option<Vec<SavedJob>> = None;
+    let mut tracker = EventTracker::new();
+
+    loop {
+        thread::sleep(TICK);
+        let Some(engine) = engine.upgrade() else {
+            return;
+        };
+        if engine.closing.load(Ordering::Relaxed) {
+            return;
+        }
+
+        engine.start_waiting();
+        engine.trim_stopped();
+
+        if let Ok(downloads) = engine.list() {
+            let events = tracker.update(&downloads);
+            if !events.is_empty() && !engine.subscribers.is_empty() {
+                engine.subscribers.publish(events);
+            }
+        }
+
+        let queue = engine.saved_queue();
+        if saved.as_ref() != Some(&queue) {
+            if let Err(e) = save_queue(&engine.queue_path, &queue) {
+                println!("Failed to save the video queue: {}", e);
+            }
+            saved = Some(queue);
+        }
+    }
+}
+
+/// Run a job's yt-dlp process on its own thread and record how it ended
+fn spawn_job(job: Arc<Job>, config: Config, limit: u64) {
+    thread::spawn(move || {
+        {
+            let mut state = job.state.lock();
+            state.done = 0;
+            state.downloaded = 0;
+            state.total = 0;
+            state.files.clear();
+        }
+        let result = launch(&job, &config, limit).and_then(|(stdout, stderr)| follow(&job, stdout, stderr));
+
+        let mut state = job.state.lock();
+        state.speed = 0;
+        match (result, job.control()) {
+            (Ok(()), _) => {
+                if let Some(file) = state.files.last() {
+                    println!("Downloaded {}", file.display());
+                }
+                state.done = state.done.max(state.downloaded);
+                state.downloaded = 0;
+                state.total = 0;
+                state.partial.clear();
+                state.status = DownloadStatus::Complete;
+            }
+            (_, Control::Remove) => {
+                discard(&state.partial);
+                state.status = DownloadStatus::Removed;
+            }
+            (_, Control::Pause) => state.status = DownloadStatus::Paused,
+            // Paused and resumed before yt-dlp exited, a new process picks up
+            (Err(_), Control::Run) if job.interrupted.load(Ordering::Relaxed) => state.status = DownloadStatus::Waiting,
+            (Err(e), Control::Run) => {
+                println!("Download {} failed: {}", job.gid, e);
+                state.status = DownloadStatus::Error;
+                state.error = Some(e.to_string());
+            }
+        }
+        drop(state);
+        job.running.store(false, Ordering::Relaxed);
+    });
+}
+
+/// Start yt-dlp for a job, handing back its output
+fn launch(job: &Job, config: &Config, limit: u64) -> anyhow::Result<(ChildStdout, ChildStderr)> {
+    let request = &job.request;
+    let url = request.uris.first().ok_or_else(|| anyhow::anyhow!("A download needs a URL"))?;
+    let dir = job.state.lock().dir.clone();
+    let format = request.format.as_deref().unwrap_or(&config.format);
+
+    let mut command = Command::new(&config.binary);
+    command
+        .args(["--newline", "--no-playlist", "--no-colors", "--progress"])
+        .args(["--progress-template", &format!(
+            "download:{}progress %(progress.status)s %(progress.downloaded_bytes)s %(progress.total_bytes)s \
+             %(progress.total_bytes_estimate)s %(progress.speed)s %(progress.tmpfilename)s",
+            MARKER,
+        )])
+        .args(["--print", &format!("before_dl:{}title %(title)s", MARKER)])
+        .args(["--print", &format!("after_move:{}file %(filepath)s", MARKER)])
+        .args(["--format", format])
+        .arg("--paths")
+        .arg(&dir);
+    if let Some(stem) = request.out.as_deref().map(Path::new).and_then(Path::file_stem) {
+        command.args(["--output", &format!("{}.%(ext)s", stem.to_string_lossy().replace('%', "%%"))]);
+    }
+    if !config.proxy.is_empty() {
+        command.args(["--proxy", &config.proxy]);
+    }
+    if limit > 0 {
+        command.args(["--limit-rate", &limit.to_string()]);
+    }
+    // yt-dlp picks user agents that sites accept, bong's own isn't passed on
+    for (name, value) in request.headers.iter().filter_map(|header| header.split_once(':')) {
+        command.args(["--add-header", &format!("{}:{}", name.trim(), value.trim())]);
+    }
+    command.arg("--").arg(url);
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| launch_error(&config.binary, e))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    // Paused or removed while starting, the process wasn't there to be killed
+    let mut slot = job.child.lock();
+    if job.control() != Control::Run {
+        let _ = child.kill();
+    }
+    *slot = Some(child);
+    Ok((stdout, stderr))
+}
+
+/// Follow a job's output until yt-dlp exits
+fn follow(job: &Job, stdout: ChildStdout, stderr: ChildStderr) -> anyhow::Result<()> {
+    let errors = thread::spawn(move || {
+        let mut output = String::new();
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            output.push_str(&line);
+            output.push('\n');
+        }
+        error_line(&output)
+    });
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        job.record(line.trim_end());
+    }
+    let error = errors.join().ok().flatten();
+
+    let Some(mut child) = job.child.lock().take() else {
+        anyhow::bail!("yt-dlp went away");
+    };
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!(error.unwrap_or_else(|| format!("yt-dlp exited with {}", status)));
+    }
+    Ok(())
+}
+
+/// The reason yt-dlp gave for failing
+fn error_line(output: &str) -> Option<String> {
+    output
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix("ERROR: "))
+        .map(str::to_string)
+}
+
+fn launch_error(binary: &Path, e: std::io::Error) -> anyhow::Error {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        anyhow::anyhow!("yt-dlp wasn't found at {}, install it or set its path in the settings", binary.display())
+    } else {
+        anyhow::anyhow!("Failed to start {}: {}", binary.display(), e)
+    }
+}
+
+/// Delete the temporary files of a removed job
+fn discard(partial: &[PathBuf]) {
+    for path in partial {
+        let _ = std::fs::remove_file(path);
+        // Fragmented formats keep their progress next to the part file
+        let _ = std::fs::remove_file(path.with_extension("ytdl"));
+    }
+}
+
+fn is_stopped(job: &Job) -> bool {
+    !job.is_running()
+        && matches!(
+            job.state.lock().status,
+            DownloadStatus::Complete | DownloadStatus::Error | DownloadStatus::Removed
+        )
+}
+
+fn load_queue(path: &Path) -> Vec<Arc<Job>> {
+    let Ok(data) = std::fs::read(path) else {
+        return Vec::new();
+    };
+    let saved: Vec<SavedJob> = match serde_json::from_slice(&data) {
+        Ok(saved) => saved,
+        Err(e) => {
+            println!("Ignoring invalid video queue {}: {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+
+    saved
+        .into_iter()
+        .map(|saved| {
+            Arc::new(Job {
+                gid: saved.gid,
+                request: saved.request,
+                control: AtomicU8::new(Control::Run as u8),
+                running: AtomicBool::new(false),
+                interrupted: AtomicBool::new(false),
+                child: Mutex::new(None),
+                state: Mutex::new(JobState {
+                    status: saved.status,
+                    title: saved.title,
+                    dir: saved.dir,
+                    partial: saved.partial,
+                    error: saved.error,
+                    ..Default::default()
+                }),
+            })
+        })
+        .collect()
+}
+
+fn save_queue(path: &Path, queue: &[SavedJob]) -> anyhow::Result<()> {
+    write_atomic(path, &serde_json::to_vec_pretty(queue)?)
+}
+
+impl DownloadEngine for YtDlpEngine {
+    fn add(&self, request: &AddRequest) -> anyhow::Result<String> {
+        let url = request.uris.first().ok_or_else(|| anyhow::anyhow!("A download needs a URL"))?;
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            anyhow::bail!("yt-dlp only downloads from http and https pages, not {}", url);
+        }
+
+        let config = self.config.read().clone();
+        let paused = config.start_paused || request.options.iter().any(|(key, value)| key == "pause" && value == "true");
+        let mut jobs = self.jobs.lock();
+        let gid = loop {
+            let gid = format!("{}{}", GID_PREFIX, &new_gid()[GID_PREFIX.len()..]);
+            if !jobs.iter().any(|job| job.gid == gid) {
+                break gid;
+            }
+        };
+        jobs.push(Arc::new(Job {
+            gid: gid.clone(),
+            request: request.clone(),
+            control: AtomicU8::new(Control::Run as u8),
+            running: AtomicBool::new(false),
+            interrupted: AtomicBool::new(false),
+            child: Mutex::new(None),
+            state: Mutex::new(JobState {
+                status: if paused { DownloadStatus::Paused } else { DownloadStatus::Waiting },
+                dir: request.dir.as_ref().map(PathBuf::from).unwrap_or(config.download_dir),
+                ..Default::default()
+            }),
+        }));
+        Ok(gid)
+    }
+
+    fn pause(&self, gid: &str) -> anyhow::Result<()> {
+        let job = self.find(gid)?;
+        let mut state = job.state.lock();
+        match state.status {
+            DownloadStatus::Active | DownloadStatus::Waiting => {
+                job.set_control(Control::Pause);
+                state.status = DownloadStatus::Paused;
+                Ok(())
+            }
+            DownloadStatus::Paused => Ok(()),
+            _ => anyhow::bail!("Download {} has already stopped", gid),
+        }
+    }
+
+    fn resume(&self, gid: &str) -> anyhow::Result<()> {
+        let job = self.find(gid)?;
+        let mut state = job.state.lock();
+        match state.status {
+            // A killed process can't pick up again, a new one is started
+            DownloadStatus::Paused | DownloadStatus::Error => {
+                job.set_control(Control::Run);
+                state.status = DownloadStatus::Waiting;
+                state.error = None;
+                Ok(())
+            }
+            DownloadStatus::Active | DownloadStatus::Waiting => Ok(()),
+            _ => anyhow::bail!("Download {} has already stopped", gid),
+        }
+    }
+
+    fn remove(&self, gid: &str) -> anyhow::Result<()> {
+        let job = self.find(gid)?;
+        let mut state = job.state.lock();
+        match state.status {
+            DownloadStatus::Active | DownloadStatus::Waiting | DownloadStatus::Paused => {
+                job.set_control(Control::Remove);
+                if !job.is_running() {
+                    discard(&state.partial);
+                }
+                state.status = DownloadStatus::Removed;
+            }
+            _ => {
+                drop(state);
+                self.jobs.lock().retain(|job| job.gid != gid || job.is_running());
+            }
+        }
+        Ok(())
+    }
+
+    fn pause_all(&self) -> anyhow::Result<()> {
+        let gids: Vec<String> = self.jobs.lock().iter().map(|job| job.gid.clone()).collect();
+        for gid in gids {
+            let _ = self.pause(&gid);
+        }
+        Ok(())
+    }
+
+    fn resume_all(&self) -> anyhow::Result<()> {
+        let paused: Vec<String> = self
+            .jobs
+            .lock()
+            .iter()
+            .filter(|job| job.state.lock().status == DownloadStatus::Paused)
+            .map(|job| job.gid.clone())
+            .collect();
+        for gid in paused {
+            self.resume(&gid)?;
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> anyhow::Result<Vec<Download>> {
+        let mut downloads: Vec<Download> = self.jobs.lock().iter().map(|job| job.to_download()).collect();
+        downloads.sort_by_key(|download| match download.status {
+            DownloadStatus::Active => 0,
+            DownloadStatus::Waiting | DownloadStatus::Paused => 1,
+            _ => 2,
+        });
+        Ok(downloads)
+    }
+
+    fn stats(&self) -> anyhow::Result<GlobalStat> {
+        let mut stat = GlobalStat::default();
+        for download in self.list()? {
+            match download.status {
+                DownloadStatus::Active => {
+                    stat.num_active += 1;
+                    stat.download_speed += download.download_speed;
+                }
+                DownloadStatus::Waiting | DownloadStatus::Paused => stat.num_waiting += 1,
+                _ => stat.num_stopped += 1,
+            }
+        }
+        Ok(stat)
+    }
+
+    /// The rate each job starts with, running jobs keep theirs
+    fn download_limit(&self) -> anyhow::Result<u64> {
+        Ok(self.limit.load(Ordering::Relaxed))
+    }
+
+    fn set_download_limit(&self, limit: u64) -> anyhow::Result<()> {
+        self.limit.store(limit, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// yt-dlp only limits single processes, so the per-download limit wins when set
+    fn apply_speed_profile(&self, profile: &SpeedProfile) -> anyhow::Result<()> {
+        let limit = if profile.per_download_limit > 0 { profile.per_download_limit } else { profile.download_limit };
+        self.set_download_limit(limit)
+    }
+
+    fn status(&self, expected_running: bool) -> EngineStatus {
+        let binary = self.config.read().binary.clone();
+        let version = Command::new(&binary)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| launch_error(&binary, e));
+
+        let mut status = EngineStatus {
+            engine: "yt-dlp".to_string(),
+            endpoint: binary.display().to_string(),
+            stat: self.stats().unwrap_or_default(),
+            ..Default::default()
+        };
+        match version {
+            Ok(output) if output.status.success() => {
+                status.running = true;
+                status.version = Some(String::from_utf8_lossy(&output.stdout).trim().to_string());
+            }
+            Ok(output) => {
+                status.error = Some(format!("yt-dlp exited with {}", output.status)).filter(|_| expected_running);
+            }
+            Err(e) => status.error = Some(e.to_string()).filter(|_| expected_running),
+        }
+        status
+    }
+
+    fn events(&self) -> EventStream {
+        self.subscribers.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::settings::VideoSettings;
+
+    #[test]
+    fn video_hosts_match_subdomains_of_the_configured_sites() {
+        let hosts = VideoSettings::default().hosts;
+        assert!(is_video_host(&hosts, "https://www.youtube.com/watch?v=abc"));
+        assert!(is_video_host(&hosts, "http://user@M.YouTube.com:443/watch"));
+        assert!(is_video_host(&hosts, "https://youtu.be/abc"));
+        assert!(!is_video_host(&hosts, "https://notyoutube.com/watch"));
+        assert!(!is_video_host(&hosts, "https://example.com/youtube.com/video.mp4"));
+        assert!(!is_video_host(&hosts, "ftp://youtube.com/video"));
+
+        let hosts = vec!["Example.org".to_string()];
+        assert!(is_video_host(&hosts, "https://videos.example.org/watch/1"));
+        assert!(!is_video_host(&hosts, "https://www.youtube.com/watch?v=abc"));
+        assert!(!is_video_host(&[], "https://www.youtube.com/watch?v=abc"));
+    }
+}
@@ -0,0 +1,637 @@
+use crate::modules::background::add_download;
+use crate::modules::engine::AddRequest;
+use crate::modules::settings::{config_dir, write_atomic, Feed, Settings};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// File name of the feed state inside the config directory
+pub const FEEDS_FILE: &str = "feeds.json";
+
+/// Checked items kept for the feed view
+const MAX_ITEMS: usize = 500;
+
+/// Downloaded episodes remembered for de-duplication
+const MAX_EPISODES: usize = 5000;
+
+/// Episode markers: S01E02, 1x02 and air dates such as 2024.01.31
+static EPISODE_PATTERNS: LazyLock<[regex::Regex; 3]> = LazyLock::new(|| {
+    [
+        regex::Regex::new(r"(?i)\bS(\d{1,3})[ .]?E(\d{1,4})\b").unwrap(),
+        regex::Regex::new(r"(?i)\b(\d{1,2})x(\d{2,3})\b").unwrap(),
+        regex::Regex::new(r"\b((?:19|20)\d{2})[.\- ](\d{2})[.\- ](\d{2})\b").unwrap(),
+    ]
+});
+
+/// An entry of an RSS or Atom feed
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FeedItem {
+    /// guid or id, the link when the feed has neither
+    pub id: String,
+    pub title: String,
+    /// Enclosure, magnet or link, in that order of preference
+    pub url: Option<String>,
+    /// Date as written in the feed
+    pub published: Option<String>,
+}
+
+/// Items of an RSS 2.0, RSS 1.0 or Atom document, in feed order
+pub fn parse_feed(xml: &str) -> anyhow::Result<Vec<FeedItem>> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let document = roxmltree::Document::parse_with_options(xml, options)
+        .map_err(|e| anyhow::anyhow!("Not a valid feed: {}", e))?;
+
+    let root = document.root_element();
+    if !matches!(root.tag_name().name(), "rss" | "RDF" | "feed") {
+        anyhow::bail!("Not an RSS or Atom feed");
+    }
+
+    let items = root
+        .descendants()
+        .filter(|node| matches!(node.tag_name().name(), "item" | "entry"))
+        .map(|node| {
+            let text = |names: &[&str]| {
+                node.children()
+                    .filter(|child| names.contains(&child.tag_name().name()))
+                    .filter_map(|child| child.text())
+                    .map(str::trim)
+                    .find(|text| !text.is_empty())
+                    .map(str::to_string)
+            };
+
+            // RSS enclosures and Atom links with rel="enclosure" point at the file itself
+            let links: Vec<(Option<&str>, &str)> = node
+                .children()
+                .filter(|child| child.tag_name().name() == "link")
+                .filter_map(|child| child.attribute("href").map(|href| (child.attribute("rel"), href)))
+                .collect();
+            let enclosure = node
+                .children()
+                .find(|child| child.tag_name().name() == "enclosure")
+                .and_then(|child| child.attribute("url"))
+                .or_else(|| links.iter().find(|(rel, _)| *rel == Some("enclosure")).map(|(_, href)| *href));
+            let page = links
+                .iter()
+                .find(|(rel, _)| rel.is_none_or(|rel| rel == "alternate"))
+                .map(|(_, href)| href.to_string())
+                .or_else(|| text(&["link"]));
+            let url = enclosure
+                .map(str::to_string)
+                .or_else(|| text(&["magnetURI"]))
+                .or(page);
+
+            FeedItem {
+                id: text(&["guid", "id"]).or_else(|| url.clone()).unwrap_or_default(),
+                title: text(&["title"]).unwrap_or_default(),
+                url,
+                published: text(&["pubDate", "published", "updated", "date"]),
+            }
+        })
+        .collect();
+    Ok(items)
+}
+
+/// Show and episode of a release title, so other releases of the same episode are
+/// recognized. "Show.Name.S01E02.720p" and "Show Name s1e2 1080p" both give "show name s1e2".
+pub fn episode_key(title: &str) -> Option<String> {
+    let (pattern, captures) = EPISODE_PATTERNS
+        .iter()
+        .enumerate()
+        .find_map(|(pattern, regex)| regex.captures(title).map(|captures| (pattern, captures)))?;
+
+    let marker = captures.get(0)?;
+    let show: Vec<String> = title[..marker.start()]
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let number = |index: usize| captures.get(index).and_then(|m| m.as_str().parse::<u32>().ok()).unwrap_or(0);
+    let episode = match pattern {
+        0 | 1 => format!("s{}e{}", number(1), number(2)),
+        _ => format!("{:04}-{:02}-{:02}", number(1), number(2), number(3)),
+    };
+    Some(format!("{} {}", show.join(" "), episode).trim_start().to_string())
+}
+
+/// What became of a feed item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ItemResult {
+    /// Added with this GID
+    Added(String),
+    /// Left out by a filter or as a duplicate, with the reason
+    Skipped(String),
+    /// The engine refused it, retried on the next check
+    Failed(String),
+}
+
+/// A checked feed item as shown in the feed view
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemRecord {
+    /// URL of the feed
+    pub feed: String,
+    pub title: String,
+    pub url: Option<String>,
+    /// Unix timestamp in seconds
+    pub checked_at: i64,
+    pub result: ItemResult,
+}
+
+/// State of a single subscription
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedStatus {
+    /// Unix timestamp of the last check in seconds
+    pub checked_at: Option<i64>,
+    /// Why the last check failed
+    pub error: Option<String>,
+    /// Filters the seen items were checked with
+    pub filters: String,
+    /// IDs of the items already added or skipped
+    pub seen: Vec<String>,
+    /// IDs of the seen items a filter skipped, checked again when the filters change
+    pub filtered: Vec<String>,
+    /// Whether the items in the feed when subscribing were taken in, either added
+    /// or marked as seen
+    pub subscribed: bool,
+}
+
+/// What the background task remembers of the feeds, keyed by feed URL
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedState {
+    pub feeds: HashMap<String, FeedStatus>,
+    /// Episodes already downloaded from any feed, see `episode_key`
+    pub episodes: Vec<String>,
+    /// Checked items, oldest first
+    pub items: Vec<ItemRecord>,
+}
+
+impl FeedState {
+    /// Items checked for a feed, newest first
+    pub fn items_of<'a>(&'a self, url: &'a str) -> impl Iterator<Item = &'a ItemRecord> + 'a {
+        self.items.iter().rev().filter(move |item| item.feed == url)
+    }
+}
+
+/// Feed state persisted as JSON, rewritten after every check
+pub struct FeedStore {
+    path: PathBuf,
+}
+
+impl FeedStore {
+    pub fn open() -> Self {
+        Self::open_at(config_dir().join(FEEDS_FILE))
+    }
+
+    pub fn open_at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The saved state, empty if there is none or it can't be read
+    pub fn load(&self) -> FeedState {
+        std::fs::read(&self.path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, state: &FeedState) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomic(&self.path, &serde_json::to_vec_pretty(state)?)
+    }
+}
+
+/// Checks the subscribed feeds when they're due and adds the items passing their filters
+pub struct FeedPoller {
+    store: FeedStore,
+    state: FeedState,
+}
+
+impl FeedPoller {
+    pub fn open() -> Self {
+        Self::with_store(FeedStore::open())
+    }
+
+    pub fn with_store(store: FeedStore) -> Self {
+        let state = store.load();
+        Self { store, state }
+    }
+
+    pub fn state(&self) -> &FeedState {
+        &self.state
+    }
+
+    /// Enabled feeds that weren't checked for the configured interval, or all of them when forced
+    pub fn due<'a>(&self, settings: &'a Settings, force: bool) -> Vec<&'a Feed> {
+        let now = chrono::Local::now().timestamp();
+        let interval = i64::from(settings.feeds.interval_mins) * 60;
+        settings
+            .feeds
+            .feeds
+            .iter()
+            .filter(|feed| feed.enabled)
+            .filter(|feed| {
+                let checked_at = self.state.feeds.get(&feed.url).and_then(|status| status.checked_at);
+                force || checked_at.is_none_or(|checked_at| now - checked_at >= interval)
+            })
+            .collect()
+    }
+
+    /// Fetch and process the due feeds, saving the state after each one
+    pub fn check_due(&mut self, settings: &Settings, force: bool) {
+        // Forget feeds that were unsubscribed
+        self.state
+            .feeds
+            .retain(|url, _| settings.feeds.feeds.iter().any(|feed| feed.url == *url));
+        self.state
+            .items
+            .retain(|item| settings.feeds.feeds.iter().any(|feed| feed.url == item.feed));
+
+        for feed in self.due(settings, force) {
+            let result = fetch_feed(settings, &feed.url)
+                .and_then(|items| self.check_items(settings, feed, items, &mut |settings, request| add_download(settings, request)));
+            let status = self.state.feeds.entry(feed.url.clone()).or_default();
+            status.checked_at = Some(chrono::Local::now().timestamp());
+            status.error = match result {
+                Ok(added) => {
+                    if added > 0 {
+                        println!("Added {} download(s) from feed {}", added, feed.name);
+                    }
+                    None
+                }
+                Err(e) => {
+                    println!("Failed to check feed {}: {}", feed.name, e);
+                    Some(e.to_string())
+                }
+            };
+
+            if let Err(e) = self.store.save(&self.state) {
+                println!("Failed to save the feed state: {}", e);
+            }
+        }
+    }
+
+    /// Add the new items of a feed that pass its filters and weren't downloaded from
+    /// another release, returning how many were added
+    pub fn check_items(
+        &mut self,
+        settings: &Settings,
+        feed: &Feed,
+        items: Vec<FeedItem>,
+        add: &mut dyn FnMut(&Settings, AddRequest) -> anyhow::Result<String>,
+    ) -> anyhow::Result<usize> {
+        let (include, exclude) = feed.filters()?;
+        let filters = format!("{}\n{}", feed.include, feed.exclude);
+        let now = chrono::Local::now().timestamp();
+
+        let status = self.state.feeds.entry(feed.url.clone()).or_default();
+        if status.filters != filters {
+            // Items a filter skipped get another chance, added ones stay added
+            status.filters = filters;
+            let filtered = std::mem::take(&mut status.filtered);
+            status.seen.retain(|id| !filtered.contains(id));
+        }
+        // States saved before `subscribed` existed already have seen items
+        let backlog = !status.subscribed && status.seen.is_empty() && !feed.backlog;
+        status.subscribed = true;
+        let mut seen = std::mem::take(&mut status.seen);
+        let mut filtered = std::mem::take(&mut status.filtered);
+
+        let mut added = 0;
+        let mut current = Vec::new();
+        for item in items {
+            current.push(item.id.clone());
+            if seen.contains(&item.id) {
+                continue;
+            }
+
+            let episode = episode_key(&item.title);
+            let skipped_by_filter = include.as_ref().is_some_and(|include| !include.is_match(&item.title))
+                || exclude.as_ref().is_some_and(|exclude| exclude.is_match(&item.title));
+            let result = if backlog {
+                ItemResult::Skipped("Published before subscribing".to_string())
+            } else if include.as_ref().is_some_and(|include| !include.is_match(&item.title)) {
+                ItemResult::Skipped("Doesn't match the include filter".to_string())
+            } else if exclude.as_ref().is_some_and(|exclude| exclude.is_match(&item.title)) {
+                ItemResult::Skipped("Matches the exclude filter".to_string())
+            } else if episode.as_ref().is_some_and(|episode| self.state.episodes.contains(episode)) {
+                ItemResult::Skipped("Episode already downloaded".to_string())
+            } else if let Some(ref url) = item.url {
+                let request = AddRequest {
+                    category: feed.category.clone(),
+                    dir: (!feed.dir.as_os_str().is_empty())
+                        .then(|| settings.downloads.download_dir.join(&feed.dir).display().to_string()),
+                    ..AddRequest::new(url.clone())
+                };
+                match add(settings, request) {
+                    Ok(gid) => {
+                        added += 1;
+                        if let Some(episode) = episode {
+                            self.state.episodes.push(episode);
+                        }
+                        ItemResult::Added(gid)
+                    }
+                    Err(e) => ItemResult::Failed(e.to_string()),
+                }
+            } else {
+                ItemResult::Skipped("No link to download".to_string())
+            };
+
+            if !matches!(result, ItemResult::Failed(_)) {
+                seen.push(item.id.clone());
+            }
+            if skipped_by_filter && !backlog {
+                filtered.push(item.id.clone());
+            }
+            self.state.items.push(ItemRecord {
+                feed: feed.url.clone(),
+                title: item.title,
+                url: item.url,
+                checked_at: now,
+                result,
+            });
+        }
+
+        // Items that dropped out of the feed won't come back
+        seen.retain(|id| current.contains(id));
+        filtered.retain(|id| current.contains(id));
+        if let Some(status) = self.state.feeds.get_mut(&feed.url) {
+            status.seen = seen;
+            status.filtered = filtered;
+        }
+        let excess = self.state.items.len().saturating_sub(MAX_ITEMS);
+        self.state.items.drain(..excess);
+        let excess = self.state.episodes.len().saturating_sub(MAX_EPISODES);
+        self.state.episodes.drain(..excess);
+        Ok(added)
+    }
+}
+
+/// Download and parse a feed
+pub fn fetch_feed(settings: &Settings, url: &str) -> anyhow::Result<Vec<FeedItem>> {
+    let mut builder = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(u64::from(settings.network.timeout_secs.max(1))))
+        .user_agent(&settings.network.user_agent);
+    if !settings.network.proxy.is_empty() {
+        let proxy = ureq::Proxy::new(&settings.network.proxy)
+            .map_err(|e| anyhow::anyhow!("Unsupported proxy {}: {}", settings.network.proxy, e))?;
+        builder = builder.proxy(proxy);
+    }
+
+    let text = builder
+        .build()
+        .get(url)
+        .call()
+        .map_err(|e| anyhow::anyhow!("Failed to fetch the feed: {}", e))?
+        .into_string()?;
+    parse_feed(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE rss PUBLIC "-//Netscape Communications//DTD RSS 0.91//EN" "http://my.netscape.com/publish/formats/rss-0.91.dtd">
+<rss version="2.0" xmlns:torrent="http://xmlns.ezrss.it/0.1/">
+  <channel>
+    <title>Releases</title>
+    <item>
+      <title>Show.Name.S01E03.1080p.WEB</title>
+      <guid>a3</guid>
+      <link>https://example.com/3</link>
+      <torrent:magnetURI>magnet:?xt=urn:btih:a3</torrent:magnetURI>
+      <enclosure url="https://example.com/3.torrent" type="application/x-bittorrent" length="1"/>
+      <pubDate>Mon, 02 Jan 2024 10:00:00 GMT</pubDate>
+    </item>
+    <item>
+      <title>Show Name s1e3 720p</title>
+      <guid>b3</guid>
+      <link>https://example.com/b3</link>
+      <torrent:magnetURI><![CDATA[magnet:?xt=urn:btih:b3]]></torrent:magnetURI>
+    </item>
+    <item>
+      <title>Show.Name.S01E02.720p.HDTV</title>
+      <guid>a2</guid>
+      <link>https://example.com/2</link>
+    </item>
+    <item>
+      <title>Other Show 2x05 1080p</title>
+      <guid>o5</guid>
+      <link>https://example.com/o5</link>
+    </item>
+    <item>
+      <title>Show Name S01E04 CAM</title>
+      <guid>cam</guid>
+      <link>https://example.com/cam</link>
+    </item>
+  </channel>
+</rss>"#;
+
+    const RDF: &str = r#"<?xml version="1.0"?>
+<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns="http://purl.org/rss/1.0/" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <channel rdf:about="https://example.com/"><title>Old</title></channel>
+  <item rdf:about="https://example.com/x"><title>X</title><link>https://example.com/x.zip</link><dc:date>2024-01-01</dc:date></item>
+</rdf:RDF>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Builds</title>
+  <entry>
+    <title>Nightly 2024.03.01</title>
+    <id>urn:n1</id>
+    <link rel="alternate" href="https://example.com/n1.html"/>
+    <link rel="enclosure" href="https://example.com/n1.iso"/>
+    <updated>2024-03-01T00:00:00Z</updated>
+  </entry>
+  <entry>
+    <title>Release notes</title>
+    <id>urn:notes</id>
+    <link href="https://example.com/notes"/>
+  </entry>
+</feed>"#;
+
+    fn feed() -> Feed {
+        Feed {
+            name: "Releases".to_string(),
+            url: "https://example.com/rss".to_string(),
+            include: "show.name".to_string(),
+            exclude: r"\bCAM\b".to_string(),
+            backlog: true,
+            ..Default::default()
+        }
+    }
+
+    fn poller(name: &str) -> FeedPoller {
+        let path = std::env::temp_dir().join(format!("bong-feeds-{}-{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        FeedPoller::with_store(FeedStore::open_at(path))
+    }
+
+    /// Results of the last check of each item, in feed order
+    fn results(poller: &FeedPoller, feed: &Feed) -> Vec<(String, ItemResult)> {
+        let mut results: Vec<(String, ItemResult)> = Vec::new();
+        for item in poller.state().items_of(&feed.url) {
+            if !results.iter().any(|(title, _)| *title == item.title) {
+                results.push((item.title.clone(), item.result.clone()));
+            }
+        }
+        results.reverse();
+        results
+    }
+
+    #[test]
+    fn prefers_enclosures_then_magnets_then_links() {
+        let items = parse_feed(RSS).unwrap();
+        assert_eq!(items.len(), 5);
+        assert_eq!(items[0].id, "a3");
+        assert_eq!(items[0].url.as_deref(), Some("https://example.com/3.torrent"));
+        assert_eq!(items[0].published.as_deref(), Some("Mon, 02 Jan 2024 10:00:00 GMT"));
+        assert_eq!(items[1].url.as_deref(), Some("magnet:?xt=urn:btih:b3"));
+        assert_eq!(items[2].url.as_deref(), Some("https://example.com/2"));
+    }
+
+    #[test]
+    fn parses_rss_1_and_atom() {
+        let items = parse_feed(RDF).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, "https://example.com/x.zip");
+        assert_eq!(items[0].published.as_deref(), Some("2024-01-01"));
+
+        let entries = parse_feed(ATOM).unwrap();
+        assert_eq!(entries[0].id, "urn:n1");
+        assert_eq!(entries[0].url.as_deref(), Some("https://example.com/n1.iso"));
+        assert_eq!(entries[0].published.as_deref(), Some("2024-03-01T00:00:00Z"));
+        assert_eq!(entries[1].url.as_deref(), Some("https://example.com/notes"));
+
+        assert!(parse_feed("<html></html>").is_err());
+        assert!(parse_feed("not xml").is_err());
+    }
+
+    #[test]
+    fn keys_episodes() {
+        assert_eq!(episode_key("Show.Name.S01E03.1080p").as_deref(), Some("show name s1e3"));
+        assert_eq!(episode_key("Show Name s1 e3 720p").as_deref(), Some("show name s1e3"));
+        assert_eq!(episode_key("Other Show 2x05").as_deref(), Some("other show s2e5"));
+        assert_eq!(episode_key("Nightly 2024.03.01").as_deref(), Some("nightly 2024-03-01"));
+        assert_eq!(episode_key("S02E10").as_deref(), Some("s2e10"));
+        assert_eq!(episode_key("Release notes"), None);
+    }
+
+    #[test]
+    fn filters_and_deduplicates_items() {
+        let settings = Settings::default();
+        let feed = feed();
+        let mut poller = poller("filters");
+        let mut added = Vec::new();
+        let mut add = |_: &Settings, request: AddRequest| {
+            added.push(request.uris[0].clone());
+            Ok(format!("gid{}", added.len()))
+        };
+
+        assert_eq!(poller.check_items(&settings, &feed, parse_feed(RSS).unwrap(), &mut add).unwrap(), 2);
+        assert_eq!(
+            results(&poller, &feed),
+            vec![
+                ("Show.Name.S01E03.1080p.WEB".to_string(), ItemResult::Added("gid1".to_string())),
+                ("Show Name s1e3 720p".to_string(), ItemResult::Skipped("Episode already downloaded".to_string())),
+                ("Show.Name.S01E02.720p.HDTV".to_string(), ItemResult::Added("gid2".to_string())),
+                ("Other Show 2x05 1080p".to_string(), ItemResult::Skipped("Doesn't match the include filter".to_string())),
+                ("Show Name S01E04 CAM".to_string(), ItemResult::Skipped("Matches the exclude filter".to_string())),
+            ]
+        );
+
+        // Seen items aren't checked again
+        assert_eq!(poller.check_items(&settings, &feed, parse_feed(RSS).unwrap(), &mut add).unwrap(), 0);
+        assert_eq!(poller.state().items.len(), 5);
+        assert_eq!(added, vec!["https://example.com/3.torrent", "https://example.com/2"]);
+    }
+
+    #[test]
+    fn rechecks_filtered_items_when_the_filters_change() {
+        let settings = Settings::default();
+        let mut feed = feed();
+        let mut poller = poller("refilter");
+        let mut added = Vec::new();
+        let mut add = |_: &Settings, request: AddRequest| {
+            added.push(request.uris[0].clone());
+            Ok(format!("gid{}", added.len()))
+        };
+        poller.check_items(&settings, &feed, parse_feed(RSS).unwrap(), &mut add).unwrap();
+
+        feed.include.clear();
+        feed.exclude.clear();
+        assert_eq!(poller.check_items(&settings, &feed, parse_feed(RSS).unwrap(), &mut add).unwrap(), 2);
+        assert_eq!(
+            added,
+            vec![
+                "https://example.com/3.torrent",
+                "https://example.com/2",
+                "https://example.com/o5",
+                "https://example.com/cam"
+            ]
+        );
+        assert!(poller.state().feeds[&feed.url].filtered.is_empty());
+    }
+
+    #[test]
+    fn retries_failed_items() {
+        let settings = Settings::default();
+        let feed = Feed {
+            include: String::new(),
+            exclude: String::new(),
+            ..feed()
+        };
+        let mut poller = poller("retry");
+        let items = parse_feed(ATOM).unwrap();
+
+        let mut fail = |_: &Settings, _: AddRequest| -> anyhow::Result<String> { anyhow::bail!("The engine is not running") };
+        assert_eq!(poller.check_items(&settings, &feed, items.clone(), &mut fail).unwrap(), 0);
+        assert!(results(&poller, &feed).iter().all(|(_, result)| matches!(result, ItemResult::Failed(_))));
+
+        let mut add = |_: &Settings, request: AddRequest| Ok(request.uris[0].clone());
+        assert_eq!(poller.check_items(&settings, &feed, items, &mut add).unwrap(), 2);
+        assert!(results(&poller, &feed).iter().all(|(_, result)| matches!(result, ItemResult::Added(_))));
+    }
+
+    #[test]
+    fn skips_the_backlog_when_subscribing() {
+        let settings = Settings::default();
+        let feed = Feed {
+            include: String::new(),
+            exclude: String::new(),
+            backlog: false,
+            ..feed()
+        };
+        let mut poller = poller("backlog");
+        let mut added = Vec::new();
+        let mut add = |_: &Settings, request: AddRequest| {
+            added.push(request.uris[0].clone());
+            Ok(format!("gid{}", added.len()))
+        };
+
+        let mut items = parse_feed(ATOM).unwrap();
+        assert_eq!(poller.check_items(&settings, &feed, items.clone(), &mut add).unwrap(), 0);
+        assert!(
+            results(&poller, &feed)
+                .iter()
+                .all(|(_, result)| *result == ItemResult::Skipped("Published before subscribing".to_string()))
+        );
+
+        items.insert(0, FeedItem {
+            id: "urn:n2".to_string(),
+            title: "Nightly 2024.03.02".to_string(),
+            url: Some("https://example.com/n2.iso".to_string()),
+            published: None,
+        });
+        assert_eq!(poller.check_items(&settings, &feed, items, &mut add).unwrap(), 1);
+        assert_eq!(added, vec!["https://example.com/n2.iso"]);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::modules::actions::{ActionOutcome, CompletedDownload};
+use crate::modules::engine::{Download, DownloadStatus};
+use crate::modules::checksum::Verification;
+use crate::modules::settings::config_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// File name of the history inside the config directory
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// A finished download as remembered after aria2 forgot it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HistoryEntry {
+    pub gid: String,
+    pub name: String,
+    /// Complete, Error or Removed
+    pub status: DownloadStatus,
+    pub error: Option<String>,
+    pub url: Option<String>,
+    /// Local folder the files ended up in
+    pub dir: PathBuf,
+    pub files: Vec<PathBuf>,
+    /// Total size in bytes, 0 if it was never known
+    pub size: u64,
+    /// Bytes downloaded before the download finished
+    pub downloaded: u64,
+    pub category: Option<String>,
+    /// Unix timestamps in seconds. The start is when the background task first saw the download.
+    pub started_at: Option<i64>,
+    pub finished_at: i64,
+    /// Average download speed in bytes per second
+    pub average_speed: Option<u64>,
+    /// Post-download actions in the order they ran
+    pub actions: Vec<ActionOutcome>,
+    /// Result of the checksum given when the download was added
+    pub verification: Option<Verification>,
+}
+
+impl HistoryEntry {
+    pub fn new(download: &CompletedDownload, actions: Vec<ActionOutcome>) -> Self {
+        let finished_at = chrono::Local::now().timestamp();
+        let average_speed = download
+            .started_at
+            .map(|started_at| (finished_at - started_at).max(1) as u64)
+            .map(|secs| download.downloaded / secs);
+
+        Self {
+            gid: download.gid.clone(),
+            name: download.name.clone(),
+            status: download.status,
+            error: download.error.clone(),
+            url: download.url.clone(),
+            dir: download.dir.clone(),
+            files: download.files.clone(),
+            size: download.size,
+            downloaded: download.downloaded,
+            category: download.category.clone(),
+            started_at: download.started_at,
+            finished_at,
+            average_speed,
+            actions,
+            verification: None,
+        }
+    }
+
+    /// Seconds between first sight and the end of the download
+    pub fn duration_secs(&self) -> Option<u64> {
+        self.started_at.map(|started_at| (self.finished_at - started_at).max(0) as u64)
+    }
+
+    /// Whether every whitespace separated term appears in one of the entry's texts
+    pub fn matches(&self, query: &str) -> bool {
+        let mut haystack = vec![
+            self.name.to_lowercase(),
+            self.url.clone().unwrap_or_default().to_lowercase(),
+            self.dir.display().to_string().to_lowercase(),
+            self.category.clone().unwrap_or_default().to_lowercase(),
+            format!("{:?}", self.status).to_lowercase(),
+            self.error.clone().unwrap_or_default().to_lowercase(),
+        ];
+        haystack.extend(self.actions.iter().map(|outcome| format!("{} {}", outcome.action, outcome.message).to_lowercase()));
+
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|term| haystack.iter().any(|text| text.contains(term)))
+    }
+}
+
+/// Download history, one JSON entry per line so appending never rewrites the file
+pub struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn open() -> Self {
+        Self::open_at(config_dir().join(HISTORY_FILE))
+    }
+
+    pub fn open_at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// All entries, oldest first. Lines that fail to parse are skipped.
+    pub fn load(&self) -> Vec<HistoryEntry> {
+        let Ok(text) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+}
+
+/// A download that completed, failed or was removed since the previous poll
+pub struct FinishedDownload {
+    pub download: Download,
+    /// Unix timestamp of the first poll that saw the download
+    pub started_at: Option<i64>,
+}
+
+/// Follows downloads across polls to spot the ones that finished
+#[derive(Default)]
+pub struct DownloadTracker {
+    /// Unfinished downloads with the time they were first seen and their latest state
+    unfinished: HashMap<String, (i64, Download)>,
+    /// GIDs already seen in a finished state
+    finished: HashSet<String>,
+    /// Whether the first poll was recorded. Downloads that were already finished
+    /// when the background task started aren't reported again.
+    primed: bool,
+}
+
+impl DownloadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a successful poll and return the downloads that finished since the last one.
+    /// Unfinished downloads that disappeared from the queue count as removed.
+    pub fn update(&mut self, downloads: &[Download]) -> Vec<FinishedDownload> {
+        let now = chrono::Local::now().timestamp();
+        let mut finished = Vec::new();
+
+        for download in downloads {
+            let is_finished = matches!(
+                download.status,
+                DownloadStatus::Complete | DownloadStatus::Error | DownloadStatus::Removed
+            );
+            if !is_finished {
+                self.unfinished
+                    .entry(download.gid.clone())
+                    .and_modify(|(_, latest)| *latest = download.clone())
+                    .or_insert_with(|| (now, download.clone()));
+            } else if self.finished.insert(download.gid.clone()) {
+                let started_at = self.unfinished.remove(&download.gid).map(|(started_at, _)| started_at);
+                finished.push(FinishedDownload {
+                    download: download.clone(),
+                    started_at,
+                });
+            }
+        }
+
+        let vanished: Vec<String> = self
+            .unfinished
+            .keys()
+            .filter(|gid| !downloads.iter().any(|download| download.gid == **gid))
+            .cloned()
+            .collect();
+        for gid in vanished {
+            if let Some((started_at, mut download)) = self.unfinished.remove(&gid) {
+                download.status = DownloadStatus::Removed;
+                self.finished.insert(gid);
+                finished.push(FinishedDownload {
+                    download,
+                    started_at: Some(started_at),
+                });
+            }
+        }
+
+        if !self.primed {
+            self.primed = true;
+            return Vec::new();
+        }
+        finished
+    }
+}
@@ -4,6 +4,11 @@ use std::net::{TcpListener, TcpStream};
 use std::sync::Arc;
 use parking_lot::Mutex;
 use std::time::Duration;
+use crate::modules::batch::{BatchResult, SharedOptions};
+use crate::modules::engine::{AddRequest, EngineStatus, TransferSnapshot};
+use crate::modules::feeds::FeedState;
+use crate::modules::history::HistoryEntry;
+use crate::modules::settings::Settings;
 
 /// IPC Commands that can be sent between processes
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +31,64 @@ pub enum IpcCommand {
     BackgroundStatus(bool),
     /// UI status
     UIStatus(bool),
+    /// Request the current settings
+    GetSettings,
+    /// Replace the settings (validated and persisted by the background task)
+    UpdateSettings(Settings),
+    /// Current settings, sent as a response and broadcast after every change
+    Settings(Settings),
+    /// Request failed with an error message
+    Error(String),
+    /// Start the managed aria2 daemon
+    StartAria2,
+    /// Stop the managed aria2 daemon
+    StopAria2,
+    /// Whether the managed aria2 daemon is running
+    Aria2Running(bool),
+    /// Request the latest status of the download engine
+    GetEngineStatus,
+    /// Latest engine status polled by the background task
+    EngineStatus(EngineStatus),
+    /// Downloads and global stats, pushed to the tray when they change
+    Transfers(TransferSnapshot),
+    /// Request the latest downloads
+    GetTransfers,
+    /// Pause every download
+    PauseAll,
+    /// Resume every paused download
+    ResumeAll,
+    /// Pause a single download by GID
+    PauseDownload(String),
+    /// Resume a single download by GID
+    ResumeDownload(String),
+    /// Remove a download by GID, finished ones are only forgotten
+    RemoveDownload(String),
+    /// Set the global download limit in bytes per second, 0 for unlimited
+    SetSpeedLimit(u64),
+    /// Switch to the named speed profile, answered with the saved settings
+    SetSpeedProfile(String),
+    /// Add a new download
+    AddDownload(AddRequest),
+    /// A download was added with this GID
+    DownloadAdded(String),
+    /// Add several downloads at once with the options they share
+    AddBatch(Vec<AddRequest>, SharedOptions),
+    /// Number of downloads of a batch that are being added
+    BatchQueued(usize),
+    /// Which downloads of a batch were added and which failed, sent once all were tried
+    BatchAdded(BatchResult),
+    /// Request the download history
+    GetHistory,
+    /// Finished downloads, oldest first
+    History(Vec<HistoryEntry>),
+    /// Request the state of the feed subscriptions
+    GetFeeds,
+    /// Last checks and checked items of the feeds
+    Feeds(FeedState),
+    /// Check every feed now instead of waiting for the interval
+    RefreshFeeds,
+    /// Request succeeded without a result
+    Ok,
 }
 
 /// IPC Server for receiving commands
@@ -61,6 +124,10 @@ impl IpcServer {
         while *self.running.lock() {
             match listener.accept() {
                 Ok((mut stream, _)) => {
+                    // Accepted sockets inherit non-blocking mode on some platforms
+                    stream.set_nonblocking(false)?;
+                    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+
                     let mut buffer = Vec::new();
                     let mut temp_buf = [0u8; 1024];
                     
@@ -69,13 +136,8 @@ impl IpcServer {
                         match stream.read(&mut temp_buf) {
                             Ok(0) => break,
                             Ok(n) => buffer.extend_from_slice(&temp_buf[..n]),
-                            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                                std::thread::sleep(Duration::from_millis(10));
-                                if buffer.len() > 0 {
-                                    break;
-                                }
-                            }
-                            Err(e) => return Err(e.into()),
+                            // Timed out or client went away, handle what we have
+                            Err(_) => break,
                         }
                     }
                     
@@ -126,17 +188,15 @@ impl IpcClient {
         let data = serde_json::to_vec(&command)?;
         stream.write_all(&data)?;
         stream.flush()?;
+        // Signal the end of the command so the server stops reading
+        stream.shutdown(std::net::Shutdown::Write)?;
         
-        // Try to read response
+        // Try to read response, the server closes the connection when done
         let mut buffer = Vec::new();
-        let mut temp_buf = [0u8; 1024];
         
-        match stream.read(&mut temp_buf) {
+        match stream.read_to_end(&mut buffer) {
             Ok(0) => Ok(None),
-            Ok(n) => {
-                buffer.extend_from_slice(&temp_buf[..n]);
-                Ok(serde_json::from_slice::<IpcCommand>(&buffer).ok())
-            }
+            Ok(_) => Ok(serde_json::from_slice::<IpcCommand>(&buffer).ok()),
             Err(_) => Ok(None),
         }
     }
@@ -145,3 +205,4 @@ impl IpcClient {
 /// Well-known ports for IPC
 pub const BACKGROUND_IPC_PORT: u16 = 45789;
 pub const UI_IPC_PORT: u16 = 45790;
+pub const TRAY_IPC_PORT: u16 = 45791;
@@ -0,0 +1,267 @@
+/// Characters left as they are when a magnet parameter is encoded
+const UNRESERVED: &[u8] = b"-._~:/?@!$'()*,;";
+
+/// The parameters of a BitTorrent magnet link
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Magnet {
+    /// Hex SHA-1 info hash from `xt=urn:btih:`, given in hex or base32
+    pub info_hash_v1: Option<String>,
+    /// Hex SHA-256 info hash from the `xt=urn:btmh:1220...` multihash
+    pub info_hash_v2: Option<String>,
+    /// Display name, `dn`
+    pub name: Option<String>,
+    /// Tracker URLs, `tr`
+    pub trackers: Vec<String>,
+    /// Total size in bytes, `xl`
+    pub size: Option<u64>,
+    /// Web seeds, `ws`
+    pub web_seeds: Vec<String>,
+    /// 0-based file indices and ranges to download such as "0,2,4-6", `so`
+    pub select_only: Option<String>,
+}
+
+impl Magnet {
+    /// The magnet link with the current parameters
+    pub fn to_uri(&self) -> String {
+        let mut params = Vec::new();
+        if let Some(ref hash) = self.info_hash_v1 {
+            params.push(format!("xt=urn:btih:{}", hash));
+        }
+        if let Some(ref hash) = self.info_hash_v2 {
+            params.push(format!("xt=urn:btmh:1220{}", hash));
+        }
+        if let Some(ref name) = self.name {
+            params.push(format!("dn={}", encode(name)));
+        }
+        if let Some(size) = self.size {
+            params.push(format!("xl={}", size));
+        }
+        params.extend(self.trackers.iter().map(|tracker| format!("tr={}", encode(tracker))));
+        params.extend(self.web_seeds.iter().map(|seed| format!("ws={}", encode(seed))));
+        if let Some(ref select_only) = self.select_only {
+            params.push(format!("so={}", select_only));
+        }
+        format!("magnet:?{}", params.join("&"))
+    }
+
+    /// aria2's select-file value for `so`, whose indices start at 0 instead of 1
+    pub fn select_file(&self) -> Option<String> {
+        let select_only = self.select_only.as_ref()?;
+        let shift = |index: &str| index.trim().parse::<usize>().ok().map(|index| (index + 1).to_string());
+        select_only
+            .split(',')
+            .map(|part| match part.split_once('-') {
+                Some((start, end)) => Some(format!("{}-{}", shift(start)?, shift(end)?)),
+                None => shift(part),
+            })
+            .collect::<Option<Vec<String>>>()
+            .map(|parts| parts.join(","))
+    }
+}
+
+/// Whether the text is a magnet link
+pub fn is_magnet(uri: &str) -> bool {
+    uri.get(..8).is_some_and(|scheme| scheme.eq_ignore_ascii_case("magnet:?"))
+}
+
+/// Parse a BitTorrent magnet link, which needs a v1 or v2 info hash
+pub fn parse_magnet(uri: &str) -> anyhow::Result<Magnet> {
+    if !is_magnet(uri) {
+        anyhow::bail!("Not a magnet link");
+    }
+
+    let mut magnet = Magnet::default();
+    for pair in uri[8..].split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode(value);
+        // Repeated parameters may be numbered, e.g. tr.1 and tr.2
+        match key.split('.').next().unwrap_or(key) {
+            "xt" => {
+                if let Some(hash) = strip_prefix_ignore_case(&value, "urn:btih:") {
+                    magnet.info_hash_v1 = Some(btih_hex(hash)?);
+                } else if let Some(hash) = strip_prefix_ignore_case(&value, "urn:btmh:") {
+                    magnet.info_hash_v2 = Some(btmh_hex(hash)?);
+                }
+            }
+            "dn" if !value.is_empty() => magnet.name = Some(value),
+            "tr" if !value.is_empty() && !magnet.trackers.contains(&value) => magnet.trackers.push(value),
+            "ws" if !value.is_empty() => magnet.web_seeds.push(value),
+            "xl" => magnet.size = value.parse().ok(),
+            "so" if !value.is_empty() => magnet.select_only = Some(value),
+            _ => {}
+        }
+    }
+
+    if magnet.info_hash_v1.is_none() && magnet.info_hash_v2.is_none() {
+        anyhow::bail!("The magnet link has no BitTorrent info hash");
+    }
+    Ok(magnet)
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    value
+        .get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &value[prefix.len()..])
+}
+
+/// A v1 info hash in lowercase hex, from 40 hex or 32 base32 characters
+fn btih_hex(hash: &str) -> anyhow::Result<String> {
+    if hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(hash.to_ascii_lowercase());
+    }
+    if hash.len() == 32 {
+        let mut bits: u64 = 0;
+        let mut count = 0;
+        let mut bytes = Vec::with_capacity(20);
+        for c in hash.chars() {
+            let value = match c.to_ascii_uppercase() {
+                c @ 'A'..='Z' => c as u64 - 'A' as u64,
+                c @ '2'..='7' => c as u64 - '2' as u64 + 26,
+                _ => anyhow::bail!("Invalid info hash {}", hash),
+            };
+            bits = (bits << 5) | value;
+            count += 5;
+            if count >= 8 {
+                count -= 8;
+                bytes.push((bits >> count) as u8);
+                bits &= (1 << count) - 1;
+            }
+        }
+        return Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect());
+    }
+    anyhow::bail!("Invalid info hash {}", hash)
+}
+
+/// The SHA-256 digest of a v2 multihash, which starts with the 1220 code and length
+fn btmh_hex(hash: &str) -> anyhow::Result<String> {
+    let digest = strip_prefix_ignore_case(hash, "1220")
+        .filter(|digest| digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| anyhow::anyhow!("Invalid v2 info hash {}", hash))?;
+    Ok(digest.to_ascii_lowercase())
+}
+
+/// Percent-decode a parameter, `+` stands for a space
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = |offset: usize| bytes.get(i + offset).and_then(|byte| (*byte as char).to_digit(16));
+        if bytes[i] == b'%'
+            && let (Some(high), Some(low)) = (hex(1), hex(2))
+        {
+            out.push((high * 16 + low) as u8);
+            i += 3;
+            continue;
+        }
+        out.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || UNRESERVED.contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "c12fe1c06bba254a9dc9f519b335aa7c1367a88a";
+    const BASE32: &str = "YEX6DQDLXISUVHOJ6UM3GNNKPQJWPKEK";
+    const V2_HASH: &str = "d8dd32ac93357c368556af3ac1d95c9d76bd0dff6fa9833ecdac3d53134efabb";
+
+    #[test]
+    fn parses_every_parameter() {
+        let magnet = parse_magnet(&format!(
+            "magnet:?xt=urn:btih:{}&dn=My+File%20%C3%A9&xl=1024&tr=udp%3A%2F%2Ft1%3A80&tr.1=udp://t2:80&tr.2=udp://t2:80&ws=https://seed/f&so=0,2",
+            HASH
+        ))
+        .unwrap();
+        assert_eq!(magnet.info_hash_v1.as_deref(), Some(HASH));
+        assert_eq!(magnet.info_hash_v2, None);
+        assert_eq!(magnet.name.as_deref(), Some("My File é"));
+        assert_eq!(magnet.size, Some(1024));
+        assert_eq!(magnet.trackers, ["udp://t1:80", "udp://t2:80"]);
+        assert_eq!(magnet.web_seeds, ["https://seed/f"]);
+        assert_eq!(magnet.select_only.as_deref(), Some("0,2"));
+    }
+
+    #[test]
+    fn decodes_base32_info_hashes() {
+        let magnet = parse_magnet(&format!("magnet:?xt=urn:btih:{}", BASE32)).unwrap();
+        assert_eq!(magnet.info_hash_v1.as_deref(), Some(HASH));
+
+        let lowercase = parse_magnet(&format!("MAGNET:?xt=URN:BTIH:{}", BASE32.to_ascii_lowercase())).unwrap();
+        assert_eq!(lowercase.info_hash_v1.as_deref(), Some(HASH));
+
+        let hex = parse_magnet(&format!("magnet:?xt=urn:btih:{}", HASH.to_ascii_uppercase())).unwrap();
+        assert_eq!(hex.info_hash_v1.as_deref(), Some(HASH));
+    }
+
+    #[test]
+    fn reads_v2_multihashes() {
+        let magnet = parse_magnet(&format!("magnet:?xt=urn:btmh:1220{}&xt=urn:btih:{}", V2_HASH, HASH)).unwrap();
+        assert_eq!(magnet.info_hash_v2.as_deref(), Some(V2_HASH));
+        assert_eq!(magnet.info_hash_v1.as_deref(), Some(HASH));
+
+        let v2_only = parse_magnet(&format!("magnet:?xt=urn:btmh:1220{}", V2_HASH)).unwrap();
+        assert_eq!(v2_only.info_hash_v1, None);
+    }
+
+    #[test]
+    fn rejects_invalid_hashes() {
+        for uri in [
+            "magnet:?dn=name",
+            "magnet:?xt=urn:btih:1234",
+            "magnet:?xt=urn:btih:YEX6DQDLXISUVHOJ6UM3GNNKPQJWPKE1",
+            "magnet:?xt=urn:btih:g12fe1c06bba254a9dc9f519b335aa7c1367a88a",
+            "magnet:?xt=urn:btmh:1114d8dd32ac93357c368556af3ac1d95c9d76bd0dff6fa9833ecdac3d53134efabb",
+            "https://example.com/file",
+        ] {
+            assert!(parse_magnet(uri).is_err(), "{} was accepted", uri);
+        }
+    }
+
+    #[test]
+    fn shifts_so_indices_for_aria2() {
+        let with = |so: &str| Magnet { select_only: Some(so.to_string()), ..Default::default() }.select_file();
+        assert_eq!(with("0").as_deref(), Some("1"));
+        assert_eq!(with("0,2,4-6").as_deref(), Some("1,3,5-7"));
+        assert_eq!(with(" 1 , 3-4").as_deref(), Some("2,4-5"));
+        assert_eq!(with("a,2"), None);
+        assert_eq!(with("-3"), None);
+        assert_eq!(Magnet::default().select_file(), None);
+    }
+
+    #[test]
+    fn to_uri_round_trips() {
+        let magnet = parse_magnet(&format!(
+            "magnet:?xt=urn:btih:{}&xt=urn:btmh:1220{}&dn=a%26b%20c&xl=5&tr=udp://t1:80/announce?x=1&so=1-2",
+            HASH, V2_HASH
+        ))
+        .unwrap();
+        let uri = magnet.to_uri();
+        assert!(uri.contains("dn=a%26b%20c"), "{}", uri);
+        assert_eq!(parse_magnet(&uri).unwrap(), magnet);
+    }
+
+    #[test]
+    fn recognizes_magnet_links() {
+        assert!(is_magnet("Magnet:?xt=urn:btih:abc"));
+        assert!(!is_magnet("magnet:"));
+        assert!(!is_magnet("magnets"));
+        assert!(!is_magnet("é"));
+    }
+}
@@ -0,0 +1,519 @@
+use crate::modules::checksum::{Checksum, HashAlgorithm};
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::time::Duration;
+
+/// XML namespace of Metalink 4, RFC 5854
+const NAMESPACE: &str = "urn:ietf:params:xml:ns:metalink";
+
+/// Namespace of the older Metalink 3 format
+const NAMESPACE_V3: &str = "http://www.metalinker.org/";
+
+/// Media type servers advertise Metalink 4 documents with, RFC 6249
+const MEDIA_TYPE: &str = "application/metalink4+xml";
+
+/// Larger downloads aren't Metalink documents
+const MAX_METALINK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Hash functions the checksum verifier supports, strongest first
+const STRENGTH: [HashAlgorithm; 4] = [HashAlgorithm::Sha512, HashAlgorithm::Sha256, HashAlgorithm::Sha1, HashAlgorithm::Md5];
+
+/// A Metalink 4 document
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Metalink {
+    pub files: Vec<MetalinkFile>,
+    pub generator: Option<String>,
+    /// Date as written in the document
+    pub published: Option<String>,
+    /// Where an up to date copy of the document is published
+    pub origin: Option<String>,
+}
+
+/// A file of a Metalink document
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MetalinkFile {
+    /// Relative path the file is saved as
+    pub name: String,
+    pub size: Option<u64>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    /// Digests of the whole file
+    pub hashes: Vec<FileHash>,
+    pub pieces: Option<Pieces>,
+    pub mirrors: Vec<Mirror>,
+    /// .torrent and other Metalink documents describing the same file
+    pub metaurls: Vec<MetaUrl>,
+}
+
+/// A digest of a file, `kind` is the IANA name such as "sha-256"
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FileHash {
+    pub kind: String,
+    /// Lowercase hex digest
+    pub value: String,
+}
+
+/// Digests of consecutive pieces of a file, checked while it downloads
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pieces {
+    pub kind: String,
+    /// Size of each piece in bytes, the last one may be shorter
+    pub length: u64,
+    pub hashes: Vec<String>,
+}
+
+/// A URL the file can be downloaded from
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mirror {
+    pub url: String,
+    /// Lowercase ISO 3166-1 alpha-2 country code
+    pub location: Option<String>,
+    /// 1 is the most preferred, mirrors without a priority come last
+    pub priority: Option<u32>,
+}
+
+/// A .torrent or another Metalink document for a file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MetaUrl {
+    pub url: String,
+    /// "torrent" or a media type
+    pub mediatype: String,
+    pub priority: Option<u32>,
+    /// Path of the file inside a multi-file torrent
+    pub name: Option<String>,
+}
+
+impl Metalink {
+    /// Countries of all mirrors, sorted
+    pub fn countries(&self) -> BTreeSet<String> {
+        self.files
+            .iter()
+            .flat_map(|file| &file.mirrors)
+            .filter_map(|mirror| mirror.location.clone())
+            .collect()
+    }
+
+    /// Size of the files that state it
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().filter_map(|file| file.size).sum()
+    }
+
+    /// Names of the files left without a mirror or metaurl when only mirrors in
+    /// `countries` are kept
+    pub fn unreachable(&self, countries: &BTreeSet<String>) -> Vec<&str> {
+        self.files
+            .iter()
+            .filter(|file| file.metaurls.is_empty() && !file.mirrors.iter().any(|mirror| mirror.in_countries(countries)))
+            .map(|file| file.name.as_str())
+            .collect()
+    }
+}
+
+impl MetalinkFile {
+    /// Mirrors with the most preferred first
+    pub fn mirrors_by_priority(&self) -> Vec<&Mirror> {
+        let mut mirrors: Vec<&Mirror> = self.mirrors.iter().collect();
+        mirrors.sort_by_key(|mirror| mirror.priority.unwrap_or(u32::MAX));
+        mirrors
+    }
+
+    /// Digests the downloaded file can be verified against, strongest first
+    pub fn checksums(&self) -> Vec<Checksum> {
+        let mut checksums: Vec<Checksum> = self.hashes.iter().filter_map(FileHash::checksum).collect();
+        checksums.sort_by_key(|checksum| STRENGTH.iter().position(|algorithm| *algorithm == checksum.algorithm));
+        checksums
+    }
+}
+
+impl FileHash {
+    /// The digest as a checksum, None for hash functions the verifier doesn't know
+    pub fn checksum(&self) -> Option<Checksum> {
+        let algorithm = HashAlgorithm::parse(&self.kind)?;
+        Checksum::from_hex(&self.value).filter(|checksum| checksum.algorithm == algorithm)
+    }
+}
+
+impl Mirror {
+    /// Whether the mirror is kept when only `countries` are allowed, any mirror is
+    /// when no country is selected
+    pub fn in_countries(&self, countries: &BTreeSet<String>) -> bool {
+        allowed(self.location.as_deref(), countries)
+    }
+}
+
+/// Whether the text of the add form names a Metalink file, by path or URL
+pub fn is_metalink_source(source: &str) -> bool {
+    let path = source.split(['?', '#']).next().unwrap_or(source).to_ascii_lowercase();
+    path.ends_with(".meta4") || path.ends_with(".metalink")
+}
+
+/// Contents of a Metalink file from a local path, a file:// URL or over HTTP
+pub fn fetch(source: &str) -> anyhow::Result<Vec<u8>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let mut data = Vec::new();
+        ureq::get(source)
+            .timeout(Duration::from_secs(30))
+            .call()
+            .map_err(|e| anyhow::anyhow!("Failed to fetch the Metalink file: {}", e))?
+            .into_reader()
+            .take(MAX_METALINK_SIZE)
+            .read_to_end(&mut data)?;
+        return Ok(data);
+    }
+
+    let path = source.strip_prefix("file://").unwrap_or(source);
+    std::fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))
+}
+
+/// URL of the Metalink document a server advertises for a download, either as a
+/// `Link: <...>; rel=describedby; type="application/metalink4+xml"` header or by
+/// serving the document itself
+pub fn discover(url: &str) -> anyhow::Result<Option<String>> {
+    let response = ureq::head(url)
+        .timeout(Duration::from_secs(10))
+        .call()
+        .map_err(|e| anyhow::anyhow!("Failed to reach {}: {}", url, e))?;
+    if response.content_type() == MEDIA_TYPE {
+        return Ok(Some(url.to_string()));
+    }
+
+    let base = response.get_url().to_string();
+    let link = response
+        .all("link")
+        .into_iter()
+        .flat_map(|header| header.split(','))
+        .find_map(described_by);
+    Ok(link.map(|link| resolve(&base, link)))
+}
+
+/// Target of a Link header value pointing at a Metalink 4 document
+fn described_by(link: &str) -> Option<&str> {
+    let mut parts = link.split(';').map(str::trim);
+    let target = parts.next()?.strip_prefix('<')?.strip_suffix('>')?;
+    let mut rel = false;
+    let mut media_type = false;
+    for param in parts {
+        let (key, value) = param.split_once('=')?;
+        let value = value.trim().trim_matches('"');
+        match key.trim().to_ascii_lowercase().as_str() {
+            "rel" => rel = value.split_whitespace().any(|rel| rel.eq_ignore_ascii_case("describedby")),
+            "type" => media_type = value.eq_ignore_ascii_case(MEDIA_TYPE),
+            _ => {}
+        }
+    }
+    (rel && media_type).then_some(target)
+}
+
+/// A link target relative to the URL it was found at
+fn resolve(base: &str, target: &str) -> String {
+    if target.contains("://") {
+        return target.to_string();
+    }
+    let scheme_end = base.find("://").map_or(0, |index| index + 3);
+    if let Some(target) = target.strip_prefix("//") {
+        return format!("{}{}", &base[..scheme_end], target);
+    }
+    let path_start = base[scheme_end..].find('/').map_or(base.len(), |index| scheme_end + index);
+    if target.starts_with('/') {
+        return format!("{}{}", &base[..path_start], target);
+    }
+    let path = base.split(['?', '#']).next().unwrap_or(base);
+    let dir_end = path[path_start..].rfind('/').map_or(path.len(), |index| path_start + index);
+    format!("{}/{}", &path[..dir_end], target)
+}
+
+/// Parse a Metalink 4 document
+pub fn parse_metalink(xml: &str) -> anyhow::Result<Metalink> {
+    let document = parse_document(xml)?;
+    let root = document.root_element();
+    let text = |node: roxmltree::Node, name: &'static str| {
+        child(node, name)
+            .and_then(|child| child.text())
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+
+    let files = children(root, "file")
+        .map(|file| {
+            let name = file.attribute("name").map(str::trim).unwrap_or_default().to_string();
+            if !is_safe_name(&name) {
+                anyhow::bail!("Invalid file name {:?} in the Metalink file", name);
+            }
+
+            let hashes = children(file, "hash")
+                .filter_map(|hash| {
+                    Some(FileHash {
+                        kind: hash.attribute("type")?.to_ascii_lowercase(),
+                        value: hash.text()?.trim().to_ascii_lowercase(),
+                    })
+                })
+                .collect();
+            let pieces = child(file, "pieces").map(|pieces| Pieces {
+                kind: pieces.attribute("type").unwrap_or_default().to_ascii_lowercase(),
+                length: pieces.attribute("length").and_then(|length| length.parse().ok()).unwrap_or(0),
+                hashes: children(pieces, "hash")
+                    .filter_map(|hash| hash.text())
+                    .map(|hash| hash.trim().to_ascii_lowercase())
+                    .collect(),
+            });
+            let mirrors: Vec<Mirror> = children(file, "url")
+                .filter_map(|url| {
+                    Some(Mirror {
+                        url: url.text()?.trim().to_string(),
+                        location: url.attribute("location").map(|location| location.trim().to_ascii_lowercase()),
+                        priority: url.attribute("priority").and_then(|priority| priority.parse().ok()),
+                    })
+                })
+                .collect();
+            let metaurls: Vec<MetaUrl> = children(file, "metaurl")
+                .filter_map(|metaurl| {
+                    Some(MetaUrl {
+                        url: metaurl.text()?.trim().to_string(),
+                        mediatype: metaurl.attribute("mediatype").unwrap_or_default().to_string(),
+                        priority: metaurl.attribute("priority").and_then(|priority| priority.parse().ok()),
+                        name: metaurl.attribute("name").map(str::to_string),
+                    })
+                })
+                .collect();
+            if mirrors.is_empty() && metaurls.is_empty() {
+                anyhow::bail!("{} has no URL to download it from", name);
+            }
+
+            Ok(MetalinkFile {
+                size: text(file, "size").and_then(|size| size.parse().ok()),
+                version: text(file, "version"),
+                description: text(file, "description"),
+                hashes,
+                pieces,
+                mirrors,
+                metaurls,
+                name,
+            })
+        })
+        .collect::<anyhow::Result<Vec<MetalinkFile>>>()?;
+    if files.is_empty() {
+        anyhow::bail!("The Metalink file lists no files");
+    }
+
+    Ok(Metalink {
+        files,
+        generator: text(root, "generator"),
+        published: text(root, "published"),
+        origin: text(root, "origin"),
+    })
+}
+
+/// The document with the mirrors outside `countries` removed, everything else is
+/// left as it was written. Fails if a file would be left without any URL.
+pub fn restrict(xml: &str, countries: &BTreeSet<String>) -> anyhow::Result<String> {
+    if countries.is_empty() {
+        return Ok(xml.to_string());
+    }
+
+    let document = parse_document(xml)?;
+    let mut removed = Vec::new();
+    for file in children(document.root_element(), "file") {
+        let (kept, dropped): (Vec<_>, Vec<_>) =
+            children(file, "url").partition(|url| {
+                let location = url.attribute("location").map(|location| location.trim().to_ascii_lowercase());
+                allowed(location.as_deref(), countries)
+            });
+        if kept.is_empty() && children(file, "metaurl").next().is_none() {
+            anyhow::bail!("{} has no mirror in the selected countries", file.attribute("name").unwrap_or_default());
+        }
+        removed.extend(dropped.iter().map(|url| url.range()));
+    }
+
+    let mut xml = xml.to_string();
+    removed.sort_by_key(|range| range.start);
+    for range in removed.into_iter().rev() {
+        xml.replace_range(range, "");
+    }
+    Ok(xml)
+}
+
+fn parse_document(xml: &str) -> anyhow::Result<roxmltree::Document<'_>> {
+    let document = roxmltree::Document::parse(xml).map_err(|e| anyhow::anyhow!("Not a valid Metalink file: {}", e))?;
+    let root = document.root_element();
+    if root.tag_name().name() != "metalink" {
+        anyhow::bail!("Not a Metalink file");
+    }
+    match root.tag_name().namespace() {
+        Some(NAMESPACE) => Ok(document),
+        Some(NAMESPACE_V3) => anyhow::bail!("Only Metalink 4 (.meta4) files can be opened, this one is Metalink 3"),
+        _ => anyhow::bail!("Not a Metalink 4 file"),
+    }
+}
+
+fn children<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> {
+    node.children().filter(move |child| child.has_tag_name((NAMESPACE, name)))
+}
+
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &'static str) -> Option<roxmltree::Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+fn allowed(location: Option<&str>, countries: &BTreeSet<String>) -> bool {
+    countries.is_empty() || location.is_some_and(|location| countries.contains(location))
+}
+
+/// Relative paths without `..` components, as RFC 5854 requires of file names
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['/', '\\'])
+        && !name.contains(':')
+        && name.split(['/', '\\']).all(|component| !component.is_empty() && component != "." && component != "..")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+    const MD5: &str = "098f6bcd4621d373cade4e832627b4f6";
+
+    const DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metalink xmlns="urn:ietf:params:xml:ns:metalink">
+  <generator>Generator/1.0</generator>
+  <published>2024-01-02T03:04:05Z</published>
+  <file name="dir/example.iso">
+    <size>4</size>
+    <version>1.0</version>
+    <description> An example </description>
+    <hash type="MD5">098F6BCD4621D373CADE4E832627B4F6</hash>
+    <hash type="sha-256">9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08</hash>
+    <hash type="sha-3">abcdef</hash>
+    <pieces length="262144" type="sha-1">
+      <hash>a94a8fe5ccb19ba61c4c0873d391e987982fbbd3</hash>
+      <hash>A94A8FE5CCB19BA61C4C0873D391E987982FBBD3</hash>
+    </pieces>
+    <url location="de" priority="2">https://de.example.com/example.iso</url>
+    <url location=" US " priority="1">https://us.example.com/example.iso</url>
+    <url>https://any.example.com/example.iso</url>
+  </file>
+  <file name="readme.txt">
+    <size>10</size>
+    <url location="fr">https://fr.example.com/readme.txt</url>
+    <metaurl mediatype="torrent" priority="1" name="readme.txt">https://example.com/readme.torrent</metaurl>
+  </file>
+</metalink>"#;
+
+    fn countries(codes: &[&str]) -> BTreeSet<String> {
+        codes.iter().map(|code| code.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_files_mirrors_and_hashes() {
+        let metalink = parse_metalink(DOCUMENT).unwrap();
+        assert_eq!(metalink.generator.as_deref(), Some("Generator/1.0"));
+        assert_eq!(metalink.published.as_deref(), Some("2024-01-02T03:04:05Z"));
+        assert_eq!(metalink.files.len(), 2);
+        assert_eq!(metalink.total_size(), 14);
+        assert_eq!(metalink.countries(), countries(&["de", "fr", "us"]));
+
+        let file = &metalink.files[0];
+        assert_eq!(file.name, "dir/example.iso");
+        assert_eq!(file.version.as_deref(), Some("1.0"));
+        assert_eq!(file.description.as_deref(), Some("An example"));
+        assert_eq!(file.hashes[0], FileHash { kind: "md5".to_string(), value: MD5.to_string() });
+
+        let pieces = file.pieces.as_ref().unwrap();
+        assert_eq!((pieces.kind.as_str(), pieces.length), ("sha-1", 262144));
+        assert_eq!(pieces.hashes[0], pieces.hashes[1]);
+
+        let urls: Vec<&str> = file.mirrors_by_priority().iter().map(|mirror| mirror.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://us.example.com/example.iso",
+                "https://de.example.com/example.iso",
+                "https://any.example.com/example.iso",
+            ]
+        );
+
+        let metaurl = &metalink.files[1].metaurls[0];
+        assert_eq!((metaurl.mediatype.as_str(), metaurl.priority), ("torrent", Some(1)));
+    }
+
+    #[test]
+    fn checksums_strongest_first_without_unknown_ones() {
+        let metalink = parse_metalink(DOCUMENT).unwrap();
+        let checksums = metalink.files[0].checksums();
+        assert_eq!(checksums.len(), 2);
+        assert_eq!((checksums[0].algorithm, checksums[0].hex.as_str()), (HashAlgorithm::Sha256, SHA256));
+        assert_eq!((checksums[1].algorithm, checksums[1].hex.as_str()), (HashAlgorithm::Md5, MD5));
+
+        // A digest whose length doesn't match its type is ignored
+        let mismatched = FileHash { kind: "sha-256".to_string(), value: MD5.to_string() };
+        assert_eq!(mismatched.checksum(), None);
+    }
+
+    #[test]
+    fn rejects_unsafe_names_and_other_formats() {
+        for name in ["../etc/passwd", "/etc/passwd", "dir/../../x", "C:\\x", "a//b", "", "./a"] {
+            let xml = format!(
+                r#"<metalink xmlns="urn:ietf:params:xml:ns:metalink"><file name="{}"><url>https://a/b</url></file></metalink>"#,
+                name
+            );
+            assert!(parse_metalink(&xml).is_err(), "{:?} was accepted", name);
+        }
+
+        let v3 = r#"<metalink xmlns="http://www.metalinker.org/" version="3.0"><files/></metalink>"#;
+        assert!(parse_metalink(v3).unwrap_err().to_string().contains("Metalink 3"));
+        assert!(parse_metalink(r#"<metalink><file name="a"><url>https://a/b</url></file></metalink>"#).is_err());
+        assert!(parse_metalink(r#"<metalink xmlns="urn:ietf:params:xml:ns:metalink"/>"#).is_err());
+        assert!(parse_metalink(r#"<metalink xmlns="urn:ietf:params:xml:ns:metalink"><file name="a"/></metalink>"#).is_err());
+        assert!(parse_metalink("<metalink").is_err());
+    }
+
+    #[test]
+    fn restrict_removes_only_other_mirrors() {
+        let restricted = restrict(DOCUMENT, &countries(&["us", "fr"])).unwrap();
+        assert!(!restricted.contains("de.example.com"));
+        assert!(!restricted.contains("any.example.com"));
+        assert!(restricted.contains("us.example.com"));
+        assert!(restricted.contains("fr.example.com"));
+        assert!(restricted.contains("<generator>Generator/1.0</generator>"));
+
+        let metalink = parse_metalink(&restricted).unwrap();
+        assert_eq!(metalink.files[0].mirrors.len(), 1);
+        assert_eq!(metalink.files[0].hashes, parse_metalink(DOCUMENT).unwrap().files[0].hashes);
+    }
+
+    #[test]
+    fn restrict_keeps_files_with_metaurls_and_fails_without_any_url() {
+        // readme.txt keeps its torrent, example.iso has no mirror left
+        assert!(restrict(DOCUMENT, &countries(&["jp"])).is_err());
+        let metalink = parse_metalink(DOCUMENT).unwrap();
+        assert_eq!(metalink.unreachable(&countries(&["de"])), Vec::<&str>::new());
+        assert_eq!(metalink.unreachable(&countries(&["jp"])), ["dir/example.iso"]);
+
+        assert_eq!(restrict(DOCUMENT, &BTreeSet::new()).unwrap(), DOCUMENT);
+    }
+
+    #[test]
+    fn finds_described_by_links() {
+        let link = r#"<https://example.com/file.meta4>; rel="describedby"; type="application/metalink4+xml""#;
+        assert_eq!(described_by(link), Some("https://example.com/file.meta4"));
+        assert_eq!(described_by(r#"</file.meta4>; rel=duplicate; type="application/metalink4+xml""#), None);
+        assert_eq!(described_by(r#"</file.torrent>; rel=describedby; type="application/x-bittorrent""#), None);
+    }
+
+    #[test]
+    fn resolves_relative_links() {
+        let base = "https://example.com/dir/file.iso?x=1";
+        assert_eq!(resolve(base, "https://other/a.meta4"), "https://other/a.meta4");
+        assert_eq!(resolve(base, "//cdn.example.com/a.meta4"), "https://cdn.example.com/a.meta4");
+        assert_eq!(resolve(base, "/a.meta4"), "https://example.com/a.meta4");
+        assert_eq!(resolve(base, "a.meta4"), "https://example.com/dir/a.meta4");
+        assert_eq!(resolve("https://example.com", "a.meta4"), "https://example.com/a.meta4");
+    }
+
+    #[test]
+    fn recognizes_metalink_sources() {
+        assert!(is_metalink_source("https://example.com/file.meta4?x=1"));
+        assert!(is_metalink_source("/tmp/FILE.METALINK"));
+        assert!(!is_metalink_source("https://example.com/file.iso"));
+    }
+}
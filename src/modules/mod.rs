@@ -2,3 +2,20 @@ pub mod background;
 pub mod tray;
 pub mod ui;
 pub mod ipc;
+pub mod settings;
+pub mod aria2;
+pub mod format;
+pub mod actions;
+pub mod checksum;
+pub mod history;
+pub mod native_host;
+pub mod api;
+pub mod engine;
+pub mod url_list;
+pub mod watch;
+pub mod feeds;
+pub mod bencode;
+pub mod torrent;
+pub mod magnet;
+pub mod metalink;
+pub mod batch;
@@ -0,0 +1,305 @@
+use crate::modules::engine::AddRequest;
+use crate::modules::ipc::{IpcClient, IpcCommand, BACKGROUND_IPC_PORT};
+use crate::modules::settings::home_dir;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Name browsers know the host by, the manifest file is called `<HOST_NAME>.json`
+pub const HOST_NAME: &str = "bong";
+
+/// ID of the Firefox extension allowed to talk to the host
+pub const FIREFOX_EXTENSION_ID: &str = "bong@extension";
+
+/// Browsers refuse messages from the host above 1 MB
+const MAX_RESPONSE_LEN: usize = 1024 * 1024;
+
+/// Requests above this are rejected instead of allocated
+const MAX_REQUEST_LEN: usize = 64 * 1024 * 1024;
+
+/// A message sent by the browser extension
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum HostRequest {
+    /// Check that the host and the background task are reachable
+    Ping,
+    /// A download captured by the extension
+    Download(CapturedDownload),
+}
+
+/// A download as seen by the browser, with what's needed to fetch it the same way
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CapturedDownload {
+    pub url: String,
+    /// File name suggested by the browser
+    pub filename: Option<String>,
+    pub referrer: Option<String>,
+    /// Cookie header value, e.g. "session=abc; theme=dark"
+    pub cookies: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl CapturedDownload {
+    /// The download request with the browser's cookies, referrer and user agent
+    pub fn to_request(&self) -> anyhow::Result<AddRequest> {
+        let url = self.url.trim();
+        if !(url.starts_with("http://") || url.starts_with("https://") || url.starts_with("ftp://")) {
+            anyhow::bail!("Unsupported URL {}", url);
+        }
+
+        let mut request = AddRequest::new(url);
+        // Only the last path segment, a suggested name mustn't escape the download folder
+        request.out = self
+            .filename
+            .as_deref()
+            .and_then(|name| name.rsplit(['/', '\\']).next())
+            .map(str::trim)
+            .filter(|name| !matches!(*name, "" | "." | ".."))
+            .map(str::to_string);
+        if let Some(cookies) = non_empty(&self.cookies) {
+            request.headers.push(format!("Cookie: {}", cookies));
+        }
+        if let Some(referrer) = non_empty(&self.referrer) {
+            request.options.push(("referer".to_string(), referrer.to_string()));
+        }
+        if let Some(user_agent) = non_empty(&self.user_agent) {
+            request.options.push(("user-agent".to_string(), user_agent.to_string()));
+        }
+        Ok(request)
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|value| !value.is_empty())
+}
+
+/// Read one length-prefixed message, `None` once the browser closed stdin
+pub fn read_message(reader: &mut impl Read) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    let mut filled = 0;
+    while filled < len.len() {
+        match reader.read(&mut len[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => anyhow::bail!("Message length cut off after {} bytes", filled),
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    // The length is in native byte order
+    let len = u32::from_ne_bytes(len) as usize;
+    if len > MAX_REQUEST_LEN {
+        anyhow::bail!("Message of {} bytes is too large", len);
+    }
+    let mut message = vec![0u8; len];
+    reader.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+/// Write one length-prefixed message
+pub fn write_message(writer: &mut impl Write, message: &serde_json::Value) -> anyhow::Result<()> {
+    let data = serde_json::to_vec(message)?;
+    if data.len() > MAX_RESPONSE_LEN {
+        anyhow::bail!("Response of {} bytes is too large", data.len());
+    }
+    writer.write_all(&(data.len() as u32).to_ne_bytes())?;
+    writer.write_all(&data)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Answer a raw message from the extension. Failures are reported to the
+/// extension rather than ending the host.
+pub fn handle_message(message: &[u8]) -> serde_json::Value {
+    let request = match serde_json::from_slice::<HostRequest>(message) {
+        Ok(request) => request,
+        Err(e) => return json!({ "ok": false, "error": format!("Invalid message: {}", e) }),
+    };
+
+    let client = IpcClient::new(BACKGROUND_IPC_PORT);
+    match request {
+        HostRequest::Ping => {
+            let running = matches!(client.send_with_response(IpcCommand::Ping), Ok(Some(IpcCommand::BackgroundStatus(_))));
+            json!({ "ok": true, "version": env!("CARGO_PKG_VERSION"), "running": running })
+        }
+        HostRequest::Download(download) => {
+            let result = download.to_request().and_then(|request| {
+                match client.send_with_response(IpcCommand::AddDownload(request)) {
+                    Ok(Some(IpcCommand::DownloadAdded(gid))) => Ok(gid),
+                    Ok(Some(IpcCommand::Error(e))) => Err(anyhow::anyhow!(e)),
+                    Ok(_) => Err(anyhow::anyhow!("Unexpected answer from the background task")),
+                    Err(_) => Err(anyhow::anyhow!("bong is not running, start it from the tray")),
+                }
+            });
+            match result {
+                Ok(gid) => json!({ "ok": true, "gid": gid }),
+                Err(e) => json!({ "ok": false, "error": e.to_string() }),
+            }
+        }
+    }
+}
+
+/// Browser families with different manifest formats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrowserKind {
+    Firefox,
+    Chromium,
+}
+
+/// Manifest telling the browser how to start the host and which extensions may use it
+pub fn manifest(kind: BrowserKind, host_path: &Path, chromium_ids: &[String]) -> serde_json::Value {
+    let mut manifest = json!({
+        "name": HOST_NAME,
+        "description": "Download with bong",
+        "path": host_path,
+        "type": "stdio",
+    });
+    match kind {
+        BrowserKind::Firefox => manifest["allowed_extensions"] = json!([FIREFOX_EXTENSION_ID]),
+        BrowserKind::Chromium => {
+            let origins: Vec<String> = chromium_ids.iter().map(|id| format!("chrome-extension://{}/", id)).collect();
+            manifest["allowed_origins"] = json!(origins);
+        }
+    }
+    manifest
+}
+
+/// Per-user manifest folders of the browsers on Linux, with the profile folder
+/// that shows the browser is installed
+pub fn manifest_dirs() -> Vec<(BrowserKind, PathBuf, PathBuf)> {
+    let Some(home) = home_dir() else {
+        return Vec::new();
+    };
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".config"));
+
+    let mut dirs = vec![
+        (BrowserKind::Firefox, home.join(".mozilla")),
+        (BrowserKind::Firefox, home.join(".librewolf")),
+        (BrowserKind::Firefox, home.join(".waterfox")),
+    ];
+    for profile in ["google-chrome", "chromium", "BraveSoftware/Brave-Browser", "vivaldi", "microsoft-edge"] {
+        dirs.push((BrowserKind::Chromium, config.join(profile)));
+    }
+
+    dirs.into_iter()
+        .map(|(kind, profile)| {
+            let hosts = match kind {
+                BrowserKind::Firefox => profile.join("native-messaging-hosts"),
+                BrowserKind::Chromium => profile.join("NativeMessagingHosts"),
+            };
+            (kind, profile, hosts)
+        })
+        .collect()
+}
+
+/// Write the manifests for every installed browser, returning the files written.
+/// Chromium browsers are skipped unless extension IDs are given, their IDs depend on how
+/// the extension was installed.
+pub fn install(host_path: &Path, chromium_ids: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for (kind, profile, hosts) in manifest_dirs() {
+        if !profile.is_dir() || (kind == BrowserKind::Chromium && chromium_ids.is_empty()) {
+            continue;
+        }
+        std::fs::create_dir_all(&hosts).map_err(|e| anyhow::anyhow!("Failed to create {}: {}", hosts.display(), e))?;
+        let path = hosts.join(format!("{}.json", HOST_NAME));
+        std::fs::write(&path, serde_json::to_vec_pretty(&manifest(kind, host_path, chromium_ids))?)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Remove the manifests written by `install`, returning the files removed
+pub fn uninstall() -> anyhow::Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for (_, _, hosts) in manifest_dirs() {
+        let path = hosts.join(format!("{}.json", HOST_NAME));
+        if path.is_file() {
+            std::fs::remove_file(&path).map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", path.display(), e))?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framed(message: &[u8]) -> Vec<u8> {
+        let mut data = (message.len() as u32).to_ne_bytes().to_vec();
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let mut data = Vec::new();
+        write_message(&mut data, &json!({"type": "ping"})).unwrap();
+        write_message(&mut data, &json!({"ok": true, "gid": "abc"})).unwrap();
+
+        let mut reader = data.as_slice();
+        let first = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(serde_json::from_slice::<HostRequest>(&first).unwrap(), HostRequest::Ping);
+        let second = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&second).unwrap(), json!({"ok": true, "gid": "abc"}));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_truncated_messages() {
+        let data = framed(b"{\"type\":\"ping\"}");
+        assert!(read_message(&mut &data[..2]).is_err());
+        assert!(read_message(&mut &data[..data.len() - 1]).is_err());
+        assert_eq!(read_message(&mut &data[..0]).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_messages_over_the_size_limit() {
+        let data = ((MAX_REQUEST_LEN + 1) as u32).to_ne_bytes();
+        let error = read_message(&mut data.as_slice()).unwrap_err();
+        assert!(error.to_string().contains("too large"), "{}", error);
+
+        let mut output = Vec::new();
+        let response = json!({"error": "x".repeat(MAX_RESPONSE_LEN)});
+        assert!(write_message(&mut output, &response).is_err());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn suggested_names_stay_inside_the_download_folder() {
+        let name = |filename: &str| {
+            let download = CapturedDownload {
+                url: "https://example.com/file".to_string(),
+                filename: Some(filename.to_string()),
+                ..Default::default()
+            };
+            download.to_request().unwrap().out
+        };
+        assert_eq!(name("file.iso"), Some("file.iso".to_string()));
+        assert_eq!(name("../../etc/file.iso"), Some("file.iso".to_string()));
+        assert_eq!(name("C:\\Users\\me\\file.iso"), Some("file.iso".to_string()));
+        assert_eq!(name(""), None);
+        assert_eq!(name("  "), None);
+        assert_eq!(name(".."), None);
+        assert_eq!(name("."), None);
+        assert_eq!(name("dir/.."), None);
+        assert_eq!(name("dir/."), None);
+    }
+
+    #[test]
+    fn rejects_unsupported_urls() {
+        let download = CapturedDownload {
+            url: "javascript:alert(1)".to_string(),
+            ..Default::default()
+        };
+        assert!(download.to_request().is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Placeholders replaced in command arguments and shown in the editor
+pub const ACTION_PLACEHOLDERS: [&str; 8] = ["{file}", "{dir}", "{name}", "{url}", "{gid}", "{size}", "{category}", "{status}"];
+
+/// Something done with a download once it completes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PostAction {
+    /// Compare the file against a sidecar checksum such as `file.sha256` or `SHA256SUMS`
+    VerifyChecksum,
+    /// Unpack zip and tar archives next to the file, other formats with 7z
+    Extract { delete_archive: bool },
+    /// Move the downloaded files into another folder, relative folders are inside the download directory
+    Move { dir: PathBuf },
+    /// Run a program, arguments may contain placeholders such as `{file}`
+    Command { program: String, args: Vec<String> },
+    /// POST the finished download as JSON to a service on this machine
+    Webhook { url: String },
+}
+
+impl PostAction {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PostAction::VerifyChecksum => "Verify checksum",
+            PostAction::Extract { .. } => "Extract",
+            PostAction::Move { .. } => "Move",
+            PostAction::Command { .. } => "Command",
+            PostAction::Webhook { .. } => "Webhook",
+        }
+    }
+
+    /// Short description shown in lists
+    pub fn describe(&self) -> String {
+        match self {
+            PostAction::VerifyChecksum => "Verify checksum".to_string(),
+            PostAction::Extract { delete_archive: true } => "Extract and delete archive".to_string(),
+            PostAction::Extract { delete_archive: false } => "Extract".to_string(),
+            PostAction::Move { dir } => format!("Move to {}", dir.display()),
+            PostAction::Command { program, .. } => format!("Run {}", program),
+            PostAction::Webhook { url } => format!("Notify {}", url),
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            PostAction::VerifyChecksum | PostAction::Extract { .. } => {}
+            PostAction::Move { dir } => {
+                if dir.as_os_str().is_empty() {
+                    anyhow::bail!("Move actions need a folder");
+                }
+            }
+            PostAction::Command { program, .. } => {
+                if program.trim().is_empty() {
+                    anyhow::bail!("Command actions need a program");
+                }
+            }
+            PostAction::Webhook { url } => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    anyhow::bail!("Webhook URL must start with http:// or https://");
+                }
+                if !is_local_url(url) {
+                    anyhow::bail!("Webhooks can only notify localhost, file names and paths would leave this machine");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether an http(s) URL points at this machine, by the name localhost or a loopback address
+pub fn is_local_url(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("http://").or_else(|| url.strip_prefix("https://")) else {
+        return false;
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    host.eq_ignore_ascii_case("localhost") || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// A post-download action and the downloads it applies to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionRule {
+    pub enabled: bool,
+    /// Category names, empty for every download
+    pub categories: Vec<String>,
+    pub action: PostAction,
+}
+
+impl Default for ActionRule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            categories: Vec::new(),
+            action: PostAction::VerifyChecksum,
+        }
+    }
+}
+
+impl ActionRule {
+    /// Whether the rule runs for a download in `category`
+    pub fn applies_to(&self, category: Option<&str>) -> bool {
+        self.enabled
+            && (self.categories.is_empty()
+                || category.is_some_and(|category| self.categories.iter().any(|name| name == category)))
+    }
+}
+
+/// Actions run by the background task when a download completes, in list order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionSettings {
+    pub enabled: bool,
+    /// Skip the remaining actions of a download after one fails
+    pub stop_on_failure: bool,
+    pub rules: Vec<ActionRule>,
+}
+
+impl Default for ActionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            stop_on_failure: true,
+            rules: vec![
+                ActionRule::default(),
+                ActionRule {
+                    enabled: false,
+                    categories: vec!["Archives".to_string()],
+                    action: PostAction::Extract { delete_archive: false },
+                },
+            ],
+        }
+    }
+}
+
+impl ActionSettings {
+    /// Actions to run for a download in `category`, with move targets made absolute
+    pub fn for_category(&self, category: Option<&str>, download_dir: &Path) -> Vec<PostAction> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        self.rules
+            .iter()
+            .filter(|rule| rule.applies_to(category))
+            .map(|rule| match rule.action {
+                PostAction::Move { ref dir } => PostAction::Move {
+                    dir: download_dir.join(dir),
+                },
+                ref action => action.clone(),
+            })
+            .collect()
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for rule in &self.rules {
+            rule.action.validate()?;
+        }
+        Ok(())
+    }
+}
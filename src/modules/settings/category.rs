@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A kind of download with its own folder, assignment rules and transfer options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Category {
+    pub name: String,
+    /// Destination folder, relative paths are inside the default download directory
+    pub dir: PathBuf,
+    /// File extensions without the dot, e.g. "mkv"
+    pub extensions: Vec<String>,
+    /// MIME types, a trailing "/*" matches a whole family such as "video/*". Only
+    /// checked for downloads from watch folders and feeds, which can wait for the server.
+    pub mime_types: Vec<String>,
+    /// Wildcard patterns matched against the URL, e.g. "*://*.youtube.com/*"
+    pub url_patterns: Vec<String>,
+    /// Connections per file, 0 to use the global setting
+    pub split: u32,
+    /// Connections per server, 0 to use the global setting
+    pub max_connection_per_server: u32,
+    /// Download speed of each download in bytes per second, 0 for the speed profile's
+    pub download_limit: u64,
+}
+
+impl Category {
+    fn new(name: &str, extensions: &[&str], mime_types: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            dir: PathBuf::from(name),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+            mime_types: mime_types.iter().map(|mime| mime.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// aria2 options for downloads added to this category
+    pub fn options(&self) -> Vec<(&'static str, String)> {
+        let mut options = Vec::new();
+        if self.split > 0 {
+            options.push(("split", self.split.to_string()));
+        }
+        if self.max_connection_per_server > 0 {
+            options.push(("max-connection-per-server", self.max_connection_per_server.to_string()));
+        }
+        if self.download_limit > 0 {
+            options.push(("max-download-limit", self.download_limit.to_string()));
+        }
+        options
+    }
+
+    /// Whether the file name has one of the category's extensions
+    pub fn matches_name(&self, name: &str) -> bool {
+        let Some((_, ext)) = name.rsplit_once('.') else {
+            return false;
+        };
+        self.extensions.iter().any(|candidate| candidate.trim_start_matches('.').eq_ignore_ascii_case(ext))
+    }
+
+    pub fn matches_mime(&self, mime: &str) -> bool {
+        let mime = mime.split(';').next().unwrap_or_default().trim();
+        self.mime_types.iter().any(|candidate| match candidate.strip_suffix("/*") {
+            Some(family) => mime
+                .split_once('/')
+                .is_some_and(|(mime_family, _)| mime_family.eq_ignore_ascii_case(family)),
+            None => candidate.eq_ignore_ascii_case(mime),
+        })
+    }
+
+    pub fn matches_url(&self, url: &str) -> bool {
+        self.url_patterns.iter().any(|pattern| wildcard_match(pattern, url))
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.name.trim().is_empty() {
+            anyhow::bail!("Categories need a name");
+        }
+        if self.dir.as_os_str().is_empty() {
+            anyhow::bail!("Category {} needs a folder", self.name);
+        }
+        if self.split > 64 {
+            anyhow::bail!("Split of category {} must be at most 64", self.name);
+        }
+        if self.max_connection_per_server > 16 {
+            anyhow::bail!("Connections per server of category {} must be at most 16", self.name);
+        }
+        Ok(())
+    }
+}
+
+/// Download categories, checked in order when assigning new downloads
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CategorySettings {
+    /// Pick a category for new downloads that don't name one
+    pub auto_assign: bool,
+    pub categories: Vec<Category>,
+}
+
+impl Default for CategorySettings {
+    fn default() -> Self {
+        Self {
+            auto_assign: true,
+            categories: vec![
+                Category::new("Video", &["mp4", "mkv", "avi", "mov", "webm", "wmv", "flv", "m4v"], &["video/*"]),
+                Category::new("Music", &["mp3", "flac", "ogg", "opus", "wav", "m4a", "aac"], &["audio/*"]),
+                Category::new(
+                    "Archives",
+                    &["zip", "rar", "7z", "tar", "gz", "bz2", "xz", "zst"],
+                    &["application/zip", "application/x-7z-compressed", "application/x-rar-compressed", "application/gzip"],
+                ),
+                Category::new("ISOs", &["iso", "img", "dmg"], &["application/x-iso9660-image"]),
+                Category::new(
+                    "Documents",
+                    &["pdf", "epub", "doc", "docx", "odt", "xls", "xlsx", "ppt", "pptx", "txt"],
+                    &["application/pdf", "application/epub+zip", "text/plain"],
+                ),
+            ],
+        }
+    }
+}
+
+impl CategorySettings {
+    pub fn find(&self, name: &str) -> Option<&Category> {
+        self.categories.iter().find(|category| category.name == name)
+    }
+
+    /// Category for a new download by URL pattern, then file extension, then MIME type
+    pub fn classify(&self, url: &str, file_name: Option<&str>, mime: Option<&str>) -> Option<&Category> {
+        let name = file_name.map(str::to_string).unwrap_or_else(|| url_file_name(url));
+        self.categories
+            .iter()
+            .find(|category| category.matches_url(url))
+            .or_else(|| self.categories.iter().find(|category| category.matches_name(&name)))
+            .or_else(|| {
+                let mime = mime?;
+                self.categories.iter().find(|category| category.matches_mime(mime))
+            })
+    }
+
+    /// Category of an existing download, by the folder it's saved in and then by its name.
+    /// `dir` is the local download directory, `download_dir` the default one.
+    pub fn for_download(&self, dir: &Path, name: &str, url: Option<&str>, download_dir: &Path) -> Option<&Category> {
+        self.categories
+            .iter()
+            .find(|category| dir.starts_with(download_dir.join(&category.dir)))
+            .or_else(|| self.classify(url.unwrap_or_default(), Some(name), None))
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (i, category) in self.categories.iter().enumerate() {
+            category.validate()?;
+            if self.categories[..i].iter().any(|other| other.name == category.name) {
+                anyhow::bail!("Category name {} is used twice", category.name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Last path segment of a URL, without query or fragment
+fn url_file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or_default().to_string()
+}
+
+/// Case-insensitive match where `*` matches any run of characters and `?` a single one
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last star and the text position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, star_t)) = backtrack {
+            p = star + 1;
+            t = star_t + 1;
+            backtrack = Some((star, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the endpoint for the daemon managed by bong
+pub const LOCAL_ENDPOINT: &str = "Local";
+
+/// An aria2 RPC interface bong can control
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Aria2Endpoint {
+    /// Unique display name
+    pub name: String,
+    /// The daemon started by the background task. Its port and secret
+    /// come from the network and aria2 settings instead of this entry.
+    pub managed: bool,
+    pub host: String,
+    pub port: u16,
+    pub secret: String,
+    /// Connect over HTTPS
+    pub tls: bool,
+    /// RPC path on the server, usually /jsonrpc
+    pub path: String,
+    /// How paths on the aria2 host translate to paths on this machine
+    pub path_mappings: Vec<PathMapping>,
+}
+
+impl Default for Aria2Endpoint {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            managed: false,
+            host: "127.0.0.1".to_string(),
+            port: 6800,
+            secret: String::new(),
+            tls: false,
+            path: "/jsonrpc".to_string(),
+            path_mappings: Vec::new(),
+        }
+    }
+}
+
+/// A directory on the aria2 host and where it is reachable locally (e.g. a mounted share)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathMapping {
+    pub remote: String,
+    pub local: PathBuf,
+}
+
+impl Aria2Endpoint {
+    /// The endpoint for the managed local daemon
+    pub fn local() -> Self {
+        Self {
+            name: LOCAL_ENDPOINT.to_string(),
+            managed: true,
+            ..Default::default()
+        }
+    }
+
+    /// Full RPC URL
+    pub fn url(&self) -> String {
+        let scheme = if self.tls { "https" } else { "http" };
+        let path = if self.path.starts_with('/') {
+            self.path.clone()
+        } else {
+            format!("/{}", self.path)
+        };
+        format!("{}://{}:{}{}", scheme, self.host, self.port, path)
+    }
+
+    /// Translate a path reported by aria2 into a local path
+    pub fn to_local(&self, remote: &str) -> PathBuf {
+        for mapping in &self.path_mappings {
+            match strip_dir_prefix(remote, &mapping.remote) {
+                Some("") => return mapping.local.clone(),
+                Some(rest) => return mapping.local.join(rest),
+                None => {}
+            }
+        }
+        PathBuf::from(remote)
+    }
+
+    /// Translate a local path into the path aria2 should use, if it is mapped
+    pub fn to_remote(&self, local: &Path) -> Option<String> {
+        self.path_mappings.iter().find_map(|mapping| {
+            let rest = local.strip_prefix(&mapping.local).ok()?;
+            let remote = mapping.remote.trim_end_matches('/');
+            if rest.as_os_str().is_empty() {
+                return Some(remote.to_string());
+            }
+            let rest = rest.to_string_lossy().replace('\\', "/");
+            Some(format!("{}/{}", remote, rest))
+        })
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.name.trim().is_empty() {
+            anyhow::bail!("Endpoint name must not be empty");
+        }
+        if self.managed {
+            return Ok(());
+        }
+        if self.host.trim().is_empty() {
+            anyhow::bail!("Endpoint {} needs a host", self.name);
+        }
+        if self.port == 0 {
+            anyhow::bail!("Endpoint {} needs a port", self.name);
+        }
+        for mapping in &self.path_mappings {
+            if mapping.remote.is_empty() || mapping.local.as_os_str().is_empty() {
+                anyhow::bail!("Endpoint {} has an incomplete path mapping", self.name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Strip a directory prefix, only matching whole path components
+fn strip_dir_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let prefix = prefix.trim_end_matches('/');
+    let rest = path.strip_prefix(prefix)?;
+    if rest.is_empty() {
+        Some(rest)
+    } else {
+        rest.strip_prefix('/')
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// An RSS or Atom feed whose new items are downloaded when their title passes the filters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Feed {
+    pub enabled: bool,
+    pub name: String,
+    pub url: String,
+    /// Regular expression titles must match, empty to accept every item
+    pub include: String,
+    /// Regular expression that skips matching titles, empty to skip none
+    pub exclude: String,
+    /// Category of the downloads, picked by the assignment rules when None
+    pub category: Option<String>,
+    /// Destination folder, relative paths are inside the default download directory.
+    /// Empty to use the category's folder.
+    pub dir: PathBuf,
+    /// Also download the items already in the feed when subscribing, only newer
+    /// items are downloaded otherwise
+    pub backlog: bool,
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            name: String::new(),
+            url: String::new(),
+            include: String::new(),
+            exclude: String::new(),
+            category: None,
+            dir: PathBuf::new(),
+            backlog: false,
+        }
+    }
+}
+
+impl Feed {
+    /// The include and exclude filters, case-insensitive
+    pub fn filters(&self) -> anyhow::Result<(Option<regex::Regex>, Option<regex::Regex>)> {
+        let compile = |pattern: &str, which: &str| -> anyhow::Result<Option<regex::Regex>> {
+            if pattern.trim().is_empty() {
+                return Ok(None);
+            }
+            regex::RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(Some)
+                .map_err(|e| anyhow::anyhow!("{} filter of feed {} is invalid: {}", which, self.name, e))
+        };
+        Ok((compile(&self.include, "Include")?, compile(&self.exclude, "Exclude")?))
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.name.trim().is_empty() {
+            anyhow::bail!("Feeds need a name");
+        }
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            anyhow::bail!("URL of feed {} must start with http:// or https://", self.name);
+        }
+        self.filters()?;
+        Ok(())
+    }
+}
+
+/// Feed subscriptions polled by the background task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedSettings {
+    /// Minutes between two checks of a feed
+    pub interval_mins: u32,
+    pub feeds: Vec<Feed>,
+}
+
+impl Default for FeedSettings {
+    fn default() -> Self {
+        Self {
+            interval_mins: 30,
+            feeds: Vec::new(),
+        }
+    }
+}
+
+impl FeedSettings {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.interval_mins < 5 {
+            anyhow::bail!("Feeds can be checked every 5 minutes at most");
+        }
+        for (i, feed) in self.feeds.iter().enumerate() {
+            feed.validate()?;
+            if self.feeds[..i].iter().any(|other| other.name == feed.name) {
+                anyhow::bail!("Feed name {} is used twice", feed.name);
+            }
+        }
+        Ok(())
+    }
+}
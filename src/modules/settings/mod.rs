@@ -0,0 +1,735 @@
+mod actions;
+mod category;
+mod endpoint;
+mod feed;
+mod profile;
+mod schedule;
+mod watch;
+
+pub use actions::{is_local_url, ActionRule, ActionSettings, PostAction, ACTION_PLACEHOLDERS};
+pub use category::{wildcard_match, Category, CategorySettings};
+pub use endpoint::{Aria2Endpoint, PathMapping, LOCAL_ENDPOINT};
+pub use feed::{Feed, FeedSettings};
+pub use profile::{BandwidthSettings, SpeedProfile, UNLIMITED_PROFILE};
+pub use schedule::{parse_time, Day, ScheduleAction, ScheduleSettings, ScheduleWindow};
+pub use watch::{WatchFolder, WatchSettings, ADDED_DIR, FAILED_DIR};
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use parking_lot::{Mutex, RwLock};
+
+/// File name of the settings file inside the config directory
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Application settings, persisted as JSON in the config directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub general: GeneralSettings,
+    pub appearance: AppearanceSettings,
+    pub downloads: DownloadSettings,
+    pub network: NetworkSettings,
+    pub engine: EngineSettings,
+    pub video: VideoSettings,
+    pub aria2: Aria2Settings,
+    pub bandwidth: BandwidthSettings,
+    pub schedule: ScheduleSettings,
+    pub categories: CategorySettings,
+    pub actions: ActionSettings,
+    pub watch: WatchSettings,
+    pub feeds: FeedSettings,
+    pub api: ApiSettings,
+}
+
+/// General application behaviour
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneralSettings {
+    /// Open the UI window when the tray starts
+    pub open_ui_on_start: bool,
+    /// Start the background task together with the tray
+    pub start_background_on_start: bool,
+}
+
+impl Default for GeneralSettings {
+    fn default() -> Self {
+        Self {
+            open_ui_on_start: true,
+            start_background_on_start: true,
+        }
+    }
+}
+
+/// Theme preference for the UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ThemePreference {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// Look and feel of the UI
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppearanceSettings {
+    pub theme: ThemePreference,
+    /// Use denser rows in lists
+    pub compact_lists: bool,
+    /// Show transfer speeds in bits instead of bytes
+    pub speed_in_bits: bool,
+}
+
+impl Default for AppearanceSettings {
+    fn default() -> Self {
+        Self {
+            theme: ThemePreference::System,
+            compact_lists: false,
+            speed_in_bits: false,
+        }
+    }
+}
+
+/// Where and how downloads are stored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadSettings {
+    /// Default destination directory
+    pub download_dir: PathBuf,
+    /// Maximum number of downloads running at the same time
+    pub max_concurrent: u32,
+    /// Start new downloads immediately instead of queueing them paused
+    pub auto_start: bool,
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        Self {
+            download_dir: default_download_dir(),
+            max_concurrent: 5,
+            auto_start: true,
+        }
+    }
+}
+
+/// Network related settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Port of the aria2 JSON-RPC interface
+    pub rpc_port: u16,
+    /// Proxy URL, empty to connect directly
+    pub proxy: String,
+    /// User agent sent with HTTP requests
+    pub user_agent: String,
+    /// Connection timeout in seconds
+    pub timeout_secs: u32,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            rpc_port: 6800,
+            proxy: String::new(),
+            user_agent: format!("bong/{}", env!("CARGO_PKG_VERSION")),
+            timeout_secs: 60,
+        }
+    }
+}
+
+/// File allocation methods supported by aria2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum FileAllocation {
+    None,
+    #[default]
+    Prealloc,
+    Trunc,
+    Falloc,
+}
+
+impl FileAllocation {
+    pub const ALL: [FileAllocation; 4] = [
+        FileAllocation::None,
+        FileAllocation::Prealloc,
+        FileAllocation::Trunc,
+        FileAllocation::Falloc,
+    ];
+
+    /// Value as written in aria2.conf
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileAllocation::None => "none",
+            FileAllocation::Prealloc => "prealloc",
+            FileAllocation::Trunc => "trunc",
+            FileAllocation::Falloc => "falloc",
+        }
+    }
+}
+
+/// Download engines bong can drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum EngineKind {
+    /// aria2 over JSON-RPC, the managed daemon or a remote endpoint
+    #[default]
+    Aria2,
+    /// The built-in HTTP(S) downloader, needs no external program
+    Native,
+}
+
+impl EngineKind {
+    pub const ALL: [EngineKind; 2] = [EngineKind::Aria2, EngineKind::Native];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EngineKind::Aria2 => "aria2",
+            EngineKind::Native => "Built-in",
+        }
+    }
+}
+
+/// Which engine runs downloads, and the options of the built-in one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineSettings {
+    pub kind: EngineKind,
+    /// Connections the built-in engine opens per file when the server supports ranges
+    pub connections: u32,
+    /// Don't split files into segments smaller than this (e.g. "4M")
+    pub min_segment_size: String,
+    /// Attempts per segment before a download fails
+    pub max_retries: u32,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            kind: EngineKind::Aria2,
+            connections: 4,
+            min_segment_size: "4M".to_string(),
+            max_retries: 5,
+        }
+    }
+}
+
+/// Downloads from video pages, run by yt-dlp next to the selected engine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoSettings {
+    /// Hand URLs of the video sites in `hosts` to yt-dlp instead of the engine
+    pub enabled: bool,
+    /// Sites whose pages are handed to yt-dlp, subdomains included
+    pub hosts: Vec<String>,
+    /// Path to the yt-dlp executable, looked up in PATH when not absolute
+    pub binary: PathBuf,
+    /// yt-dlp format selector used when no format was picked
+    pub format: String,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hosts: [
+                "youtube.com",
+                "youtu.be",
+                "youtube-nocookie.com",
+                "vimeo.com",
+                "dailymotion.com",
+                "twitch.tv",
+                "tiktok.com",
+                "bilibili.com",
+                "rumble.com",
+                "streamable.com",
+                "soundcloud.com",
+                "bandcamp.com",
+                "odysee.com",
+                "peertube.tv",
+            ]
+            .map(String::from)
+            .to_vec(),
+            binary: PathBuf::from("yt-dlp"),
+            format: "bv*+ba/b".to_string(),
+        }
+    }
+}
+
+/// The managed aria2 daemon and the options passed to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Aria2Settings {
+    /// aria2c executable, looked up in PATH when not absolute
+    pub binary: PathBuf,
+    /// Launch aria2c together with the background task
+    pub auto_start: bool,
+    /// Secret token for the RPC interface, empty to disable
+    pub rpc_secret: String,
+    /// Connections used to download a single file
+    pub split: u32,
+    /// Don't split files into pieces smaller than this (e.g. "20M")
+    pub min_split_size: String,
+    pub max_connection_per_server: u32,
+    /// Write cache size (e.g. "16M"), only applied when aria2c starts
+    pub disk_cache: String,
+    pub file_allocation: FileAllocation,
+    /// Stop seeding torrents at this share ratio, 0 seeds forever
+    pub seed_ratio: f32,
+    /// aria2 instances bong can control, including the managed one
+    pub endpoints: Vec<Aria2Endpoint>,
+    /// Name of the endpoint the UI currently controls
+    pub active_endpoint: String,
+}
+
+impl Default for Aria2Settings {
+    fn default() -> Self {
+        Self {
+            binary: PathBuf::from("aria2c"),
+            auto_start: true,
+            rpc_secret: String::new(),
+            split: 5,
+            min_split_size: "20M".to_string(),
+            max_connection_per_server: 1,
+            disk_cache: "16M".to_string(),
+            file_allocation: FileAllocation::Prealloc,
+            seed_ratio: 1.0,
+            endpoints: vec![Aria2Endpoint::local()],
+            active_endpoint: LOCAL_ENDPOINT.to_string(),
+        }
+    }
+}
+
+/// The HTTP control API served by the background task on localhost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Bearer token every request must carry, generated when the API starts without one
+    pub token: String,
+    /// Also serve the web dashboard at the API's root
+    pub web_ui: bool,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 45792,
+            token: String::new(),
+            web_ui: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Load settings from the default location, falling back to defaults
+    pub fn load() -> anyhow::Result<Self> {
+        Self::load_from(&settings_path())
+    }
+
+    /// Load settings from a file, falling back to defaults if it doesn't exist
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read settings file: {}", e))?;
+        let settings: Settings = serde_json::from_slice(&data)
+            .map_err(|e| anyhow::anyhow!("Failed to parse settings file: {}", e))?;
+        settings.validate()?;
+
+        Ok(settings)
+    }
+
+    /// Save settings to the default location
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.save_to(&settings_path())
+    }
+
+    /// Validate and atomically write settings to a file
+    pub fn save_to(&self, path: &Path) -> anyhow::Result<()> {
+        self.validate()?;
+        let data = serde_json::to_vec_pretty(self)?;
+        write_atomic(path, &data)
+    }
+
+    /// Check that all values are within sensible ranges
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.downloads.download_dir.as_os_str().is_empty() {
+            anyhow::bail!("Download directory must not be empty");
+        }
+        if !(1..=64).contains(&self.downloads.max_concurrent) {
+            anyhow::bail!("Max concurrent downloads must be between 1 and 64");
+        }
+        if self.network.rpc_port < 1024 {
+            anyhow::bail!("RPC port must be 1024 or higher");
+        }
+        if !self.network.proxy.is_empty() && !self.network.proxy.contains("://") {
+            anyhow::bail!("Proxy must be a URL such as http://host:port");
+        }
+        if !(1..=600).contains(&self.network.timeout_secs) {
+            anyhow::bail!("Timeout must be between 1 and 600 seconds");
+        }
+
+        if !(1..=16).contains(&self.engine.connections) {
+            anyhow::bail!("Connections per download must be between 1 and 16");
+        }
+        match parse_size(&self.engine.min_segment_size) {
+            Some(size) if (64 << 10..=1 << 30).contains(&size) => {}
+            _ => anyhow::bail!("Min segment size must be between 64K and 1024M"),
+        }
+        if self.engine.max_retries > 100 {
+            anyhow::bail!("Retries must be 100 or fewer");
+        }
+
+        if self.video.binary.as_os_str().is_empty() {
+            anyhow::bail!("yt-dlp path must not be empty");
+        }
+        if self.video.format.trim().is_empty() {
+            anyhow::bail!("Video format must not be empty");
+        }
+        for host in &self.video.hosts {
+            if host.is_empty() || host.contains(['/', ':', '@', ' ']) {
+                anyhow::bail!("Video site {} must be a host name such as example.com", host);
+            }
+        }
+
+        let aria2 = &self.aria2;
+        if aria2.binary.as_os_str().is_empty() {
+            anyhow::bail!("aria2c path must not be empty");
+        }
+        if !(1..=64).contains(&aria2.split) {
+            anyhow::bail!("Split must be between 1 and 64");
+        }
+        if !(1..=16).contains(&aria2.max_connection_per_server) {
+            anyhow::bail!("Max connections per server must be between 1 and 16");
+        }
+        match parse_size(&aria2.min_split_size) {
+            Some(size) if (1 << 20..=1 << 30).contains(&size) => {}
+            _ => anyhow::bail!("Min split size must be between 1M and 1024M"),
+        }
+        if parse_size(&aria2.disk_cache).is_none() {
+            anyhow::bail!("Disk cache must be a size such as 16M");
+        }
+        if aria2.seed_ratio.is_nan() || aria2.seed_ratio < 0.0 {
+            anyhow::bail!("Seed ratio must not be negative");
+        }
+
+        for (i, endpoint) in aria2.endpoints.iter().enumerate() {
+            endpoint.validate()?;
+            if aria2.endpoints[..i].iter().any(|other| other.name == endpoint.name) {
+                anyhow::bail!("Endpoint name {} is used twice", endpoint.name);
+            }
+        }
+        if aria2.endpoints.iter().filter(|endpoint| endpoint.managed).count() != 1 {
+            anyhow::bail!("Exactly one endpoint must be the managed local daemon");
+        }
+        if !aria2.endpoints.iter().any(|endpoint| endpoint.name == aria2.active_endpoint) {
+            anyhow::bail!("Active endpoint {} doesn't exist", aria2.active_endpoint);
+        }
+
+        self.bandwidth.validate()?;
+        self.schedule.validate()?;
+        self.categories.validate()?;
+        self.actions.validate()?;
+        self.watch.validate()?;
+        self.feeds.validate()?;
+
+        if self.api.port < 1024 {
+            anyhow::bail!("API port must be 1024 or higher");
+        }
+        if !self.api.token.is_empty() && self.api.token.len() < 16 {
+            anyhow::bail!("API token must be at least 16 characters");
+        }
+        Ok(())
+    }
+
+    /// Local folder of a category
+    pub fn category_dir(&self, category: &Category) -> PathBuf {
+        self.downloads.download_dir.join(&category.dir)
+    }
+
+    /// The endpoint the UI controls, with the managed daemon's port and secret filled in.
+    /// The built-in engine downloads on this machine, so it counts as the local endpoint.
+    pub fn active_endpoint(&self) -> Aria2Endpoint {
+        let endpoint = match self.engine.kind {
+            EngineKind::Native => Aria2Endpoint::local(),
+            EngineKind::Aria2 => self
+                .aria2
+                .endpoints
+                .iter()
+                .find(|endpoint| endpoint.name == self.aria2.active_endpoint)
+                .cloned()
+                .unwrap_or_else(Aria2Endpoint::local),
+        };
+
+        if endpoint.managed {
+            Aria2Endpoint {
+                host: "127.0.0.1".to_string(),
+                port: self.network.rpc_port,
+                secret: self.aria2.rpc_secret.clone(),
+                tls: false,
+                path: "/jsonrpc".to_string(),
+                ..endpoint
+            }
+        } else {
+            endpoint
+        }
+    }
+}
+
+/// Settings shared inside a process, reloaded when the file changes on disk
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Arc<RwLock<Settings>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl SettingsStore {
+    /// Open the store at the default location
+    pub fn open() -> Self {
+        Self::open_at(settings_path())
+    }
+
+    /// Open the store at a specific path, using defaults if loading fails
+    pub fn open_at(path: PathBuf) -> Self {
+        let settings = Settings::load_from(&path).unwrap_or_else(|e| {
+            println!("Using default settings: {}", e);
+            Settings::default()
+        });
+        let modified = modified_time(&path);
+
+        Self {
+            path,
+            settings: Arc::new(RwLock::new(settings)),
+            modified: Mutex::new(modified),
+        }
+    }
+
+    /// Snapshot of the current settings
+    pub fn get(&self) -> Settings {
+        self.settings.read().clone()
+    }
+
+    /// Validate, persist and apply new settings
+    pub fn update(&self, settings: Settings) -> anyhow::Result<()> {
+        settings.save_to(&self.path)?;
+        *self.modified.lock() = modified_time(&self.path);
+        *self.settings.write() = settings;
+        Ok(())
+    }
+
+    /// Reload the file if it was changed by someone else.
+    /// Returns the new settings when they differ from the current ones.
+    pub fn reload_if_changed(&self) -> Option<Settings> {
+        let modified = modified_time(&self.path);
+        {
+            let mut last = self.modified.lock();
+            if modified == *last {
+                return None;
+            }
+            *last = modified;
+        }
+
+        match Settings::load_from(&self.path) {
+            Ok(settings) => {
+                let mut current = self.settings.write();
+                if *current == settings {
+                    return None;
+                }
+                *current = settings.clone();
+                Some(settings)
+            }
+            Err(e) => {
+                println!("Ignoring invalid settings file: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Directory holding bong's configuration files
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = env_path("XDG_CONFIG_HOME") {
+        return dir.join("bong");
+    }
+    #[cfg(windows)]
+    if let Some(dir) = env_path("APPDATA") {
+        return dir.join("bong");
+    }
+    match home_dir() {
+        Some(home) => home.join(".config").join("bong"),
+        None => PathBuf::from(".bong"),
+    }
+}
+
+/// Full path of the settings file
+pub fn settings_path() -> PathBuf {
+    config_dir().join(SETTINGS_FILE)
+}
+
+/// Write a file by writing a temporary sibling and renaming it over the target
+pub fn write_atomic(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    {
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| anyhow::anyhow!("Failed to replace {:?}: {}", path, e))
+}
+
+/// Parse an aria2 style size such as "1024", "16K" or "20M" into bytes
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last()?.to_ascii_uppercase() {
+        'K' => (&value[..value.len() - 1], 1 << 10),
+        'M' => (&value[..value.len() - 1], 1 << 20),
+        'G' => (&value[..value.len() - 1], 1 << 30),
+        _ => (value, 1),
+    };
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+fn default_download_dir() -> PathBuf {
+    match home_dir() {
+        Some(home) => home.join("Downloads"),
+        None => PathBuf::from("Downloads"),
+    }
+}
+
+/// A random token for the HTTP API
+pub fn generate_token() -> String {
+    use std::hash::{BuildHasher, Hasher};
+
+    // Every RandomState is seeded from the OS, mixing in the time keeps tokens apart
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    (0..4)
+        .map(|i| {
+            let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            hasher.write_u32(i);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+/// The user's home directory
+pub fn home_dir() -> Option<PathBuf> {
+    #[cfg(windows)]
+    if let Some(dir) = env_path("USERPROFILE") {
+        return Some(dir);
+    }
+    env_path("HOME")
+}
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bong-settings-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("settings.json")
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        Settings::default().validate().unwrap();
+    }
+
+    fn assert_rejected(change: impl FnOnce(&mut Settings), message: &str) {
+        let mut settings = Settings::default();
+        change(&mut settings);
+        let error = settings.validate().unwrap_err().to_string();
+        assert!(error.contains(message), "expected {:?}, got {:?}", message, error);
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert_rejected(|s| s.downloads.download_dir = PathBuf::new(), "Download directory");
+        assert_rejected(|s| s.downloads.max_concurrent = 0, "Max concurrent downloads");
+        assert_rejected(|s| s.downloads.max_concurrent = 65, "Max concurrent downloads");
+        assert_rejected(|s| s.network.rpc_port = 80, "RPC port");
+        assert_rejected(|s| s.network.proxy = "localhost:8080".to_string(), "Proxy");
+        assert_rejected(|s| s.network.timeout_secs = 0, "Timeout");
+        assert_rejected(|s| s.engine.connections = 17, "Connections per download");
+        assert_rejected(|s| s.engine.min_segment_size = "1K".to_string(), "Min segment size");
+        assert_rejected(|s| s.engine.min_segment_size = "lots".to_string(), "Min segment size");
+        assert_rejected(|s| s.engine.max_retries = 101, "Retries");
+        assert_rejected(|s| s.video.binary = PathBuf::new(), "yt-dlp path");
+        assert_rejected(|s| s.video.format = " ".to_string(), "Video format");
+        assert_rejected(|s| s.video.hosts = vec!["https://example.com".to_string()], "Video site");
+        assert_rejected(|s| s.aria2.split = 0, "Split");
+        assert_rejected(|s| s.aria2.max_connection_per_server = 17, "Max connections per server");
+        assert_rejected(|s| s.aria2.min_split_size = "512K".to_string(), "Min split size");
+        assert_rejected(|s| s.aria2.disk_cache = "much".to_string(), "Disk cache");
+        assert_rejected(|s| s.aria2.seed_ratio = -1.0, "Seed ratio");
+        assert_rejected(|s| s.aria2.seed_ratio = f32::NAN, "Seed ratio");
+        assert_rejected(|s| s.aria2.active_endpoint = "missing".to_string(), "Active endpoint");
+        assert_rejected(|s| s.aria2.endpoints.clear(), "managed local daemon");
+        assert_rejected(|s| s.api.port = 1023, "API port");
+        assert_rejected(|s| s.api.token = "short".to_string(), "API token");
+    }
+
+    #[test]
+    fn save_refuses_invalid_settings() {
+        let path = temp_path("invalid");
+        let mut settings = Settings::default();
+        settings.network.timeout_secs = 0;
+        assert!(settings.save_to(&path).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn reloads_settings_written_by_another_process() {
+        let path = temp_path("reload");
+        let store = SettingsStore::open_at(path.clone());
+        assert_eq!(store.get(), Settings::default());
+        assert_eq!(store.reload_if_changed(), None);
+
+        let mut settings = Settings::default();
+        settings.downloads.max_concurrent = 7;
+        write_atomic(&path, &serde_json::to_vec_pretty(&settings).unwrap()).unwrap();
+        assert_eq!(store.reload_if_changed(), Some(settings.clone()));
+        assert_eq!(store.get(), settings);
+        assert_eq!(store.reload_if_changed(), None);
+
+        // An invalid file is ignored and the last good settings stay
+        write_atomic(&path, b"{\"downloads\": {\"max_concurrent\": 0}}").unwrap();
+        assert_eq!(store.reload_if_changed(), None);
+        assert_eq!(store.get(), settings);
+
+        settings.downloads.max_concurrent = 3;
+        write_atomic(&path, &serde_json::to_vec_pretty(&settings).unwrap()).unwrap();
+        assert_eq!(store.reload_if_changed(), Some(settings.clone()));
+        assert!(!path.with_extension("json.tmp").exists());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+/// Name of the profile without any limits
+pub const UNLIMITED_PROFILE: &str = "Unlimited";
+
+/// A named set of bandwidth limits, all in bytes per second with 0 meaning unlimited
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SpeedProfile {
+    pub name: String,
+    /// Combined download speed of all downloads
+    pub download_limit: u64,
+    /// Combined upload speed of all downloads
+    pub upload_limit: u64,
+    /// Download speed of each download
+    pub per_download_limit: u64,
+    /// Upload speed of each download
+    pub per_upload_limit: u64,
+}
+
+impl SpeedProfile {
+    pub fn unlimited() -> Self {
+        Self {
+            name: UNLIMITED_PROFILE.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Global aria2 options. The per-download limits set here apply to new downloads.
+    pub fn global_options(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("max-overall-download-limit", self.download_limit.to_string()),
+            ("max-overall-upload-limit", self.upload_limit.to_string()),
+            ("max-download-limit", self.per_download_limit.to_string()),
+            ("max-upload-limit", self.per_upload_limit.to_string()),
+        ]
+    }
+
+    /// Options changed on each existing download
+    pub fn download_options(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("max-download-limit", self.per_download_limit.to_string()),
+            ("max-upload-limit", self.per_upload_limit.to_string()),
+        ]
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.name.trim().is_empty() {
+            anyhow::bail!("Speed profiles need a name");
+        }
+        Ok(())
+    }
+}
+
+/// Speed profiles and the one currently applied
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandwidthSettings {
+    pub profiles: Vec<SpeedProfile>,
+    pub active_profile: String,
+}
+
+impl Default for BandwidthSettings {
+    fn default() -> Self {
+        Self {
+            profiles: vec![
+                SpeedProfile::unlimited(),
+                SpeedProfile {
+                    name: "Video call".to_string(),
+                    download_limit: 512 * 1024,
+                    upload_limit: 64 * 1024,
+                    per_download_limit: 256 * 1024,
+                    per_upload_limit: 32 * 1024,
+                },
+                SpeedProfile {
+                    name: "Metered".to_string(),
+                    download_limit: 128 * 1024,
+                    upload_limit: 16 * 1024,
+                    per_download_limit: 64 * 1024,
+                    per_upload_limit: 8 * 1024,
+                },
+            ],
+            active_profile: UNLIMITED_PROFILE.to_string(),
+        }
+    }
+}
+
+impl BandwidthSettings {
+    /// The applied profile, unlimited if it doesn't exist
+    pub fn active(&self) -> SpeedProfile {
+        self.profiles
+            .iter()
+            .find(|profile| profile.name == self.active_profile)
+            .cloned()
+            .unwrap_or_else(SpeedProfile::unlimited)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (i, profile) in self.profiles.iter().enumerate() {
+            profile.validate()?;
+            if self.profiles[..i].iter().any(|other| other.name == profile.name) {
+                anyhow::bail!("Speed profile name {} is used twice", profile.name);
+            }
+        }
+        if !self.profiles.iter().any(|profile| profile.name == self.active_profile) {
+            anyhow::bail!("Active speed profile {} doesn't exist", self.active_profile);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+
+/// Day of the week, as used by schedule windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Day {
+    pub const ALL: [Day; 7] = [Day::Mon, Day::Tue, Day::Wed, Day::Thu, Day::Fri, Day::Sat, Day::Sun];
+    pub const WEEKDAYS: [Day; 5] = [Day::Mon, Day::Tue, Day::Wed, Day::Thu, Day::Fri];
+
+    /// Day from its index, Monday being 0
+    pub fn from_index(index: u32) -> Self {
+        Day::ALL[index as usize % 7]
+    }
+
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+
+    pub fn previous(self) -> Self {
+        Day::from_index(self.index() + 6)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Day::Mon => "Mon",
+            Day::Tue => "Tue",
+            Day::Wed => "Wed",
+            Day::Thu => "Thu",
+            Day::Fri => "Fri",
+            Day::Sat => "Sat",
+            Day::Sun => "Sun",
+        }
+    }
+}
+
+/// What the queue does while a window is in effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ScheduleAction {
+    /// Run downloads without a speed limit
+    #[default]
+    Run,
+    /// Pause every download
+    Pause,
+    /// Run downloads with a global limit in bytes per second
+    Limit(u64),
+}
+
+impl ScheduleAction {
+    /// Short description such as "limit 200 KB/s"
+    pub fn describe(self) -> String {
+        match self {
+            ScheduleAction::Run => "run unlimited".to_string(),
+            ScheduleAction::Pause => "pause".to_string(),
+            ScheduleAction::Limit(limit) => format!("limit {} KB/s", limit / 1024),
+        }
+    }
+}
+
+/// A weekly time range with the action applied during it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleWindow {
+    pub name: String,
+    pub enabled: bool,
+    /// Days the window starts on
+    pub days: Vec<Day>,
+    /// Start time as "HH:MM"
+    pub start: String,
+    /// End time as "HH:MM". An end before the start runs past midnight,
+    /// an end equal to the start covers the whole day.
+    pub end: String,
+    pub action: ScheduleAction,
+}
+
+impl Default for ScheduleWindow {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            enabled: true,
+            days: Day::ALL.to_vec(),
+            start: "00:00".to_string(),
+            end: "00:00".to_string(),
+            action: ScheduleAction::Run,
+        }
+    }
+}
+
+impl ScheduleWindow {
+    /// Whether the window covers `minute` (since midnight) on `day`
+    pub fn contains(&self, day: Day, minute: u32) -> bool {
+        let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+
+        if start < end {
+            self.days.contains(&day) && (start..end).contains(&minute)
+        } else if start > end {
+            (self.days.contains(&day) && minute >= start)
+                || (self.days.contains(&day.previous()) && minute < end)
+        } else {
+            self.days.contains(&day)
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.name.trim().is_empty() {
+            anyhow::bail!("Schedule windows need a name");
+        }
+        if parse_time(&self.start).is_none() || parse_time(&self.end).is_none() {
+            anyhow::bail!("Window {} needs times such as 09:00", self.name);
+        }
+        if self.days.is_empty() {
+            anyhow::bail!("Window {} must run on at least one day", self.name);
+        }
+        if self.action == ScheduleAction::Limit(0) {
+            anyhow::bail!("Window {} needs a speed limit above 0", self.name);
+        }
+        Ok(())
+    }
+}
+
+/// Weekly time windows evaluated by the background task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleSettings {
+    pub enabled: bool,
+    /// Applied when no window matches
+    pub default_action: ScheduleAction,
+    /// Checked in order, the first matching window wins
+    pub windows: Vec<ScheduleWindow>,
+}
+
+impl Default for ScheduleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            default_action: ScheduleAction::Run,
+            windows: vec![
+                ScheduleWindow {
+                    name: "Work hours".to_string(),
+                    days: Day::WEEKDAYS.to_vec(),
+                    start: "09:00".to_string(),
+                    end: "18:00".to_string(),
+                    action: ScheduleAction::Limit(200 * 1024),
+                    ..Default::default()
+                },
+                ScheduleWindow {
+                    name: "Nights".to_string(),
+                    start: "23:00".to_string(),
+                    end: "07:00".to_string(),
+                    action: ScheduleAction::Run,
+                    ..Default::default()
+                },
+            ],
+        }
+    }
+}
+
+impl ScheduleSettings {
+    /// The action in effect at `minute` (since midnight) on `day`, and the window it comes from
+    pub fn action_at(&self, day: Day, minute: u32) -> (ScheduleAction, Option<&ScheduleWindow>) {
+        self.windows
+            .iter()
+            .find(|window| window.enabled && window.contains(day, minute))
+            .map(|window| (window.action, Some(window)))
+            .unwrap_or((self.default_action, None))
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for window in &self.windows {
+            window.validate()?;
+        }
+        if self.default_action == ScheduleAction::Limit(0) {
+            anyhow::bail!("The default schedule action needs a speed limit above 0");
+        }
+        Ok(())
+    }
+}
+
+/// Parse "HH:MM" into minutes since midnight
+pub fn parse_time(text: &str) -> Option<u32> {
+    let (hours, minutes) = text.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(days: &[Day], start: &str, end: &str, action: ScheduleAction) -> ScheduleWindow {
+        ScheduleWindow {
+            name: format!("{}-{}", start, end),
+            days: days.to_vec(),
+            start: start.to_string(),
+            end: end.to_string(),
+            action,
+            ..Default::default()
+        }
+    }
+
+    fn with_windows(windows: Vec<ScheduleWindow>) -> ScheduleSettings {
+        ScheduleSettings {
+            enabled: true,
+            default_action: ScheduleAction::Run,
+            windows,
+        }
+    }
+
+    #[test]
+    fn windows_within_a_day() {
+        let schedule = with_windows(vec![window(&Day::WEEKDAYS, "09:00", "18:00", ScheduleAction::Limit(100))]);
+        assert_eq!(schedule.action_at(Day::Mon, 9 * 60).0, ScheduleAction::Limit(100));
+        assert_eq!(schedule.action_at(Day::Fri, 18 * 60 - 1).0, ScheduleAction::Limit(100));
+        assert_eq!(schedule.action_at(Day::Fri, 18 * 60).0, ScheduleAction::Run);
+        assert_eq!(schedule.action_at(Day::Mon, 8 * 60 + 59).0, ScheduleAction::Run);
+        // Day matching
+        assert_eq!(schedule.action_at(Day::Sat, 12 * 60).0, ScheduleAction::Run);
+    }
+
+    #[test]
+    fn windows_crossing_midnight_belong_to_their_start_day() {
+        let schedule = with_windows(vec![window(&[Day::Fri], "23:00", "07:00", ScheduleAction::Pause)]);
+        assert_eq!(schedule.action_at(Day::Fri, 23 * 60).0, ScheduleAction::Pause);
+        assert_eq!(schedule.action_at(Day::Sat, 6 * 60 + 59).0, ScheduleAction::Pause);
+        assert_eq!(schedule.action_at(Day::Sat, 7 * 60).0, ScheduleAction::Run);
+        // The morning of the start day belongs to the night before, which isn't listed
+        assert_eq!(schedule.action_at(Day::Fri, 60).0, ScheduleAction::Run);
+        assert_eq!(schedule.action_at(Day::Sat, 23 * 60).0, ScheduleAction::Run);
+
+        // Sunday nights run into Monday
+        let sunday = with_windows(vec![window(&[Day::Sun], "22:00", "01:00", ScheduleAction::Pause)]);
+        assert_eq!(sunday.action_at(Day::Mon, 30).0, ScheduleAction::Pause);
+    }
+
+    #[test]
+    fn equal_times_cover_the_whole_day() {
+        let schedule = with_windows(vec![window(&[Day::Sun], "00:00", "00:00", ScheduleAction::Pause)]);
+        assert_eq!(schedule.action_at(Day::Sun, 0).0, ScheduleAction::Pause);
+        assert_eq!(schedule.action_at(Day::Sun, 24 * 60 - 1).0, ScheduleAction::Pause);
+        assert_eq!(schedule.action_at(Day::Mon, 0).0, ScheduleAction::Run);
+    }
+
+    #[test]
+    fn first_enabled_window_wins_and_default_applies_otherwise() {
+        let mut disabled = window(&Day::ALL, "00:00", "00:00", ScheduleAction::Pause);
+        disabled.enabled = false;
+        let mut schedule = with_windows(vec![
+            disabled,
+            window(&Day::ALL, "10:00", "12:00", ScheduleAction::Limit(1)),
+            window(&Day::ALL, "11:00", "13:00", ScheduleAction::Limit(2)),
+        ]);
+        schedule.default_action = ScheduleAction::Limit(3);
+
+        let (action, window) = schedule.action_at(Day::Wed, 11 * 60);
+        assert_eq!(action, ScheduleAction::Limit(1));
+        assert_eq!(window.map(|window| window.name.as_str()), Some("10:00-12:00"));
+        assert_eq!(schedule.action_at(Day::Wed, 12 * 60).0, ScheduleAction::Limit(2));
+
+        let (action, window) = schedule.action_at(Day::Wed, 14 * 60);
+        assert_eq!(action, ScheduleAction::Limit(3));
+        assert!(window.is_none());
+        assert_eq!(ScheduleSettings { windows: Vec::new(), ..schedule }.action_at(Day::Wed, 0).0, ScheduleAction::Limit(3));
+    }
+
+    #[test]
+    fn windows_with_invalid_times_never_match() {
+        let schedule = with_windows(vec![window(&Day::ALL, "25:00", "26:00", ScheduleAction::Pause)]);
+        assert_eq!(schedule.action_at(Day::Mon, 60).0, ScheduleAction::Run);
+        assert!(schedule.validate().is_err());
+        assert_eq!(parse_time(" 07:05 "), Some(7 * 60 + 5));
+        assert_eq!(parse_time("7:60"), None);
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Subfolder of a watch folder that files go to once their downloads were added
+pub const ADDED_DIR: &str = "added";
+/// Subfolder of a watch folder that files go to when they couldn't be added
+pub const FAILED_DIR: &str = "failed";
+
+/// A folder the background task takes .torrent, .meta4 and .txt URL list files from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchFolder {
+    pub enabled: bool,
+    pub path: PathBuf,
+    /// Category of the downloads added from this folder, picked by the
+    /// assignment rules when None
+    pub category: Option<String>,
+}
+
+impl Default for WatchFolder {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: PathBuf::new(),
+            category: None,
+        }
+    }
+}
+
+/// Folders watched for files to enqueue
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchSettings {
+    pub folders: Vec<WatchFolder>,
+}
+
+impl WatchSettings {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (i, folder) in self.folders.iter().enumerate() {
+            if folder.path.as_os_str().is_empty() {
+                anyhow::bail!("Watch folders need a path");
+            }
+            if !folder.path.is_absolute() {
+                anyhow::bail!("Watch folder {} must be an absolute path", folder.path.display());
+            }
+            if self.folders[..i].iter().any(|other| other.path == folder.path) {
+                anyhow::bail!("Watch folder {} is listed twice", folder.path.display());
+            }
+        }
+        Ok(())
+    }
+}
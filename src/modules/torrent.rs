@@ -0,0 +1,430 @@
+use crate::modules::bencode::{self, Value};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::time::Duration;
+
+/// Larger downloads aren't .torrent files
+const MAX_TORRENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// What a .torrent file contains, read before it's handed to the engine
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TorrentInfo {
+    pub name: String,
+    /// SHA-1 of the info dictionary, for v1 and hybrid torrents
+    pub info_hash_v1: Option<String>,
+    /// SHA-256 of the info dictionary, for v2 and hybrid torrents
+    pub info_hash_v2: Option<String>,
+    /// Size of the files in bytes, padding excluded
+    pub total_size: u64,
+    pub piece_length: u64,
+    /// Files in the order aria2 numbers them
+    pub files: Vec<TorrentFile>,
+    /// Announce URLs of every tier, without duplicates
+    pub trackers: Vec<String>,
+    /// Peers only come from the trackers, DHT and peer exchange are off
+    pub private: bool,
+    pub comment: Option<String>,
+}
+
+/// A file of a torrent
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TorrentFile {
+    /// 1-based index as used by aria2's select-file option
+    pub index: usize,
+    /// Path components, starting with the torrent's folder for multi-file torrents
+    pub path: Vec<String>,
+    pub length: u64,
+    /// Padding files align v1 pieces to files in hybrid torrents and aren't shown
+    pub padding: bool,
+}
+
+impl TorrentFile {
+    pub fn path_string(&self) -> String {
+        self.path.join("/")
+    }
+}
+
+/// A line of the file tree, folders before the files they contain
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeRow {
+    pub depth: usize,
+    pub name: String,
+    /// Size of the file, or of everything in the folder
+    pub size: u64,
+    /// Index into `TorrentInfo::files` for files, None for folders
+    pub file: Option<usize>,
+    /// Indices into `TorrentInfo::files` of the file or everything in the folder
+    pub files: Vec<usize>,
+}
+
+impl TorrentInfo {
+    /// Whether aria2 can download it, which requires the v1 metadata
+    pub fn has_v1(&self) -> bool {
+        self.info_hash_v1.is_some()
+    }
+
+    /// The files as a folder tree, sorted by name
+    pub fn tree(&self) -> Vec<TreeRow> {
+        #[derive(Default)]
+        struct Node {
+            children: BTreeMap<String, Node>,
+            file: Option<usize>,
+            files: Vec<usize>,
+            size: u64,
+        }
+
+        let mut root = Node::default();
+        for (position, file) in self.files.iter().enumerate().filter(|(_, file)| !file.padding) {
+            let mut node = &mut root;
+            for component in &file.path {
+                node.size += file.length;
+                node.files.push(position);
+                node = node.children.entry(component.clone()).or_default();
+            }
+            node.size += file.length;
+            node.files.push(position);
+            node.file = Some(position);
+        }
+
+        fn flatten(node: &Node, depth: usize, rows: &mut Vec<TreeRow>) {
+            for (name, child) in &node.children {
+                rows.push(TreeRow {
+                    depth,
+                    name: name.clone(),
+                    size: child.size,
+                    file: child.file,
+                    files: child.files.clone(),
+                });
+                flatten(child, depth + 1, rows);
+            }
+        }
+        let mut rows = Vec::new();
+        flatten(&root, 0, &mut rows);
+        rows
+    }
+
+    /// Indices of every file that can be selected
+    pub fn all_files(&self) -> BTreeSet<usize> {
+        self.files.iter().filter(|file| !file.padding).map(|file| file.index).collect()
+    }
+
+    /// Value of aria2's select-file option for a selection such as "1,3-5", None when
+    /// every file is selected
+    pub fn select_file(&self, selected: &BTreeSet<usize>) -> Option<String> {
+        if self.all_files().is_subset(selected) {
+            return None;
+        }
+
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for &index in selected {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == index => *end = index,
+                _ => ranges.push((index, index)),
+            }
+        }
+        let ranges: Vec<String> = ranges
+            .into_iter()
+            .map(|(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+            .collect();
+        Some(ranges.join(","))
+    }
+}
+
+/// Whether the text of the add form names a .torrent file, by path or URL
+pub fn is_torrent_source(source: &str) -> bool {
+    let path = source.split(['?', '#']).next().unwrap_or(source);
+    path.to_ascii_lowercase().ends_with(".torrent")
+}
+
+/// Contents of a .torrent file from a local path, a file:// URL or over HTTP
+pub fn fetch(source: &str) -> anyhow::Result<Vec<u8>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let mut data = Vec::new();
+        ureq::get(source)
+            .timeout(Duration::from_secs(30))
+            .call()
+            .map_err(|e| anyhow::anyhow!("Failed to fetch the torrent: {}", e))?
+            .into_reader()
+            .take(MAX_TORRENT_SIZE)
+            .read_to_end(&mut data)?;
+        return Ok(data);
+    }
+
+    let path = source.strip_prefix("file://").unwrap_or(source);
+    std::fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))
+}
+
+/// Read the metadata of a .torrent file
+pub fn inspect(data: &[u8]) -> anyhow::Result<TorrentInfo> {
+    let torrent = bencode::decode(data).map_err(|e| anyhow::anyhow!("Not a .torrent file: {}", e))?;
+    let raw_info = bencode::raw_entry(data, "info")?.ok_or_else(|| anyhow::anyhow!("The torrent has no info dictionary"))?;
+    let info = torrent.get("info").filter(|info| info.as_dict().is_some()).ok_or_else(|| anyhow::anyhow!("The torrent has no info dictionary"))?;
+
+    let name = text(info, "name").ok_or_else(|| anyhow::anyhow!("The torrent has no name"))?;
+    let is_v1 = info.get("pieces").is_some();
+    let is_v2 = info.get("meta version").and_then(Value::as_int) == Some(2);
+    if !is_v1 && !is_v2 {
+        anyhow::bail!("The torrent has no pieces");
+    }
+
+    let mut files = if is_v1 { v1_files(info, &name)? } else { v2_files(info, &name)? };
+    for (position, file) in files.iter_mut().enumerate() {
+        file.index = position + 1;
+    }
+
+    let mut trackers: Vec<String> = Vec::new();
+    let tiers = torrent.get("announce-list").and_then(Value::as_list).unwrap_or_default();
+    let announce = tiers
+        .iter()
+        .flat_map(|tier| tier.as_list().unwrap_or_default())
+        .chain(torrent.get("announce"))
+        .filter_map(Value::as_str);
+    for tracker in announce {
+        if !tracker.is_empty() && !trackers.iter().any(|known| known == tracker) {
+            trackers.push(tracker.to_string());
+        }
+    }
+
+    Ok(TorrentInfo {
+        name,
+        info_hash_v1: is_v1.then(|| hex(&Sha1::digest(raw_info))),
+        info_hash_v2: is_v2.then(|| hex(&Sha256::digest(raw_info))),
+        total_size: files.iter().filter(|file| !file.padding).map(|file| file.length).sum(),
+        piece_length: info.get("piece length").and_then(Value::as_int).unwrap_or(0).max(0) as u64,
+        files,
+        trackers,
+        private: info.get("private").and_then(Value::as_int) == Some(1),
+        comment: text(&torrent, "comment"),
+    })
+}
+
+/// Files of the v1 metadata, a single one named after the torrent unless `files` is given
+fn v1_files(info: &Value, name: &str) -> anyhow::Result<Vec<TorrentFile>> {
+    let Some(entries) = info.get("files").and_then(Value::as_list) else {
+        return Ok(vec![TorrentFile {
+            path: vec![name.to_string()],
+            length: length(info)?,
+            ..Default::default()
+        }]);
+    };
+
+    entries
+        .iter()
+        .map(|entry| {
+            let components = entry
+                .get("path.utf-8")
+                .or_else(|| entry.get("path"))
+                .and_then(Value::as_list)
+                .ok_or_else(|| anyhow::anyhow!("A file of the torrent has no path"))?;
+            let mut path = vec![name.to_string()];
+            path.extend(components.iter().map(|component| String::from_utf8_lossy(component.as_bytes().unwrap_or_default()).into_owned()));
+            let padding = entry
+                .get("attr")
+                .and_then(Value::as_bytes)
+                .is_some_and(|attr| attr.contains(&b'p'));
+            Ok(TorrentFile {
+                path,
+                length: length(entry)?,
+                padding,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Files of a v2-only torrent from its file tree
+fn v2_files(info: &Value, name: &str) -> anyhow::Result<Vec<TorrentFile>> {
+    fn walk(node: &Value, path: &mut Vec<String>, files: &mut Vec<TorrentFile>) -> anyhow::Result<()> {
+        let entries = node.as_dict().ok_or_else(|| anyhow::anyhow!("Invalid file tree"))?;
+        for (key, child) in entries {
+            if key.is_empty() {
+                files.push(TorrentFile {
+                    path: path.clone(),
+                    length: length(child)?,
+                    ..Default::default()
+                });
+            } else {
+                path.push(String::from_utf8_lossy(key).into_owned());
+                walk(child, path, files)?;
+                path.pop();
+            }
+        }
+        Ok(())
+    }
+
+    let tree = info.get("file tree").ok_or_else(|| anyhow::anyhow!("The torrent has no file tree"))?;
+    let mut files = Vec::new();
+    walk(tree, &mut Vec::new(), &mut files)?;
+
+    // A single file is stored as the torrent's name, several inside a folder of that name
+    let single = files.len() == 1 && files[0].path.len() == 1;
+    if !single {
+        for file in &mut files {
+            file.path.insert(0, name.to_string());
+        }
+    }
+    Ok(files)
+}
+
+fn length(entry: &Value) -> anyhow::Result<u64> {
+    entry
+        .get("length")
+        .and_then(Value::as_int)
+        .filter(|length| *length >= 0)
+        .map(|length| length as u64)
+        .ok_or_else(|| anyhow::anyhow!("A file of the torrent has no length"))
+}
+
+/// A string field, preferring its `.utf-8` variant
+fn text(dict: &Value, key: &str) -> Option<String> {
+    dict.get(&format!("{}.utf-8", key))
+        .or_else(|| dict.get(key))
+        .and_then(Value::as_bytes)
+        .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Multi-file v1 info dictionary with a padding file between the two real files
+    const V1_INFO: &[u8] = b"d5:filesld6:lengthi100e4:pathl5:a.txteed4:attr1:p6:lengthi28e4:pathl4:.pad2:28eed6:lengthi50e4:pathl3:sub5:b.txteee4:name3:dir12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa7:privatei1ee";
+    /// Single-file v2-only info dictionary
+    const V2_INFO: &[u8] = b"d9:file treed5:c.bind0:d6:lengthi42e11:pieces root32:bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbeee12:meta versioni2e4:name5:c.bin12:piece lengthi16384ee";
+    /// Single-file hybrid info dictionary
+    const HYBRID_INFO: &[u8] = b"d9:file treed4:filed0:d6:lengthi7eeee6:lengthi7e12:meta versioni2e4:name4:file12:piece lengthi16384e6:pieces20:cccccccccccccccccccce";
+
+    fn torrent(info: &[u8]) -> Vec<u8> {
+        [
+            &b"d8:announce11:http://t3/a13:announce-listll11:http://t1/a11:http://t2/ael11:http://t1/aee7:comment5:hello4:info"[..],
+            info,
+            b"e",
+        ]
+        .concat()
+    }
+
+    fn files(lengths: &[u64]) -> TorrentInfo {
+        TorrentInfo {
+            files: lengths
+                .iter()
+                .enumerate()
+                .map(|(position, length)| TorrentFile {
+                    index: position + 1,
+                    path: vec![format!("{}.bin", position + 1)],
+                    length: *length,
+                    padding: false,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reads_v1_metadata() {
+        let info = inspect(&torrent(V1_INFO)).unwrap();
+        assert_eq!(info.name, "dir");
+        assert_eq!(info.info_hash_v1.as_deref(), Some("3b991e3a19438ef6c7b81bf2172367237d775746"));
+        assert_eq!(info.info_hash_v2, None);
+        assert!(info.has_v1());
+        assert!(info.private);
+        assert_eq!(info.comment.as_deref(), Some("hello"));
+        assert_eq!(info.piece_length, 16384);
+        assert_eq!(info.total_size, 150);
+        assert_eq!(info.trackers, ["http://t1/a", "http://t2/a", "http://t3/a"]);
+
+        let paths: Vec<(usize, String, bool)> = info.files.iter().map(|f| (f.index, f.path_string(), f.padding)).collect();
+        assert_eq!(
+            paths,
+            [
+                (1, "dir/a.txt".to_string(), false),
+                (2, "dir/.pad/28".to_string(), true),
+                (3, "dir/sub/b.txt".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_v2_only_metadata() {
+        let info = inspect(&torrent(V2_INFO)).unwrap();
+        assert_eq!(info.info_hash_v1, None);
+        assert_eq!(
+            info.info_hash_v2.as_deref(),
+            Some("9fa586caec2fc0c775a01afae36dd4d5b68ed3383518ef2893e8beec16513da6")
+        );
+        assert!(!info.has_v1());
+        assert_eq!(info.files.len(), 1);
+        assert_eq!(info.files[0].path, ["c.bin"]);
+        assert_eq!(info.total_size, 42);
+    }
+
+    #[test]
+    fn reads_both_hashes_of_hybrid_torrents() {
+        let info = inspect(&torrent(HYBRID_INFO)).unwrap();
+        assert_eq!(info.info_hash_v1.as_deref(), Some("08ee20dfce7614ecab448fe67cc5912ea2af3ad9"));
+        assert_eq!(
+            info.info_hash_v2.as_deref(),
+            Some("3a99aa7cfb57fc9a803dcca42b4fe7a313f7aaa68c01526e6c1a3cd18640b79c")
+        );
+        assert_eq!(info.files[0].path, ["file"]);
+        assert_eq!(info.total_size, 7);
+    }
+
+    #[test]
+    fn rejects_incomplete_torrents() {
+        assert!(inspect(b"d8:announce3:urle").is_err());
+        assert!(inspect(b"d4:infoi1ee").is_err());
+        assert!(inspect(b"d4:infod4:name1:x6:lengthi1eee").is_err());
+        assert!(inspect(b"d4:infod6:lengthi-1e4:name1:x6:pieces0:ee").is_err());
+        assert!(inspect(b"not a torrent").is_err());
+    }
+
+    #[test]
+    fn builds_a_tree_without_padding() {
+        let info = inspect(&torrent(V1_INFO)).unwrap();
+        let tree = info.tree();
+        let rows: Vec<_> = tree.iter().map(|row| (row.depth, row.name.as_str(), row.size, row.file)).collect();
+        assert_eq!(
+            rows,
+            [
+                (0, "dir", 150, None),
+                (1, "a.txt", 100, Some(0)),
+                (1, "sub", 50, None),
+                (2, "b.txt", 50, Some(2)),
+            ]
+        );
+        assert_eq!(tree[0].files, [0, 2]);
+    }
+
+    #[test]
+    fn select_file_joins_ranges() {
+        let info = files(&[1, 1, 1, 1, 1, 1, 1]);
+        let select = |indices: &[usize]| info.select_file(&indices.iter().copied().collect());
+        assert_eq!(select(&[1, 2, 3, 4, 5, 6, 7]), None);
+        assert_eq!(select(&[2]).as_deref(), Some("2"));
+        assert_eq!(select(&[1, 2, 3, 5]).as_deref(), Some("1-3,5"));
+        assert_eq!(select(&[1, 3, 5, 6, 7]).as_deref(), Some("1,3,5-7"));
+        assert_eq!(select(&[6, 7]).as_deref(), Some("6-7"));
+    }
+
+    #[test]
+    fn select_file_ignores_padding() {
+        let info = inspect(&torrent(V1_INFO)).unwrap();
+        assert_eq!(info.all_files(), BTreeSet::from([1, 3]));
+        assert_eq!(info.select_file(&BTreeSet::from([1, 3])), None);
+        assert_eq!(info.select_file(&BTreeSet::from([3])).as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn recognizes_torrent_sources() {
+        assert!(is_torrent_source("https://example.com/file.TORRENT?token=1"));
+        assert!(is_torrent_source("/home/me/file.torrent"));
+        assert!(!is_torrent_source("https://example.com/file.torrent.zip"));
+        assert!(!is_torrent_source("magnet:?xt=urn:btih:abc"));
+    }
+}
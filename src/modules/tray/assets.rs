@@ -0,0 +1,57 @@
+use image::RgbaImage;
+use rust_embed::RustEmbed;
+
+/// Icons compiled into the binary, so installed copies don't depend on the cwd
+#[derive(RustEmbed)]
+#[folder = "src/icons/"]
+#[include = "tray/*"]
+struct IconAssets;
+
+/// PNG sizes shipped for each variant under `src/icons/tray/<variant>/`
+const SIZES: [u32; 6] = [16, 22, 24, 32, 48, 64];
+
+/// Color scheme of the application icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconVariant {
+    /// Light tile, for dark panels
+    Light,
+    /// Dark tile, for light panels
+    Dark,
+}
+
+impl IconVariant {
+    /// Pick the variant that stands out against the desktop panel
+    ///
+    /// The panel follows the desktop theme, not the theme chosen for the app windows.
+    pub fn detect() -> Self {
+        if dark_light::detect() == dark_light::Mode::Dark {
+            IconVariant::Light
+        } else {
+            IconVariant::Dark
+        }
+    }
+
+    fn dir(self) -> &'static str {
+        match self {
+            IconVariant::Light => "light",
+            IconVariant::Dark => "dark",
+        }
+    }
+}
+
+/// Decode the embedded icon for `variant` whose size is closest to `size`, the larger
+/// one when two are as close
+pub fn load_icon(variant: IconVariant, size: u32) -> anyhow::Result<RgbaImage> {
+    let size = SIZES
+        .into_iter()
+        .min_by_key(|available| (available.abs_diff(size), std::cmp::Reverse(*available)))
+        .unwrap_or(size);
+    let path = format!("tray/{}/{}.png", variant.dir(), size);
+
+    let file = IconAssets::get(&path)
+        .ok_or_else(|| anyhow::anyhow!("Icon {} is not embedded", path))?;
+    let img = image::load_from_memory(&file.data)
+        .map_err(|e| anyhow::anyhow!("Failed to decode icon {}: {}", path, e))?;
+
+    Ok(img.to_rgba8())
+}
@@ -0,0 +1,249 @@
+use crate::modules::engine::{DownloadStatus, TransferSnapshot};
+use crate::modules::format::{format_duration, format_speed};
+use image::{imageops, Rgba, RgbaImage};
+use std::collections::HashSet;
+use std::f32::consts::PI;
+
+/// Size the tray icon is rendered at: the notification area takes 32px icons and
+/// scales them down at 100%, the macOS menu bar is 22pt on Retina screens, and
+/// Linux panels are usually 22px
+#[cfg(target_os = "windows")]
+pub const ICON_SIZE: u32 = 32;
+#[cfg(target_os = "macos")]
+pub const ICON_SIZE: u32 = 44;
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub const ICON_SIZE: u32 = 22;
+
+/// Size of the application icon inside the progress ring, one of the shipped PNG sizes
+#[cfg(target_os = "windows")]
+pub const BASE_SIZE: u32 = 24;
+#[cfg(target_os = "macos")]
+pub const BASE_SIZE: u32 = 32;
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub const BASE_SIZE: u32 = 16;
+
+const RING_COLOR: Rgba<u8> = Rgba([34, 197, 94, 255]);
+const RING_PAUSED_COLOR: Rgba<u8> = Rgba([234, 179, 8, 255]);
+const TRACK_COLOR: Rgba<u8> = Rgba([55, 65, 81, 200]);
+const PAUSED_BADGE: Rgba<u8> = Rgba([234, 179, 8, 255]);
+const ERROR_BADGE: Rgba<u8> = Rgba([239, 68, 68, 255]);
+const IDLE_BADGE: Rgba<u8> = Rgba([156, 163, 175, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// Overall state shown by the tray icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayState {
+    Idle,
+    Downloading,
+    Paused,
+    Error,
+}
+
+/// Aggregate of all unfinished downloads
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraySummary {
+    pub state: TrayState,
+    /// Combined progress from 0.0 to 1.0, None when no size is known yet
+    pub progress: Option<f32>,
+    pub active: usize,
+    pub waiting: usize,
+    pub paused: usize,
+    /// Failed downloads the user hasn't seen yet
+    pub errors: usize,
+    pub download_speed: u64,
+    pub eta_secs: Option<u64>,
+}
+
+impl TraySummary {
+    /// Summarize `snapshot`, leaving out the failed downloads in `acknowledged`
+    pub fn from_snapshot(snapshot: &TransferSnapshot, acknowledged: &HashSet<String>) -> Self {
+        let count = |status: DownloadStatus| snapshot.downloads.iter().filter(|d| d.status == status).count();
+        let active = count(DownloadStatus::Active);
+        let waiting = count(DownloadStatus::Waiting);
+        let paused = count(DownloadStatus::Paused);
+        let errors = snapshot
+            .downloads
+            .iter()
+            .filter(|d| d.status == DownloadStatus::Error && !acknowledged.contains(&d.gid))
+            .count();
+
+        let unfinished = snapshot.downloads.iter().filter(|d| {
+            matches!(d.status, DownloadStatus::Active | DownloadStatus::Waiting | DownloadStatus::Paused)
+        });
+        let (completed, total) = unfinished.fold((0u64, 0u64), |(completed, total), d| {
+            (completed + d.completed_length, total + d.total_length)
+        });
+        let progress = (total > 0).then(|| completed as f32 / total as f32);
+
+        let download_speed = snapshot.stat.download_speed;
+        let eta_secs = (download_speed > 0 && total > 0).then(|| total.saturating_sub(completed) / download_speed);
+
+        let state = if errors > 0 {
+            TrayState::Error
+        } else if active + waiting > 0 {
+            TrayState::Downloading
+        } else if paused > 0 {
+            TrayState::Paused
+        } else {
+            TrayState::Idle
+        };
+
+        Self {
+            state,
+            progress,
+            active,
+            waiting,
+            paused,
+            errors,
+            download_speed,
+            eta_secs,
+        }
+    }
+
+    /// Text such as "3 active, 2.1 MB/s, ETA 4m"
+    pub fn tooltip(&self, speed_in_bits: bool) -> String {
+        let mut parts = Vec::new();
+        if self.active > 0 {
+            parts.push(format!("{} active", self.active));
+            parts.push(format_speed(self.download_speed, speed_in_bits));
+            if let Some(eta) = self.eta_secs {
+                parts.push(format!("ETA {}", format_duration(eta)));
+            }
+        }
+        if self.waiting > 0 {
+            parts.push(format!("{} waiting", self.waiting));
+        }
+        if self.paused > 0 {
+            parts.push(format!("{} paused", self.paused));
+        }
+        if self.errors > 0 {
+            parts.push(format!("{} failed", self.errors));
+        }
+
+        if parts.is_empty() {
+            "Bong - idle".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    /// Identifies what the icon looks like, so it is only redrawn when this changes
+    pub fn icon_key(&self) -> (TrayState, Option<u32>) {
+        // Round to 2% steps, finer changes aren't visible at tray size
+        (self.state, self.progress.map(|p| (p * 50.0).round() as u32))
+    }
+}
+
+/// Draw the application icon with a progress ring and a state badge
+pub fn render(base: &RgbaImage, summary: &TraySummary) -> RgbaImage {
+    let size = ICON_SIZE as f32;
+    let center = size / 2.0;
+    // The ring and badges are drawn for 32px and scaled to the actual size
+    let px = |value: f32| value * size / 32.0;
+
+    // Leave room for the ring around the application icon
+    let mut image = RgbaImage::new(ICON_SIZE, ICON_SIZE);
+    let offset = ((ICON_SIZE - BASE_SIZE) / 2) as i64;
+    if base.dimensions() == (BASE_SIZE, BASE_SIZE) {
+        imageops::overlay(&mut image, base, offset, offset);
+    } else {
+        let scaled = imageops::resize(base, BASE_SIZE, BASE_SIZE, imageops::FilterType::Triangle);
+        imageops::overlay(&mut image, &scaled, offset, offset);
+    }
+
+    if let Some(progress) = summary.progress
+        && summary.state != TrayState::Idle
+    {
+        let color = if summary.state == TrayState::Paused { RING_PAUSED_COLOR } else { RING_COLOR };
+        for y in 0..ICON_SIZE {
+            for x in 0..ICON_SIZE {
+                let dx = x as f32 + 0.5 - center;
+                let dy = y as f32 + 0.5 - center;
+                let coverage = band(dx.hypot(dy), center - px(3.5), center - px(0.5));
+                if coverage <= 0.0 {
+                    continue;
+                }
+
+                // Clockwise from 12 o'clock
+                let angle = (dx.atan2(-dy) + 2.0 * PI) % (2.0 * PI);
+                let color = if angle / (2.0 * PI) <= progress { color } else { TRACK_COLOR };
+                blend(&mut image, x, y, color, coverage);
+            }
+        }
+    }
+
+    let badge_center = (size - px(7.0), size - px(7.0));
+    let at = |x: f32, y: f32| (badge_center.0 + px(x), badge_center.1 + px(y));
+    match summary.state {
+        TrayState::Downloading => {}
+        TrayState::Paused => {
+            disc(&mut image, badge_center, px(7.0), PAUSED_BADGE);
+            rect(&mut image, at(-3.0, -3.5), (px(2.0), px(7.0)), WHITE);
+            rect(&mut image, at(1.0, -3.5), (px(2.0), px(7.0)), WHITE);
+        }
+        TrayState::Error => {
+            disc(&mut image, badge_center, px(7.0), ERROR_BADGE);
+            rect(&mut image, at(-1.0, -4.5), (px(2.0), px(5.0)), WHITE);
+            rect(&mut image, at(-1.0, 2.0), (px(2.0), px(2.0)), WHITE);
+        }
+        TrayState::Idle => {
+            disc(&mut image, badge_center, px(4.0), IDLE_BADGE);
+        }
+    }
+
+    image
+}
+
+/// Procedural icon used when the embedded icon can't be decoded
+pub fn fallback_base() -> RgbaImage {
+    let mut image = RgbaImage::new(BASE_SIZE, BASE_SIZE);
+    let center = BASE_SIZE as f32 / 2.0;
+    disc(&mut image, (center, center), center - 1.0, Rgba([100, 150, 255, 255]));
+    image
+}
+
+/// Coverage of a pixel at `distance` from the center by the ring between `inner` and `outer`
+fn band(distance: f32, inner: f32, outer: f32) -> f32 {
+    ((distance - inner + 0.5).min(outer - distance + 0.5)).clamp(0.0, 1.0)
+}
+
+fn disc(image: &mut RgbaImage, center: (f32, f32), radius: f32, color: Rgba<u8>) {
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            let distance = (x as f32 + 0.5 - center.0).hypot(y as f32 + 0.5 - center.1);
+            let coverage = (radius - distance + 0.5).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                blend(image, x, y, color, coverage);
+            }
+        }
+    }
+}
+
+fn rect(image: &mut RgbaImage, origin: (f32, f32), size: (f32, f32), color: Rgba<u8>) {
+    let x0 = origin.0.round().max(0.0) as u32;
+    let y0 = origin.1.round().max(0.0) as u32;
+    let x1 = ((origin.0 + size.0).round() as u32).min(image.width());
+    let y1 = ((origin.1 + size.1).round() as u32).min(image.height());
+    for y in y0..y1 {
+        for x in x0..x1 {
+            blend(image, x, y, color, 1.0);
+        }
+    }
+}
+
+/// Paint `color` over the pixel with the given coverage
+fn blend(image: &mut RgbaImage, x: u32, y: u32, color: Rgba<u8>, coverage: f32) {
+    let src_alpha = color[3] as f32 / 255.0 * coverage;
+    let dst = image.get_pixel_mut(x, y);
+    let dst_alpha = dst[3] as f32 / 255.0;
+    let out_alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+    if out_alpha <= 0.0 {
+        return;
+    }
+
+    for channel in 0..3 {
+        let value = (color[channel] as f32 * src_alpha + dst[channel] as f32 * dst_alpha * (1.0 - src_alpha)) / out_alpha;
+        dst[channel] = value.round() as u8;
+    }
+    dst[3] = (out_alpha * 255.0).round() as u8;
+}
@@ -0,0 +1,151 @@
+use crate::modules::engine::{DownloadStatus, TransferSnapshot};
+use crate::modules::settings::BandwidthSettings;
+use tray_icon::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+
+/// Entries of the speed limit submenu, in bytes per second
+const SPEED_LIMITS: [(&str, u64); 6] = [
+    ("Unlimited", 0),
+    ("256 KB/s", 256 * 1024),
+    ("512 KB/s", 512 * 1024),
+    ("1 MB/s", 1024 * 1024),
+    ("5 MB/s", 5 * 1024 * 1024),
+    ("10 MB/s", 10 * 1024 * 1024),
+];
+
+/// Position of the first download line, after the queue controls and their separator
+const DOWNLOADS_POSITION: usize = 8;
+
+/// Tray menu whose items are updated in place, so an open menu doesn't close or jump
+pub struct TrayMenu {
+    menu: Menu,
+    pause_all: MenuItem,
+    resume_all: MenuItem,
+    speed_items: Vec<(u64, CheckMenuItem)>,
+    profile_menu: Submenu,
+    profile_items: Vec<(String, CheckMenuItem)>,
+    /// Shown instead of the download lines when nothing is downloading
+    no_downloads: MenuItem,
+    download_items: Vec<MenuItem>,
+}
+
+impl TrayMenu {
+    pub fn new() -> anyhow::Result<Self> {
+        let menu = Menu::new();
+        menu.append(&MenuItem::with_id("open", "Open", true, None))?;
+        menu.append(&PredefinedMenuItem::separator())?;
+
+        // Queue controls
+        let pause_all = MenuItem::with_id("pause-all", "Pause all", false, None);
+        let resume_all = MenuItem::with_id("resume-all", "Resume all", false, None);
+        menu.append(&pause_all)?;
+        menu.append(&resume_all)?;
+
+        let speed_menu = Submenu::new("Speed limit", true);
+        let mut speed_items = Vec::new();
+        for (label, limit) in SPEED_LIMITS {
+            let item = CheckMenuItem::with_id(format!("speed:{}", limit), label, true, limit == 0, None);
+            speed_menu.append(&item)?;
+            speed_items.push((limit, item));
+        }
+        menu.append(&speed_menu)?;
+
+        let profile_menu = Submenu::new("Speed profile", true);
+        menu.append(&profile_menu)?;
+        menu.append(&MenuItem::with_id("add-clipboard", "Add from clipboard URL", true, None))?;
+        menu.append(&PredefinedMenuItem::separator())?;
+
+        // Active downloads go here
+        let no_downloads = MenuItem::new("No active downloads", false, None);
+        menu.append(&no_downloads)?;
+
+        menu.append(&PredefinedMenuItem::separator())?;
+        menu.append(&MenuItem::with_id("quit", "Exit", true, None))?;
+
+        Ok(Self {
+            menu,
+            pause_all,
+            resume_all,
+            speed_items,
+            profile_menu,
+            profile_items: Vec::new(),
+            no_downloads,
+            download_items: Vec::new(),
+        })
+    }
+
+    pub fn menu(&self) -> &Menu {
+        &self.menu
+    }
+
+    /// Show the state of `snapshot`, with one line per entry of `downloads`
+    pub fn update(
+        &mut self,
+        snapshot: &TransferSnapshot,
+        bandwidth: &BandwidthSettings,
+        downloads: &[String],
+    ) -> anyhow::Result<()> {
+        let has_active = snapshot
+            .downloads
+            .iter()
+            .any(|d| matches!(d.status, DownloadStatus::Active | DownloadStatus::Waiting));
+        let has_paused = snapshot.downloads.iter().any(|d| d.status == DownloadStatus::Paused);
+        self.pause_all.set_enabled(has_active);
+        self.resume_all.set_enabled(has_paused);
+
+        // Clicking a check item toggles it right away, this also undoes that when the change failed
+        for (limit, item) in &self.speed_items {
+            item.set_checked(snapshot.download_limit == *limit);
+        }
+
+        self.update_profiles(bandwidth)?;
+        self.update_downloads(downloads)
+    }
+
+    fn update_profiles(&mut self, bandwidth: &BandwidthSettings) -> anyhow::Result<()> {
+        let unchanged = self.profile_items.len() == bandwidth.profiles.len()
+            && self.profile_items.iter().zip(&bandwidth.profiles).all(|((name, _), p)| *name == p.name);
+        if !unchanged {
+            for (_, item) in self.profile_items.drain(..) {
+                self.profile_menu.remove(&item)?;
+            }
+            for profile in &bandwidth.profiles {
+                let item = CheckMenuItem::with_id(format!("profile:{}", profile.name), &profile.name, true, false, None);
+                self.profile_menu.append(&item)?;
+                self.profile_items.push((profile.name.clone(), item));
+            }
+        }
+
+        for (name, item) in &self.profile_items {
+            item.set_checked(*name == bandwidth.active_profile);
+        }
+        Ok(())
+    }
+
+    fn update_downloads(&mut self, downloads: &[String]) -> anyhow::Result<()> {
+        if downloads.is_empty() && !self.download_items.is_empty() {
+            self.menu.insert(&self.no_downloads, DOWNLOADS_POSITION)?;
+        } else if !downloads.is_empty() && self.download_items.is_empty() {
+            self.menu.remove(&self.no_downloads)?;
+        }
+
+        // Lines are numbered by position, clicking any of them opens the UI
+        while self.download_items.len() < downloads.len() {
+            let index = self.download_items.len();
+            let item = MenuItem::with_id(format!("download:{}", index), "", true, None);
+            self.menu.insert(&item, DOWNLOADS_POSITION + index)?;
+            self.download_items.push(item);
+        }
+        while self.download_items.len() > downloads.len() {
+            if let Some(item) = self.download_items.pop() {
+                self.menu.remove(&item)?;
+            }
+        }
+
+        for (item, label) in self.download_items.iter().zip(downloads) {
+            if item.text() != *label {
+                item.set_text(label);
+            }
+        }
+        Ok(())
+    }
+}
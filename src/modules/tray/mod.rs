@@ -1,120 +1,265 @@
-use tray_icon::{
-    menu::{Menu, MenuItem, MenuId},
-    TrayIcon, TrayIconBuilder, Icon,
-};
-use std::path::PathBuf;
-
-/// Menu item identifiers
-pub struct MenuItems {
-    pub open_id: MenuId,
-    pub quit_id: MenuId,
+mod assets;
+mod icon;
+mod menu;
+
+pub use assets::IconVariant;
+
+pub use icon::{TrayState, TraySummary};
+
+use menu::TrayMenu;
+use tray_icon::{menu::MenuId, TrayIcon, TrayIconBuilder, Icon};
+use crate::modules::engine::{DownloadStatus, TransferSnapshot};
+use crate::modules::format::format_speed;
+use crate::modules::settings::{BandwidthSettings, Settings};
+use image::RgbaImage;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+/// Number of active downloads listed in the menu
+const RECENT_DOWNLOADS: usize = 5;
+
+/// Something the user picked from the tray menu
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrayAction {
+    Open,
+    Quit,
+    PauseAll,
+    ResumeAll,
+    /// Global download limit in bytes per second, 0 for unlimited
+    SpeedLimit(u64),
+    /// Name of the speed profile to switch to
+    SpeedProfile(String),
+    AddFromClipboard,
+}
+
+impl TrayAction {
+    /// Map a menu event back to the action it stands for
+    pub fn from_menu_id(id: &MenuId) -> Option<Self> {
+        let id = id.0.as_str();
+        match id {
+            "open" => Some(TrayAction::Open),
+            "quit" => Some(TrayAction::Quit),
+            "pause-all" => Some(TrayAction::PauseAll),
+            "resume-all" => Some(TrayAction::ResumeAll),
+            "add-clipboard" => Some(TrayAction::AddFromClipboard),
+            _ => {
+                if let Some(limit) = id.strip_prefix("speed:") {
+                    limit.parse().ok().map(TrayAction::SpeedLimit)
+                } else if let Some(name) = id.strip_prefix("profile:") {
+                    Some(TrayAction::SpeedProfile(name.to_string()))
+                } else if id.starts_with("download:") {
+                    // Clicking a download shows it in the UI
+                    Some(TrayAction::Open)
+                } else {
+                    None
+                }
+            }
+        }
+    }
 }
 
 /// Tray icon manager for the application
 pub struct TrayManager {
     _tray_icon: Option<TrayIcon>,
+    menu: TrayMenu,
+    /// When each active download was first seen active, the newest are listed in the menu
+    active_since: HashMap<String, Instant>,
+    /// Latest transfers pushed by the background task
+    snapshot: TransferSnapshot,
+    /// Speed profiles offered in the menu
+    bandwidth: BandwidthSettings,
+    speed_in_bits: bool,
+    variant: IconVariant,
+    /// Application icon the progress ring and badges are drawn around
+    base_icon: RgbaImage,
+    summary: Option<TraySummary>,
+    /// Failed downloads that no longer turn the icon red
+    acknowledged_errors: HashSet<String>,
+    /// Whether a snapshot from the background task arrived yet
+    received_snapshot: bool,
 }
 
 impl TrayManager {
-    /// Create a new tray manager
-    pub fn new() -> anyhow::Result<Self> {
+    /// Create a new tray manager for the given settings
+    pub fn new(settings: &Settings) -> anyhow::Result<Self> {
+        let variant = IconVariant::detect();
         Ok(Self {
             _tray_icon: None,
+            menu: TrayMenu::new()?,
+            active_since: HashMap::new(),
+            snapshot: TransferSnapshot::default(),
+            bandwidth: settings.bandwidth.clone(),
+            speed_in_bits: settings.appearance.speed_in_bits,
+            variant,
+            base_icon: Self::load_base_icon(variant),
+            summary: None,
+            acknowledged_errors: HashSet::new(),
+            received_snapshot: false,
         })
     }
 
     /// Initialize and show the tray icon
-    pub fn setup(&mut self) -> anyhow::Result<MenuItems> {
-        // Create menu items
-        let open_item = MenuItem::new("Open", true, None);
-        let quit_item = MenuItem::new("Exit", true, None);
-
-        let open_id = open_item.id().clone();
-        let quit_id = quit_item.id().clone();
+    pub fn setup(&mut self) -> anyhow::Result<()> {
+        self.refresh_menu()?;
 
-        // Build the menu
-        let menu = Menu::new();
-        menu.append(&open_item)?;
-        menu.append(&quit_item)?;
-
-        // Load icon from file
-        let icon = Self::load_icon_from_file()?;
+        let summary = TraySummary::from_snapshot(&self.snapshot, &self.acknowledged_errors);
+        let icon = self.render_icon(&summary)?;
 
         // Build the tray icon
         let tray_icon = TrayIconBuilder::new()
-            .with_menu(Box::new(menu))
-            .with_tooltip("Bong App")
+            .with_menu(Box::new(self.menu.menu().clone()))
+            .with_tooltip(summary.tooltip(self.speed_in_bits))
             .with_icon(icon)
             .build()?;
 
         self._tray_icon = Some(tray_icon);
+        self.summary = Some(summary);
 
-        Ok(MenuItems { open_id, quit_id })
+        Ok(())
     }
 
-    /// Load icon from icons/app.ico file
-    fn load_icon_from_file() -> anyhow::Result<Icon> {
-        // Try to load from src/icons/app.ico (when running in dev)
-        let mut icon_path = PathBuf::from("src/icons/app.ico");
-        
-        // If not found, try relative to executable (when running as built binary)
-        if !icon_path.exists() {
-            if let Ok(exe_path) = std::env::current_exe() {
-                if let Some(exe_dir) = exe_path.parent() {
-                    icon_path = exe_dir.join("icons/app.ico");
-                }
-            }
+    /// Refresh the menu, icon and tooltip from the latest transfers pushed by the background task
+    pub fn update(&mut self, snapshot: TransferSnapshot) -> anyhow::Result<()> {
+        self.snapshot = snapshot;
+        let now = Instant::now();
+        let active: HashSet<&str> = self
+            .snapshot
+            .downloads
+            .iter()
+            .filter(|d| d.status == DownloadStatus::Active)
+            .map(|d| d.gid.as_str())
+            .collect();
+        self.active_since.retain(|gid, _| active.contains(gid.as_str()));
+        for gid in active {
+            self.active_since.entry(gid.to_string()).or_insert(now);
         }
-        
-        // Load and decode the icon
-        if icon_path.exists() {
-            let icon_data = std::fs::read(&icon_path)
-                .map_err(|e| anyhow::anyhow!("Failed to read icon file: {}", e))?;
-            
-            let img = image::load_from_memory(&icon_data)
-                .map_err(|e| anyhow::anyhow!("Failed to decode icon: {}", e))?;
-            
-            let rgba = img.to_rgba8();
-            let (width, height) = rgba.dimensions();
-            
-            Icon::from_rgba(rgba.into_raw(), width, height)
-                .map_err(|e| anyhow::anyhow!("Failed to create tray icon: {:?}", e))
+
+        let errors = self.error_gids();
+        if self.received_snapshot {
+            self.acknowledged_errors.retain(|gid| errors.contains(gid));
         } else {
-            // Fallback: Create a simple default icon if file not found
-            let width = 32u32;
-            let height = 32u32;
-            let mut rgba = vec![0u8; (width * height * 4) as usize];
-            
-            let center_x = width / 2;
-            let center_y = height / 2;
-            let radius = 12.0;
-            
-            for y in 0..height {
-                for x in 0..width {
-                    let dx = x as f32 - center_x as f32;
-                    let dy = y as f32 - center_y as f32;
-                    let distance = (dx * dx + dy * dy).sqrt();
-                    
-                    let idx = ((y * width + x) * 4) as usize;
-                    if distance <= radius {
-                        rgba[idx] = 100;
-                        rgba[idx + 1] = 150;
-                        rgba[idx + 2] = 255;
-                        rgba[idx + 3] = 255;
-                    } else {
-                        rgba[idx + 3] = 0;
-                    }
-                }
-            }
-            
-            Icon::from_rgba(rgba, width, height)
-                .map_err(|e| anyhow::anyhow!("Failed to create icon: {:?}", e))
+            // Failures from before the tray started are old news
+            self.received_snapshot = true;
+            self.acknowledged_errors = errors;
+        }
+        self.refresh_menu()?;
+        self.refresh_icon()
+    }
+
+    /// Stop showing the current failures in the icon, once the user opened the UI to see them
+    pub fn acknowledge_errors(&mut self) -> anyhow::Result<()> {
+        self.acknowledged_errors = self.error_gids();
+        self.refresh_icon()
+    }
+
+    /// Redraw the icon and tooltip if the summary of the downloads changed
+    fn refresh_icon(&mut self) -> anyhow::Result<()> {
+        let Some(ref tray_icon) = self._tray_icon else {
+            return Ok(());
+        };
+        let summary = TraySummary::from_snapshot(&self.snapshot, &self.acknowledged_errors);
+        let previous = self.summary.as_ref();
+        if previous.map(TraySummary::icon_key) != Some(summary.icon_key()) {
+            tray_icon.set_icon(Some(self.render_icon(&summary)?))?;
         }
+        let tooltip = summary.tooltip(self.speed_in_bits);
+        if previous.map(|previous| previous.tooltip(self.speed_in_bits)) != Some(tooltip.clone()) {
+            tray_icon.set_tooltip(Some(tooltip))?;
+        }
+        self.summary = Some(summary);
+
+        Ok(())
+    }
+
+    /// Follow settings pushed by the background task
+    pub fn set_settings(&mut self, settings: &Settings) -> anyhow::Result<()> {
+        self.bandwidth = settings.bandwidth.clone();
+        self.speed_in_bits = settings.appearance.speed_in_bits;
+        self.refresh_menu()
+    }
+
+    /// Show the latest transfers and settings in the menu
+    fn refresh_menu(&mut self) -> anyhow::Result<()> {
+        let labels = Self::download_labels(&self.snapshot, &self.active_since, self.speed_in_bits);
+        self.menu.update(&self.snapshot, &self.bandwidth, &labels)
+    }
+
+    /// Detect the desktop theme again and switch icon variant when it changed
+    pub fn refresh_theme(&mut self) -> anyhow::Result<()> {
+        let variant = IconVariant::detect();
+        if variant == self.variant {
+            return Ok(());
+        }
+
+        self.variant = variant;
+        self.base_icon = Self::load_base_icon(variant);
+        if let Some(ref tray_icon) = self._tray_icon
+            && let Some(ref summary) = self.summary
+        {
+            tray_icon.set_icon(Some(self.render_icon(summary)?))?;
+        }
+
+        Ok(())
+    }
+
+    fn error_gids(&self) -> HashSet<String> {
+        self.snapshot
+            .downloads
+            .iter()
+            .filter(|d| d.status == DownloadStatus::Error)
+            .map(|d| d.gid.clone())
+            .collect()
+    }
+
+    fn render_icon(&self, summary: &TraySummary) -> anyhow::Result<Icon> {
+        let image = icon::render(&self.base_icon, summary);
+        let (width, height) = image.dimensions();
+        Icon::from_rgba(image.into_raw(), width, height)
+            .map_err(|e| anyhow::anyhow!("Failed to create tray icon: {:?}", e))
+    }
+
+    /// Labels of the most recently started active downloads, newest first
+    fn download_labels(
+        snapshot: &TransferSnapshot,
+        active_since: &HashMap<String, Instant>,
+        speed_in_bits: bool,
+    ) -> Vec<String> {
+        let mut active: Vec<_> = snapshot
+            .downloads
+            .iter()
+            .filter(|d| d.status == DownloadStatus::Active)
+            .collect();
+        // Downloads seen at the same time keep the engine's order
+        active.sort_by_key(|d| std::cmp::Reverse(active_since.get(&d.gid).copied()));
+        active
+            .into_iter()
+            .take(RECENT_DOWNLOADS)
+            .map(|d| {
+                let mut name = d.name.clone();
+                if name.chars().count() > 40 {
+                    name = name.chars().take(39).collect::<String>() + "…";
+                }
+                format!(
+                    "{}  {:.0}%  {}",
+                    name,
+                    d.progress() * 100.0,
+                    format_speed(d.download_speed, speed_in_bits),
+                )
+            })
+            .collect()
+    }
+
+    /// Load the embedded application icon
+    fn load_base_icon(variant: IconVariant) -> RgbaImage {
+        assets::load_icon(variant, icon::BASE_SIZE).unwrap_or_else(|e| {
+            println!("{}", e);
+            icon::fallback_base()
+        })
     }
 }
 
 impl Default for TrayManager {
     fn default() -> Self {
-        Self::new().unwrap()
+        Self::new(&Settings::default()).unwrap()
     }
 }
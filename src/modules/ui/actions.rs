@@ -0,0 +1,421 @@
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
+use gpui_component::input::{Input, InputState};
+use crate::modules::settings::{ActionRule, ActionSettings, PostAction, Settings, ACTION_PLACEHOLDERS};
+use super::settings::{field, persist_settings};
+use super::SettingsEvent;
+use std::path::PathBuf;
+
+/// Editor for the actions run when a download completes
+pub struct ActionsView {
+    settings: Settings,
+    actions: ActionSettings,
+    selected: usize,
+    /// Comma separated category names
+    categories: Entity<InputState>,
+    dir: Entity<InputState>,
+    program: Entity<InputState>,
+    args: Entity<InputState>,
+    url: Entity<InputState>,
+    /// Result of the last action, (is_error, message)
+    message: Option<(bool, String)>,
+}
+
+impl EventEmitter<SettingsEvent> for ActionsView {}
+
+impl ActionsView {
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let mut view = Self {
+            actions: settings.actions.clone(),
+            selected: 0,
+            categories: cx.new(|cx| InputState::new(window, cx).placeholder("All categories")),
+            dir: cx.new(|cx| InputState::new(window, cx).placeholder("Finished")),
+            program: cx.new(|cx| InputState::new(window, cx).placeholder("notify-send")),
+            args: cx.new(|cx| InputState::new(window, cx).placeholder("\"Download finished\" {name}")),
+            url: cx.new(|cx| InputState::new(window, cx).placeholder("http://127.0.0.1:8080/hooks/download")),
+            settings,
+            message: None,
+        };
+        view.load_selected(window, cx);
+        view
+    }
+
+    /// Replace the edited settings, e.g. after a change broadcast by the background task
+    pub fn set_settings(&mut self, settings: Settings, window: &mut Window, cx: &mut Context<Self>) {
+        self.actions = settings.actions.clone();
+        self.settings = settings;
+        self.selected = self.selected.min(self.actions.rules.len().saturating_sub(1));
+        self.load_selected(window, cx);
+        cx.notify();
+    }
+
+    /// Fill the form with the selected rule
+    fn load_selected(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(rule) = self.actions.rules.get(self.selected) else {
+            return;
+        };
+
+        let (dir, program, args, url) = match rule.action {
+            PostAction::Move { ref dir } => (dir.display().to_string(), String::new(), String::new(), String::new()),
+            PostAction::Command { ref program, ref args } => (String::new(), program.clone(), join_args(args), String::new()),
+            PostAction::Webhook { ref url } => (String::new(), String::new(), String::new(), url.clone()),
+            _ => Default::default(),
+        };
+        let values = [
+            (&self.categories, rule.categories.join(", ")),
+            (&self.dir, dir),
+            (&self.program, program),
+            (&self.args, args),
+            (&self.url, url),
+        ];
+        for (input, value) in values {
+            input.update(cx, |state, cx| state.set_value(value, window, cx));
+        }
+    }
+
+    /// Write the form back into the selected rule
+    fn store_selected(&mut self, cx: &App) -> anyhow::Result<()> {
+        let categories: Vec<String> = self
+            .categories
+            .read(cx)
+            .value()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        let dir = PathBuf::from(self.dir.read(cx).value().trim());
+        let program = self.program.read(cx).value().trim().to_string();
+        let args = split_args(&self.args.read(cx).value());
+        let url = self.url.read(cx).value().trim().to_string();
+
+        let Some(rule) = self.actions.rules.get_mut(self.selected) else {
+            return Ok(());
+        };
+        for name in &categories {
+            if self.settings.categories.find(name).is_none() {
+                anyhow::bail!("Category {} doesn't exist", name);
+            }
+        }
+        rule.categories = categories;
+        match rule.action {
+            PostAction::Move { .. } => rule.action = PostAction::Move { dir },
+            PostAction::Command { .. } => rule.action = PostAction::Command { program, args },
+            PostAction::Webhook { .. } => rule.action = PostAction::Webhook { url },
+            _ => {}
+        }
+        rule.action.validate()
+    }
+
+    fn select(&mut self, index: usize, window: &mut Window, cx: &mut Context<Self>) {
+        match self.store_selected(cx) {
+            Ok(()) => {
+                self.selected = index;
+                self.message = None;
+                self.load_selected(window, cx);
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+
+    fn add(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.actions.rules.push(ActionRule::default());
+        self.select(self.actions.rules.len() - 1, window, cx);
+    }
+
+    fn remove(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.selected < self.actions.rules.len() {
+            self.actions.rules.remove(self.selected);
+            self.selected = self.selected.saturating_sub(1);
+            self.load_selected(window, cx);
+            cx.notify();
+        }
+    }
+
+    /// Move the selected rule up, actions run in list order
+    fn move_up(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.selected > 0 && self.store_selected(cx).is_ok() {
+            self.actions.rules.swap(self.selected, self.selected - 1);
+            self.selected -= 1;
+            self.load_selected(window, cx);
+            cx.notify();
+        }
+    }
+
+    /// Change the kind of the selected action, keeping what was typed into the form
+    fn set_kind(&mut self, action: PostAction, window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(rule) = self.actions.rules.get_mut(self.selected) {
+            rule.action = action;
+        }
+        let _ = self.store_selected(cx);
+        self.load_selected(window, cx);
+        cx.notify();
+    }
+
+    fn save(&mut self, cx: &mut Context<Self>) {
+        let result = self.store_selected(cx).and_then(|_| {
+            let mut settings = self.settings.clone();
+            settings.actions = self.actions.clone();
+            persist_settings(settings)
+        });
+
+        match result {
+            Ok(settings) => {
+                self.settings = settings.clone();
+                self.message = Some((false, "Actions saved".to_string()));
+                cx.emit(SettingsEvent::Saved(settings));
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+}
+
+impl Render for ActionsView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let selected = self.actions.rules.get(self.selected).cloned();
+
+        let form = match selected {
+            Some(rule) => {
+                let kinds = [
+                    PostAction::VerifyChecksum,
+                    PostAction::Extract { delete_archive: false },
+                    PostAction::Move { dir: PathBuf::new() },
+                    PostAction::Command { program: String::new(), args: Vec::new() },
+                    PostAction::Webhook { url: String::new() },
+                ];
+
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_4()
+                    .child(
+                        Checkbox::new("action-enabled")
+                            .label("Action enabled")
+                            .checked(rule.enabled)
+                            .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                if let Some(rule) = this.actions.rules.get_mut(this.selected) {
+                                    rule.enabled = *checked;
+                                }
+                                cx.notify();
+                            }))
+                    )
+                    .child(field(
+                        "Action",
+                        "What happens with the finished download",
+                        div()
+                            .flex()
+                            .gap_2()
+                            .children(kinds.into_iter().enumerate().map(|(index, kind)| {
+                                let active = std::mem::discriminant(&kind) == std::mem::discriminant(&rule.action);
+                                let button = Button::new(("action-kind", index))
+                                    .label(kind.kind())
+                                    .on_click(cx.listener(move |this, _, window, cx| {
+                                        if !active {
+                                            this.set_kind(kind.clone(), window, cx);
+                                        }
+                                    }));
+                                if active { button.primary() } else { button.ghost() }
+                            })),
+                    ))
+                    .child(field(
+                        "Categories",
+                        "Comma separated, empty runs the action for every download",
+                        Input::new(&self.categories),
+                    ))
+                    .map(|this| match rule.action {
+                        PostAction::VerifyChecksum => this.child(
+                            div()
+                                .text_sm()
+                                .text_color(rgb(0x9ca3af))
+                                .child("Compares the file with a published checksum such as file.iso.sha256 or SHA256SUMS in the same folder.")
+                        ),
+                        PostAction::Extract { delete_archive } => this.child(
+                            Checkbox::new("action-delete-archive")
+                                .label("Delete the archive after extracting")
+                                .checked(delete_archive)
+                                .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                    if let Some(rule) = this.actions.rules.get_mut(this.selected) {
+                                        rule.action = PostAction::Extract { delete_archive: *checked };
+                                    }
+                                    cx.notify();
+                                }))
+                        ),
+                        PostAction::Move { .. } => {
+                            this.child(field("Folder", "Relative folders are inside the download directory", Input::new(&self.dir)))
+                        }
+                        PostAction::Command { .. } => this
+                            .child(field("Program", "Run directly, not through a shell", Input::new(&self.program)))
+                            .child(field("Arguments", "Quote arguments containing spaces", Input::new(&self.args)))
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .text_color(rgb(0x9ca3af))
+                                    .child(format!("Placeholders: {}", ACTION_PLACEHOLDERS.join(" ")))
+                            ),
+                        PostAction::Webhook { .. } => {
+                            this.child(field("URL", "Receives a JSON POST describing the download", Input::new(&self.url)))
+                        }
+                    })
+            }
+            None => div()
+                .text_sm()
+                .text_color(rgb(0x9ca3af))
+                .child("No actions yet. Add one to process finished downloads."),
+        };
+
+        div()
+            .flex()
+            .size_full()
+            .child(
+                // Action list
+                div()
+                    .flex()
+                    .flex_col()
+                    .w(px(220.0))
+                    .p_4()
+                    .gap_1()
+                    .border_r_1()
+                    .border_color(rgb(0x374151))
+                    .children(self.actions.rules.iter().enumerate().map(|(index, rule)| {
+                        let label = if rule.enabled {
+                            rule.action.kind().to_string()
+                        } else {
+                            format!("{} (off)", rule.action.kind())
+                        };
+                        let button = Button::new(("action-rule", index))
+                            .label(label)
+                            .w_full()
+                            .on_click(cx.listener(move |this, _, window, cx| this.select(index, window, cx)));
+                        if index == self.selected { button.primary() } else { button.ghost() }
+                    }))
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .mt_2()
+                            .child(
+                                Button::new("action-add")
+                                    .label("Add")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, window, cx| this.add(window, cx)))
+                            )
+                            .child(
+                                Button::new("action-remove")
+                                    .label("Remove")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, window, cx| this.remove(window, cx)))
+                            )
+                            .child(
+                                Button::new("action-up")
+                                    .label("Up")
+                                    .ghost()
+                                    .disabled(self.selected == 0)
+                                    .on_click(cx.listener(|this, _, window, cx| this.move_up(window, cx)))
+                            )
+                    )
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .p_6()
+                    .gap_4()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0xffffff))
+                            .child("Post-download Actions")
+                    )
+                    .child(
+                        Checkbox::new("actions-enabled")
+                            .label("Run these actions when a download completes")
+                            .checked(self.actions.enabled)
+                            .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                this.actions.enabled = *checked;
+                                cx.notify();
+                            }))
+                    )
+                    .child(
+                        Checkbox::new("actions-stop-on-failure")
+                            .label("Skip the remaining actions after one fails")
+                            .checked(self.actions.stop_on_failure)
+                            .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                this.actions.stop_on_failure = *checked;
+                                cx.notify();
+                            }))
+                    )
+                    .child(form)
+                    .child(
+                        // Footer with save / close
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .mt_4()
+                            .child(
+                                Button::new("actions-save")
+                                    .label("Save")
+                                    .primary()
+                                    .on_click(cx.listener(|this, _, _, cx| this.save(cx)))
+                            )
+                            .child(
+                                Button::new("actions-close")
+                                    .label("Close")
+                                    .ghost()
+                                    .on_click(cx.listener(|_, _, _, cx| cx.emit(SettingsEvent::Close)))
+                            )
+                            .when_some(self.message.clone(), |this, (is_error, message)| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                        .child(message)
+                                )
+                            })
+                    )
+            )
+    }
+}
+
+/// Split command arguments on whitespace, double quotes group words
+fn split_args(text: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut has_arg = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// Inverse of `split_args` for showing saved arguments
+fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| if arg.is_empty() || arg.contains(char::is_whitespace) { format!("\"{}\"", arg) } else { arg.clone() })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
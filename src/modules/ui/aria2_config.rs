@@ -0,0 +1,227 @@
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::input::{Input, InputState};
+use crate::modules::aria2::conf_path;
+use crate::modules::settings::{FileAllocation, Settings};
+use super::settings::{field, parse_field, persist_settings};
+use super::SettingsEvent;
+use std::path::PathBuf;
+
+/// Editor for the options passed to aria2
+pub struct Aria2ConfigView {
+    settings: Settings,
+    binary: Entity<InputState>,
+    max_concurrent: Entity<InputState>,
+    split: Entity<InputState>,
+    min_split_size: Entity<InputState>,
+    max_connection_per_server: Entity<InputState>,
+    disk_cache: Entity<InputState>,
+    seed_ratio: Entity<InputState>,
+    /// Result of the last save, (is_error, message)
+    message: Option<(bool, String)>,
+}
+
+impl EventEmitter<SettingsEvent> for Aria2ConfigView {}
+
+impl Aria2ConfigView {
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let values = Self::values(&settings);
+        let mut inputs = values
+            .into_iter()
+            .map(|value| cx.new(|cx| InputState::new(window, cx).default_value(value)));
+
+        Self {
+            binary: inputs.next().unwrap(),
+            max_concurrent: inputs.next().unwrap(),
+            split: inputs.next().unwrap(),
+            min_split_size: inputs.next().unwrap(),
+            max_connection_per_server: inputs.next().unwrap(),
+            disk_cache: inputs.next().unwrap(),
+            seed_ratio: inputs.next().unwrap(),
+            settings,
+            message: None,
+        }
+    }
+
+    /// Form values in field order
+    fn values(settings: &Settings) -> [String; 7] {
+        let aria2 = &settings.aria2;
+        [
+            aria2.binary.display().to_string(),
+            settings.downloads.max_concurrent.to_string(),
+            aria2.split.to_string(),
+            aria2.min_split_size.clone(),
+            aria2.max_connection_per_server.to_string(),
+            aria2.disk_cache.clone(),
+            aria2.seed_ratio.to_string(),
+        ]
+    }
+
+    fn inputs(&self) -> [&Entity<InputState>; 7] {
+        [
+            &self.binary,
+            &self.max_concurrent,
+            &self.split,
+            &self.min_split_size,
+            &self.max_connection_per_server,
+            &self.disk_cache,
+            &self.seed_ratio,
+        ]
+    }
+
+    /// Replace the edited settings, e.g. after a change broadcast by the background task
+    pub fn set_settings(&mut self, settings: Settings, window: &mut Window, cx: &mut Context<Self>) {
+        for (input, value) in self.inputs().into_iter().zip(Self::values(&settings)) {
+            input.update(cx, |state, cx| state.set_value(value, window, cx));
+        }
+
+        self.settings = settings;
+        cx.notify();
+    }
+
+    /// Build settings from the form fields
+    fn collect(&self, cx: &App) -> anyhow::Result<Settings> {
+        let mut settings = self.settings.clone();
+
+        settings.aria2.binary = PathBuf::from(self.binary.read(cx).value().trim());
+        settings.downloads.max_concurrent = parse_field(&self.max_concurrent, "Max concurrent downloads", cx)?;
+        settings.aria2.split = parse_field(&self.split, "Split", cx)?;
+        settings.aria2.min_split_size = self.min_split_size.read(cx).value().trim().to_string();
+        settings.aria2.max_connection_per_server =
+            parse_field(&self.max_connection_per_server, "Max connections per server", cx)?;
+        settings.aria2.disk_cache = self.disk_cache.read(cx).value().trim().to_string();
+        settings.aria2.seed_ratio = parse_field(&self.seed_ratio, "Seed ratio", cx)?;
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn save(&mut self, cx: &mut Context<Self>) {
+        match self.collect(cx).and_then(persist_settings) {
+            Ok(settings) => {
+                self.settings = settings.clone();
+                self.message = Some((false, "aria2 options applied".to_string()));
+                cx.emit(SettingsEvent::Saved(settings));
+            }
+            Err(e) => {
+                self.message = Some((true, e.to_string()));
+            }
+        }
+        cx.notify();
+    }
+}
+
+impl Render for Aria2ConfigView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let allocation = self.settings.aria2.file_allocation;
+
+        div()
+            .flex()
+            .flex_col()
+            .size_full()
+            .p_6()
+            .gap_4()
+            .child(
+                div()
+                    .text_2xl()
+                    .font_weight(FontWeight::BOLD)
+                    .text_color(rgb(0xffffff))
+                    .child("aria2 Configuration")
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(rgb(0x9ca3af))
+                    .child(format!("Applied to the managed aria2c and saved to {}", conf_path().display()))
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_wrap()
+                    .gap_6()
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .gap_4()
+                            .child(field("aria2c path", "Executable name or full path", Input::new(&self.binary)))
+                            .child(field(
+                                "Max concurrent downloads",
+                                "Downloads beyond this limit wait in the queue",
+                                Input::new(&self.max_concurrent),
+                            ))
+                            .child(field("Split", "Connections used for a single file", Input::new(&self.split)))
+                            .child(field(
+                                "Min split size",
+                                "Don't split pieces smaller than this, e.g. 20M",
+                                Input::new(&self.min_split_size),
+                            ))
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .gap_4()
+                            .child(field(
+                                "Max connections per server",
+                                "Between 1 and 16",
+                                Input::new(&self.max_connection_per_server),
+                            ))
+                            .child(field(
+                                "Disk cache",
+                                "Write cache such as 16M, applied when aria2c restarts",
+                                Input::new(&self.disk_cache),
+                            ))
+                            .child(field(
+                                "Seed ratio",
+                                "Stop seeding torrents at this ratio, 0 seeds forever",
+                                Input::new(&self.seed_ratio),
+                            ))
+                            .child(field(
+                                "File allocation",
+                                "How space is reserved before downloading",
+                                div()
+                                    .flex()
+                                    .gap_2()
+                                    .children(FileAllocation::ALL.into_iter().map(|item| {
+                                        let button = Button::new(item.as_str())
+                                            .label(item.as_str())
+                                            .on_click(cx.listener(move |this, _, _, cx| {
+                                                this.settings.aria2.file_allocation = item;
+                                                cx.notify();
+                                            }));
+                                        if item == allocation { button.primary() } else { button.ghost() }
+                                    })),
+                            ))
+                    )
+            )
+            .child(
+                // Footer with save / close
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .mt_4()
+                    .child(
+                        Button::new("aria2-config-save")
+                            .label("Apply")
+                            .primary()
+                            .on_click(cx.listener(|this, _, _, cx| this.save(cx)))
+                    )
+                    .child(
+                        Button::new("aria2-config-close")
+                            .label("Close")
+                            .ghost()
+                            .on_click(cx.listener(|_, _, _, cx| cx.emit(SettingsEvent::Close)))
+                    )
+                    .when_some(self.message.clone(), |this, (is_error, message)| {
+                        this.child(
+                            div()
+                                .text_sm()
+                                .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                .child(message)
+                        )
+                    })
+            )
+    }
+}
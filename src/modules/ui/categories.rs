@@ -0,0 +1,300 @@
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
+use gpui_component::input::{Input, InputState};
+use crate::modules::settings::{Category, CategorySettings, Settings};
+use super::settings::{field, parse_field, persist_settings};
+use super::SettingsEvent;
+use std::path::PathBuf;
+
+/// Editor for download categories and their assignment rules
+pub struct CategoriesView {
+    settings: Settings,
+    categories: CategorySettings,
+    selected: usize,
+    name: Entity<InputState>,
+    dir: Entity<InputState>,
+    extensions: Entity<InputState>,
+    mime_types: Entity<InputState>,
+    url_patterns: Entity<InputState>,
+    split: Entity<InputState>,
+    max_connection_per_server: Entity<InputState>,
+    /// Per download limit in KB/s
+    download_limit: Entity<InputState>,
+    /// Result of the last action, (is_error, message)
+    message: Option<(bool, String)>,
+}
+
+impl EventEmitter<SettingsEvent> for CategoriesView {}
+
+impl CategoriesView {
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let mut view = Self {
+            categories: settings.categories.clone(),
+            selected: 0,
+            name: cx.new(|cx| InputState::new(window, cx)),
+            dir: cx.new(|cx| InputState::new(window, cx)),
+            extensions: cx.new(|cx| InputState::new(window, cx).placeholder("mp4, mkv, avi")),
+            mime_types: cx.new(|cx| InputState::new(window, cx).placeholder("video/*, application/pdf")),
+            url_patterns: cx.new(|cx| InputState::new(window, cx).placeholder("*://*.example.com/videos/*")),
+            split: cx.new(|cx| InputState::new(window, cx)),
+            max_connection_per_server: cx.new(|cx| InputState::new(window, cx)),
+            download_limit: cx.new(|cx| InputState::new(window, cx)),
+            settings,
+            message: None,
+        };
+        view.load_selected(window, cx);
+        view
+    }
+
+    /// Replace the edited settings, e.g. after a change broadcast by the background task
+    pub fn set_settings(&mut self, settings: Settings, window: &mut Window, cx: &mut Context<Self>) {
+        self.categories = settings.categories.clone();
+        self.settings = settings;
+        self.selected = self.selected.min(self.categories.categories.len().saturating_sub(1));
+        self.load_selected(window, cx);
+        cx.notify();
+    }
+
+    /// Fill the form with the selected category
+    fn load_selected(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(category) = self.categories.categories.get(self.selected) else {
+            return;
+        };
+
+        let values = [
+            (&self.name, category.name.clone()),
+            (&self.dir, category.dir.display().to_string()),
+            (&self.extensions, category.extensions.join(", ")),
+            (&self.mime_types, category.mime_types.join(", ")),
+            (&self.url_patterns, category.url_patterns.join(", ")),
+            (&self.split, category.split.to_string()),
+            (&self.max_connection_per_server, category.max_connection_per_server.to_string()),
+            (&self.download_limit, (category.download_limit / 1024).to_string()),
+        ];
+        for (input, value) in values {
+            input.update(cx, |state, cx| state.set_value(value, window, cx));
+        }
+    }
+
+    /// Write the form back into the selected category
+    fn store_selected(&mut self, cx: &App) -> anyhow::Result<()> {
+        let download_limit: u64 = parse_field(&self.download_limit, "Speed limit", cx)?;
+        let updated = Category {
+            name: self.name.read(cx).value().trim().to_string(),
+            dir: PathBuf::from(self.dir.read(cx).value().trim()),
+            extensions: split_list(&self.extensions.read(cx).value()),
+            mime_types: split_list(&self.mime_types.read(cx).value()),
+            url_patterns: split_list(&self.url_patterns.read(cx).value()),
+            split: parse_field(&self.split, "Split", cx)?,
+            max_connection_per_server: parse_field(&self.max_connection_per_server, "Connections per server", cx)?,
+            download_limit: download_limit * 1024,
+        };
+
+        let Some(category) = self.categories.categories.get_mut(self.selected) else {
+            return Ok(());
+        };
+        *category = updated;
+        category.validate()
+    }
+
+    fn select(&mut self, index: usize, window: &mut Window, cx: &mut Context<Self>) {
+        match self.store_selected(cx) {
+            Ok(()) => {
+                self.selected = index;
+                self.message = None;
+                self.load_selected(window, cx);
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+
+    fn add(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let name = (1..)
+            .map(|i| format!("Category {}", i))
+            .find(|name| self.categories.find(name).is_none())
+            .unwrap();
+        self.categories.categories.push(Category {
+            dir: PathBuf::from(&name),
+            name,
+            ..Default::default()
+        });
+        self.select(self.categories.categories.len() - 1, window, cx);
+    }
+
+    fn remove(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.selected < self.categories.categories.len() {
+            self.categories.categories.remove(self.selected);
+            self.selected = self.selected.saturating_sub(1);
+            self.load_selected(window, cx);
+            cx.notify();
+        }
+    }
+
+    /// Move the selected category up, rules are checked in list order
+    fn move_up(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.selected > 0 && self.store_selected(cx).is_ok() {
+            self.categories.categories.swap(self.selected, self.selected - 1);
+            self.selected -= 1;
+            self.load_selected(window, cx);
+            cx.notify();
+        }
+    }
+
+    fn save(&mut self, cx: &mut Context<Self>) {
+        let result = self.store_selected(cx).and_then(|_| {
+            let mut settings = self.settings.clone();
+            settings.categories = self.categories.clone();
+            persist_settings(settings)
+        });
+
+        match result {
+            Ok(settings) => {
+                self.settings = settings.clone();
+                self.message = Some((false, "Categories saved".to_string()));
+                cx.emit(SettingsEvent::Saved(settings));
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+}
+
+impl Render for CategoriesView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let has_selection = self.selected < self.categories.categories.len();
+
+        div()
+            .flex()
+            .size_full()
+            .child(
+                // Category list
+                div()
+                    .flex()
+                    .flex_col()
+                    .w(px(220.0))
+                    .p_4()
+                    .gap_1()
+                    .border_r_1()
+                    .border_color(rgb(0x374151))
+                    .children(self.categories.categories.iter().enumerate().map(|(index, category)| {
+                        let button = Button::new(("category", index))
+                            .label(category.name.clone())
+                            .w_full()
+                            .on_click(cx.listener(move |this, _, window, cx| this.select(index, window, cx)));
+                        if index == self.selected { button.primary() } else { button.ghost() }
+                    }))
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .mt_2()
+                            .child(
+                                Button::new("category-add")
+                                    .label("Add")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, window, cx| this.add(window, cx)))
+                            )
+                            .child(
+                                Button::new("category-remove")
+                                    .label("Remove")
+                                    .ghost()
+                                    .disabled(!has_selection)
+                                    .on_click(cx.listener(|this, _, window, cx| this.remove(window, cx)))
+                            )
+                            .child(
+                                Button::new("category-up")
+                                    .label("Up")
+                                    .ghost()
+                                    .disabled(self.selected == 0)
+                                    .on_click(cx.listener(|this, _, window, cx| this.move_up(window, cx)))
+                            )
+                    )
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .p_6()
+                    .gap_4()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0xffffff))
+                            .child("Categories")
+                    )
+                    .child(
+                        Checkbox::new("categories-auto-assign")
+                            .label("Assign new downloads by URL pattern, extension and MIME type")
+                            .checked(self.categories.auto_assign)
+                            .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                this.categories.auto_assign = *checked;
+                                cx.notify();
+                            }))
+                    )
+                    .when(has_selection, |this| {
+                        this.child(field("Name", "Shown in the downloads filter", Input::new(&self.name)))
+                            .child(field(
+                                "Folder",
+                                "Relative folders are inside the download directory",
+                                Input::new(&self.dir),
+                            ))
+                            .child(field("Extensions", "Comma separated, without the dot", Input::new(&self.extensions)))
+                            .child(field("MIME types", "Comma separated, video/* matches all videos", Input::new(&self.mime_types)))
+                            .child(field("URL patterns", "Comma separated, * matches anything", Input::new(&self.url_patterns)))
+                            .child(
+                                div()
+                                    .flex()
+                                    .gap_6()
+                                    .child(field("Split", "0 uses the aria2 setting", Input::new(&self.split)))
+                                    .child(field(
+                                        "Connections per server",
+                                        "0 uses the aria2 setting",
+                                        Input::new(&self.max_connection_per_server),
+                                    ))
+                                    .child(field("Speed limit", "Per download in KB/s, 0 for none", Input::new(&self.download_limit)))
+                            )
+                    })
+                    .child(
+                        // Footer with save / close
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .mt_4()
+                            .child(
+                                Button::new("categories-save")
+                                    .label("Save")
+                                    .primary()
+                                    .on_click(cx.listener(|this, _, _, cx| this.save(cx)))
+                            )
+                            .child(
+                                Button::new("categories-close")
+                                    .label("Close")
+                                    .ghost()
+                                    .on_click(cx.listener(|_, _, _, cx| cx.emit(SettingsEvent::Close)))
+                            )
+                            .when_some(self.message.clone(), |this, (is_error, message)| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                        .child(message)
+                                )
+                            })
+                    )
+            )
+    }
+}
+
+/// Split a comma separated list, dropping empty entries
+fn split_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -1,16 +1,654 @@
 use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
+use gpui_component::input::{Input, InputState};
+use crate::modules::actions::ActionOutcome;
+use crate::modules::batch::{batch_requests, read_source, BatchResult, SharedOptions};
+use crate::modules::engine::{is_video_host, is_video_download, AddRequest, Download, DownloadFile, DownloadStatus, GlobalStat, TransferSnapshot};
+use crate::modules::checksum::{ChecksumSource, Verification};
+use crate::modules::ipc::{IpcClient, IpcCommand, BACKGROUND_IPC_PORT};
+use crate::modules::format::{format_bytes, format_duration, format_speed};
+use crate::modules::magnet::{is_magnet, parse_magnet, Magnet};
+use crate::modules::metalink::{self, is_metalink_source, parse_metalink, Metalink};
+use crate::modules::settings::{AppearanceSettings, EngineKind, Settings};
+use crate::modules::torrent::{self, is_torrent_source, TorrentInfo};
+use super::settings::field;
+use std::collections::{BTreeSet, HashMap};
+
+/// Which downloads the list shows
+#[derive(Clone, Debug, PartialEq)]
+pub enum CategoryFilter {
+    All,
+    Category(String),
+    /// Downloads that don't belong to any category
+    Uncategorized,
+}
+
+/// A .torrent file shown before it's added
+struct TorrentPreview {
+    info: TorrentInfo,
+    data: Vec<u8>,
+    /// aria2 indices of the files to download
+    selected: BTreeSet<usize>,
+}
+
+/// A magnet link shown before it's added, its name and trackers can be edited
+struct MagnetPreview {
+    magnet: Magnet,
+    name: Entity<InputState>,
+    /// Comma separated tracker URLs
+    trackers: Entity<InputState>,
+}
+
+/// A Metalink document shown before it's added
+struct MetalinkPreview {
+    metalink: Metalink,
+    /// The document as fetched, mirrors are cut from it when countries are picked
+    xml: String,
+    /// Countries whose mirrors are used, every mirror when empty
+    countries: BTreeSet<String>,
+    /// Digest of a single-file Metalink verified once it completes, by position in
+    /// `MetalinkFile::checksums`
+    checksum: Option<usize>,
+}
+
+/// Downloads shown with the options they'll share before a batch import
+struct BatchImport {
+    /// Pasted text, a pattern or the path of a URL list file
+    text: Entity<InputState>,
+    dir: Entity<InputState>,
+    category: Option<String>,
+    /// Downloads found by the last preview
+    requests: Vec<AddRequest>,
+    /// The background task is adding them
+    adding: bool,
+}
 
 /// Downloads view component
-pub struct DownloadsView {}
+pub struct DownloadsView {
+    stat: GlobalStat,
+    downloads: Vec<Download>,
+    /// Results of post-download actions by GID
+    actions: HashMap<String, Vec<ActionOutcome>>,
+    /// Checksum results by GID
+    verifications: HashMap<String, Verification>,
+    settings: Settings,
+    filter: CategoryFilter,
+    /// URL of a new download
+    url: Entity<InputState>,
+    /// Expected digest or checksum file URL of a new download
+    checksum: Entity<InputState>,
+    /// Torrent waiting for the files to be picked
+    torrent: Option<TorrentPreview>,
+    /// A .torrent or Metalink file is being read
+    loading: bool,
+    /// Magnet link waiting to be confirmed
+    magnet: Option<MagnetPreview>,
+    /// Metalink waiting for mirrors and a digest to be picked
+    metalink: Option<MetalinkPreview>,
+    /// Batch import form, when open
+    batch: Option<BatchImport>,
+    /// Result of the last add, (is_error, message)
+    message: Option<(bool, String)>,
+}
 
 impl DownloadsView {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        Self {
+            stat: GlobalStat::default(),
+            downloads: Vec::new(),
+            actions: HashMap::new(),
+            verifications: HashMap::new(),
+            settings,
+            filter: CategoryFilter::All,
+            url: cx.new(|cx| InputState::new(window, cx).placeholder("https://example.com/file.iso")),
+            checksum: cx.new(|cx| InputState::new(window, cx).placeholder("sha256:<hex> or checksum file URL (optional)")),
+            torrent: None,
+            loading: false,
+            magnet: None,
+            metalink: None,
+            batch: None,
+            message: None,
+        }
+    }
+
+    /// Open or close the batch import form
+    fn toggle_batch(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.batch = match self.batch {
+            Some(_) => None,
+            None => Some(BatchImport {
+                text: cx.new(|cx| {
+                    InputState::new(window, cx)
+                        .multi_line(true)
+                        .rows(6)
+                        .placeholder("Paste URLs, a pattern such as https://example.com/part[001-120].rar, or the path of a URL list")
+                }),
+                dir: cx.new(|cx| InputState::new(window, cx).placeholder("Default download folder")),
+                category: None,
+                requests: Vec::new(),
+                adding: false,
+            }),
+        };
+        self.message = None;
+        cx.notify();
+    }
+
+    /// List the downloads the batch form describes
+    fn preview_batch(&mut self, cx: &mut Context<Self>) {
+        let Some(ref mut batch) = self.batch else {
+            return;
+        };
+        let text = batch.text.read(cx).value().to_string();
+        match read_source(&text).and_then(|text| batch_requests(&text)) {
+            Ok(requests) => {
+                batch.requests = requests;
+                self.message = None;
+            }
+            Err(e) => {
+                batch.requests.clear();
+                self.message = Some((true, e.to_string()));
+            }
+        }
+        cx.notify();
+    }
+
+    /// Hand the previewed downloads to the background task, which adds them on its own
+    /// and reports back with `batch_added`
+    fn start_batch(&mut self, cx: &mut Context<Self>) {
+        let Some(ref mut batch) = self.batch else {
+            return;
+        };
+        let dir = batch.dir.read(cx).value().trim().to_string();
+        let shared = SharedOptions {
+            dir: Some(dir).filter(|dir| !dir.is_empty()),
+            category: batch.category.clone(),
+            ..Default::default()
+        };
+        batch.adding = true;
+        self.message = None;
+        cx.notify();
+
+        let command = IpcCommand::AddBatch(batch.requests.clone(), shared);
+        cx.spawn(async move |this, cx| {
+            let response = cx
+                .background_executor()
+                .spawn(async move { IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(command) })
+                .await;
+            let _ = this.update(cx, |this, cx| {
+                this.message = match response {
+                    Ok(Some(IpcCommand::BatchQueued(count))) => Some((false, format!("Adding {} downloads", count))),
+                    Ok(Some(IpcCommand::Error(e))) => Some((true, e)),
+                    Ok(Some(_)) => Some((true, "Unexpected answer from the background task".to_string())),
+                    Ok(None) => Some((true, "The background task didn't answer in time".to_string())),
+                    Err(_) => Some((true, "Background task is not running".to_string())),
+                };
+                if !matches!(this.message, Some((false, _)))
+                    && let Some(ref mut batch) = this.batch
+                {
+                    batch.adding = false;
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    /// Outcome of a batch import, the downloads that failed stay listed
+    pub fn batch_added(&mut self, result: BatchResult, cx: &mut Context<Self>) {
+        if let Some(ref mut batch) = self.batch
+            && batch.adding
+        {
+            batch.adding = false;
+            batch
+                .requests
+                .retain(|request| result.failed.iter().any(|(uri, _)| request.uris.first() == Some(uri)));
+            if batch.requests.is_empty() {
+                self.batch = None;
+            }
+        }
+        self.message = match result.failed.first() {
+            None => Some((false, format!("{} downloads added", result.added.len()))),
+            Some((uri, error)) => Some((
+                true,
+                format!("{} added, {} failed, the first one {}: {}", result.added.len(), result.failed.len(), uri, error),
+            )),
+        };
+        cx.notify();
+    }
+
+    /// Hand the URL in the form to the background task
+    fn add_download(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let url = self.url.read(cx).value().trim().to_string();
+        let checksum = self.checksum.read(cx).value().trim().to_string();
+        if url.is_empty() {
+            self.message = Some((true, "Enter a URL to download".to_string()));
+            cx.notify();
+            return;
+        }
+        // Torrents are opened first so their files can be picked
+        if is_torrent_source(&url) {
+            self.open_torrent(url, cx);
+            return;
+        }
+        if is_magnet(&url) {
+            self.open_magnet(&url, window, cx);
+            return;
+        }
+        if is_metalink_source(&url) {
+            self.open_metalink(url, cx);
+            return;
+        }
+        // Catch typos here, the background task would reject them too
+        if !checksum.is_empty()
+            && let Err(e) = ChecksumSource::parse(&checksum)
+        {
+            self.message = Some((true, e.to_string()));
+            cx.notify();
+            return;
+        }
+
+        let is_video = self.settings.video.enabled && is_video_host(&self.settings.video.hosts, &url);
+        let added = if is_video {
+            "Video added, yt-dlp downloads it in the default format"
+        } else {
+            "Download added"
+        };
+        let is_http = url.starts_with("http://") || url.starts_with("https://");
+        let request = AddRequest {
+            checksum: Some(checksum).filter(|checksum| !checksum.is_empty()),
+            ..AddRequest::new(url)
+        };
+        // Mirror networks advertise a Metalink for their files, aria2 can use all the mirrors
+        if is_http && !is_video && self.settings.engine.kind == EngineKind::Aria2 {
+            self.discover_metalink(request, added.to_string(), window, cx);
+            return;
+        }
+        self.send_add(request, added.to_string(), window, cx);
+        cx.notify();
+    }
+
+    /// Send a new download to the background task and clear the form once it's added
+    fn send_add(&mut self, request: AddRequest, added: String, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        self.message = match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::AddDownload(request)) {
+            Ok(Some(IpcCommand::DownloadAdded(_))) => {
+                self.url.update(cx, |state, cx| state.set_value("", window, cx));
+                self.checksum.update(cx, |state, cx| state.set_value("", window, cx));
+                Some((false, added))
+            }
+            Ok(Some(IpcCommand::Error(e))) => Some((true, e)),
+            Ok(_) => Some((true, "Unexpected answer from the background task".to_string())),
+            Err(_) => Some((true, "Background task is not running".to_string())),
+        };
+        self.message.as_ref().is_some_and(|(is_error, _)| !is_error)
+    }
+
+    /// Ask the server whether it advertises a Metalink for the URL, showing it instead
+    /// of adding the plain download if it does
+    fn discover_metalink(&mut self, request: AddRequest, added: String, window: &mut Window, cx: &mut Context<Self>) {
+        self.loading = true;
+        self.message = None;
+        cx.notify();
+
+        let url = request.uris[0].clone();
+        cx.spawn_in(window, async move |this, cx| {
+            let link = cx.background_executor().spawn(async move { metalink::discover(&url) }).await;
+            let _ = this.update_in(cx, |this, window, cx| {
+                this.loading = false;
+                match link {
+                    Ok(Some(link)) => this.open_metalink(link, cx),
+                    // Servers that don't answer HEAD requests still get the plain download
+                    _ => {
+                        this.send_add(request, added, window, cx);
+                    }
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    /// Read a Metalink file off the UI thread and show its files and mirrors
+    fn open_metalink(&mut self, source: String, cx: &mut Context<Self>) {
+        self.loading = true;
+        self.torrent = None;
+        self.magnet = None;
+        self.metalink = None;
+        self.message = None;
+        cx.notify();
+
+        cx.spawn(async move |this, cx| {
+            let result = cx
+                .background_executor()
+                .spawn(async move {
+                    let data = metalink::fetch(&source)?;
+                    let xml = String::from_utf8(data).map_err(|_| anyhow::anyhow!("Metalink files must be UTF-8 text"))?;
+                    let metalink = parse_metalink(&xml)?;
+                    anyhow::Ok((xml, metalink))
+                })
+                .await;
+            let _ = this.update(cx, |this, cx| {
+                this.loading = false;
+                match result {
+                    Ok((xml, metalink)) => {
+                        let single = metalink.files.len() == 1 && !metalink.files[0].checksums().is_empty();
+                        this.metalink = Some(MetalinkPreview {
+                            checksum: single.then_some(0),
+                            countries: BTreeSet::new(),
+                            metalink,
+                            xml,
+                        })
+                    }
+                    Err(e) => this.message = Some((true, e.to_string())),
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    /// Use or stop using the mirrors of a country, None goes back to every mirror
+    fn toggle_country(&mut self, country: Option<String>, cx: &mut Context<Self>) {
+        let Some(ref mut preview) = self.metalink else {
+            return;
+        };
+        match country {
+            Some(country) => {
+                if !preview.countries.remove(&country) {
+                    preview.countries.insert(country);
+                }
+            }
+            None => preview.countries.clear(),
+        }
+        cx.notify();
+    }
+
+    /// Pick the digest of a single-file Metalink to verify, None leaves the checks to aria2
+    fn select_checksum(&mut self, position: Option<usize>, cx: &mut Context<Self>) {
+        if let Some(ref mut preview) = self.metalink {
+            preview.checksum = position;
+            cx.notify();
+        }
+    }
+
+    /// Add the previewed Metalink with the mirrors of the selected countries
+    fn start_metalink(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(ref preview) = self.metalink else {
+            return;
+        };
+
+        let xml = match metalink::restrict(&preview.xml, &preview.countries) {
+            Ok(xml) => xml,
+            Err(e) => {
+                self.message = Some((true, e.to_string()));
+                cx.notify();
+                return;
+            }
+        };
+        // A digest typed into the form wins over the one picked from the Metalink
+        let typed = self.checksum.read(cx).value().trim().to_string();
+        let picked = preview
+            .checksum
+            .and_then(|position| preview.metalink.files[0].checksums().into_iter().nth(position))
+            .map(|checksum| format!("{}:{}", checksum.algorithm.as_str(), checksum.hex));
+        let request = AddRequest {
+            checksum: Some(typed).filter(|typed| !typed.is_empty()).or(picked),
+            file: Some(DownloadFile::metalink(xml.as_bytes())),
+            ..Default::default()
+        };
+
+        let added = match preview.metalink.files.as_slice() {
+            [file] => format!("{} added", file.name),
+            files => format!("{} files added", files.len()),
+        };
+        if self.send_add(request, added, window, cx) {
+            self.metalink = None;
+        }
+        cx.notify();
+    }
+
+    /// Read a .torrent file off the UI thread and show what it contains
+    fn open_torrent(&mut self, source: String, cx: &mut Context<Self>) {
+        self.loading = true;
+        self.torrent = None;
+        self.magnet = None;
+        self.metalink = None;
+        self.message = None;
+        cx.notify();
+
+        cx.spawn(async move |this, cx| {
+            let result = cx
+                .background_executor()
+                .spawn(async move {
+                    let data = torrent::fetch(&source)?;
+                    let info = torrent::inspect(&data)?;
+                    anyhow::Ok((data, info))
+                })
+                .await;
+            let _ = this.update(cx, |this, cx| {
+                this.loading = false;
+                match result {
+                    Ok((data, info)) => {
+                        this.torrent = Some(TorrentPreview {
+                            selected: info.all_files(),
+                            info,
+                            data,
+                        })
+                    }
+                    Err(e) => this.message = Some((true, e.to_string())),
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    /// Show the name and trackers of a magnet link before adding it
+    fn open_magnet(&mut self, uri: &str, window: &mut Window, cx: &mut Context<Self>) {
+        match parse_magnet(uri) {
+            Ok(magnet) => {
+                let name = magnet.name.clone().unwrap_or_default();
+                let trackers = magnet.trackers.join(", ");
+                self.magnet = Some(MagnetPreview {
+                    name: cx.new(|cx| InputState::new(window, cx).placeholder("Named by the torrent").default_value(name)),
+                    trackers: cx.new(|cx| InputState::new(window, cx).placeholder("udp://tracker.example.com:1337").default_value(trackers)),
+                    magnet,
+                });
+                self.torrent = None;
+                self.metalink = None;
+                self.message = None;
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+
+    /// Add the previewed magnet link with the edited name and trackers
+    fn start_magnet(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(ref preview) = self.magnet else {
+            return;
+        };
+
+        let mut magnet = preview.magnet.clone();
+        let name = preview.name.read(cx).value().trim().to_string();
+        magnet.name = Some(name).filter(|name| !name.is_empty());
+        magnet.trackers = preview
+            .trackers
+            .read(cx)
+            .value()
+            .split(',')
+            .map(str::trim)
+            .filter(|tracker| !tracker.is_empty())
+            .map(str::to_string)
+            .collect();
+        let checksum = self.checksum.read(cx).value().trim().to_string();
+        let mut request = AddRequest {
+            checksum: Some(checksum).filter(|checksum| !checksum.is_empty()),
+            ..AddRequest::new(magnet.to_uri())
+        };
+        if let Some(selection) = magnet.select_file() {
+            request.options.push(("select-file".to_string(), selection));
+        }
+
+        let label = magnet.name.clone().unwrap_or_else(|| "Magnet link".to_string());
+        self.message = match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::AddDownload(request)) {
+            Ok(Some(IpcCommand::DownloadAdded(_))) => {
+                self.url.update(cx, |state, cx| state.set_value("", window, cx));
+                self.checksum.update(cx, |state, cx| state.set_value("", window, cx));
+                self.magnet = None;
+                Some((false, format!("{} added, fetching its metadata", label)))
+            }
+            Ok(Some(IpcCommand::Error(e))) => Some((true, e)),
+            Ok(_) => Some((true, "Unexpected answer from the background task".to_string())),
+            Err(_) => Some((true, "Background task is not running".to_string())),
+        };
+        cx.notify();
+    }
+
+    /// Select or deselect files of the previewed torrent by their position in the file list
+    fn toggle_files(&mut self, files: &[usize], checked: bool, cx: &mut Context<Self>) {
+        let Some(ref mut preview) = self.torrent else {
+            return;
+        };
+        for index in files.iter().filter_map(|position| preview.info.files.get(*position)).map(|file| file.index) {
+            if checked {
+                preview.selected.insert(index);
+            } else {
+                preview.selected.remove(&index);
+            }
+        }
+        cx.notify();
+    }
+
+    /// Add the previewed torrent with the selected files
+    fn start_torrent(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(ref preview) = self.torrent else {
+            return;
+        };
+
+        let mut request = AddRequest {
+            file: Some(DownloadFile::torrent(&preview.data)),
+            ..Default::default()
+        };
+        if let Some(selection) = preview.info.select_file(&preview.selected) {
+            request.options.push(("select-file".to_string(), selection));
+        }
+        self.message = match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::AddDownload(request)) {
+            Ok(Some(IpcCommand::DownloadAdded(_))) => {
+                self.url.update(cx, |state, cx| state.set_value("", window, cx));
+                Some((false, format!("{} added", preview.info.name)))
+            }
+            Ok(Some(IpcCommand::Error(e))) => Some((true, e)),
+            Ok(_) => Some((true, "Unexpected answer from the background task".to_string())),
+            Err(_) => Some((true, "Background task is not running".to_string())),
+        };
+        if self.message.as_ref().is_some_and(|(is_error, _)| !is_error) {
+            self.torrent = None;
+        }
+        cx.notify();
+    }
+
+    /// Update the stats bar and the list with the latest downloads polled by the
+    /// background task, yt-dlp jobs included
+    pub fn set_transfers(&mut self, snapshot: TransferSnapshot, cx: &mut Context<Self>) {
+        if self.stat != snapshot.stat
+            || self.downloads != snapshot.downloads
+            || self.actions != snapshot.actions
+            || self.verifications != snapshot.verifications
+        {
+            self.stat = snapshot.stat;
+            self.downloads = snapshot.downloads;
+            self.actions = snapshot.actions;
+            self.verifications = snapshot.verifications;
+            cx.notify();
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: Settings, cx: &mut Context<Self>) {
+        // Drop a filter for a category that was removed
+        if let CategoryFilter::Category(ref name) = self.filter
+            && settings.categories.find(name).is_none()
+        {
+            self.filter = CategoryFilter::All;
+        }
+        self.settings = settings;
+        cx.notify();
+    }
+
+    /// Name of the category a download belongs to
+    fn category_of(&self, download: &Download) -> Option<String> {
+        let dir = self.settings.active_endpoint().to_local(&download.dir);
+        self.settings
+            .categories
+            .for_download(&dir, &download.name, download.uris.first().map(String::as_str), &self.settings.downloads.download_dir)
+            .map(|category| category.name.clone())
+    }
+
+    fn matches_filter(&self, category: &Option<String>) -> bool {
+        match self.filter {
+            CategoryFilter::All => true,
+            CategoryFilter::Category(ref name) => category.as_ref() == Some(name),
+            CategoryFilter::Uncategorized => category.is_none(),
+        }
     }
 }
 
 impl Render for DownloadsView {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let stat = &self.stat;
+
+        let mut filters = vec![("All".to_string(), CategoryFilter::All)];
+        filters.extend(
+            self.settings
+                .categories
+                .categories
+                .iter()
+                .map(|category| (category.name.clone(), CategoryFilter::Category(category.name.clone()))),
+        );
+        filters.push(("Other".to_string(), CategoryFilter::Uncategorized));
+
+        let rows: Vec<(Download, Option<String>)> = self
+            .downloads
+            .iter()
+            .filter(|download| !download.is_replaced())
+            .map(|download| (download.clone(), self.category_of(download)))
+            .filter(|(_, category)| self.matches_filter(category))
+            .collect();
+
+        let list = if rows.is_empty() {
+            div()
+                .flex()
+                .flex_1()
+                .flex_col()
+                .items_center()
+                .justify_center()
+                .gap_3()
+                .child(
+                    div()
+                        .text_base()
+                        .text_color(rgb(0x6b7280))
+                        .child(if self.downloads.is_empty() { "No active downloads" } else { "No downloads in this category" })
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x4b5563))
+                        .child("Add a download to get started")
+                )
+        } else {
+            div()
+                .flex()
+                .flex_1()
+                .flex_col()
+                .px_6()
+                .when(self.settings.appearance.compact_lists, |this| this.gap_1())
+                .when(!self.settings.appearance.compact_lists, |this| this.gap_2())
+                .children(rows.into_iter().map(|(download, category)| {
+                    let actions = self.actions.get(&download.gid).cloned().unwrap_or_default();
+                    let verification = self.verifications.get(&download.gid).cloned();
+                    download_row(download, category, actions, verification, &self.settings.appearance)
+                }))
+        };
+
         div()
             .flex()
             .flex_col()
@@ -46,86 +684,778 @@ impl Render for DownloadsView {
                             .rounded(px(10.0))
                             .border_1()
                             .border_color(rgb(0x374151))
+                            .child(stat_item("Active", stat.num_active.to_string()))
+                            .child(stat_item("Waiting", stat.num_waiting.to_string()))
+                            .child(stat_item("Stopped", stat.num_stopped.to_string()))
+                            .child(stat_item("Download", format_speed(stat.download_speed, self.settings.appearance.speed_in_bits)))
+                            .child(stat_item("Upload", format_speed(stat.upload_speed, self.settings.appearance.speed_in_bits)))
+                    )
+                    .child(
+                        // Add a download
+                        div()
+                            .flex()
+                            .gap_2()
+                            .child(div().flex_1().child(Input::new(&self.url)))
+                            .child(div().w(px(320.0)).child(Input::new(&self.checksum)))
                             .child(
-                                div()
-                                    .flex()
-                                    .flex_col()
-                                    .gap_1()
-                                    .child(
-                                        div()
-                                            .text_xs()
-                                            .text_color(rgb(0x9ca3af))
-                                            .child("Active")
-                                    )
-                                    .child(
-                                        div()
-                                            .text_xl()
-                                            .font_weight(FontWeight::SEMIBOLD)
-                                            .text_color(rgb(0xffffff))
-                                            .child("0")
-                                    )
-                            )
-                            .child(
-                                div()
-                                    .flex()
-                                    .flex_col()
-                                    .gap_1()
-                                    .child(
-                                        div()
-                                            .text_xs()
-                                            .text_color(rgb(0x9ca3af))
-                                            .child("Completed")
-                                    )
-                                    .child(
-                                        div()
-                                            .text_xl()
-                                            .font_weight(FontWeight::SEMIBOLD)
-                                            .text_color(rgb(0xffffff))
-                                            .child("0")
-                                    )
+                                Button::new("download-add")
+                                    .label("Add")
+                                    .primary()
+                                    .disabled(self.loading)
+                                    .on_click(cx.listener(|this, _, window, cx| this.add_download(window, cx)))
                             )
                             .child(
-                                div()
-                                    .flex()
-                                    .flex_col()
-                                    .gap_1()
-                                    .child(
-                                        div()
-                                            .text_xs()
-                                            .text_color(rgb(0x9ca3af))
-                                            .child("Speed")
-                                    )
-                                    .child(
-                                        div()
-                                            .text_xl()
-                                            .font_weight(FontWeight::SEMIBOLD)
-                                            .text_color(rgb(0xffffff))
-                                            .child("0 KB/s")
-                                    )
+                                Button::new("download-batch")
+                                    .label("Batch import")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, window, cx| this.toggle_batch(window, cx)))
                             )
                     )
+                    .when_some(self.message.clone(), |this, (is_error, message)| {
+                        this.child(
+                            div()
+                                .text_sm()
+                                .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                .child(message)
+                        )
+                    })
+                    .when_some(self.torrent.as_ref().map(|preview| torrent_preview(preview, cx)), |this, preview| {
+                        this.child(preview)
+                    })
+                    .when_some(self.magnet.as_ref().map(|preview| magnet_preview(preview, cx)), |this, preview| {
+                        this.child(preview)
+                    })
+                    .when_some(self.metalink.as_ref().map(|preview| metalink_preview(preview, cx)), |this, preview| {
+                        this.child(preview)
+                    })
+                    .when_some(self.batch.as_ref().map(|batch| batch_import(batch, &self.settings, cx)), |this, batch| {
+                        this.child(batch)
+                    })
+                    .child(
+                        // Category filter
+                        div()
+                            .flex()
+                            .flex_wrap()
+                            .gap_2()
+                            .children(filters.into_iter().enumerate().map(|(index, (label, filter))| {
+                                let selected = filter == self.filter;
+                                let button = Button::new(("category-filter", index))
+                                    .label(label)
+                                    .on_click(cx.listener(move |this, _, _, cx| {
+                                        this.filter = filter.clone();
+                                        cx.notify();
+                                    }));
+                                if selected { button.primary() } else { button.ghost() }
+                            }))
+                    )
+            )
+            .child(list)
+    }
+}
+
+/// Contents of a .torrent file with a checkbox per file and folder
+fn torrent_preview(preview: &TorrentPreview, cx: &Context<DownloadsView>) -> Div {
+    let info = &preview.info;
+    let file_count = info.files.iter().filter(|file| !file.padding).count();
+    let mut details = vec![
+        format_bytes(info.total_size),
+        format!("{} files", file_count),
+        format!("{} pieces", format_bytes(info.piece_length)),
+    ];
+    if info.private {
+        details.push("Private".to_string());
+    }
+    let mut hashes = Vec::new();
+    if let Some(ref hash) = info.info_hash_v1 {
+        hashes.push(format!("v1 {}", hash));
+    }
+    if let Some(ref hash) = info.info_hash_v2 {
+        hashes.push(format!("v2 {}", hash));
+    }
+    let selected_size: u64 = info
+        .files
+        .iter()
+        .filter(|file| preview.selected.contains(&file.index))
+        .map(|file| file.length)
+        .sum();
+
+    let rows = info.tree().into_iter().enumerate().map(|(index, row)| {
+        let checked = row
+            .files
+            .iter()
+            .all(|position| preview.selected.contains(&info.files[*position].index));
+        let files = row.files.clone();
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .pl(px(row.depth as f32 * 20.0))
+            .child(
+                Checkbox::new(("torrent-file", index))
+                    .label(if row.file.is_some() { row.name } else { format!("{}/", row.name) })
+                    .checked(checked)
+                    .on_click(cx.listener(move |this, checked: &bool, _, cx| this.toggle_files(&files, *checked, cx)))
             )
             .child(
-                // Downloads list
                 div()
+                    .text_xs()
+                    .text_color(rgb(0x9ca3af))
+                    .child(format_bytes(row.size))
+            )
+    });
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_2()
+        .p_4()
+        .bg(rgb(0x1f2937))
+        .rounded(px(10.0))
+        .border_1()
+        .border_color(rgb(0x374151))
+        .child(
+            div()
+                .text_base()
+                .font_weight(FontWeight::SEMIBOLD)
+                .text_color(rgb(0xffffff))
+                .child(info.name.clone())
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x9ca3af))
+                .child(details.join(" - "))
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x6b7280))
+                .child(format!("Info hash {}", hashes.join(", ")))
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x6b7280))
+                .child(if info.trackers.is_empty() {
+                    "No trackers, peers come from DHT".to_string()
+                } else {
+                    format!("Trackers: {}", info.trackers.join(", "))
+                })
+        )
+        .when(!info.has_v1(), |this| {
+            this.child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0xef4444))
+                    .child("aria2 can't download torrents that only have v2 metadata")
+            )
+        })
+        .child(
+            div()
+                .id("torrent-files")
+                .flex()
+                .flex_col()
+                .gap_1()
+                .max_h(px(280.0))
+                .overflow_y_scroll()
+                .children(rows)
+        )
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(
+                    Button::new("torrent-start")
+                        .label("Start download")
+                        .primary()
+                        .disabled(preview.selected.is_empty() || !info.has_v1())
+                        .on_click(cx.listener(|this, _, window, cx| this.start_torrent(window, cx)))
+                )
+                .child(
+                    Button::new("torrent-cancel")
+                        .label("Cancel")
+                        .ghost()
+                        .on_click(cx.listener(|this, _, _, cx| {
+                            this.torrent = None;
+                            cx.notify();
+                        }))
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x9ca3af))
+                        .child(format!("{} selected", format_bytes(selected_size)))
+                )
+        )
+}
+
+/// Name, hashes and trackers of a magnet link, with the name and trackers editable
+fn magnet_preview(preview: &MagnetPreview, cx: &Context<DownloadsView>) -> Div {
+    let magnet = &preview.magnet;
+    let mut details = Vec::new();
+    if let Some(size) = magnet.size {
+        details.push(format_bytes(size));
+    }
+    if let Some(ref hash) = magnet.info_hash_v1 {
+        details.push(format!("v1 {}", hash));
+    }
+    if let Some(ref hash) = magnet.info_hash_v2 {
+        details.push(format!("v2 {}", hash));
+    }
+    if !magnet.web_seeds.is_empty() {
+        details.push(format!("{} web seeds", magnet.web_seeds.len()));
+    }
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_2()
+        .p_4()
+        .bg(rgb(0x1f2937))
+        .rounded(px(10.0))
+        .border_1()
+        .border_color(rgb(0x374151))
+        .child(
+            div()
+                .text_base()
+                .font_weight(FontWeight::SEMIBOLD)
+                .text_color(rgb(0xffffff))
+                .child(magnet.name.clone().unwrap_or_else(|| "Magnet link".to_string()))
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x9ca3af))
+                .child(details.join(" - "))
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x6b7280))
+                .child("The files are listed once the metadata arrives from peers")
+        )
+        .child(field("Name", "Display name sent with the magnet link", Input::new(&preview.name)))
+        .child(field("Trackers", "Comma separated, asked for peers next to DHT", Input::new(&preview.trackers)))
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(
+                    Button::new("magnet-start")
+                        .label("Start download")
+                        .primary()
+                        .on_click(cx.listener(|this, _, window, cx| this.start_magnet(window, cx)))
+                )
+                .child(
+                    Button::new("magnet-cancel")
+                        .label("Cancel")
+                        .ghost()
+                        .on_click(cx.listener(|this, _, _, cx| {
+                            this.magnet = None;
+                            cx.notify();
+                        }))
+                )
+        )
+}
+
+/// Files of a Metalink with their digests, piece hashes and mirrors, and a choice of
+/// countries to download from
+fn metalink_preview(preview: &MetalinkPreview, cx: &Context<DownloadsView>) -> Div {
+    let metalink = &preview.metalink;
+    let short = |hash: &str| if hash.len() > 16 { format!("{}...", &hash[..16]) } else { hash.to_string() };
+    let title = match metalink.files.as_slice() {
+        [file] => file.name.clone(),
+        files => format!("{} files", files.len()),
+    };
+    let countries = metalink.countries();
+    let mut details = vec![
+        format_bytes(metalink.total_size()),
+        format!("{} mirrors", metalink.files.iter().map(|file| file.mirrors.len()).sum::<usize>()),
+        format!("{} countries", countries.len()),
+    ];
+    if let Some(ref generator) = metalink.generator {
+        details.push(format!("by {}", generator));
+    }
+    if let Some(ref published) = metalink.published {
+        details.push(published.clone());
+    }
+    let unreachable = metalink.unreachable(&preview.countries);
+
+    let country_buttons = countries.into_iter().enumerate().map(|(index, country)| {
+        let button = Button::new(("metalink-country", index)).label(country.to_uppercase());
+        let button = if preview.countries.contains(&country) { button.primary() } else { button.ghost() };
+        button.on_click(cx.listener(move |this, _, _, cx| this.toggle_country(Some(country.clone()), cx)))
+    });
+    let all_button = Button::new("metalink-country-all").label("All");
+    let all_button = if preview.countries.is_empty() { all_button.primary() } else { all_button.ghost() };
+
+    let files = metalink.files.iter().map(|file| {
+        let mut about = Vec::new();
+        if let Some(size) = file.size {
+            about.push(format_bytes(size));
+        }
+        if let Some(ref version) = file.version {
+            about.push(format!("version {}", version));
+        }
+        if let Some(ref description) = file.description {
+            about.push(description.clone());
+        }
+
+        // Only a single file can be verified by its GID, aria2 checks the others itself
+        let checksums = file.checksums();
+        let hashes = if metalink.files.len() == 1 && !checksums.is_empty() {
+            let none = Button::new("metalink-checksum-none").label("aria2 only");
+            let none = if preview.checksum.is_none() { none.primary() } else { none.ghost() };
+            div()
+                .flex()
+                .flex_wrap()
+                .items_center()
+                .gap_2()
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x9ca3af))
+                        .child("Verify with")
+                )
+                .children(checksums.iter().enumerate().map(|(position, checksum)| {
+                    let button = Button::new(("metalink-checksum", position))
+                        .label(format!("{} {}", checksum.algorithm.as_str(), short(&checksum.hex)));
+                    let button = if preview.checksum == Some(position) { button.primary() } else { button.ghost() };
+                    button.on_click(cx.listener(move |this, _, _, cx| this.select_checksum(Some(position), cx)))
+                }))
+                .child(none.on_click(cx.listener(|this, _, _, cx| this.select_checksum(None, cx))))
+        } else {
+            let hashes: Vec<String> = file.hashes.iter().map(|hash| format!("{} {}", hash.kind, short(&hash.value))).collect();
+            div()
+                .text_xs()
+                .text_color(rgb(0x6b7280))
+                .child(if hashes.is_empty() { "No digest".to_string() } else { format!("Hashes: {}", hashes.join(", ")) })
+        };
+
+        let mirrors = file.mirrors_by_priority().into_iter().map(|mirror| {
+            let used = mirror.in_countries(&preview.countries);
+            div()
+                .flex()
+                .gap_3()
+                .text_xs()
+                .text_color(if used { rgb(0xd1d5db) } else { rgb(0x4b5563) })
+                .child(div().w(px(40.0)).child(mirror.location.as_deref().map(str::to_uppercase).unwrap_or_else(|| "-".to_string())))
+                .child(div().w(px(80.0)).child(mirror.priority.map(|priority| format!("priority {}", priority)).unwrap_or_default()))
+                .child(div().flex_1().child(mirror.url.clone()))
+        });
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(
+                div()
+                    .text_sm()
+                    .font_weight(FontWeight::SEMIBOLD)
+                    .text_color(rgb(0xffffff))
+                    .child(file.name.clone())
+            )
+            .when(!about.is_empty(), |this| {
+                this.child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x9ca3af))
+                        .child(about.join(" - "))
+                )
+            })
+            .child(hashes)
+            .when_some(file.pieces.as_ref(), |this, pieces| {
+                this.child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x6b7280))
+                        .child(format!("{} pieces of {}, {} each", pieces.hashes.len(), format_bytes(pieces.length), pieces.kind))
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x4b5563))
+                        .child({
+                            let mut shown: Vec<String> = pieces
+                                .hashes
+                                .iter()
+                                .take(8)
+                                .enumerate()
+                                .map(|(index, hash)| format!("#{} {}", index + 1, short(hash)))
+                                .collect();
+                            if pieces.hashes.len() > shown.len() {
+                                shown.push(format!("and {} more", pieces.hashes.len() - shown.len()));
+                            }
+                            shown.join("  ")
+                        })
+                )
+            })
+            .children(mirrors)
+            .children(file.metaurls.iter().map(|metaurl| {
+                div()
+                    .text_xs()
+                    .text_color(rgb(0x9ca3af))
+                    .child(format!("Also described by a {}: {}", metaurl.mediatype, metaurl.url))
+            }))
+    });
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_2()
+        .p_4()
+        .bg(rgb(0x1f2937))
+        .rounded(px(10.0))
+        .border_1()
+        .border_color(rgb(0x374151))
+        .child(
+            div()
+                .text_base()
+                .font_weight(FontWeight::SEMIBOLD)
+                .text_color(rgb(0xffffff))
+                .child(title)
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x9ca3af))
+                .child(details.join(" - "))
+        )
+        .child(
+            div()
+                .flex()
+                .flex_wrap()
+                .items_center()
+                .gap_2()
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x9ca3af))
+                        .child("Mirrors in")
+                )
+                .child(all_button.on_click(cx.listener(|this, _, _, cx| this.toggle_country(None, cx))))
+                .children(country_buttons)
+        )
+        .when(!unreachable.is_empty(), |this| {
+            this.child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0xef4444))
+                    .child(format!("No mirror of {} is in the selected countries", unreachable.join(", ")))
+            )
+        })
+        .child(
+            div()
+                .id("metalink-files")
+                .flex()
+                .flex_col()
+                .gap_3()
+                .max_h(px(320.0))
+                .overflow_y_scroll()
+                .children(files)
+        )
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(
+                    Button::new("metalink-start")
+                        .label("Start download")
+                        .primary()
+                        .disabled(!unreachable.is_empty())
+                        .on_click(cx.listener(|this, _, window, cx| this.start_metalink(window, cx)))
+                )
+                .child(
+                    Button::new("metalink-cancel")
+                        .label("Cancel")
+                        .ghost()
+                        .on_click(cx.listener(|this, _, _, cx| {
+                            this.metalink = None;
+                            cx.notify();
+                        }))
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x9ca3af))
+                        .child("aria2 checks the piece hashes as the files download")
+                )
+        )
+}
+
+/// Text to import and the options the downloads share, with the downloads found
+fn batch_import(batch: &BatchImport, settings: &Settings, cx: &Context<DownloadsView>) -> Div {
+    let mut categories = vec![("Automatic".to_string(), None)];
+    categories.extend(
+        settings
+            .categories
+            .categories
+            .iter()
+            .map(|category| (category.name.clone(), Some(category.name.clone()))),
+    );
+    let shown = 200;
+    let found = if batch.requests.is_empty() {
+        "Preview to see the downloads found".to_string()
+    } else {
+        format!("{} downloads", batch.requests.len())
+    };
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_3()
+        .p_4()
+        .bg(rgb(0x1f2937))
+        .rounded(px(10.0))
+        .border_1()
+        .border_color(rgb(0x374151))
+        .child(
+            div()
+                .text_base()
+                .font_weight(FontWeight::SEMIBOLD)
+                .text_color(rgb(0xffffff))
+                .child("Batch import")
+        )
+        .child(Input::new(&batch.text))
+        .child(field("Folder", "Where the downloads are saved, unless a URL list sets its own", Input::new(&batch.dir)))
+        .child(
+            div()
+                .flex()
+                .flex_wrap()
+                .gap_2()
+                .children(categories.into_iter().enumerate().map(|(index, (label, category))| {
+                    let selected = category == batch.category;
+                    let button = Button::new(("batch-category", index))
+                        .label(label)
+                        .on_click(cx.listener(move |this, _, _, cx| {
+                            if let Some(ref mut batch) = this.batch {
+                                batch.category = category.clone();
+                                cx.notify();
+                            }
+                        }));
+                    if selected { button.primary() } else { button.ghost() }
+                }))
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x9ca3af))
+                .child(found)
+        )
+        .when(!batch.requests.is_empty(), |this| {
+            this.child(
+                div()
+                    .id("batch-urls")
                     .flex()
-                    .flex_1()
                     .flex_col()
-                    .items_center()
-                    .justify_center()
-                    .gap_3()
-                    .child(
+                    .gap_1()
+                    .max_h(px(240.0))
+                    .overflow_y_scroll()
+                    .children(batch.requests.iter().take(shown).map(|request| {
                         div()
-                            .text_base()
-                            .text_color(rgb(0x6b7280))
-                            .child("No active downloads")
+                            .text_xs()
+                            .text_color(rgb(0xd1d5db))
+                            .child(request.uris.join("  "))
+                    }))
+                    .when(batch.requests.len() > shown, |this| {
+                        this.child(
+                            div()
+                                .text_xs()
+                                .text_color(rgb(0x6b7280))
+                                .child(format!("and {} more", batch.requests.len() - shown))
+                        )
+                    })
+            )
+        })
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(
+                    Button::new("batch-preview")
+                        .label("Preview")
+                        .on_click(cx.listener(|this, _, _, cx| this.preview_batch(cx)))
+                )
+                .child(
+                    Button::new("batch-start")
+                        .label(if batch.adding { "Adding downloads".to_string() } else { format!("Add {} downloads", batch.requests.len()) })
+                        .primary()
+                        .disabled(batch.requests.is_empty() || batch.adding)
+                        .on_click(cx.listener(|this, _, _, cx| this.start_batch(cx)))
+                )
+                .child(
+                    Button::new("batch-cancel")
+                        .label("Cancel")
+                        .ghost()
+                        .on_click(cx.listener(|this, _, _, cx| {
+                            this.batch = None;
+                            cx.notify();
+                        }))
+                )
+        )
+}
+
+/// One download with its progress
+fn download_row(
+    download: Download,
+    category: Option<String>,
+    actions: Vec<ActionOutcome>,
+    verification: Option<Verification>,
+    appearance: &AppearanceSettings,
+) -> Div {
+    let is_video = is_video_download(&download.gid);
+    let progress = download.progress().clamp(0.0, 1.0);
+    let (status, status_color) = match (download.status, verification) {
+        (DownloadStatus::Complete, Some(Verification::Verifying)) => ("Verifying", rgb(0x9ca3af)),
+        (DownloadStatus::Complete, Some(Verification::Verified)) => ("Verified", rgb(0x22c55e)),
+        (DownloadStatus::Complete, Some(Verification::Corrupt)) => ("Corrupt", rgb(0xef4444)),
+        (DownloadStatus::Complete, Some(Verification::Failed(_))) => ("Not verified", rgb(0xeab308)),
+        (DownloadStatus::Active | DownloadStatus::Waiting, _) if download.metadata => ("Fetching metadata", rgb(0xa78bfa)),
+        (status, _) => status_text(status),
+    };
+    let mut details = format!("{} of {}", format_bytes(download.completed_length), format_bytes(download.total_length));
+    if download.status == DownloadStatus::Active {
+        details.push_str(&format!(" - {}", format_speed(download.download_speed, appearance.speed_in_bits)));
+        if let Some(eta) = download.eta_secs() {
+            details.push_str(&format!(" - {} left", format_duration(eta)));
+        }
+    }
+    if download.metadata && download.status == DownloadStatus::Active {
+        details = "Asking peers for the list of files".to_string();
+    }
+    if let Some(ref error) = download.error_message {
+        details = error.clone();
+    }
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .when(appearance.compact_lists, |this| this.px_3().py_1())
+        .when(!appearance.compact_lists, |this| this.p_3())
+        .bg(rgb(0x1f2937))
+        .rounded(px(8.0))
+        .border_1()
+        .border_color(rgb(0x374151))
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(
+                    div()
+                        .flex_1()
+                        .text_sm()
+                        .font_weight(FontWeight::SEMIBOLD)
+                        .text_color(rgb(0xffffff))
+                        .child(download.name.clone())
+                )
+                .when(is_video, |this| {
+                    this.child(
+                        div()
+                            .px_2()
+                            .rounded(px(4.0))
+                            .bg(rgb(0x312e81))
+                            .text_xs()
+                            .text_color(rgb(0xc7d2fe))
+                            .child("yt-dlp")
                     )
-                    .child(
+                })
+                .when_some(category, |this, category| {
+                    this.child(
                         div()
+                            .px_2()
+                            .rounded(px(4.0))
+                            .bg(rgb(0x374151))
                             .text_xs()
-                            .text_color(rgb(0x4b5563))
-                            .child("Add a download to get started")
+                            .text_color(rgb(0xd1d5db))
+                            .child(category)
                     )
+                })
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(status_color)
+                        .child(status)
+                )
+        )
+        .child(
+            // Progress bar
+            div()
+                .h(px(4.0))
+                .w_full()
+                .rounded(px(2.0))
+                .bg(rgb(0x374151))
+                .child(
+                    div()
+                        .h_full()
+                        .w(relative(progress))
+                        .rounded(px(2.0))
+                        .bg(status_color)
+                )
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x9ca3af))
+                .child(details)
+        )
+        .when(!actions.is_empty(), |this| {
+            // Post-download actions, failures show their reason
+            this.child(
+                div()
+                    .flex()
+                    .flex_wrap()
+                    .gap_2()
+                    .children(actions.into_iter().map(|outcome| {
+                        let (mark, color) = if outcome.success { ("✓", rgb(0x22c55e)) } else { ("✗", rgb(0xef4444)) };
+                        let label = if outcome.success {
+                            format!("{} {}", mark, outcome.action)
+                        } else {
+                            format!("{} {}: {}", mark, outcome.action, outcome.message)
+                        };
+                        div()
+                            .text_xs()
+                            .text_color(color)
+                            .child(label)
+                    }))
             )
+        })
+}
+
+/// Label and color of a download status
+fn status_text(status: DownloadStatus) -> (&'static str, Rgba) {
+    match status {
+        DownloadStatus::Active => ("Downloading", rgb(0x22c55e)),
+        DownloadStatus::Waiting => ("Queued", rgb(0x9ca3af)),
+        DownloadStatus::Paused => ("Paused", rgb(0xeab308)),
+        DownloadStatus::Error => ("Failed", rgb(0xef4444)),
+        DownloadStatus::Complete => ("Complete", rgb(0x3b82f6)),
+        DownloadStatus::Removed => ("Removed", rgb(0x6b7280)),
     }
 }
+
+/// A labelled value in the stats bar
+fn stat_item(label: &'static str, value: String) -> Div {
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x9ca3af))
+                .child(label)
+        )
+        .child(
+            div()
+                .text_xl()
+                .font_weight(FontWeight::SEMIBOLD)
+                .text_color(rgb(0xffffff))
+                .child(value)
+        )
+}
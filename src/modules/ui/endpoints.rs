@@ -0,0 +1,321 @@
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
+use gpui_component::input::{Input, InputState};
+use crate::modules::settings::{Aria2Endpoint, PathMapping, Settings};
+use super::settings::{field, parse_field, persist_settings};
+use super::SettingsEvent;
+use std::path::PathBuf;
+
+/// Editor for the list of aria2 endpoints
+pub struct EndpointsView {
+    settings: Settings,
+    endpoints: Vec<Aria2Endpoint>,
+    /// Name of the active endpoint, following renames and removals
+    active: String,
+    selected: usize,
+    name: Entity<InputState>,
+    host: Entity<InputState>,
+    port: Entity<InputState>,
+    secret: Entity<InputState>,
+    path: Entity<InputState>,
+    mappings: Entity<InputState>,
+    /// Result of the last action, (is_error, message)
+    message: Option<(bool, String)>,
+}
+
+impl EventEmitter<SettingsEvent> for EndpointsView {}
+
+impl EndpointsView {
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let mut view = Self {
+            endpoints: settings.aria2.endpoints.clone(),
+            active: settings.aria2.active_endpoint.clone(),
+            selected: 0,
+            name: cx.new(|cx| InputState::new(window, cx)),
+            host: cx.new(|cx| InputState::new(window, cx)),
+            port: cx.new(|cx| InputState::new(window, cx)),
+            secret: cx.new(|cx| InputState::new(window, cx).masked(true)),
+            path: cx.new(|cx| InputState::new(window, cx)),
+            mappings: cx.new(|cx| {
+                InputState::new(window, cx).placeholder("/remote/downloads => /mnt/nas/downloads; ...")
+            }),
+            settings,
+            message: None,
+        };
+        view.load_selected(window, cx);
+        view
+    }
+
+    /// Replace the edited settings, e.g. after a change broadcast by the background task
+    pub fn set_settings(&mut self, settings: Settings, window: &mut Window, cx: &mut Context<Self>) {
+        self.endpoints = settings.aria2.endpoints.clone();
+        self.active = settings.aria2.active_endpoint.clone();
+        self.settings = settings;
+        self.selected = self.selected.min(self.endpoints.len().saturating_sub(1));
+        self.load_selected(window, cx);
+        cx.notify();
+    }
+
+    /// Fill the form with the selected endpoint
+    fn load_selected(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(endpoint) = self.endpoints.get(self.selected) else {
+            return;
+        };
+
+        let mappings = endpoint
+            .path_mappings
+            .iter()
+            .map(|mapping| format!("{} => {}", mapping.remote, mapping.local.display()))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let values = [
+            (&self.name, endpoint.name.clone()),
+            (&self.host, endpoint.host.clone()),
+            (&self.port, endpoint.port.to_string()),
+            (&self.secret, endpoint.secret.clone()),
+            (&self.path, endpoint.path.clone()),
+            (&self.mappings, mappings),
+        ];
+        for (input, value) in values {
+            input.update(cx, |state, cx| state.set_value(value, window, cx));
+        }
+    }
+
+    /// Write the form back into the selected endpoint
+    fn store_selected(&mut self, cx: &App) -> anyhow::Result<()> {
+        let name = self.name.read(cx).value().trim().to_string();
+        let host = self.host.read(cx).value().trim().to_string();
+        let port = parse_field(&self.port, "Port", cx)?;
+        let secret = self.secret.read(cx).value().to_string();
+        let path = self.path.read(cx).value().trim().to_string();
+        let path_mappings = parse_mappings(&self.mappings.read(cx).value())?;
+
+        let Some(endpoint) = self.endpoints.get_mut(self.selected) else {
+            return Ok(());
+        };
+        if endpoint.name == self.active {
+            self.active = name.clone();
+        }
+        endpoint.name = name;
+        if !endpoint.managed {
+            endpoint.host = host;
+            endpoint.port = port;
+            endpoint.secret = secret;
+            endpoint.path = path;
+            endpoint.path_mappings = path_mappings;
+        }
+        endpoint.validate()
+    }
+
+    fn select(&mut self, index: usize, window: &mut Window, cx: &mut Context<Self>) {
+        match self.store_selected(cx) {
+            Ok(()) => {
+                self.selected = index;
+                self.message = None;
+                self.load_selected(window, cx);
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+
+    fn add(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let name = (1..)
+            .map(|i| format!("Remote {}", i))
+            .find(|name| !self.endpoints.iter().any(|endpoint| &endpoint.name == name))
+            .unwrap();
+        self.endpoints.push(Aria2Endpoint {
+            name,
+            ..Default::default()
+        });
+        self.select(self.endpoints.len() - 1, window, cx);
+    }
+
+    fn remove(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.endpoints.get(self.selected).is_some_and(|endpoint| !endpoint.managed) {
+            let removed = self.endpoints.remove(self.selected);
+            if removed.name == self.active
+                && let Some(local) = self.endpoints.iter().find(|endpoint| endpoint.managed)
+            {
+                self.active = local.name.clone();
+            }
+            self.selected = self.selected.saturating_sub(1);
+            self.load_selected(window, cx);
+            cx.notify();
+        }
+    }
+
+    fn save(&mut self, cx: &mut Context<Self>) {
+        let result = self.store_selected(cx).and_then(|_| {
+            let mut settings = self.settings.clone();
+            settings.aria2.endpoints = self.endpoints.clone();
+            settings.aria2.active_endpoint = self.active.clone();
+            persist_settings(settings)
+        });
+
+        match result {
+            Ok(settings) => {
+                self.settings = settings.clone();
+                self.message = Some((false, "Endpoints saved".to_string()));
+                cx.emit(SettingsEvent::Saved(settings));
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+}
+
+impl Render for EndpointsView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let selected = self.endpoints.get(self.selected).cloned().unwrap_or_default();
+        let active = self.active.clone();
+
+        let form = if selected.managed {
+            div()
+                .flex()
+                .flex_col()
+                .gap_4()
+                .child(field("Name", "Shown in the aria2c status menu", Input::new(&self.name)))
+                .child(
+                    div()
+                        .text_sm()
+                        .text_color(rgb(0x9ca3af))
+                        .child("The daemon started by bong. Its port and secret come from the Network and aria2 settings.")
+                )
+        } else {
+            div()
+                .flex()
+                .flex_col()
+                .gap_4()
+                .child(field("Name", "Shown in the aria2c status menu", Input::new(&self.name)))
+                .child(field("Host", "Hostname or IP address of the aria2 server", Input::new(&self.host)))
+                .child(field("Port", "RPC port, usually 6800", Input::new(&self.port)))
+                .child(field("Secret", "Value of rpc-secret on the server", Input::new(&self.secret)))
+                .child(field("Path", "RPC path, usually /jsonrpc", Input::new(&self.path)))
+                .child(
+                    Checkbox::new("endpoint-tls")
+                        .label("Use TLS (https)")
+                        .checked(selected.tls)
+                        .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                            if let Some(endpoint) = this.endpoints.get_mut(this.selected) {
+                                endpoint.tls = *checked;
+                            }
+                            cx.notify();
+                        }))
+                )
+                .child(field(
+                    "Path mappings",
+                    "Remote directory => local directory, separated by ;",
+                    Input::new(&self.mappings),
+                ))
+        };
+
+        div()
+            .flex()
+            .size_full()
+            .child(
+                // Endpoint list
+                div()
+                    .flex()
+                    .flex_col()
+                    .w(px(220.0))
+                    .p_4()
+                    .gap_1()
+                    .border_r_1()
+                    .border_color(rgb(0x374151))
+                    .children(self.endpoints.iter().enumerate().map(|(index, endpoint)| {
+                        let label = if endpoint.name == active {
+                            format!("{} (active)", endpoint.name)
+                        } else {
+                            endpoint.name.clone()
+                        };
+                        let button = Button::new(("endpoint", index))
+                            .label(label)
+                            .w_full()
+                            .on_click(cx.listener(move |this, _, window, cx| this.select(index, window, cx)));
+                        if index == self.selected { button.primary() } else { button.ghost() }
+                    }))
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .mt_2()
+                            .child(
+                                Button::new("endpoint-add")
+                                    .label("Add")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, window, cx| this.add(window, cx)))
+                            )
+                            .child(
+                                Button::new("endpoint-remove")
+                                    .label("Remove")
+                                    .ghost()
+                                    .disabled(selected.managed)
+                                    .on_click(cx.listener(|this, _, window, cx| this.remove(window, cx)))
+                            )
+                    )
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .p_6()
+                    .gap_4()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0xffffff))
+                            .child("aria2 Endpoints")
+                    )
+                    .child(form)
+                    .child(
+                        // Footer with save / close
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .mt_4()
+                            .child(
+                                Button::new("endpoints-save")
+                                    .label("Save")
+                                    .primary()
+                                    .on_click(cx.listener(|this, _, _, cx| this.save(cx)))
+                            )
+                            .child(
+                                Button::new("endpoints-close")
+                                    .label("Close")
+                                    .ghost()
+                                    .on_click(cx.listener(|_, _, _, cx| cx.emit(SettingsEvent::Close)))
+                            )
+                            .when_some(self.message.clone(), |this, (is_error, message)| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                        .child(message)
+                                )
+                            })
+                    )
+            )
+    }
+}
+
+/// Parse "remote => local; remote => local"
+fn parse_mappings(text: &str) -> anyhow::Result<Vec<PathMapping>> {
+    text.split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (remote, local) = entry
+                .split_once("=>")
+                .ok_or_else(|| anyhow::anyhow!("Path mapping \"{}\" needs the form remote => local", entry))?;
+            Ok(PathMapping {
+                remote: remote.trim().to_string(),
+                local: PathBuf::from(local.trim()),
+            })
+        })
+        .collect()
+}
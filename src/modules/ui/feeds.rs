@@ -0,0 +1,442 @@
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
+use gpui_component::input::{Input, InputState};
+use crate::modules::feeds::{FeedState, ItemRecord, ItemResult};
+use crate::modules::ipc::{IpcClient, IpcCommand, BACKGROUND_IPC_PORT};
+use crate::modules::settings::{Feed, FeedSettings, Settings};
+use super::settings::{field, parse_field, persist_settings};
+use super::SettingsEvent;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Items of the selected feed rendered at once
+const MAX_ROWS: usize = 100;
+
+/// Feed subscriptions with their filters, and the items each check added or skipped
+pub struct FeedsView {
+    settings: Settings,
+    feeds: FeedSettings,
+    selected: usize,
+    name: Entity<InputState>,
+    url: Entity<InputState>,
+    include: Entity<InputState>,
+    exclude: Entity<InputState>,
+    /// Category name, empty for automatic assignment
+    category: Entity<InputState>,
+    dir: Entity<InputState>,
+    interval_mins: Entity<InputState>,
+    /// Last state loaded from the background task
+    state: FeedState,
+    /// Result of the last action, (is_error, message)
+    message: Option<(bool, String)>,
+}
+
+impl EventEmitter<SettingsEvent> for FeedsView {}
+
+impl FeedsView {
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let mut view = Self {
+            feeds: settings.feeds.clone(),
+            selected: 0,
+            name: cx.new(|cx| InputState::new(window, cx)),
+            url: cx.new(|cx| InputState::new(window, cx).placeholder("https://example.com/releases.rss")),
+            include: cx.new(|cx| InputState::new(window, cx).placeholder("show.name.*1080p")),
+            exclude: cx.new(|cx| InputState::new(window, cx).placeholder("\\b(cam|ts)\\b")),
+            category: cx.new(|cx| InputState::new(window, cx).placeholder("Automatic")),
+            dir: cx.new(|cx| InputState::new(window, cx).placeholder("Category folder")),
+            interval_mins: cx.new(|cx| InputState::new(window, cx).default_value(settings.feeds.interval_mins.to_string())),
+            settings,
+            state: FeedState::default(),
+            message: None,
+        };
+        view.load_selected(window, cx);
+        view
+    }
+
+    /// Replace the edited settings, e.g. after a change broadcast by the background task
+    pub fn set_settings(&mut self, settings: Settings, window: &mut Window, cx: &mut Context<Self>) {
+        self.feeds = settings.feeds.clone();
+        self.settings = settings;
+        self.selected = self.selected.min(self.feeds.feeds.len().saturating_sub(1));
+        let interval = self.feeds.interval_mins.to_string();
+        self.interval_mins.update(cx, |state, cx| state.set_value(interval, window, cx));
+        self.load_selected(window, cx);
+        cx.notify();
+    }
+
+    /// Load the checked items from the background task
+    pub fn refresh(&mut self, cx: &mut Context<Self>) {
+        match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::GetFeeds) {
+            Ok(Some(IpcCommand::Feeds(state))) => self.state = state,
+            Ok(Some(IpcCommand::Error(e))) => self.message = Some((true, e)),
+            _ => self.message = Some((true, "Background task is not running".to_string())),
+        }
+        cx.notify();
+    }
+
+    /// Fill the form with the selected feed
+    fn load_selected(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(feed) = self.feeds.feeds.get(self.selected) else {
+            return;
+        };
+
+        let values = [
+            (&self.name, feed.name.clone()),
+            (&self.url, feed.url.clone()),
+            (&self.include, feed.include.clone()),
+            (&self.exclude, feed.exclude.clone()),
+            (&self.category, feed.category.clone().unwrap_or_default()),
+            (&self.dir, feed.dir.display().to_string()),
+        ];
+        for (input, value) in values {
+            input.update(cx, |state, cx| state.set_value(value, window, cx));
+        }
+    }
+
+    /// Write the form back into the selected feed
+    fn store_selected(&mut self, cx: &App) -> anyhow::Result<()> {
+        self.feeds.interval_mins = parse_field(&self.interval_mins, "Check interval", cx)?;
+
+        let category = self.category.read(cx).value().trim().to_string();
+        if !category.is_empty() && self.settings.categories.find(&category).is_none() {
+            anyhow::bail!("Category {} doesn't exist", category);
+        }
+        let Some(feed) = self.feeds.feeds.get_mut(self.selected) else {
+            return Ok(());
+        };
+        *feed = Feed {
+            enabled: feed.enabled,
+            name: self.name.read(cx).value().trim().to_string(),
+            url: self.url.read(cx).value().trim().to_string(),
+            include: self.include.read(cx).value().trim().to_string(),
+            exclude: self.exclude.read(cx).value().trim().to_string(),
+            category: (!category.is_empty()).then_some(category),
+            dir: PathBuf::from(self.dir.read(cx).value().trim()),
+            backlog: feed.backlog,
+        };
+        feed.validate()
+    }
+
+    fn select(&mut self, index: usize, window: &mut Window, cx: &mut Context<Self>) {
+        match self.store_selected(cx) {
+            Ok(()) => {
+                self.selected = index;
+                self.message = None;
+                self.load_selected(window, cx);
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+
+    fn add(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let name = (1..)
+            .map(|i| format!("Feed {}", i))
+            .find(|name| !self.feeds.feeds.iter().any(|feed| feed.name == *name))
+            .unwrap();
+        self.feeds.feeds.push(Feed {
+            name,
+            url: "https://".to_string(),
+            ..Default::default()
+        });
+        self.select(self.feeds.feeds.len() - 1, window, cx);
+    }
+
+    fn remove(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.selected < self.feeds.feeds.len() {
+            self.feeds.feeds.remove(self.selected);
+            self.selected = self.selected.saturating_sub(1);
+            self.load_selected(window, cx);
+            cx.notify();
+        }
+    }
+
+    fn save(&mut self, cx: &mut Context<Self>) {
+        let result = self.store_selected(cx).and_then(|_| {
+            let mut settings = self.settings.clone();
+            settings.feeds = self.feeds.clone();
+            persist_settings(settings)
+        });
+
+        match result {
+            Ok(settings) => {
+                self.settings = settings.clone();
+                self.message = Some((false, "Feeds saved".to_string()));
+                cx.emit(SettingsEvent::Saved(settings));
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+
+    /// Ask the background task to check every feed, then show what it found
+    fn check_now(&mut self, cx: &mut Context<Self>) {
+        match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::RefreshFeeds) {
+            Ok(Some(IpcCommand::Ok)) => {
+                self.message = Some((false, "Checking feeds...".to_string()));
+                cx.spawn(async move |this, cx| {
+                    cx.background_executor().timer(Duration::from_secs(5)).await;
+                    let _ = this.update(cx, |this, cx| {
+                        this.message = None;
+                        this.refresh(cx);
+                    });
+                })
+                .detach();
+            }
+            Ok(Some(IpcCommand::Error(e))) => self.message = Some((true, e)),
+            _ => self.message = Some((true, "Background task is not running".to_string())),
+        }
+        cx.notify();
+    }
+}
+
+impl Render for FeedsView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let selected = self.feeds.feeds.get(self.selected).cloned();
+        let status = selected.as_ref().and_then(|feed| self.state.feeds.get(&feed.url).cloned());
+        let items: Vec<ItemRecord> = selected
+            .as_ref()
+            .map(|feed| self.state.items_of(&feed.url).take(MAX_ROWS).cloned().collect())
+            .unwrap_or_default();
+
+        let last_check = match status {
+            Some(ref status) if status.error.is_some() => status.error.clone().unwrap_or_default(),
+            Some(ref status) => status
+                .checked_at
+                .map(|checked_at| format!("Last checked {}", format_time(checked_at)))
+                .unwrap_or_else(|| "Not checked yet".to_string()),
+            None => "Not checked yet".to_string(),
+        };
+        let check_failed = status.is_some_and(|status| status.error.is_some());
+
+        div()
+            .flex()
+            .size_full()
+            .child(
+                // Feed list
+                div()
+                    .flex()
+                    .flex_col()
+                    .w(px(220.0))
+                    .p_4()
+                    .gap_1()
+                    .border_r_1()
+                    .border_color(rgb(0x374151))
+                    .children(self.feeds.feeds.iter().enumerate().map(|(index, feed)| {
+                        let button = Button::new(("feed", index))
+                            .label(feed.name.clone())
+                            .w_full()
+                            .on_click(cx.listener(move |this, _, window, cx| this.select(index, window, cx)));
+                        if index == self.selected { button.primary() } else { button.ghost() }
+                    }))
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .mt_2()
+                            .child(
+                                Button::new("feed-add")
+                                    .label("Add")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, window, cx| this.add(window, cx)))
+                            )
+                            .child(
+                                Button::new("feed-remove")
+                                    .label("Remove")
+                                    .ghost()
+                                    .disabled(selected.is_none())
+                                    .on_click(cx.listener(|this, _, window, cx| this.remove(window, cx)))
+                            )
+                    )
+            )
+            .child(
+                div()
+                    .id("feed-editor")
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .p_6()
+                    .gap_4()
+                    .overflow_y_scroll()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0xffffff))
+                            .child("Feeds")
+                    )
+                    .child(field("Check interval", "Minutes between two checks of a feed", Input::new(&self.interval_mins)))
+                    .when_some(selected.clone(), |this, feed| {
+                        this.child(
+                            Checkbox::new("feed-enabled")
+                                .label("Download new items of this feed")
+                                .checked(feed.enabled)
+                                .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                    if let Some(feed) = this.feeds.feeds.get_mut(this.selected) {
+                                        feed.enabled = *checked;
+                                    }
+                                    cx.notify();
+                                }))
+                        )
+                        .child(
+                            Checkbox::new("feed-backlog")
+                                .label("Also download the items already in the feed when subscribing")
+                                .checked(feed.backlog)
+                                .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                    if let Some(feed) = this.feeds.feeds.get_mut(this.selected) {
+                                        feed.backlog = *checked;
+                                    }
+                                    cx.notify();
+                                }))
+                        )
+                        .child(field("Name", "Shown in the feed list", Input::new(&self.name)))
+                        .child(field("URL", "RSS or Atom feed", Input::new(&self.url)))
+                        .child(
+                            div()
+                                .flex()
+                                .gap_6()
+                                .child(field("Include", "Regular expression titles must match, empty for all", Input::new(&self.include)))
+                                .child(field("Exclude", "Regular expression of titles to skip", Input::new(&self.exclude)))
+                        )
+                        .child(
+                            div()
+                                .flex()
+                                .gap_6()
+                                .child(field("Category", "Empty to assign one automatically", Input::new(&self.category)))
+                                .child(field(
+                                    "Folder",
+                                    "Relative folders are inside the download directory",
+                                    Input::new(&self.dir),
+                                ))
+                        )
+                    })
+                    .child(
+                        // Footer with save / check / close
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .child(
+                                Button::new("feeds-save")
+                                    .label("Save")
+                                    .primary()
+                                    .on_click(cx.listener(|this, _, _, cx| this.save(cx)))
+                            )
+                            .child(
+                                Button::new("feeds-check")
+                                    .label("Check now")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, _, cx| this.check_now(cx)))
+                            )
+                            .child(
+                                Button::new("feeds-close")
+                                    .label("Close")
+                                    .ghost()
+                                    .on_click(cx.listener(|_, _, _, cx| cx.emit(SettingsEvent::Close)))
+                            )
+                            .when_some(self.message.clone(), |this, (is_error, message)| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                        .child(message)
+                                )
+                            })
+                    )
+                    .when(selected.is_some(), |this| {
+                        this.child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .gap_2()
+                                .mt_4()
+                                .child(
+                                    div()
+                                        .text_base()
+                                        .font_weight(FontWeight::SEMIBOLD)
+                                        .text_color(rgb(0xffffff))
+                                        .child("Items")
+                                )
+                                .child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(if check_failed { rgb(0xef4444) } else { rgb(0x9ca3af) })
+                                        .child(last_check)
+                                )
+                        )
+                        .when(items.is_empty(), |this| {
+                            this.child(
+                                div()
+                                    .text_sm()
+                                    .text_color(rgb(0x6b7280))
+                                    .child("No items checked yet")
+                            )
+                        })
+                        .children(items.into_iter().map(item_row))
+                    })
+            )
+    }
+}
+
+/// One checked feed item and what became of it
+fn item_row(item: ItemRecord) -> Div {
+    let (status, reason, color) = match item.result {
+        ItemResult::Added(_) => ("Added", None, rgb(0x22c55e)),
+        ItemResult::Skipped(reason) => ("Skipped", Some(reason), rgb(0x6b7280)),
+        ItemResult::Failed(error) => ("Failed", Some(error), rgb(0xef4444)),
+    };
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .p_3()
+        .bg(rgb(0x1f2937))
+        .rounded(px(8.0))
+        .border_1()
+        .border_color(rgb(0x374151))
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(
+                    div()
+                        .flex_1()
+                        .text_sm()
+                        .text_color(rgb(0xffffff))
+                        .child(item.title)
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(color)
+                        .child(status)
+                )
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x9ca3af))
+                .child(match reason {
+                    Some(reason) => format!("{} - {}", format_time(item.checked_at), reason),
+                    None => format_time(item.checked_at),
+                })
+        )
+        .when_some(item.url, |this, url| {
+            this.child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0x6b7280))
+                    .child(url)
+            )
+        })
+}
+
+/// Local date and time of a unix timestamp
+fn format_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,368 @@
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::input::{Input, InputState};
+use crate::modules::engine::{AddRequest, DownloadStatus};
+use crate::modules::format::{format_bytes, format_duration, format_speed};
+use crate::modules::history::HistoryEntry;
+use crate::modules::ipc::{IpcClient, IpcCommand, BACKGROUND_IPC_PORT};
+use crate::modules::settings::{AppearanceSettings, Settings};
+use super::SettingsEvent;
+
+/// Entries rendered at once, the search narrows down the rest
+const MAX_ROWS: usize = 200;
+
+/// How far back the history goes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateFilter {
+    All,
+    Today,
+    Week,
+    Month,
+}
+
+impl DateFilter {
+    const ALL: [DateFilter; 4] = [DateFilter::All, DateFilter::Today, DateFilter::Week, DateFilter::Month];
+
+    fn label(self) -> &'static str {
+        match self {
+            DateFilter::All => "All time",
+            DateFilter::Today => "Today",
+            DateFilter::Week => "Last 7 days",
+            DateFilter::Month => "Last 30 days",
+        }
+    }
+
+    /// Earliest finish time shown, as a unix timestamp
+    fn since(self) -> Option<i64> {
+        let now = chrono::Local::now();
+        match self {
+            DateFilter::All => None,
+            DateFilter::Today => now
+                .date_naive()
+                .and_hms_opt(0, 0, 0)
+                .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+                .map(|midnight| midnight.timestamp()),
+            DateFilter::Week => Some((now - chrono::Duration::days(7)).timestamp()),
+            DateFilter::Month => Some((now - chrono::Duration::days(30)).timestamp()),
+        }
+    }
+}
+
+/// Finished downloads with search, date filter and re-download
+pub struct HistoryView {
+    settings: Settings,
+    /// Newest first
+    entries: Vec<HistoryEntry>,
+    search: Entity<InputState>,
+    date_filter: DateFilter,
+    /// Result of the last action, (is_error, message)
+    message: Option<(bool, String)>,
+}
+
+impl EventEmitter<SettingsEvent> for HistoryView {}
+
+impl HistoryView {
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let search = cx.new(|cx| InputState::new(window, cx).placeholder("Search name, URL, folder or status"));
+        // Filter while typing
+        cx.subscribe(&search, |_, _, _: &gpui_component::input::InputEvent, cx| cx.notify()).detach();
+
+        Self {
+            settings,
+            entries: Vec::new(),
+            search,
+            date_filter: DateFilter::All,
+            message: None,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: Settings, cx: &mut Context<Self>) {
+        self.settings = settings;
+        cx.notify();
+    }
+
+    /// Load the history from the background task
+    pub fn refresh(&mut self, cx: &mut Context<Self>) {
+        match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::GetHistory) {
+            Ok(Some(IpcCommand::History(mut entries))) => {
+                entries.reverse();
+                self.entries = entries;
+                self.message = None;
+            }
+            Ok(Some(IpcCommand::Error(e))) => self.message = Some((true, e)),
+            _ => self.message = Some((true, "Background task is not running".to_string())),
+        }
+        cx.notify();
+    }
+
+    fn download_again(&mut self, entry: &HistoryEntry, cx: &mut Context<Self>) {
+        let Some(ref url) = entry.url else {
+            return;
+        };
+        // Keep the category unless it was deleted since
+        let category = entry
+            .category
+            .clone()
+            .filter(|name| self.settings.categories.find(name).is_some());
+        let request = AddRequest {
+            category,
+            ..AddRequest::new(url.clone())
+        };
+
+        self.message = match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::AddDownload(request)) {
+            Ok(Some(IpcCommand::DownloadAdded(_))) => Some((false, format!("Downloading {} again", entry.name))),
+            Ok(Some(IpcCommand::Error(e))) => Some((true, e)),
+            Ok(_) => Some((true, "Unexpected answer from the background task".to_string())),
+            Err(_) => Some((true, "Background task is not running".to_string())),
+        };
+        cx.notify();
+    }
+
+    fn open_folder(&mut self, entry: &HistoryEntry, cx: &mut Context<Self>) {
+        if entry.dir.is_dir() {
+            cx.open_with_system(&entry.dir);
+        } else {
+            self.message = Some((true, format!("{} doesn't exist anymore", entry.dir.display())));
+            cx.notify();
+        }
+    }
+
+    fn copy_url(&mut self, entry: &HistoryEntry, cx: &mut Context<Self>) {
+        if let Some(ref url) = entry.url {
+            cx.write_to_clipboard(ClipboardItem::new_string(url.clone()));
+            self.message = Some((false, "URL copied".to_string()));
+            cx.notify();
+        }
+    }
+}
+
+impl Render for HistoryView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let query = self.search.read(cx).value().to_string();
+        let since = self.date_filter.since();
+        let matching: Vec<HistoryEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| since.is_none_or(|since| entry.finished_at >= since))
+            .filter(|entry| entry.matches(&query))
+            .cloned()
+            .collect();
+        let total = matching.len();
+
+        div()
+            .flex()
+            .flex_col()
+            .size_full()
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .p_6()
+                    .gap_4()
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .text_2xl()
+                                    .font_weight(FontWeight::BOLD)
+                                    .text_color(rgb(0xffffff))
+                                    .child("History")
+                            )
+                            .child(
+                                Button::new("history-refresh")
+                                    .label("Refresh")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, _, cx| this.refresh(cx)))
+                            )
+                            .child(
+                                Button::new("history-close")
+                                    .label("Close")
+                                    .ghost()
+                                    .on_click(cx.listener(|_, _, _, cx| cx.emit(SettingsEvent::Close)))
+                            )
+                    )
+                    .child(Input::new(&self.search))
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .children(DateFilter::ALL.into_iter().enumerate().map(|(index, filter)| {
+                                let button = Button::new(("history-date", index))
+                                    .label(filter.label())
+                                    .on_click(cx.listener(move |this, _, _, cx| {
+                                        this.date_filter = filter;
+                                        cx.notify();
+                                    }));
+                                if filter == self.date_filter { button.primary() } else { button.ghost() }
+                            }))
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .text_color(rgb(0x9ca3af))
+                                    .child(if total > MAX_ROWS {
+                                        format!("Showing {} of {} downloads", MAX_ROWS, total)
+                                    } else {
+                                        format!("{} downloads", total)
+                                    })
+                            )
+                    )
+                    .when_some(self.message.clone(), |this, (is_error, message)| {
+                        this.child(
+                            div()
+                                .text_sm()
+                                .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                .child(message)
+                        )
+                    })
+            )
+            .child(
+                div()
+                    .id("history-list")
+                    .flex()
+                    .flex_1()
+                    .flex_col()
+                    .px_6()
+                    .pb_6()
+                    .gap_2()
+                    .overflow_y_scroll()
+                    .when(matching.is_empty(), |this| {
+                        this.child(
+                            div()
+                                .text_base()
+                                .text_color(rgb(0x6b7280))
+                                .child(if self.entries.is_empty() { "No finished downloads yet" } else { "Nothing matches the search" })
+                        )
+                    })
+                    .children(matching.into_iter().take(MAX_ROWS).enumerate().map(|(index, entry)| {
+                        history_row(index, entry, &self.settings.appearance, cx)
+                    }))
+            )
+    }
+}
+
+/// One finished download with its details and actions
+fn history_row(index: usize, entry: HistoryEntry, appearance: &AppearanceSettings, cx: &Context<HistoryView>) -> Div {
+    let (status, status_color) = match entry.status {
+        DownloadStatus::Complete => ("Complete", rgb(0x22c55e)),
+        DownloadStatus::Error => ("Failed", rgb(0xef4444)),
+        _ => ("Removed", rgb(0x6b7280)),
+    };
+    let finished = chrono::DateTime::from_timestamp(entry.finished_at, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
+
+    let mut details = vec![finished, format_bytes(entry.size.max(entry.downloaded))];
+    if let Some(duration) = entry.duration_secs() {
+        details.push(format!("took {}", format_duration(duration)));
+    }
+    if let Some(speed) = entry.average_speed.filter(|speed| *speed > 0) {
+        details.push(format!("avg {}", format_speed(speed, appearance.speed_in_bits)));
+    }
+    if let Some(ref category) = entry.category {
+        details.push(category.clone());
+    }
+
+    let failed_actions: Vec<String> = entry
+        .actions
+        .iter()
+        .filter(|outcome| !outcome.success)
+        .map(|outcome| format!("{}: {}", outcome.action, outcome.message))
+        .collect();
+    let has_url = entry.url.is_some();
+    let (open_entry, copy_entry, again_entry) = (entry.clone(), entry.clone(), entry.clone());
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .when(appearance.compact_lists, |this| this.px_3().py_1())
+        .when(!appearance.compact_lists, |this| this.p_3())
+        .bg(rgb(0x1f2937))
+        .rounded(px(8.0))
+        .border_1()
+        .border_color(rgb(0x374151))
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(
+                    div()
+                        .flex_1()
+                        .text_sm()
+                        .font_weight(FontWeight::SEMIBOLD)
+                        .text_color(rgb(0xffffff))
+                        .child(entry.name.clone())
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(status_color)
+                        .child(status)
+                )
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x9ca3af))
+                .child(details.join(" - "))
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x6b7280))
+                .child(entry.dir.display().to_string())
+        )
+        .when_some(entry.url.clone(), |this, url| {
+            this.child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0x6b7280))
+                    .child(url)
+            )
+        })
+        .when_some(entry.error.clone(), |this, error| {
+            this.child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0xef4444))
+                    .child(error)
+            )
+        })
+        .children(failed_actions.into_iter().map(|failure| {
+            div()
+                .text_xs()
+                .text_color(rgb(0xef4444))
+                .child(failure)
+        }))
+        .child(
+            div()
+                .flex()
+                .gap_2()
+                .mt_1()
+                .child(
+                    Button::new(("history-open", index))
+                        .label("Open folder")
+                        .ghost()
+                        .on_click(cx.listener(move |this, _, _, cx| this.open_folder(&open_entry, cx)))
+                )
+                .child(
+                    Button::new(("history-copy", index))
+                        .label("Copy URL")
+                        .ghost()
+                        .disabled(!has_url)
+                        .on_click(cx.listener(move |this, _, _, cx| this.copy_url(&copy_entry, cx)))
+                )
+                .child(
+                    Button::new(("history-again", index))
+                        .label("Download again")
+                        .ghost()
+                        .disabled(!has_url)
+                        .on_click(cx.listener(move |this, _, _, cx| this.download_again(&again_entry, cx)))
+                )
+        )
+}
@@ -1,8 +1,28 @@
 mod search;
 mod downloads;
+mod settings;
+mod aria2_config;
+mod endpoints;
+mod schedule;
+mod profiles;
+mod categories;
+mod actions;
+mod history;
+mod feeds;
 
 pub use search::SearchView;
 pub use downloads::DownloadsView;
+pub use aria2_config::Aria2ConfigView;
+pub use endpoints::EndpointsView;
+pub use schedule::ScheduleView;
+pub use profiles::ProfilesView;
+pub use categories::CategoriesView;
+pub use actions::ActionsView;
+pub use history::HistoryView;
+pub use feeds::FeedsView;
+pub use settings::{SettingsEvent, SettingsPage, SettingsView};
+
+use settings::persist_settings;
 
 use gpui::*;
 use gpui_component::*;
@@ -10,6 +30,16 @@ use gpui_component::button::Button;
 use gpui_component::IconNamed;
 use gpui_component::menu::DropdownMenu;
 use gpui_component::menu::PopupMenuItem;
+use crate::modules::engine::EngineStatus;
+use crate::modules::format::format_speed;
+use crate::modules::ipc::{IpcClient, IpcCommand, BACKGROUND_IPC_PORT};
+use crate::modules::settings::{EngineKind, Settings, ThemePreference};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Commands received by the UI's IPC server, drained by `BongApp`
+pub type IpcInbox = Arc<Mutex<Vec<IpcCommand>>>;
 
 
 #[allow(dead_code)]
@@ -31,54 +61,295 @@ impl IconNamed for AppIcon {
 pub enum ViewMode {
     Downloads,
     Search,
+    Settings,
+    Aria2Config,
+    Endpoints,
+    Schedule,
+    Profiles,
+    Categories,
+    Actions,
+    History,
+    Feeds,
 }
 
 /// Main application view
 pub struct BongApp {
-    aria2c_status: Aria2cStatus,
+    engine_state: EngineState,
+    engine_info: EngineStatus,
     view_mode: ViewMode,
     search_view: Entity<SearchView>,
     downloads_view: Entity<DownloadsView>,
+    settings_view: Entity<SettingsView>,
+    aria2_config_view: Entity<Aria2ConfigView>,
+    endpoints_view: Entity<EndpointsView>,
+    schedule_view: Entity<ScheduleView>,
+    profiles_view: Entity<ProfilesView>,
+    categories_view: Entity<CategoriesView>,
+    actions_view: Entity<ActionsView>,
+    history_view: Entity<HistoryView>,
+    feeds_view: Entity<FeedsView>,
+    settings: Settings,
 } 
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum Aria2cStatus {
+/// Whether the download engine answers, shown by the icon in the title bar
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineState {
     Running,
     Stopped,
-    Error,
+    Error(String),
 }
 
 impl BongApp {
-    pub fn new(cx: &mut Context<Self>) -> Self {
+    pub fn new(inbox: IpcInbox, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let settings = fetch_settings();
+        apply_theme(settings.appearance.theme, window, cx);
+
+        let settings_view = cx.new(|cx| SettingsView::new(settings.clone(), window, cx));
+        cx.subscribe_in(&settings_view, window, Self::on_settings_event).detach();
+        let aria2_config_view = cx.new(|cx| Aria2ConfigView::new(settings.clone(), window, cx));
+        cx.subscribe_in(&aria2_config_view, window, Self::on_settings_event).detach();
+        let endpoints_view = cx.new(|cx| EndpointsView::new(settings.clone(), window, cx));
+        cx.subscribe_in(&endpoints_view, window, Self::on_settings_event).detach();
+        let schedule_view = cx.new(|cx| ScheduleView::new(settings.clone(), window, cx));
+        cx.subscribe_in(&schedule_view, window, Self::on_settings_event).detach();
+        let profiles_view = cx.new(|cx| ProfilesView::new(settings.clone(), window, cx));
+        cx.subscribe_in(&profiles_view, window, Self::on_settings_event).detach();
+        let categories_view = cx.new(|cx| CategoriesView::new(settings.clone(), window, cx));
+        cx.subscribe_in(&categories_view, window, Self::on_settings_event).detach();
+        let actions_view = cx.new(|cx| ActionsView::new(settings.clone(), window, cx));
+        cx.subscribe_in(&actions_view, window, Self::on_settings_event).detach();
+        let history_view = cx.new(|cx| HistoryView::new(settings.clone(), window, cx));
+        cx.subscribe_in(&history_view, window, Self::on_settings_event).detach();
+        let feeds_view = cx.new(|cx| FeedsView::new(settings.clone(), window, cx));
+        cx.subscribe_in(&feeds_view, window, Self::on_settings_event).detach();
+
+        // Drain commands pushed by other processes
+        cx.spawn_in(window, async move |this, cx| {
+            loop {
+                cx.background_executor().timer(Duration::from_millis(250)).await;
+                let commands = std::mem::take(&mut *inbox.lock());
+                if commands.is_empty() {
+                    continue;
+                }
+                let result = this.update_in(cx, |this, window, cx| {
+                    for command in commands {
+                        this.handle_ipc(command, window, cx);
+                    }
+                });
+                if result.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        // Refresh the engine status from the background task
+        cx.spawn(async move |this, cx| {
+            loop {
+                let response = cx
+                    .background_executor()
+                    .spawn(async {
+                        IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::GetEngineStatus)
+                    })
+                    .await;
+                let result = this.update(cx, |this, cx| match response {
+                    Ok(Some(IpcCommand::EngineStatus(status))) => this.set_engine_status(status, cx),
+                    _ => {
+                        this.engine_state = EngineState::Error("Background task is not running".to_string());
+                        cx.notify();
+                    }
+                });
+                if result.is_err() {
+                    break;
+                }
+
+                let response = cx
+                    .background_executor()
+                    .spawn(async {
+                        IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::GetTransfers)
+                    })
+                    .await;
+                if let Ok(Some(IpcCommand::Transfers(snapshot))) = response {
+                    let result = this.update(cx, |this, cx| {
+                        this.downloads_view.update(cx, |view, cx| view.set_transfers(snapshot, cx));
+                    });
+                    if result.is_err() {
+                        break;
+                    }
+                }
+                cx.background_executor().timer(Duration::from_secs(2)).await;
+            }
+        })
+        .detach();
+
         Self {
-            aria2c_status: Aria2cStatus::Stopped,
+            engine_state: EngineState::Stopped,
+            engine_info: EngineStatus::default(),
             view_mode: ViewMode::Downloads,
-            search_view: cx.new(|_| SearchView::new()),
-            downloads_view: cx.new(|_| DownloadsView::new()),
+            search_view: cx.new(|cx| SearchView::new(settings.clone(), window, cx)),
+            downloads_view: cx.new(|cx| DownloadsView::new(settings.clone(), window, cx)),
+            settings_view,
+            aria2_config_view,
+            endpoints_view,
+            schedule_view,
+            profiles_view,
+            categories_view,
+            actions_view,
+            history_view,
+            feeds_view,
+            settings,
+        }
+    }
+
+    fn on_settings_event<V>(&mut self, _: &Entity<V>, event: &SettingsEvent, window: &mut Window, cx: &mut Context<Self>) {
+        match event {
+            SettingsEvent::Saved(settings) => self.apply_settings(settings.clone(), window, cx),
+            SettingsEvent::Close => {
+                self.view_mode = ViewMode::Downloads;
+                cx.notify();
+            }
+        }
+    }
+
+    fn handle_ipc(&mut self, command: IpcCommand, window: &mut Window, cx: &mut Context<Self>) {
+        match command {
+            IpcCommand::Settings(settings) => {
+                if settings != self.settings {
+                    self.apply_settings(settings, window, cx);
+                }
+            }
+            IpcCommand::BatchAdded(result) => {
+                self.downloads_view.update(cx, |view, cx| view.batch_added(result, cx));
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_settings(&mut self, settings: Settings, window: &mut Window, cx: &mut Context<Self>) {
+        if settings.appearance.theme != self.settings.appearance.theme {
+            apply_theme(settings.appearance.theme, window, cx);
+        }
+
+        // Keep every editor working on the latest settings
+        self.settings_view.update(cx, |view, cx| view.set_settings(settings.clone(), window, cx));
+        self.aria2_config_view.update(cx, |view, cx| view.set_settings(settings.clone(), window, cx));
+        self.endpoints_view.update(cx, |view, cx| view.set_settings(settings.clone(), window, cx));
+        self.schedule_view.update(cx, |view, cx| view.set_settings(settings.clone(), window, cx));
+        self.profiles_view.update(cx, |view, cx| view.set_settings(settings.clone(), window, cx));
+        self.categories_view.update(cx, |view, cx| view.set_settings(settings.clone(), window, cx));
+        self.actions_view.update(cx, |view, cx| view.set_settings(settings.clone(), window, cx));
+        self.history_view.update(cx, |view, cx| view.set_settings(settings.clone(), cx));
+        self.feeds_view.update(cx, |view, cx| view.set_settings(settings.clone(), window, cx));
+        self.downloads_view.update(cx, |view, cx| view.set_settings(settings.clone(), cx));
+        self.search_view.update(cx, |view, cx| view.set_settings(settings.clone(), cx));
+
+        self.settings = settings;
+        cx.notify();
+    }
+
+    fn set_engine_status(&mut self, status: EngineStatus, cx: &mut Context<Self>) {
+        self.engine_state = match status.error {
+            Some(ref e) => EngineState::Error(e.clone()),
+            None if status.running => EngineState::Running,
+            None => EngineState::Stopped,
+        };
+        self.engine_info = status;
+        cx.notify();
+    }
+
+    /// Switch to another download engine
+    fn select_engine(&mut self, kind: EngineKind, window: &mut Window, cx: &mut Context<Self>) {
+        let mut settings = self.settings.clone();
+        settings.engine.kind = kind;
+
+        match persist_settings(settings) {
+            Ok(settings) => self.apply_settings(settings, window, cx),
+            Err(e) => {
+                self.engine_state = EngineState::Error(e.to_string());
+                cx.notify();
+            }
         }
     }
+
+    /// Switch the UI to another aria2 endpoint
+    fn select_endpoint(&mut self, name: String, window: &mut Window, cx: &mut Context<Self>) {
+        let mut settings = self.settings.clone();
+        settings.aria2.active_endpoint = name;
+
+        match persist_settings(settings) {
+            Ok(settings) => self.apply_settings(settings, window, cx),
+            Err(e) => {
+                self.engine_state = EngineState::Error(e.to_string());
+                cx.notify();
+            }
+        }
+    }
+
+    /// Apply another speed profile
+    fn select_profile(&mut self, name: String, window: &mut Window, cx: &mut Context<Self>) {
+        let mut settings = self.settings.clone();
+        settings.bandwidth.active_profile = name;
+
+        match persist_settings(settings) {
+            Ok(settings) => self.apply_settings(settings, window, cx),
+            Err(e) => {
+                self.engine_state = EngineState::Error(e.to_string());
+                cx.notify();
+            }
+        }
+    }
+
+    /// Ask the background task to start or stop the managed aria2 daemon.
+    /// The status itself is picked up by the next poll.
+    fn control_aria2(&mut self, command: IpcCommand, cx: &mut Context<Self>) {
+        match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(command) {
+            Ok(Some(IpcCommand::Error(e))) => self.engine_state = EngineState::Error(e),
+            Ok(_) => {}
+            Err(_) => {
+                self.engine_state = EngineState::Error("Background task is not running".to_string());
+            }
+        }
+        cx.notify();
+    }
+
+    /// Switch between the downloads and the history, reloading the history when it opens
+    fn toggle_history(&mut self, cx: &mut Context<Self>) {
+        if self.view_mode == ViewMode::History {
+            self.view_mode = ViewMode::Downloads;
+        } else {
+            self.history_view.update(cx, |view, cx| view.refresh(cx));
+            self.view_mode = ViewMode::History;
+        }
+        cx.notify();
+    }
+
+    fn open_settings(&mut self, page: SettingsPage, cx: &mut Context<Self>) {
+        self.settings_view.update(cx, |view, cx| view.set_page(page, cx));
+        self.view_mode = ViewMode::Settings;
+        cx.notify();
+    }
     
-    fn get_aria2c_icon(&self) -> IconName {
-        match self.aria2c_status {
-            Aria2cStatus::Running => IconName::Heart,
-            Aria2cStatus::Stopped => IconName::HeartOff,
-            Aria2cStatus::Error => IconName::CircleX,
+    fn get_engine_icon(&self) -> IconName {
+        match self.engine_state {
+            EngineState::Running => IconName::Heart,
+            EngineState::Stopped => IconName::HeartOff,
+            EngineState::Error(_) => IconName::CircleX,
         }
     }
     
-    fn get_aria2c_color(&self) -> Hsla {
-        match self.aria2c_status {
-            Aria2cStatus::Running => rgb(0x22c55e).into(), // green
-            Aria2cStatus::Stopped => rgb(0x6b7280).into(), // gray
-            Aria2cStatus::Error => rgb(0xef4444).into(),   // red
+    fn get_engine_color(&self) -> Hsla {
+        match self.engine_state {
+            EngineState::Running => rgb(0x22c55e).into(), // green
+            EngineState::Stopped => rgb(0x6b7280).into(), // gray
+            EngineState::Error(_) => rgb(0xef4444).into(), // red
         }
     }
     
-    fn get_aria2c_status_text(&self) -> &'static str {
-        match self.aria2c_status {
-            Aria2cStatus::Running => "Running",
-            Aria2cStatus::Stopped => "Stopped",
-            Aria2cStatus::Error => "Error",
+    fn get_engine_status_text(&self) -> &'static str {
+        match self.engine_state {
+            EngineState::Running => "Running",
+            EngineState::Stopped => "Stopped",
+            EngineState::Error(_) => "Error",
         }
     }
 }
@@ -86,8 +357,20 @@ impl BongApp {
 impl Render for BongApp {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let view = cx.entity();
-        let status = self.aria2c_status;
-        let status_color = self.get_aria2c_color();
+        let settings_view = view.clone();
+        let status_text = match self.engine_state {
+            EngineState::Error(ref e) => format!("Error: {}", e),
+            _ => self.get_engine_status_text().to_string(),
+        };
+        let info = self.engine_info.clone();
+        let in_bits = self.settings.appearance.speed_in_bits;
+        let engine_kind = self.settings.engine.kind;
+        let endpoints: Vec<String> = self.settings.aria2.endpoints.iter().map(|e| e.name.clone()).collect();
+        let active_endpoint = self.settings.aria2.active_endpoint.clone();
+        let profiles: Vec<String> = self.settings.bandwidth.profiles.iter().map(|p| p.name.clone()).collect();
+        let active_profile = self.settings.bandwidth.active_profile.clone();
+        let profile_view = view.clone();
+        let status_color = self.get_engine_color();
         
         div()
             .size_full()
@@ -126,54 +409,134 @@ impl Render for BongApp {
                                     .gap_2()
                                     .items_center()
                                     .child(
-                                        // Aria2c Status Dropdown
-                                        Button::new("aria2c-status")
-                                            .icon(self.get_aria2c_icon())
+                                        Button::new("history")
+                                            .label(if self.view_mode == ViewMode::History { "Downloads" } else { "History" })
+                                            .on_click(cx.listener(|this, _, _, cx| this.toggle_history(cx)))
+                                    )
+                                    .child(
+                                        // Speed profile Dropdown
+                                        Button::new("speed-profile")
+                                            .label(active_profile.clone())
+                                            .dropdown_menu_with_anchor(Corner::BottomRight, move |menu, window, _cx| {
+                                                let menu = menu.label("Speed profile").separator();
+                                                let menu = profiles.iter().fold(menu, |menu, name| {
+                                                    let selected = name.clone();
+                                                    let item = PopupMenuItem::new(name.clone())
+                                                        .on_click(window.listener_for(&profile_view, move |this, _, window, cx| {
+                                                            this.select_profile(selected.clone(), window, cx);
+                                                        }));
+                                                    menu.item(if *name == active_profile { item.icon(IconName::Check) } else { item })
+                                                });
+
+                                                menu
+                                                    .separator()
+                                                    .item(
+                                                        PopupMenuItem::new("Edit profiles")
+                                                            .icon(IconName::Settings)
+                                                            .on_click(window.listener_for(&profile_view, |this, _, _, cx| {
+                                                                this.view_mode = ViewMode::Profiles;
+                                                                cx.notify();
+                                                            }))
+                                                    )
+                                            })
+                                    )
+                                    .child(
+                                        // Engine status Dropdown
+                                        Button::new("engine-status")
+                                            .icon(self.get_engine_icon())
                                             .text_color(status_color)
                                             .dropdown_menu_with_anchor(Corner::BottomRight, move |menu, window, cx| {
-                                                menu
-                                                    .label("aria2c Status")
+                                                let menu = menu
+                                                    .label("Download engine")
                                                     .separator()
                                                     .item(
-                                                        PopupMenuItem::new(format!("Status: {}", match status {
-                                                            Aria2cStatus::Running => "Running",
-                                                            Aria2cStatus::Stopped => "Stopped",
-                                                            Aria2cStatus::Error => "Error",
-                                                        }))
+                                                        PopupMenuItem::new(format!("Status: {}", status_text))
+                                                            .disabled(true)
+                                                    )
+                                                    .item(
+                                                        PopupMenuItem::new(format!(
+                                                            "Downloads: {} active, {} waiting, {} stopped",
+                                                            info.stat.num_active,
+                                                            info.stat.num_waiting,
+                                                            info.stat.num_stopped,
+                                                        ))
                                                         .disabled(true)
                                                     )
                                                     .item(
-                                                        PopupMenuItem::new("Downloads: 0 active")
-                                                            .disabled(true)
+                                                        PopupMenuItem::new(format!(
+                                                            "Speed: {} down, {} up",
+                                                            format_speed(info.stat.download_speed, in_bits),
+                                                            format_speed(info.stat.upload_speed, in_bits),
+                                                        ))
+                                                        .disabled(true)
                                                     )
                                                     .item(
-                                                        PopupMenuItem::new("Port: 6800")
+                                                        PopupMenuItem::new(format!(
+                                                            "Version: {}",
+                                                            info.version.as_deref().unwrap_or("unknown"),
+                                                        ))
+                                                        .disabled(true)
+                                                    )
+                                                    .item(
+                                                        PopupMenuItem::new(format!("Endpoint: {}", info.endpoint))
                                                             .disabled(true)
                                                     )
                                                     .separator()
-                                                    .label("Settings")
+                                                    .label("Engine");
+
+                                                // One item per engine, the selected one marked
+                                                let menu = EngineKind::ALL.into_iter().fold(menu, |menu, kind| {
+                                                    let item = PopupMenuItem::new(kind.label())
+                                                        .on_click(window.listener_for(&view, move |this, _, window, cx| {
+                                                            this.select_engine(kind, window, cx);
+                                                        }));
+                                                    menu.item(if kind == engine_kind { item.icon(IconName::Check) } else { item })
+                                                });
+                                                if engine_kind != EngineKind::Aria2 {
+                                                    return menu;
+                                                }
+
+                                                // One item per endpoint, the active one marked
+                                                let menu = menu.separator().label("aria2 endpoints");
+                                                let menu = endpoints.iter().fold(menu, |menu, name| {
+                                                    let selected = name.clone();
+                                                    let item = PopupMenuItem::new(name.clone())
+                                                        .on_click(window.listener_for(&view, move |this, _, window, cx| {
+                                                            this.select_endpoint(selected.clone(), window, cx);
+                                                        }));
+                                                    menu.item(if *name == active_endpoint { item.icon(IconName::Check) } else { item })
+                                                });
+
+                                                menu
                                                     .item(
-                                                        PopupMenuItem::new("Start aria2c")
-                                                            .on_click(window.listener_for(&view, |this, _, window, cx| {
-                                                                println!("Start aria2c clicked");
-                                                                this.aria2c_status = Aria2cStatus::Running;
+                                                        PopupMenuItem::new("Manage endpoints")
+                                                            .icon(IconName::Globe)
+                                                            .on_click(window.listener_for(&view, |this, _, _, cx| {
+                                                                this.view_mode = ViewMode::Endpoints;
                                                                 cx.notify();
                                                             }))
                                                     )
+                                                    .separator()
+                                                    .label("aria2 daemon")
+                                                    .item(
+                                                        PopupMenuItem::new("Start aria2c")
+                                                            .on_click(window.listener_for(&view, |this, _, _, cx| {
+                                                                this.control_aria2(IpcCommand::StartAria2, cx);
+                                                            }))
+                                                    )
                                                     .item(
                                                         PopupMenuItem::new("Stop aria2c")
-                                                            .on_click(window.listener_for(&view, |this, _, window, cx| {
-                                                                println!("Stop aria2c clicked");
-                                                                this.aria2c_status = Aria2cStatus::Stopped;
-                                                                cx.notify();
+                                                            .on_click(window.listener_for(&view, |this, _, _, cx| {
+                                                                this.control_aria2(IpcCommand::StopAria2, cx);
                                                             }))
                                                     )
                                                     .item(
                                                         PopupMenuItem::new("Configuration")
                                                             .icon(IconName::Settings)
-                                                            .on_click(|_, window, cx| {
-                                                                println!("aria2c configuration clicked");
-                                                            })
+                                                            .on_click(window.listener_for(&view, |this, _, _, cx| {
+                                                                this.view_mode = ViewMode::Aria2Config;
+                                                                cx.notify();
+                                                            }))
                                                     )
                                             })
                                     )
@@ -181,45 +544,92 @@ impl Render for BongApp {
                                         // Settings Dropdown
                                         Button::new("settings")
                                             .icon(IconName::Settings)
-                                            .dropdown_menu_with_anchor(Corner::BottomRight, |menu, window, cx| {
+                                            .dropdown_menu_with_anchor(Corner::BottomRight, move |menu, window, cx| {
                                                 menu
                                                     .label("Settings")
                                                     .separator()
                                                     .item(
                                                         PopupMenuItem::new("General")
                                                             .icon(IconName::Settings)
-                                                            .on_click(|_, window, cx| {
-                                                                println!("General settings clicked");
-                                                            })
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.open_settings(SettingsPage::General, cx);
+                                                            }))
                                                     )
                                                     .item(
                                                         PopupMenuItem::new("Appearance")
                                                             .icon(IconName::Palette)
-                                                            .on_click(|_, window, cx| {
-                                                                println!("Appearance settings clicked");
-                                                            })
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.open_settings(SettingsPage::Appearance, cx);
+                                                            }))
                                                     )
                                                     .item(
                                                         PopupMenuItem::new("Downloads")
                                                             .icon(IconName::ArrowDown)
-                                                            .on_click(|_, window, cx| {
-                                                                println!("Download settings clicked");
-                                                            })
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.open_settings(SettingsPage::Downloads, cx);
+                                                            }))
+                                                    )
+                                                    .item(
+                                                        PopupMenuItem::new("Watch folders")
+                                                            .icon(IconName::Folder)
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.open_settings(SettingsPage::Watch, cx);
+                                                            }))
                                                     )
                                                     .item(
                                                         PopupMenuItem::new("Network")
                                                             .icon(IconName::Globe)
-                                                            .on_click(|_, window, cx| {
-                                                                println!("Network settings clicked");
-                                                            })
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.open_settings(SettingsPage::Network, cx);
+                                                            }))
+                                                    )
+                                                    .item(
+                                                        PopupMenuItem::new("HTTP API")
+                                                            .icon(IconName::SquareTerminal)
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.open_settings(SettingsPage::Api, cx);
+                                                            }))
+                                                    )
+                                                    .item(
+                                                        PopupMenuItem::new("Schedule")
+                                                            .icon(IconName::Calendar)
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.view_mode = ViewMode::Schedule;
+                                                                cx.notify();
+                                                            }))
+                                                    )
+                                                    .item(
+                                                        PopupMenuItem::new("Categories")
+                                                            .icon(IconName::Folder)
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.view_mode = ViewMode::Categories;
+                                                                cx.notify();
+                                                            }))
+                                                    )
+                                                    .item(
+                                                        PopupMenuItem::new("Feeds")
+                                                            .icon(IconName::Globe)
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.feeds_view.update(cx, |view, cx| view.refresh(cx));
+                                                                this.view_mode = ViewMode::Feeds;
+                                                                cx.notify();
+                                                            }))
+                                                    )
+                                                    .item(
+                                                        PopupMenuItem::new("Post-download actions")
+                                                            .icon(IconName::CircleCheck)
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.view_mode = ViewMode::Actions;
+                                                                cx.notify();
+                                                            }))
                                                     )
                                                     .separator()
                                                     .item(
                                                         PopupMenuItem::new("About")
                                                             .icon(IconName::Info)
-                                                            .on_click(|_, window, cx| {
-                                                                println!("About clicked");
-                                                            })
+                                                            .on_click(window.listener_for(&settings_view, |this, _, _, cx| {
+                                                                this.open_settings(SettingsPage::About, cx);
+                                                            }))
                                                     )
                                             })
                                     )
@@ -231,7 +641,32 @@ impl Render for BongApp {
                 match self.view_mode {
                     ViewMode::Search => self.search_view.clone().into_any_element(),
                     ViewMode::Downloads => self.downloads_view.clone().into_any_element(),
+                    ViewMode::Settings => self.settings_view.clone().into_any_element(),
+                    ViewMode::Aria2Config => self.aria2_config_view.clone().into_any_element(),
+                    ViewMode::Endpoints => self.endpoints_view.clone().into_any_element(),
+                    ViewMode::Schedule => self.schedule_view.clone().into_any_element(),
+                    ViewMode::Profiles => self.profiles_view.clone().into_any_element(),
+                    ViewMode::Categories => self.categories_view.clone().into_any_element(),
+                    ViewMode::Actions => self.actions_view.clone().into_any_element(),
+                    ViewMode::History => self.history_view.clone().into_any_element(),
+                    ViewMode::Feeds => self.feeds_view.clone().into_any_element(),
                 }
             )
     }
 }
+
+/// Ask the background task for the current settings, reading the file if it isn't running
+fn fetch_settings() -> Settings {
+    match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::GetSettings) {
+        Ok(Some(IpcCommand::Settings(settings))) => settings,
+        _ => Settings::load().unwrap_or_default(),
+    }
+}
+
+fn apply_theme(theme: ThemePreference, window: &mut Window, cx: &mut App) {
+    match theme {
+        ThemePreference::System => Theme::sync_system_appearance(Some(window), cx),
+        ThemePreference::Light => Theme::change(ThemeMode::Light, Some(window), cx),
+        ThemePreference::Dark => Theme::change(ThemeMode::Dark, Some(window), cx),
+    }
+}
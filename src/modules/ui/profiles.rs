@@ -0,0 +1,270 @@
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::input::{Input, InputState};
+use crate::modules::settings::{BandwidthSettings, Settings, SpeedProfile};
+use super::settings::{field, parse_field, persist_settings};
+use super::SettingsEvent;
+
+/// Editor for the bandwidth speed profiles
+pub struct ProfilesView {
+    settings: Settings,
+    bandwidth: BandwidthSettings,
+    selected: usize,
+    name: Entity<InputState>,
+    /// Limits in KB/s, 0 for unlimited
+    download_limit: Entity<InputState>,
+    upload_limit: Entity<InputState>,
+    per_download_limit: Entity<InputState>,
+    per_upload_limit: Entity<InputState>,
+    /// Result of the last action, (is_error, message)
+    message: Option<(bool, String)>,
+}
+
+impl EventEmitter<SettingsEvent> for ProfilesView {}
+
+impl ProfilesView {
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let mut view = Self {
+            bandwidth: settings.bandwidth.clone(),
+            selected: 0,
+            name: cx.new(|cx| InputState::new(window, cx)),
+            download_limit: cx.new(|cx| InputState::new(window, cx)),
+            upload_limit: cx.new(|cx| InputState::new(window, cx)),
+            per_download_limit: cx.new(|cx| InputState::new(window, cx)),
+            per_upload_limit: cx.new(|cx| InputState::new(window, cx)),
+            settings,
+            message: None,
+        };
+        view.load_selected(window, cx);
+        view
+    }
+
+    /// Replace the edited settings, e.g. after a change broadcast by the background task
+    pub fn set_settings(&mut self, settings: Settings, window: &mut Window, cx: &mut Context<Self>) {
+        self.bandwidth = settings.bandwidth.clone();
+        self.settings = settings;
+        self.selected = self.selected.min(self.bandwidth.profiles.len().saturating_sub(1));
+        self.load_selected(window, cx);
+        cx.notify();
+    }
+
+    /// Fill the form with the selected profile
+    fn load_selected(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(profile) = self.bandwidth.profiles.get(self.selected) else {
+            return;
+        };
+
+        let values = [
+            (&self.name, profile.name.clone()),
+            (&self.download_limit, (profile.download_limit / 1024).to_string()),
+            (&self.upload_limit, (profile.upload_limit / 1024).to_string()),
+            (&self.per_download_limit, (profile.per_download_limit / 1024).to_string()),
+            (&self.per_upload_limit, (profile.per_upload_limit / 1024).to_string()),
+        ];
+        for (input, value) in values {
+            input.update(cx, |state, cx| state.set_value(value, window, cx));
+        }
+    }
+
+    /// Write the form back into the selected profile
+    fn store_selected(&mut self, cx: &App) -> anyhow::Result<()> {
+        let name = self.name.read(cx).value().trim().to_string();
+        let download_limit: u64 = parse_field(&self.download_limit, "Download limit", cx)?;
+        let upload_limit: u64 = parse_field(&self.upload_limit, "Upload limit", cx)?;
+        let per_download_limit: u64 = parse_field(&self.per_download_limit, "Per download limit", cx)?;
+        let per_upload_limit: u64 = parse_field(&self.per_upload_limit, "Per upload limit", cx)?;
+
+        let Some(profile) = self.bandwidth.profiles.get_mut(self.selected) else {
+            return Ok(());
+        };
+        if profile.name == self.bandwidth.active_profile {
+            self.bandwidth.active_profile = name.clone();
+        }
+        *profile = SpeedProfile {
+            name,
+            download_limit: download_limit * 1024,
+            upload_limit: upload_limit * 1024,
+            per_download_limit: per_download_limit * 1024,
+            per_upload_limit: per_upload_limit * 1024,
+        };
+        profile.validate()
+    }
+
+    fn select(&mut self, index: usize, window: &mut Window, cx: &mut Context<Self>) {
+        match self.store_selected(cx) {
+            Ok(()) => {
+                self.selected = index;
+                self.message = None;
+                self.load_selected(window, cx);
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+
+    fn add(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let name = (1..)
+            .map(|i| format!("Profile {}", i))
+            .find(|name| !self.bandwidth.profiles.iter().any(|profile| &profile.name == name))
+            .unwrap();
+        self.bandwidth.profiles.push(SpeedProfile {
+            name,
+            ..Default::default()
+        });
+        self.select(self.bandwidth.profiles.len() - 1, window, cx);
+    }
+
+    fn remove(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        // The active profile can't be removed, switch to another one first
+        let removable = self
+            .bandwidth
+            .profiles
+            .get(self.selected)
+            .is_some_and(|profile| profile.name != self.bandwidth.active_profile);
+        if removable {
+            self.bandwidth.profiles.remove(self.selected);
+            self.selected = self.selected.saturating_sub(1);
+            self.load_selected(window, cx);
+            cx.notify();
+        }
+    }
+
+    fn save(&mut self, cx: &mut Context<Self>) {
+        let result = self.store_selected(cx).and_then(|_| {
+            let mut settings = self.settings.clone();
+            settings.bandwidth = self.bandwidth.clone();
+            persist_settings(settings)
+        });
+
+        match result {
+            Ok(settings) => {
+                self.settings = settings.clone();
+                self.message = Some((false, "Speed profiles saved".to_string()));
+                cx.emit(SettingsEvent::Saved(settings));
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+}
+
+impl Render for ProfilesView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let active = self.bandwidth.active_profile.clone();
+        let selected_active = self
+            .bandwidth
+            .profiles
+            .get(self.selected)
+            .is_some_and(|profile| profile.name == active);
+
+        div()
+            .flex()
+            .size_full()
+            .child(
+                // Profile list
+                div()
+                    .flex()
+                    .flex_col()
+                    .w(px(220.0))
+                    .p_4()
+                    .gap_1()
+                    .border_r_1()
+                    .border_color(rgb(0x374151))
+                    .children(self.bandwidth.profiles.iter().enumerate().map(|(index, profile)| {
+                        let label = if profile.name == active {
+                            format!("{} (active)", profile.name)
+                        } else {
+                            profile.name.clone()
+                        };
+                        let button = Button::new(("speed-profile", index))
+                            .label(label)
+                            .w_full()
+                            .on_click(cx.listener(move |this, _, window, cx| this.select(index, window, cx)));
+                        if index == self.selected { button.primary() } else { button.ghost() }
+                    }))
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .mt_2()
+                            .child(
+                                Button::new("profile-add")
+                                    .label("Add")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, window, cx| this.add(window, cx)))
+                            )
+                            .child(
+                                Button::new("profile-remove")
+                                    .label("Remove")
+                                    .ghost()
+                                    .disabled(selected_active)
+                                    .on_click(cx.listener(|this, _, window, cx| this.remove(window, cx)))
+                            )
+                    )
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .p_6()
+                    .gap_4()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0xffffff))
+                            .child("Speed Profiles")
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(rgb(0x9ca3af))
+                            .child("All limits are in KB/s, 0 means unlimited.")
+                    )
+                    .child(field("Name", "Shown in the tray and title bar menus", Input::new(&self.name)))
+                    .child(
+                        div()
+                            .flex()
+                            .gap_6()
+                            .child(field("Download limit", "Combined speed of all downloads", Input::new(&self.download_limit)))
+                            .child(field("Upload limit", "Combined upload speed, e.g. for seeding", Input::new(&self.upload_limit)))
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .gap_6()
+                            .child(field("Per download", "Download speed of each download", Input::new(&self.per_download_limit)))
+                            .child(field("Per upload", "Upload speed of each download", Input::new(&self.per_upload_limit)))
+                    )
+                    .child(
+                        // Footer with save / close
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .mt_4()
+                            .child(
+                                Button::new("profiles-save")
+                                    .label("Save")
+                                    .primary()
+                                    .on_click(cx.listener(|this, _, _, cx| this.save(cx)))
+                            )
+                            .child(
+                                Button::new("profiles-close")
+                                    .label("Close")
+                                    .ghost()
+                                    .on_click(cx.listener(|_, _, _, cx| cx.emit(SettingsEvent::Close)))
+                            )
+                            .when_some(self.message.clone(), |this, (is_error, message)| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                        .child(message)
+                                )
+                            })
+                    )
+            )
+    }
+}
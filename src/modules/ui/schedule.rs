@@ -0,0 +1,373 @@
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
+use gpui_component::input::{Input, InputState};
+use crate::modules::settings::{Day, ScheduleAction, ScheduleSettings, ScheduleWindow, Settings};
+use super::settings::{field, parse_field, persist_settings};
+use super::SettingsEvent;
+use std::rc::Rc;
+
+/// Limit offered when switching an action to "Limit", in KB/s
+const DEFAULT_LIMIT_KB: u64 = 200;
+
+/// Editor for the weekly schedule windows
+pub struct ScheduleView {
+    settings: Settings,
+    schedule: ScheduleSettings,
+    selected: usize,
+    name: Entity<InputState>,
+    start: Entity<InputState>,
+    end: Entity<InputState>,
+    /// Limit of the selected window in KB/s
+    limit: Entity<InputState>,
+    /// Limit outside all windows in KB/s
+    default_limit: Entity<InputState>,
+    /// Result of the last action, (is_error, message)
+    message: Option<(bool, String)>,
+}
+
+impl EventEmitter<SettingsEvent> for ScheduleView {}
+
+impl ScheduleView {
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let mut view = Self {
+            schedule: settings.schedule.clone(),
+            selected: 0,
+            name: cx.new(|cx| InputState::new(window, cx)),
+            start: cx.new(|cx| InputState::new(window, cx).placeholder("09:00")),
+            end: cx.new(|cx| InputState::new(window, cx).placeholder("18:00")),
+            limit: cx.new(|cx| InputState::new(window, cx)),
+            default_limit: cx.new(|cx| InputState::new(window, cx)),
+            settings,
+            message: None,
+        };
+        view.load_selected(window, cx);
+        view
+    }
+
+    /// Replace the edited settings, e.g. after a change broadcast by the background task
+    pub fn set_settings(&mut self, settings: Settings, window: &mut Window, cx: &mut Context<Self>) {
+        self.schedule = settings.schedule.clone();
+        self.settings = settings;
+        self.selected = self.selected.min(self.schedule.windows.len().saturating_sub(1));
+        self.load_selected(window, cx);
+        cx.notify();
+    }
+
+    /// Fill the form with the selected window
+    fn load_selected(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let default_limit = limit_kb(self.schedule.default_action).to_string();
+        self.default_limit.update(cx, |state, cx| state.set_value(default_limit, window, cx));
+
+        let Some(schedule_window) = self.schedule.windows.get(self.selected) else {
+            return;
+        };
+        let values = [
+            (&self.name, schedule_window.name.clone()),
+            (&self.start, schedule_window.start.clone()),
+            (&self.end, schedule_window.end.clone()),
+            (&self.limit, limit_kb(schedule_window.action).to_string()),
+        ];
+        for (input, value) in values {
+            input.update(cx, |state, cx| state.set_value(value, window, cx));
+        }
+    }
+
+    /// Write the form back into the selected window
+    fn store_selected(&mut self, cx: &App) -> anyhow::Result<()> {
+        if let ScheduleAction::Limit(_) = self.schedule.default_action {
+            let limit: u64 = parse_field(&self.default_limit, "Default limit", cx)?;
+            self.schedule.default_action = ScheduleAction::Limit(limit * 1024);
+        }
+
+        let name = self.name.read(cx).value().trim().to_string();
+        let start = self.start.read(cx).value().trim().to_string();
+        let end = self.end.read(cx).value().trim().to_string();
+        let limit: u64 = parse_field(&self.limit, "Limit", cx)?;
+
+        let Some(schedule_window) = self.schedule.windows.get_mut(self.selected) else {
+            return Ok(());
+        };
+        schedule_window.name = name;
+        schedule_window.start = start;
+        schedule_window.end = end;
+        if let ScheduleAction::Limit(_) = schedule_window.action {
+            schedule_window.action = ScheduleAction::Limit(limit * 1024);
+        }
+        schedule_window.validate()
+    }
+
+    fn select(&mut self, index: usize, window: &mut Window, cx: &mut Context<Self>) {
+        match self.store_selected(cx) {
+            Ok(()) => {
+                self.selected = index;
+                self.message = None;
+                self.load_selected(window, cx);
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+
+    fn add(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let name = (1..)
+            .map(|i| format!("Window {}", i))
+            .find(|name| !self.schedule.windows.iter().any(|w| &w.name == name))
+            .unwrap();
+        self.schedule.windows.push(ScheduleWindow {
+            name,
+            ..Default::default()
+        });
+        self.select(self.schedule.windows.len() - 1, window, cx);
+    }
+
+    fn remove(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.selected < self.schedule.windows.len() {
+            self.schedule.windows.remove(self.selected);
+            self.selected = self.selected.saturating_sub(1);
+            self.load_selected(window, cx);
+            cx.notify();
+        }
+    }
+
+    fn set_action(&mut self, action: ScheduleAction, cx: &mut Context<Self>) {
+        if let Some(schedule_window) = self.schedule.windows.get_mut(self.selected) {
+            schedule_window.action = action;
+        }
+        cx.notify();
+    }
+
+    fn toggle_day(&mut self, day: Day, checked: bool, cx: &mut Context<Self>) {
+        if let Some(schedule_window) = self.schedule.windows.get_mut(self.selected) {
+            schedule_window.days.retain(|d| *d != day);
+            if checked {
+                schedule_window.days.push(day);
+                schedule_window.days.sort_by_key(|d| d.index());
+            }
+        }
+        cx.notify();
+    }
+
+    fn save(&mut self, cx: &mut Context<Self>) {
+        let result = self.store_selected(cx).and_then(|_| {
+            let mut settings = self.settings.clone();
+            settings.schedule = self.schedule.clone();
+            persist_settings(settings)
+        });
+
+        match result {
+            Ok(settings) => {
+                self.settings = settings.clone();
+                self.message = Some((false, "Schedule saved".to_string()));
+                cx.emit(SettingsEvent::Saved(settings));
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+}
+
+impl Render for ScheduleView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let selected = self.schedule.windows.get(self.selected).cloned();
+        let default_action = self.schedule.default_action;
+
+        let form = match selected {
+            Some(selected) => div()
+                .flex()
+                .flex_col()
+                .gap_4()
+                .child(
+                    Checkbox::new("window-enabled")
+                        .label("Window enabled")
+                        .checked(selected.enabled)
+                        .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                            if let Some(schedule_window) = this.schedule.windows.get_mut(this.selected) {
+                                schedule_window.enabled = *checked;
+                            }
+                            cx.notify();
+                        }))
+                )
+                .child(field("Name", "Shown in the background task log", Input::new(&self.name)))
+                .child(field(
+                    "Days",
+                    "Days the window starts on",
+                    div()
+                        .flex()
+                        .gap_3()
+                        .children(Day::ALL.into_iter().map(|day| {
+                            Checkbox::new(("window-day", day.index() as usize))
+                                .label(day.as_str())
+                                .checked(selected.days.contains(&day))
+                                .on_click(cx.listener(move |this, checked: &bool, _, cx| {
+                                    this.toggle_day(day, *checked, cx);
+                                }))
+                        })),
+                ))
+                .child(
+                    div()
+                        .flex()
+                        .gap_4()
+                        .child(field("Start", "24 hour time, e.g. 09:00", Input::new(&self.start)))
+                        .child(field("End", "Before the start to run past midnight", Input::new(&self.end)))
+                )
+                .child(field(
+                    "Action",
+                    "What the queue does during the window",
+                    action_buttons("window-action", selected.action, cx.listener(|this, action: &ScheduleAction, _, cx| {
+                        this.set_action(*action, cx);
+                    })),
+                ))
+                .when(matches!(selected.action, ScheduleAction::Limit(_)), |this| {
+                    this.child(field("Limit", "Download limit in KB/s", Input::new(&self.limit)))
+                }),
+            None => div()
+                .text_sm()
+                .text_color(rgb(0x9ca3af))
+                .child("No windows yet. Add one to start scheduling."),
+        };
+
+        div()
+            .flex()
+            .size_full()
+            .child(
+                // Window list
+                div()
+                    .flex()
+                    .flex_col()
+                    .w(px(220.0))
+                    .p_4()
+                    .gap_1()
+                    .border_r_1()
+                    .border_color(rgb(0x374151))
+                    .children(self.schedule.windows.iter().enumerate().map(|(index, schedule_window)| {
+                        let label = if schedule_window.enabled {
+                            schedule_window.name.clone()
+                        } else {
+                            format!("{} (off)", schedule_window.name)
+                        };
+                        let button = Button::new(("schedule-window", index))
+                            .label(label)
+                            .w_full()
+                            .on_click(cx.listener(move |this, _, window, cx| this.select(index, window, cx)));
+                        if index == self.selected { button.primary() } else { button.ghost() }
+                    }))
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .mt_2()
+                            .child(
+                                Button::new("schedule-add")
+                                    .label("Add")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, window, cx| this.add(window, cx)))
+                            )
+                            .child(
+                                Button::new("schedule-remove")
+                                    .label("Remove")
+                                    .ghost()
+                                    .on_click(cx.listener(|this, _, window, cx| this.remove(window, cx)))
+                            )
+                    )
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .p_6()
+                    .gap_4()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0xffffff))
+                            .child("Schedule")
+                    )
+                    .child(
+                        Checkbox::new("schedule-enabled")
+                            .label("Start, pause and throttle downloads on a weekly schedule")
+                            .checked(self.schedule.enabled)
+                            .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                this.schedule.enabled = *checked;
+                                cx.notify();
+                            }))
+                    )
+                    .child(field(
+                        "Outside windows",
+                        "Applied when no window matches, the first matching window wins",
+                        action_buttons("default-action", default_action, cx.listener(|this, action: &ScheduleAction, _, cx| {
+                            this.schedule.default_action = *action;
+                            cx.notify();
+                        })),
+                    ))
+                    .when(matches!(default_action, ScheduleAction::Limit(_)), |this| {
+                        this.child(field("Default limit", "Download limit in KB/s", Input::new(&self.default_limit)))
+                    })
+                    .child(form)
+                    .child(
+                        // Footer with save / close
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .mt_4()
+                            .child(
+                                Button::new("schedule-save")
+                                    .label("Save")
+                                    .primary()
+                                    .on_click(cx.listener(|this, _, _, cx| this.save(cx)))
+                            )
+                            .child(
+                                Button::new("schedule-close")
+                                    .label("Close")
+                                    .ghost()
+                                    .on_click(cx.listener(|_, _, _, cx| cx.emit(SettingsEvent::Close)))
+                            )
+                            .when_some(self.message.clone(), |this, (is_error, message)| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                        .child(message)
+                                )
+                            })
+                    )
+            )
+    }
+}
+
+/// Run / Pause / Limit buttons with the current action highlighted
+fn action_buttons(
+    id: &'static str,
+    current: ScheduleAction,
+    on_select: impl Fn(&ScheduleAction, &mut Window, &mut App) + 'static,
+) -> Div {
+    let on_select = Rc::new(on_select);
+    let limit = match current {
+        ScheduleAction::Limit(limit) => ScheduleAction::Limit(limit),
+        _ => ScheduleAction::Limit(DEFAULT_LIMIT_KB * 1024),
+    };
+    let choices = [("Run", ScheduleAction::Run), ("Pause", ScheduleAction::Pause), ("Limit", limit)];
+
+    div()
+        .flex()
+        .gap_2()
+        .children(choices.into_iter().enumerate().map(|(index, (label, action))| {
+            let on_select = on_select.clone();
+            let button = Button::new((id, index))
+                .label(label)
+                .on_click(move |_, window, cx| on_select(&action, window, cx));
+            let active = std::mem::discriminant(&action) == std::mem::discriminant(&current);
+            if active { button.primary() } else { button.ghost() }
+        }))
+}
+
+/// Limit of an action in KB/s, or the default for actions without one
+fn limit_kb(action: ScheduleAction) -> u64 {
+    match action {
+        ScheduleAction::Limit(limit) => limit / 1024,
+        _ => DEFAULT_LIMIT_KB,
+    }
+}
@@ -1,17 +1,166 @@
 use gpui::*;
-use gpui_component::button::Button;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::input::{Input, InputState};
+use crate::modules::engine::{probe_video, AddRequest, VideoFormat, VideoInfo};
+use crate::modules::format::{format_bytes, format_duration};
+use crate::modules::ipc::{IpcClient, IpcCommand, BACKGROUND_IPC_PORT};
+use crate::modules::settings::Settings;
 
-/// Search view component
-pub struct SearchView {}
+/// Search view component, looks up the formats of video pages with yt-dlp
+pub struct SearchView {
+    settings: Settings,
+    /// URL of a video page
+    url: Entity<InputState>,
+    /// Page the listed formats belong to
+    video: Option<(String, VideoInfo)>,
+    /// yt-dlp is reading the page
+    probing: bool,
+    /// Result of the last lookup or download, (is_error, message)
+    message: Option<(bool, String)>,
+}
 
 impl SearchView {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        Self {
+            settings,
+            url: cx.new(|cx| InputState::new(window, cx).placeholder("https://www.youtube.com/watch?v=...")),
+            video: None,
+            probing: false,
+            message: None,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: Settings, cx: &mut Context<Self>) {
+        self.settings = settings;
+        cx.notify();
+    }
+
+    /// List the formats of the page in the form, yt-dlp takes a few seconds
+    fn find_formats(&mut self, cx: &mut Context<Self>) {
+        let url = self.url.read(cx).value().trim().to_string();
+        if url.is_empty() {
+            self.message = Some((true, "Enter the URL of a video page".to_string()));
+            cx.notify();
+            return;
+        }
+
+        self.probing = true;
+        self.video = None;
+        self.message = None;
+        cx.notify();
+
+        let settings = self.settings.clone();
+        cx.spawn(async move |this, cx| {
+            let page = url.clone();
+            let result = cx
+                .background_executor()
+                .spawn(async move { probe_video(&settings, &page) })
+                .await;
+            let _ = this.update(cx, |this, cx| {
+                this.probing = false;
+                match result {
+                    Ok(video) => this.video = Some((url, video)),
+                    Err(e) => this.message = Some((true, e.to_string())),
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    /// Hand the listed page to yt-dlp in the given format, the default one when None
+    fn download(&mut self, format: Option<String>, window: &mut Window, cx: &mut Context<Self>) {
+        let Some((ref url, _)) = self.video else {
+            return;
+        };
+
+        let request = AddRequest {
+            format: Some(format.unwrap_or_else(|| self.settings.video.format.clone())),
+            ..AddRequest::new(url.clone())
+        };
+        self.message = match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::AddDownload(request)) {
+            Ok(Some(IpcCommand::DownloadAdded(_))) => {
+                self.url.update(cx, |state, cx| state.set_value("", window, cx));
+                self.video = None;
+                Some((false, "Video added to the downloads".to_string()))
+            }
+            Ok(Some(IpcCommand::Error(e))) => Some((true, e)),
+            Ok(_) => Some((true, "Unexpected answer from the background task".to_string())),
+            Err(_) => Some((true, "Background task is not running".to_string())),
+        };
+        cx.notify();
     }
 }
 
 impl Render for SearchView {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let results = match self.video {
+            Some((_, ref video)) => {
+                let mut heading = video.title.clone();
+                if let Some(duration) = video.duration {
+                    heading.push_str(&format!(" ({})", format_duration(duration)));
+                }
+
+                div()
+                    .flex()
+                    .flex_1()
+                    .flex_col()
+                    .px_6()
+                    .gap_2()
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .text_base()
+                                    .font_weight(FontWeight::SEMIBOLD)
+                                    .text_color(rgb(0xffffff))
+                                    .child(heading)
+                            )
+                            .child(
+                                Button::new("video-default")
+                                    .label("Download best")
+                                    .primary()
+                                    .on_click(cx.listener(|this, _, window, cx| this.download(None, window, cx)))
+                            )
+                    )
+                    // Best formats first
+                    .children(video.formats.iter().rev().enumerate().map(|(index, format)| {
+                        let selector = format.selector();
+                        format_row(format).child(
+                            Button::new(("video-format", index))
+                                .label("Download")
+                                .ghost()
+                                .on_click(cx.listener(move |this, _, window, cx| {
+                                    this.download(Some(selector.clone()), window, cx);
+                                }))
+                        )
+                    }))
+            }
+            None => div()
+                .flex()
+                .flex_1()
+                .flex_col()
+                .items_center()
+                .justify_center()
+                .gap_3()
+                .child(
+                    div()
+                        .text_base()
+                        .text_color(rgb(0x6b7280))
+                        .child(if self.probing { "Looking up formats..." } else { "No video selected yet" })
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x4b5563))
+                        .child("Paste a video page URL to pick a format")
+                ),
+        };
+
         div()
             .flex()
             .flex_col()
@@ -34,7 +183,7 @@ impl Render for SearchView {
                         div()
                             .text_sm()
                             .text_color(rgb(0x9ca3af))
-                            .child("Search for torrents, videos, and other content")
+                            .child("Find the formats of videos, downloaded with yt-dlp")
                     )
                     .child(
                         // Search input
@@ -42,58 +191,63 @@ impl Render for SearchView {
                             .flex()
                             .gap_2()
                             .mt_4()
-                            .child(
-                                // Search input placeholder
-                                div()
-                                    .flex_1()
-                                    .px_4()
-                                    .py_3()
-                                    .bg(rgb(0x1f2937))
-                                    .border_1()
-                                    .border_color(rgb(0x374151))
-                                    .rounded(px(8.0))
-                                    .child(
-                                        div()
-                                            .text_sm()
-                                            .text_color(rgb(0x6b7280))
-                                            .child("Enter search query...")
-                                    )
-                            )
+                            .child(div().flex_1().child(Input::new(&self.url)))
                             .child(
                                 Button::new("search-btn")
-                                    .child("Search")
-                                    .bg(rgb(0x3b82f6))
-                                    .text_color(rgb(0xffffff))
-                                    .px(px(24.0))
-                                    .py(px(10.0))
-                                    .rounded(px(8.0))
-                                    .on_click(|_, _window, _cx| {
-                                        println!("Search button clicked");
-                                    })
+                                    .label("Find formats")
+                                    .primary()
+                                    .disabled(self.probing)
+                                    .on_click(cx.listener(|this, _, _, cx| this.find_formats(cx)))
                             )
                     )
+                    .when_some(self.message.clone(), |this, (is_error, message)| {
+                        this.child(
+                            div()
+                                .text_sm()
+                                .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                .child(message)
+                        )
+                    })
             )
-            .child(
-                // Search results placeholder
-                div()
-                    .flex()
-                    .flex_1()
-                    .flex_col()
-                    .items_center()
-                    .justify_center()
-                    .gap_3()
-                    .child(
-                        div()
-                            .text_base()
-                            .text_color(rgb(0x6b7280))
-                            .child("No search results yet")
-                    )
-                    .child(
-                        div()
-                            .text_xs()
-                            .text_color(rgb(0x4b5563))
-                            .child("Enter a search term to get started")
-                    )
-            )
+            .child(results)
+    }
+}
+
+/// One format of a video, the caller adds its button
+fn format_row(format: &VideoFormat) -> Div {
+    let kind = match (format.has_video, format.has_audio) {
+        (true, true) => "video and audio",
+        (true, false) => "video, best audio added",
+        (false, true) => "audio only",
+        (false, false) => "unknown",
+    };
+    let mut details = format!("{} - {}", format.ext, kind);
+    if let Some(size) = format.filesize {
+        details.push_str(&format!(" - {}", format_bytes(size)));
     }
+
+    div()
+        .flex()
+        .items_center()
+        .gap_3()
+        .p_3()
+        .bg(rgb(0x1f2937))
+        .rounded(px(8.0))
+        .border_1()
+        .border_color(rgb(0x374151))
+        .child(
+            div()
+                .w(px(160.0))
+                .text_sm()
+                .font_weight(FontWeight::SEMIBOLD)
+                .text_color(rgb(0xffffff))
+                .child(format!("{} {}", format.resolution, format.note).trim().to_string())
+        )
+        .child(
+            div()
+                .flex_1()
+                .text_xs()
+                .text_color(rgb(0x9ca3af))
+                .child(details)
+        )
 }
@@ -0,0 +1,742 @@
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::checkbox::Checkbox;
+use gpui_component::input::{Input, InputState};
+use crate::modules::ipc::{IpcClient, IpcCommand, BACKGROUND_IPC_PORT};
+use crate::modules::settings::{generate_token, EngineKind, Settings, ThemePreference, WatchFolder, ADDED_DIR, FAILED_DIR};
+use std::path::PathBuf;
+
+/// Pages reachable from the Settings menu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SettingsPage {
+    General,
+    Appearance,
+    Downloads,
+    Watch,
+    Network,
+    Api,
+    About,
+}
+
+impl SettingsPage {
+    const ALL: [SettingsPage; 7] = [
+        SettingsPage::General,
+        SettingsPage::Appearance,
+        SettingsPage::Downloads,
+        SettingsPage::Watch,
+        SettingsPage::Network,
+        SettingsPage::Api,
+        SettingsPage::About,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            SettingsPage::General => "General",
+            SettingsPage::Appearance => "Appearance",
+            SettingsPage::Downloads => "Downloads",
+            SettingsPage::Watch => "Watch folders",
+            SettingsPage::Network => "Network",
+            SettingsPage::Api => "HTTP API",
+            SettingsPage::About => "About",
+        }
+    }
+}
+
+/// Events emitted by the settings view
+pub enum SettingsEvent {
+    /// Settings were saved successfully
+    Saved(Settings),
+    /// The user left the settings view
+    Close,
+}
+
+/// Settings view component
+pub struct SettingsView {
+    page: SettingsPage,
+    settings: Settings,
+    download_dir: Entity<InputState>,
+    max_concurrent: Entity<InputState>,
+    connections: Entity<InputState>,
+    min_segment_size: Entity<InputState>,
+    max_retries: Entity<InputState>,
+    video_binary: Entity<InputState>,
+    video_format: Entity<InputState>,
+    /// Comma separated video sites
+    video_hosts: Entity<InputState>,
+    /// Path of a new watch folder
+    watch_path: Entity<InputState>,
+    /// Category of a new watch folder
+    watch_category: Option<String>,
+    rpc_port: Entity<InputState>,
+    proxy: Entity<InputState>,
+    user_agent: Entity<InputState>,
+    timeout: Entity<InputState>,
+    api_port: Entity<InputState>,
+    api_token: Entity<InputState>,
+    /// Result of the last save, (is_error, message)
+    message: Option<(bool, String)>,
+}
+
+impl EventEmitter<SettingsEvent> for SettingsView {}
+
+impl SettingsView {
+    pub fn new(settings: Settings, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let download_dir = settings.downloads.download_dir.display().to_string();
+        let max_concurrent = settings.downloads.max_concurrent.to_string();
+        let connections = settings.engine.connections.to_string();
+        let min_segment_size = settings.engine.min_segment_size.clone();
+        let max_retries = settings.engine.max_retries.to_string();
+        let video_binary = settings.video.binary.display().to_string();
+        let video_format = settings.video.format.clone();
+        let video_hosts = settings.video.hosts.join(", ");
+        let rpc_port = settings.network.rpc_port.to_string();
+        let proxy = settings.network.proxy.clone();
+        let user_agent = settings.network.user_agent.clone();
+        let timeout = settings.network.timeout_secs.to_string();
+        let api_port = settings.api.port.to_string();
+        let api_token = settings.api.token.clone();
+
+        Self {
+            page: SettingsPage::General,
+            download_dir: cx.new(|cx| InputState::new(window, cx).default_value(download_dir)),
+            max_concurrent: cx.new(|cx| InputState::new(window, cx).default_value(max_concurrent)),
+            connections: cx.new(|cx| InputState::new(window, cx).default_value(connections)),
+            min_segment_size: cx.new(|cx| InputState::new(window, cx).default_value(min_segment_size)),
+            max_retries: cx.new(|cx| InputState::new(window, cx).default_value(max_retries)),
+            video_binary: cx.new(|cx| InputState::new(window, cx).default_value(video_binary)),
+            video_format: cx.new(|cx| InputState::new(window, cx).default_value(video_format)),
+            video_hosts: cx.new(|cx| {
+                InputState::new(window, cx)
+                    .placeholder("youtube.com, vimeo.com")
+                    .default_value(video_hosts)
+            }),
+            watch_path: cx.new(|cx| InputState::new(window, cx).placeholder("/path/to/shared/folder")),
+            watch_category: None,
+            rpc_port: cx.new(|cx| InputState::new(window, cx).default_value(rpc_port)),
+            proxy: cx.new(|cx| {
+                InputState::new(window, cx)
+                    .placeholder("http://host:port")
+                    .default_value(proxy)
+            }),
+            user_agent: cx.new(|cx| InputState::new(window, cx).default_value(user_agent)),
+            timeout: cx.new(|cx| InputState::new(window, cx).default_value(timeout)),
+            api_port: cx.new(|cx| InputState::new(window, cx).default_value(api_port)),
+            api_token: cx.new(|cx| {
+                InputState::new(window, cx)
+                    .placeholder("Generated when the API is turned on")
+                    .default_value(api_token)
+            }),
+            settings,
+            message: None,
+        }
+    }
+
+    pub fn set_page(&mut self, page: SettingsPage, cx: &mut Context<Self>) {
+        self.page = page;
+        self.message = None;
+        cx.notify();
+    }
+
+    /// Replace the edited settings, e.g. after a change broadcast by the background task
+    pub fn set_settings(&mut self, settings: Settings, window: &mut Window, cx: &mut Context<Self>) {
+        let values = [
+            (&self.download_dir, settings.downloads.download_dir.display().to_string()),
+            (&self.max_concurrent, settings.downloads.max_concurrent.to_string()),
+            (&self.connections, settings.engine.connections.to_string()),
+            (&self.min_segment_size, settings.engine.min_segment_size.clone()),
+            (&self.max_retries, settings.engine.max_retries.to_string()),
+            (&self.video_binary, settings.video.binary.display().to_string()),
+            (&self.video_format, settings.video.format.clone()),
+            (&self.video_hosts, settings.video.hosts.join(", ")),
+            (&self.rpc_port, settings.network.rpc_port.to_string()),
+            (&self.proxy, settings.network.proxy.clone()),
+            (&self.user_agent, settings.network.user_agent.clone()),
+            (&self.timeout, settings.network.timeout_secs.to_string()),
+            (&self.api_port, settings.api.port.to_string()),
+            (&self.api_token, settings.api.token.clone()),
+        ];
+        for (input, value) in values {
+            input.update(cx, |state, cx| state.set_value(value, window, cx));
+        }
+
+        self.settings = settings;
+        cx.notify();
+    }
+
+    /// Build settings from the form fields
+    fn collect(&self, cx: &App) -> anyhow::Result<Settings> {
+        let mut settings = self.settings.clone();
+
+        settings.downloads.download_dir = PathBuf::from(self.download_dir.read(cx).value().trim());
+        settings.downloads.max_concurrent = parse_field(&self.max_concurrent, "Max concurrent downloads", cx)?;
+        settings.engine.connections = parse_field(&self.connections, "Connections per download", cx)?;
+        settings.engine.min_segment_size = self.min_segment_size.read(cx).value().trim().to_string();
+        settings.engine.max_retries = parse_field(&self.max_retries, "Retries", cx)?;
+        settings.video.binary = PathBuf::from(self.video_binary.read(cx).value().trim());
+        settings.video.format = self.video_format.read(cx).value().trim().to_string();
+        settings.video.hosts = self
+            .video_hosts
+            .read(cx)
+            .value()
+            .split(',')
+            .map(|host| host.trim().to_ascii_lowercase())
+            .filter(|host| !host.is_empty())
+            .collect();
+        settings.network.rpc_port = parse_field(&self.rpc_port, "RPC port", cx)?;
+        settings.network.proxy = self.proxy.read(cx).value().trim().to_string();
+        settings.network.user_agent = self.user_agent.read(cx).value().trim().to_string();
+        settings.network.timeout_secs = parse_field(&self.timeout, "Timeout", cx)?;
+        settings.api.port = parse_field(&self.api_port, "API port", cx)?;
+        settings.api.token = self.api_token.read(cx).value().trim().to_string();
+        if settings.api.enabled && settings.api.token.is_empty() {
+            settings.api.token = generate_token();
+        }
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn save(&mut self, cx: &mut Context<Self>) {
+        match self.collect(cx).and_then(persist_settings) {
+            Ok(settings) => {
+                self.settings = settings.clone();
+                self.message = Some((false, "Settings saved".to_string()));
+                cx.emit(SettingsEvent::Saved(settings));
+            }
+            Err(e) => {
+                self.message = Some((true, e.to_string()));
+            }
+        }
+        cx.notify();
+    }
+
+    fn render_general(&self, cx: &mut Context<Self>) -> Div {
+        let general = &self.settings.general;
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_4()
+            .child(
+                Checkbox::new("open-ui-on-start")
+                    .label("Open the window when bong starts")
+                    .checked(general.open_ui_on_start)
+                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                        this.settings.general.open_ui_on_start = *checked;
+                        cx.notify();
+                    }))
+            )
+            .child(
+                Checkbox::new("start-background-on-start")
+                    .label("Start the background task when bong starts")
+                    .checked(general.start_background_on_start)
+                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                        this.settings.general.start_background_on_start = *checked;
+                        cx.notify();
+                    }))
+            )
+    }
+
+    fn render_appearance(&self, cx: &mut Context<Self>) -> Div {
+        let appearance = &self.settings.appearance;
+        let current = appearance.theme;
+
+        let theme_button = |id: &'static str, label: &'static str, theme: ThemePreference| {
+            let button = Button::new(id)
+                .label(label)
+                .on_click(cx.listener(move |this, _, _, cx| {
+                    this.settings.appearance.theme = theme;
+                    cx.notify();
+                }));
+            if current == theme { button.primary() } else { button.ghost() }
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_4()
+            .child(
+                field(
+                    "Theme",
+                    "Follow the system or force a light or dark theme",
+                    div()
+                        .flex()
+                        .gap_2()
+                        .child(theme_button("theme-system", "System", ThemePreference::System))
+                        .child(theme_button("theme-light", "Light", ThemePreference::Light))
+                        .child(theme_button("theme-dark", "Dark", ThemePreference::Dark)),
+                )
+            )
+            .child(
+                Checkbox::new("compact-lists")
+                    .label("Compact download lists")
+                    .checked(appearance.compact_lists)
+                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                        this.settings.appearance.compact_lists = *checked;
+                        cx.notify();
+                    }))
+            )
+            .child(
+                Checkbox::new("speed-in-bits")
+                    .label("Show speeds in bits per second")
+                    .checked(appearance.speed_in_bits)
+                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                        this.settings.appearance.speed_in_bits = *checked;
+                        cx.notify();
+                    }))
+            )
+    }
+
+    fn render_downloads(&self, cx: &mut Context<Self>) -> Div {
+        let current = self.settings.engine.kind;
+        let engine_button = |(index, kind): (usize, EngineKind)| {
+            let button = Button::new(("engine", index))
+                .label(kind.label())
+                .on_click(cx.listener(move |this, _, _, cx| {
+                    this.settings.engine.kind = kind;
+                    cx.notify();
+                }));
+            if current == kind { button.primary() } else { button.ghost() }
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_4()
+            .child(field(
+                "Download engine",
+                "aria2 handles torrents and remote endpoints, the built-in engine needs no aria2c",
+                div()
+                    .flex()
+                    .gap_2()
+                    .children(EngineKind::ALL.into_iter().enumerate().map(engine_button)),
+            ))
+            .when(current == EngineKind::Native, |this| {
+                this.child(field(
+                    "Connections per download",
+                    "Files are split over this many connections when the server allows it",
+                    Input::new(&self.connections),
+                ))
+                .child(field(
+                    "Min segment size",
+                    "Don't split files into pieces smaller than this, e.g. 4M",
+                    Input::new(&self.min_segment_size),
+                ))
+                .child(field(
+                    "Retries",
+                    "Attempts per connection before a download fails",
+                    Input::new(&self.max_retries),
+                ))
+            })
+            .child(field(
+                "Download folder",
+                "Where new downloads are saved",
+                Input::new(&self.download_dir),
+            ))
+            .child(field(
+                "Max concurrent downloads",
+                "Downloads beyond this limit wait in the queue",
+                Input::new(&self.max_concurrent),
+            ))
+            .child(
+                Checkbox::new("auto-start")
+                    .label("Start new downloads immediately")
+                    .checked(self.settings.downloads.auto_start)
+                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                        this.settings.downloads.auto_start = *checked;
+                        cx.notify();
+                    }))
+            )
+            .child(
+                Checkbox::new("video-enabled")
+                    .label("Download video pages with yt-dlp")
+                    .checked(self.settings.video.enabled)
+                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                        this.settings.video.enabled = *checked;
+                        cx.notify();
+                    }))
+            )
+            .when(self.settings.video.enabled, |this| {
+                this.child(field(
+                    "yt-dlp path",
+                    "The yt-dlp executable, looked up in PATH when not absolute",
+                    Input::new(&self.video_binary),
+                ))
+                .child(field(
+                    "Video format",
+                    "yt-dlp format used unless another one is picked in Search",
+                    Input::new(&self.video_format),
+                ))
+                .child(field(
+                    "Video sites",
+                    "Comma separated, subdomains included. Pages on these sites are handed to yt-dlp",
+                    Input::new(&self.video_hosts),
+                ))
+            })
+    }
+
+    /// Add the folder in the form to the watch list, saved with the other settings
+    fn add_watch_folder(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let path = PathBuf::from(self.watch_path.read(cx).value().trim());
+        let mut watch = self.settings.watch.clone();
+        watch.folders.push(WatchFolder {
+            path,
+            category: self.watch_category.clone(),
+            ..Default::default()
+        });
+
+        match watch.validate() {
+            Ok(()) => {
+                self.settings.watch = watch;
+                self.watch_path.update(cx, |state, cx| state.set_value("", window, cx));
+                self.message = None;
+            }
+            Err(e) => self.message = Some((true, e.to_string())),
+        }
+        cx.notify();
+    }
+
+    fn render_watch(&self, cx: &mut Context<Self>) -> Div {
+        let folders = self.settings.watch.folders.clone();
+        let mut categories = vec![("Automatic".to_string(), None)];
+        categories.extend(
+            self.settings
+                .categories
+                .categories
+                .iter()
+                .map(|category| (category.name.clone(), Some(category.name.clone()))),
+        );
+
+        let list = if folders.is_empty() {
+            div()
+                .text_sm()
+                .text_color(rgb(0x6b7280))
+                .child("No folders are watched")
+        } else {
+            div()
+                .flex()
+                .flex_col()
+                .gap_2()
+                .children(folders.into_iter().enumerate().map(|(index, folder)| {
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_3()
+                        .p_3()
+                        .bg(rgb(0x1f2937))
+                        .rounded(px(8.0))
+                        .border_1()
+                        .border_color(rgb(0x374151))
+                        .child(
+                            Checkbox::new(("watch-enabled", index))
+                                .checked(folder.enabled)
+                                .on_click(cx.listener(move |this, checked: &bool, _, cx| {
+                                    this.settings.watch.folders[index].enabled = *checked;
+                                    cx.notify();
+                                }))
+                        )
+                        .child(
+                            div()
+                                .flex_1()
+                                .text_sm()
+                                .text_color(rgb(0xffffff))
+                                .child(folder.path.display().to_string())
+                        )
+                        .child(
+                            div()
+                                .text_xs()
+                                .text_color(rgb(0x9ca3af))
+                                .child(folder.category.unwrap_or_else(|| "Automatic".to_string()))
+                        )
+                        .child(
+                            Button::new(("watch-remove", index))
+                                .label("Remove")
+                                .ghost()
+                                .on_click(cx.listener(move |this, _, _, cx| {
+                                    this.settings.watch.folders.remove(index);
+                                    cx.notify();
+                                }))
+                        )
+                }))
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_4()
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(rgb(0x9ca3af))
+                    .child(format!(
+                        "New .torrent, .meta4 and .txt URL list files are added to the queue, then moved into {}/ or {}/",
+                        ADDED_DIR, FAILED_DIR,
+                    ))
+            )
+            .child(list)
+            .child(field(
+                "Add a folder",
+                "Downloads from this folder get the category picked below",
+                div()
+                    .flex()
+                    .gap_2()
+                    .child(div().flex_1().child(Input::new(&self.watch_path)))
+                    .child(
+                        Button::new("watch-add")
+                            .label("Add")
+                            .on_click(cx.listener(|this, _, window, cx| this.add_watch_folder(window, cx)))
+                    ),
+            ))
+            .child(
+                div()
+                    .flex()
+                    .flex_wrap()
+                    .gap_2()
+                    .children(categories.into_iter().enumerate().map(|(index, (label, category))| {
+                        let selected = category == self.watch_category;
+                        let button = Button::new(("watch-category", index))
+                            .label(label)
+                            .on_click(cx.listener(move |this, _, _, cx| {
+                                this.watch_category = category.clone();
+                                cx.notify();
+                            }));
+                        if selected { button.primary() } else { button.ghost() }
+                    }))
+            )
+    }
+
+    fn render_network(&self) -> Div {
+        div()
+            .flex()
+            .flex_col()
+            .gap_4()
+            .child(field(
+                "RPC port",
+                "Port of the aria2 JSON-RPC interface",
+                Input::new(&self.rpc_port),
+            ))
+            .child(field(
+                "Proxy",
+                "Leave empty to connect directly",
+                Input::new(&self.proxy),
+            ))
+            .child(field(
+                "User agent",
+                "Sent with every HTTP request",
+                Input::new(&self.user_agent),
+            ))
+            .child(field(
+                "Timeout (seconds)",
+                "How long to wait for a server to respond",
+                Input::new(&self.timeout),
+            ))
+    }
+
+    fn render_api(&self, cx: &mut Context<Self>) -> Div {
+        div()
+            .flex()
+            .flex_col()
+            .gap_4()
+            .child(
+                Checkbox::new("api-enabled")
+                    .label("Serve the HTTP API on localhost")
+                    .checked(self.settings.api.enabled)
+                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                        this.settings.api.enabled = *checked;
+                        cx.notify();
+                    }))
+            )
+            .child(
+                Checkbox::new("api-web-ui")
+                    .label("Serve the web dashboard at the API's address")
+                    .checked(self.settings.api.web_ui)
+                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                        this.settings.api.web_ui = *checked;
+                        cx.notify();
+                    }))
+            )
+            .child(field(
+                "Port",
+                "The API only accepts connections from this machine",
+                Input::new(&self.api_port),
+            ))
+            .child(field(
+                "Token",
+                "Sent as Authorization: Bearer <token> with every request",
+                div()
+                    .flex()
+                    .gap_2()
+                    .child(div().flex_1().child(Input::new(&self.api_token)))
+                    .child(
+                        Button::new("api-generate-token")
+                            .label("Generate")
+                            .ghost()
+                            .on_click(cx.listener(|this, _, window, cx| {
+                                this.api_token.update(cx, |state, cx| state.set_value(generate_token(), window, cx));
+                            }))
+                    ),
+            ))
+    }
+
+    fn render_about(&self) -> Div {
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(
+                div()
+                    .text_xl()
+                    .font_weight(FontWeight::SEMIBOLD)
+                    .text_color(rgb(0xffffff))
+                    .child("bong")
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(rgb(0x9ca3af))
+                    .child(format!("Version {}", env!("CARGO_PKG_VERSION")))
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(rgb(0x9ca3af))
+                    .child("A download manager built on aria2")
+            )
+            .child(
+                div()
+                    .mt_4()
+                    .text_xs()
+                    .text_color(rgb(0x6b7280))
+                    .child(format!("Settings file: {}", crate::modules::settings::settings_path().display()))
+            )
+    }
+}
+
+impl Render for SettingsView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let page = self.page;
+
+        let content = match page {
+            SettingsPage::General => self.render_general(cx),
+            SettingsPage::Appearance => self.render_appearance(cx),
+            SettingsPage::Downloads => self.render_downloads(cx),
+            SettingsPage::Watch => self.render_watch(cx),
+            SettingsPage::Network => self.render_network(),
+            SettingsPage::Api => self.render_api(cx),
+            SettingsPage::About => self.render_about(),
+        };
+
+        div()
+            .flex()
+            .size_full()
+            .child(
+                // Page list
+                div()
+                    .flex()
+                    .flex_col()
+                    .w(px(200.0))
+                    .p_4()
+                    .gap_1()
+                    .border_r_1()
+                    .border_color(rgb(0x374151))
+                    .children(SettingsPage::ALL.into_iter().map(|item| {
+                        let button = Button::new(item.title())
+                            .label(item.title())
+                            .w_full()
+                            .on_click(cx.listener(move |this, _, _, cx| {
+                                this.set_page(item, cx);
+                            }));
+                        if item == page { button.primary() } else { button.ghost() }
+                    }))
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .p_6()
+                    .gap_4()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0xffffff))
+                            .child(page.title())
+                    )
+                    .child(content)
+                    .child(
+                        // Footer with save / close
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .mt_4()
+                            .when(page != SettingsPage::About, |this| {
+                                this.child(
+                                    Button::new("settings-save")
+                                        .label("Save")
+                                        .primary()
+                                        .on_click(cx.listener(|this, _, _, cx| this.save(cx)))
+                                )
+                            })
+                            .child(
+                                Button::new("settings-close")
+                                    .label("Close")
+                                    .ghost()
+                                    .on_click(cx.listener(|_, _, _, cx| cx.emit(SettingsEvent::Close)))
+                            )
+                            .when_some(self.message.clone(), |this, (is_error, message)| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(if is_error { rgb(0xef4444) } else { rgb(0x22c55e) })
+                                        .child(message)
+                                )
+                            })
+                    )
+            )
+    }
+}
+
+/// Hand settings to the background task, which owns the settings file.
+/// Saves directly only when the background task isn't running.
+pub(super) fn persist_settings(settings: Settings) -> anyhow::Result<Settings> {
+    match IpcClient::new(BACKGROUND_IPC_PORT).send_with_response(IpcCommand::UpdateSettings(settings.clone())) {
+        Ok(Some(IpcCommand::Settings(saved))) => Ok(saved),
+        Ok(Some(IpcCommand::Error(e))) => Err(anyhow::anyhow!(e)),
+        Ok(_) => Err(anyhow::anyhow!("Unexpected response from background task")),
+        Err(_) => settings.save().map(|_| settings),
+    }
+}
+
+/// A labelled form row
+pub(super) fn field(label: &'static str, description: &'static str, control: impl IntoElement) -> Div {
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .child(
+            div()
+                .text_sm()
+                .font_weight(FontWeight::SEMIBOLD)
+                .text_color(rgb(0xffffff))
+                .child(label)
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(rgb(0x9ca3af))
+                .child(description)
+        )
+        .child(div().mt_1().max_w(px(420.0)).child(control))
+}
+
+/// Parse a numeric form field, naming it in the error
+pub(super) fn parse_field<T: std::str::FromStr>(input: &Entity<InputState>, name: &str, cx: &App) -> anyhow::Result<T> {
+    input
+        .read(cx)
+        .value()
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("{} must be a number", name))
+}
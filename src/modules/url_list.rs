@@ -0,0 +1,114 @@
+use crate::modules::engine::AddRequest;
+
+/// Parse a URL list in aria2's input file format: one download per line with its
+/// mirrors separated by tabs, options such as `out=name.iso` on indented lines below
+/// it, and `#` comments. `dir`, `out` and `header` fill in the request, any other
+/// option is passed on to aria2.
+pub fn parse_url_list(text: &str) -> anyhow::Result<Vec<AddRequest>> {
+    let mut requests: Vec<AddRequest> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with([' ', '\t']) {
+            let Some(request) = requests.last_mut() else {
+                anyhow::bail!("Line {}: options must follow a URL", number);
+            };
+            let Some((key, value)) = trimmed.split_once('=') else {
+                anyhow::bail!("Line {}: expected an option such as out=file.iso", number);
+            };
+            let (key, value) = (key.trim().to_string(), value.trim().to_string());
+            match key.as_str() {
+                "dir" => request.dir = Some(value),
+                "out" => request.out = Some(value),
+                "header" => request.headers.push(value),
+                _ => request.options.push((key, value)),
+            }
+            continue;
+        }
+
+        let uris: Vec<String> = trimmed
+            .split('\t')
+            .map(str::trim)
+            .filter(|uri| !uri.is_empty())
+            .map(str::to_string)
+            .collect();
+        if let Some(uri) = uris.iter().find(|uri| !is_uri(uri)) {
+            anyhow::bail!("Line {}: {} is not a URL", number, uri);
+        }
+        requests.push(AddRequest {
+            uris,
+            ..Default::default()
+        });
+    }
+
+    if requests.is_empty() {
+        anyhow::bail!("The list contains no URLs");
+    }
+    Ok(requests)
+}
+
+/// Whether a list entry looks like something an engine can download
+fn is_uri(value: &str) -> bool {
+    value.starts_with("magnet:?")
+        || value
+            .split_once("://")
+            .is_some_and(|(scheme, rest)| !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric()) && !rest.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tab_separated_mirrors_make_one_download() {
+        let requests = parse_url_list("https://a.example/f.iso\thttps://b.example/f.iso\t\tftp://c.example/f.iso\n").unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].uris, ["https://a.example/f.iso", "https://b.example/f.iso", "ftp://c.example/f.iso"]);
+    }
+
+    #[test]
+    fn indented_options_apply_to_the_url_above() {
+        let text = "\
+https://a.example/one.iso
+  dir=/tmp/isos
+\tout = first.iso
+  header=Cookie: a=b
+  header=Referer: https://a.example/
+  max-connection-per-server=4
+magnet:?xt=urn:btih:0123456789abcdef0123456789abcdef01234567
+  seed-ratio=0.0
+";
+        let requests = parse_url_list(text).unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].dir.as_deref(), Some("/tmp/isos"));
+        assert_eq!(requests[0].out.as_deref(), Some("first.iso"));
+        assert_eq!(requests[0].headers, ["Cookie: a=b", "Referer: https://a.example/"]);
+        assert_eq!(requests[0].options, [("max-connection-per-server".to_string(), "4".to_string())]);
+        assert_eq!(requests[1].out, None);
+        assert_eq!(requests[1].options, [("seed-ratio".to_string(), "0.0".to_string())]);
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let text = "# mirrors of the release\n\nhttps://a.example/one.iso\n   \n  # out=ignored.iso\n\t\nhttps://a.example/two.iso\n";
+        let requests = parse_url_list(text).unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|request| request.out.is_none() && request.options.is_empty()));
+        assert!(parse_url_list("# nothing here\n\n").is_err());
+    }
+
+    #[test]
+    fn reports_the_line_of_a_malformed_entry() {
+        let error = parse_url_list("https://a.example/one.iso\n  out\n").unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: expected an option such as out=file.iso");
+        let error = parse_url_list("# options first\n  out=file.iso\nhttps://a.example/one.iso\n").unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: options must follow a URL");
+        let error = parse_url_list("https://a.example/one.iso\tnot a url\n").unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: not a url is not a URL");
+    }
+}
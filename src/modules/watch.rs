@@ -0,0 +1,183 @@
+use crate::modules::background::add_download;
+use crate::modules::engine::{AddRequest, DownloadFile};
+use crate::modules::settings::{Settings, SettingsStore, WatchFolder, ADDED_DIR, FAILED_DIR};
+use crate::modules::torrent::inspect as inspect_torrent;
+use crate::modules::url_list::parse_url_list;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How often the watch folders are scanned
+const SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Enqueue the files dropped into the watch folders of the settings, following
+/// changes to the folder list
+pub fn spawn(settings: Arc<SettingsStore>) {
+    std::thread::spawn(move || {
+        let mut watcher = FolderWatcher::default();
+        loop {
+            watcher.scan(&settings.get());
+            std::thread::sleep(SCAN_INTERVAL);
+        }
+    });
+}
+
+/// Files taken from watch folders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchedFile {
+    Torrent,
+    Metalink,
+    /// One URL per line, see `parse_url_list`
+    UrlList,
+}
+
+impl WatchedFile {
+    pub fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "torrent" => Some(WatchedFile::Torrent),
+            "meta4" | "metalink" => Some(WatchedFile::Metalink),
+            "txt" => Some(WatchedFile::UrlList),
+            _ => None,
+        }
+    }
+
+    /// Downloads described by the contents of a file
+    pub fn requests(self, data: &[u8]) -> anyhow::Result<Vec<AddRequest>> {
+        match self {
+            WatchedFile::Torrent => {
+                inspect_torrent(data)?;
+                Ok(vec![AddRequest {
+                    file: Some(DownloadFile::torrent(data)),
+                    ..Default::default()
+                }])
+            }
+            WatchedFile::Metalink => {
+                if !String::from_utf8_lossy(data).contains("<metalink") {
+                    anyhow::bail!("Not a Metalink file");
+                }
+                Ok(vec![AddRequest {
+                    file: Some(DownloadFile::metalink(data)),
+                    ..Default::default()
+                }])
+            }
+            WatchedFile::UrlList => {
+                let text = std::str::from_utf8(data).map_err(|_| anyhow::anyhow!("URL lists must be UTF-8 text"))?;
+                parse_url_list(text)
+            }
+        }
+    }
+}
+
+/// Size and modification time of a file
+type Stamp = (u64, SystemTime);
+
+/// Picks up new files in the watch folders once they stopped changing, so files
+/// still being copied from another machine are left alone
+#[derive(Default)]
+pub struct FolderWatcher {
+    /// Files seen by the last scan
+    seen: HashMap<PathBuf, Stamp>,
+    /// Files enqueued that couldn't be moved away, not enqueued again unless they change
+    stuck: HashMap<PathBuf, Stamp>,
+}
+
+impl FolderWatcher {
+    /// Enqueue the files that didn't change since the last scan
+    pub fn scan(&mut self, settings: &Settings) {
+        let mut seen = HashMap::new();
+
+        for folder in settings.watch.folders.iter().filter(|folder| folder.enabled) {
+            let Ok(entries) = std::fs::read_dir(&folder.path) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                let Some(kind) = WatchedFile::of(&path).filter(|_| !hidden) else {
+                    continue;
+                };
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                if !metadata.is_file() {
+                    continue;
+                }
+
+                let stamp = (metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+                if self.stuck.get(&path) == Some(&stamp) {
+                    seen.insert(path, stamp);
+                } else if self.seen.get(&path) == Some(&stamp) {
+                    self.stuck.remove(&path);
+                    if !process(settings, folder, &path, kind) {
+                        self.stuck.insert(path.clone(), stamp);
+                        seen.insert(path, stamp);
+                    }
+                } else {
+                    seen.insert(path, stamp);
+                }
+            }
+        }
+
+        self.stuck.retain(|path, _| seen.contains_key(path));
+        self.seen = seen;
+    }
+}
+
+/// Enqueue the downloads of a file with the folder's category, then move it into
+/// the added or failed subfolder. Returns false if the file couldn't be moved.
+fn process(settings: &Settings, folder: &WatchFolder, path: &Path, kind: WatchedFile) -> bool {
+    let requests = std::fs::read(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| kind.requests(&data));
+
+    let result = requests.and_then(|requests| {
+        let total = requests.len();
+        for (added, mut request) in requests.into_iter().enumerate() {
+            if folder.category.is_some() {
+                request.category = folder.category.clone();
+            }
+            if let Err(e) = add_download(settings, request) {
+                anyhow::bail!("{} ({} of {} added)", e, added, total);
+            }
+        }
+        Ok(total)
+    });
+    let subfolder = match result {
+        Ok(count) => {
+            println!("Added {} download(s) from {}", count, path.display());
+            ADDED_DIR
+        }
+        Err(e) => {
+            println!("Failed to add {}: {}", path.display(), e);
+            FAILED_DIR
+        }
+    };
+
+    match move_into(path, &folder.path.join(subfolder)) {
+        Ok(()) => true,
+        Err(e) => {
+            println!("Failed to move {} into {}/: {}", path.display(), subfolder, e);
+            false
+        }
+    }
+}
+
+/// Move a file into a directory, numbering it if the name is taken
+fn move_into(path: &Path, dir: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let name = path.file_name().ok_or_else(|| anyhow::anyhow!("{} has no file name", path.display()))?;
+
+    let mut target = dir.join(name);
+    let stem = path.file_stem().unwrap_or(name).to_string_lossy().into_owned();
+    let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy()));
+    let mut number = 1;
+    while target.exists() {
+        target = dir.join(format!("{} ({}){}", stem, number, extension.as_deref().unwrap_or_default()));
+        number += 1;
+    }
+
+    std::fs::rename(path, target)?;
+    Ok(())
+}
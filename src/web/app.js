@@ -0,0 +1,226 @@
+"use strict";
+
+// Dashboard for the bong HTTP API. The token is kept in localStorage and sent
+// as a bearer token, EventSource can't set headers so it passes it in the query.
+
+const TOKEN_KEY = "bong-token";
+
+const STATUS = {
+  Active: ["Downloading", "var(--green)"],
+  Waiting: ["Queued", "var(--muted)"],
+  Paused: ["Paused", "var(--yellow)"],
+  Error: ["Failed", "var(--red)"],
+  Complete: ["Complete", "var(--blue)"],
+  Removed: ["Removed", "var(--faint)"],
+};
+
+const $ = (id) => document.getElementById(id);
+
+let token = localStorage.getItem(TOKEN_KEY) || "";
+let events = null;
+
+function formatBytes(bytes) {
+  const units = ["B", "KB", "MB", "GB", "TB"];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit += 1;
+  }
+  return unit === 0 ? `${bytes} B` : `${value.toFixed(1)} ${units[unit]}`;
+}
+
+function formatSpeed(bytesPerSec) {
+  return `${formatBytes(bytesPerSec)}/s`;
+}
+
+function formatDuration(secs) {
+  if (secs < 60) return `${secs}s`;
+  if (secs < 3600) return `${Math.floor(secs / 60)}m`;
+  return `${Math.floor(secs / 3600)}h ${Math.floor((secs % 3600) / 60)}m`;
+}
+
+async function api(method, path, body) {
+  const response = await fetch(path, {
+    method,
+    headers: {
+      Authorization: `Bearer ${token}`,
+      ...(body ? { "Content-Type": "application/json" } : {}),
+    },
+    body: body ? JSON.stringify(body) : undefined,
+  });
+  const data = await response.json().catch(() => ({}));
+  if (response.status === 401) {
+    logout("The token was rejected");
+  }
+  if (!response.ok) {
+    throw new Error(data.error || `Request failed with ${response.status}`);
+  }
+  return data;
+}
+
+function showMessage(text, isError) {
+  const message = $("message");
+  message.textContent = text;
+  message.className = isError ? "error" : "success";
+}
+
+// Status label and color, a completed download shows its checksum result
+function statusOf(download, verification) {
+  if (download.metadata && (download.status === "Active" || download.status === "Waiting")) {
+    return ["Fetching metadata", "var(--muted)"];
+  }
+  if (download.status === "Complete" && verification) {
+    if (verification === "Verifying") return ["Verifying", "var(--muted)"];
+    if (verification === "Verified") return ["Verified", "var(--green)"];
+    if (verification === "Corrupt") return ["Corrupt", "var(--red)"];
+    return ["Not verified", "var(--yellow)"];
+  }
+  return STATUS[download.status] || [download.status, "var(--muted)"];
+}
+
+function renderDownload(download, actions, verification) {
+  const row = $("download-row").content.firstElementChild.cloneNode(true);
+  const [label, color] = statusOf(download, verification);
+  const progress = download.total_length > 0 ? download.completed_length / download.total_length : 0;
+
+  row.querySelector(".name").textContent = download.name || download.gid;
+  const status = row.querySelector(".status");
+  status.textContent = label;
+  status.style.color = color;
+  const bar = row.querySelector(".bar");
+  bar.style.width = `${Math.min(Math.max(progress, 0), 1) * 100}%`;
+  bar.style.background = color;
+
+  let details = `${formatBytes(download.completed_length)} of ${formatBytes(download.total_length)}`;
+  if (download.status === "Active") {
+    details += ` - ${formatSpeed(download.download_speed)}`;
+    if (download.download_speed > 0 && download.total_length > 0) {
+      const eta = Math.floor((download.total_length - download.completed_length) / download.download_speed);
+      details += ` - ${formatDuration(Math.max(eta, 0))} left`;
+    }
+  }
+  if (download.error_message) {
+    details = download.error_message;
+  }
+  row.querySelector(".details").textContent = details;
+
+  const outcomes = row.querySelector(".actions");
+  for (const outcome of actions || []) {
+    const item = document.createElement("span");
+    item.textContent = outcome.success ? `✓ ${outcome.action}` : `✗ ${outcome.action}: ${outcome.message}`;
+    item.style.color = outcome.success ? "var(--green)" : "var(--red)";
+    outcomes.appendChild(item);
+  }
+
+  const toggle = row.querySelector(".toggle");
+  const gid = encodeURIComponent(download.gid);
+  if (download.status === "Active" || download.status === "Waiting") {
+    toggle.textContent = "Pause";
+    toggle.onclick = () => run(api("POST", `/api/downloads/${gid}/pause`));
+  } else if (download.status === "Paused") {
+    toggle.textContent = "Resume";
+    toggle.onclick = () => run(api("POST", `/api/downloads/${gid}/resume`));
+  } else {
+    toggle.remove();
+  }
+  row.querySelector(".remove").onclick = () => run(api("DELETE", `/api/downloads/${gid}`));
+  return row;
+}
+
+function render(snapshot) {
+  const stat = snapshot.stat;
+  $("stat-active").textContent = stat.num_active;
+  $("stat-waiting").textContent = stat.num_waiting;
+  $("stat-stopped").textContent = stat.num_stopped;
+  $("stat-download").textContent = formatSpeed(stat.download_speed);
+  $("stat-upload").textContent = formatSpeed(stat.upload_speed);
+
+  // A magnet link's metadata download is replaced by the real one once the metadata arrives
+  const downloads = snapshot.downloads.filter((download) => !(download.metadata && download.followed_by));
+  const list = $("downloads");
+  list.replaceChildren(
+    ...downloads.map((download) =>
+      renderDownload(download, snapshot.actions[download.gid], snapshot.verifications[download.gid]),
+    ),
+  );
+  $("empty").hidden = downloads.length > 0;
+}
+
+// Report a failed request, the event stream shows the effect of successful ones
+function run(request) {
+  request.catch((error) => showMessage(error.message, true));
+}
+
+function connect() {
+  if (events) events.close();
+  events = new EventSource(`/api/events?token=${encodeURIComponent(token)}`);
+  events.addEventListener("transfers", (event) => {
+    $("connection").textContent = "";
+    render(JSON.parse(event.data));
+  });
+  events.addEventListener("download", (event) => {
+    const change = JSON.parse(event.data);
+    if (change.type !== "status_changed") return;
+    const { status, name, error_message, metadata } = change.download;
+    if (metadata && status === "Complete") return;
+    if (status === "Complete") showMessage(`Finished ${name}`, false);
+    if (status === "Error") showMessage(`${name} failed: ${error_message || "unknown error"}`, true);
+  });
+  events.onerror = () => {
+    $("connection").textContent = "Reconnecting...";
+  };
+}
+
+async function login(candidate) {
+  token = candidate;
+  try {
+    await api("GET", "/api/stats");
+  } catch (error) {
+    logout(error.message);
+    return;
+  }
+  localStorage.setItem(TOKEN_KEY, token);
+  $("login").hidden = true;
+  $("dashboard").hidden = false;
+  connect();
+}
+
+function logout(reason) {
+  if (events) events.close();
+  events = null;
+  token = "";
+  localStorage.removeItem(TOKEN_KEY);
+  $("dashboard").hidden = true;
+  $("login").hidden = false;
+  $("login-error").textContent = reason || "";
+}
+
+$("login-form").onsubmit = (event) => {
+  event.preventDefault();
+  login($("token").value.trim());
+};
+
+$("add-form").onsubmit = async (event) => {
+  event.preventDefault();
+  const url = $("url").value.trim();
+  const checksum = $("checksum").value.trim();
+  try {
+    await api("POST", "/api/downloads", { uris: [url], checksum: checksum || null });
+    $("url").value = "";
+    $("checksum").value = "";
+    showMessage("Download added", false);
+  } catch (error) {
+    showMessage(error.message, true);
+  }
+};
+
+$("pause-all").onclick = () => run(api("POST", "/api/pause"));
+$("resume-all").onclick = () => run(api("POST", "/api/resume"));
+$("logout").onclick = () => logout();
+
+if (token) {
+  login(token);
+} else {
+  logout();
+}